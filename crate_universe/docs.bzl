@@ -25,6 +25,11 @@ call above or [crates_repository::generator_urls](#crates_repository-generator_u
 
 - [crate_universe_dependencies](#crate_universe_dependencies)
 - [crate.annotation](#crateannotation)
+- [crate.cc_library](#cratecc_library)
+- [crate.duplicate_versions](#crateduplicate_versions)
+- [crate.license_policy](#cratelicense_policy)
+- [crate.platform](#crateplatform)
+- [crate.recipes](#craterecipes)
 - [crate.select](#crateselect)
 - [crate.spec](#cratespec)
 - [crate.workspace_member](#crateworkspace_member)
@@ -93,6 +98,13 @@ rust_test(
 )
 ```
 
+Settings from the `[profile.dev]` and `[profile.release]` tables of the root `Cargo.toml` (such as `opt-level`,
+`codegen-units` and `[profile.dev.package.<name>]` overrides) are applied to the generated crates through
+`rustc_flags`. `lto` is not applied since it only affects the final linked artifact. The `dev` profile is used for the `dbg` and `fastbuild` [compilation modes][cm] while the `release`
+profile is used for `opt`.
+
+[cm]: https://bazel.build/docs/user-manual#compilation-mode
+
 ### Direct Packages
 
 In cases where Rust targets have heavy interractions with other Bazel targests ([Cc][cc], [Proto][proto], etc.),
@@ -155,6 +167,172 @@ rust_test(
 )
 ```
 
+### Resolved Lockfiles
+
+By default, the `lockfile` of `crates_repository` contains the fully rendered dependency graph which can produce
+large diffs for small dependency changes. Alternatively, a lockfile can record only the resolved inputs of the graph
+(the contents of the `Cargo.lock` file, the features and dependencies of each crate per platform, and where the
+source of each crate is downloaded from). The dependency graph is then derived from these inputs when the repository
+is fetched, which splices the workspace and runs `cargo metadata` but doesn't re-resolve features or query registries.
+
+Existing lockfiles can be converted using the `migrate` command of `cargo-bazel`. The config and splicing manifest
+it requires are written to the root of the `crates_repository` (e.g. `$(bazel info output_base)/external/crate_index`)
+by any fetch of the repository.
+
+```shell
+cargo-bazel migrate \\
+    --lockfile Cargo.Bazel.lock \\
+    --cargo-lockfile Cargo.lock \\
+    --config $(bazel info output_base)/external/crate_index/cargo-bazel.json \\
+    --splicing-manifest $(bazel info output_base)/external/crate_index/splicing_manifest.json \\
+    --cargo $(which cargo) \\
+    --rustc $(which rustc)
+```
+
+The migrated lockfile must render the same dependency graph as the original, so lockfiles which are out of date
+should be repinned before they're migrated. Repinning a migrated lockfile keeps it in the resolved format.
+
+Lockfiles in either format can be passed to the `lockfile` attribute of the `from_cargo` and `from_specs` tags of
+the `crate` module extension, which then renders the dependency graph from the lockfile instead of resolving it on
+each evaluation. The `diff`, `why`, `audit` and `sbom` commands of `cargo-bazel` also accept either format, though
+reading a resolved lockfile requires the same `--config`, `--splicing-manifest`, `--cargo` and `--rustc` arguments
+as `migrate`.
+
+### Comparing lockfiles
+
+The `diff` command of `cargo-bazel` explains what changed between two lockfiles, for example when reviewing a
+repin. It reports the crates which were added or removed, the crates which were upgraded or downgraded and, for
+crates present in both lockfiles, the attributes (features, dependencies, rustc flags, build script settings, etc.)
+which gained or lost values. Any other change to a crate, such as its source or targets, is listed by name as
+`changed`. Changes to the platforms each `cfg` condition applies to are listed last. Passing `--json` prints the
+same report in a machine readable form.
+
+```shell
+git show HEAD:Cargo.Bazel.lock > /tmp/Cargo.Bazel.lock
+cargo-bazel diff \\
+    --old /tmp/Cargo.Bazel.lock \\
+    --new Cargo.Bazel.lock
+```
+
+```text
+Added crates:
+  + serde 1.0.197
+Upgraded crates:
+  ~ anyhow 1.0.79 -> 1.0.80
+Changed crates:
+  mock 0.1.0
+    crate_features:
+      + default
+    deps:
+      + libc 0.2.153 (cfg(unix))
+      - libc 0.2.152 (cfg(unix))
+```
+
+### Software Bill of Materials
+
+The `sbom` command of `cargo-bazel` produces an [SPDX 2.3][spdx] or [CycloneDX 1.5][cdx] JSON document from a
+lockfile. Each crate is reported with its package URL, checksum, declared license and dependencies. Passing
+`--member` restricts the document to the transitive dependencies of specific workspace members.
+
+```shell
+cargo-bazel sbom \\
+    --lockfile Cargo.Bazel.lock \\
+    --format spdx \\
+    --member my_binary \\
+    --output sbom.spdx.json
+```
+
+[spdx]: https://spdx.github.io/spdx-spec/v2.3/
+[cdx]: https://cyclonedx.org/docs/1.5/json/
+
+### Auditing dependencies
+
+The `audit` command of `cargo-bazel` checks the crates in a lockfile against a local checkout of the
+[RustSec advisory database][advisory-db], similar to [cargo-audit][cargo-audit]. Only crates reachable from a
+workspace member on at least one of the `supported_platform_triples` (or custom platforms) are considered,
+so dependencies of platforms which aren't built are not reported. Passing `--deny` causes the command to fail
+when a vulnerability is found and `--ignore` skips specific advisories.
+
+```shell
+git clone https://github.com/rustsec/advisory-db.git /tmp/advisory-db
+cargo-bazel audit \\
+    --lockfile Cargo.Bazel.lock \\
+    --advisory-db /tmp/advisory-db \\
+    --deny
+```
+
+[advisory-db]: https://github.com/rustsec/advisory-db
+[cargo-audit]: https://github.com/rustsec/rustsec/tree/main/cargo-audit
+
+### Explaining dependencies
+
+Similar to `cargo tree -i`, the `why` command of `cargo-bazel` prints the crates which depend on a crate in a
+lockfile as an inverted tree. The dependents of a crate are only listed the first time it appears, later occurrences
+are marked with `(*)`. Passing `--no-dedupe` lists every path instead, marking only cycles. Each edge is annotated with the kind of dependency (normal, dev, proc-macro or build) and,
+for dependencies only used on some platforms, the `cfg` condition and the platforms which satisfy it. A
+specific version can be selected with `name@version`, where the version may also be a semver requirement.
+
+```shell
+cargo-bazel why --lockfile Cargo.Bazel.lock winapi@0.3
+```
+
+```text
+winapi 0.3.9
+├── app 0.1.0 (normal, cfg(windows): x86_64-pc-windows-msvc)
+└── chrono 0.4.19 (normal, cfg(windows): x86_64-pc-windows-msvc)
+    └── app 0.1.0 (normal)
+```
+
+### Feature variants
+
+Crates are resolved together, so every crate is built with the union of the features enabled on it anywhere in the
+dependency graph. To also build a crate with only a specific set of features, declare a feature variant for it with
+the `feature_variants` parameter of [crate.annotation](#crateannotation). The annotation must match exactly one crate
+of the dependency graph, so give it a `version` when several versions of the crate are used. Feature variant names
+must be unique among all annotations.
+
+Each variant is resolved with Cargo on its own. Crates which are built differently for the variant, starting with the
+annotated crate itself, are rendered as additional targets named `{target}-{variant}` next to their regular targets,
+with the features and dependencies of the variant. The rest of the dependency graph is shared with the regular
+targets.
+
+```python
+crates_repository(
+    name = "crate_index",
+    annotations = {
+        "reqwest": [crate.annotation(
+            version = "0.12.5",
+            feature_variants = {"tls": ["rustls-tls"]},
+        )],
+    },
+    ...
+)
+```
+
+```python
+rust_library(
+    name = "client",
+    srcs = glob(["src/**/*.rs"]),
+    deps = ["@crate_index//:reqwest-tls"],
+)
+```
+
+Variants of workspace members are built from the sources in your workspace, so no target is rendered for them.
+Instead, pass the name of the variant to `all_crate_deps`, `crate_deps` or `aliases` to get the dependencies of the
+member built with the variant's features.
+
+```python
+rust_library(
+    name = "my_lib_tls",
+    srcs = glob(["src/**/*.rs"]),
+    crate_features = ["tls"],
+    deps = all_crate_deps(feature_variant = "tls"),
+)
+```
+
+Crates which are only used by a variant are locked when the variant is resolved, since Cargo would drop them from the
+`Cargo.lock` file of the workspace.
+
 ### Binary dependencies
 
 Neither of the above approaches supports depending on binary-only packages.
@@ -278,6 +456,83 @@ We can use those hooks by specifying paths (generally using the `build_script_da
 
 There is an example of this in the "complicated dependencies" section of https://github.com/bazelbuild/rules_rust/blob/main/examples/crate_universe/WORKSPACE.bazel which builds boring-sys.
 
+### Replacing build scripts with a `cc_library`
+
+Build scripts which only compile bundled C or C++ sources can be replaced entirely by a `cc_library` built by Bazel, which is faster and more hermetic. Anything else the build script would have produced (`cargo:rustc-env` and `cargo:rustc-cfg` outputs) needs to be declared as well:
+
+```python
+crates_repository(
+    name = "crate_index",
+    annotations = {
+        "zstd-sys": [crate.annotation(
+            cc_library = crate.cc_library(
+                srcs = ["zstd/lib/**/*.c"],
+                hdrs = ["zstd/lib/**/*.h"],
+                includes = ["zstd/lib"],
+                defines = ["ZSTD_MULTITHREAD"],
+            ),
+        )],
+    },
+    ...
+)
+```
+
+### Recipes
+
+Annotations needed by commonly used crates can be shared as recipes using [crate.recipes](#craterecipes). `cargo-bazel` bundles recipes for a few crates with well known requirements, and more can be supplied as json files mapping crate names and semver requirements to the attributes of [crate.annotation](#crateannotation):
+
+```json
+{
+    "etcd-client *": {
+        "build_script_data": ["@com_google_protobuf//:protoc"],
+        "build_script_env": {
+            "PROTOC": "$(execpath @com_google_protobuf//:protoc)"
+        }
+    }
+}
+```
+
+```python
+crates_repository(
+    name = "crate_index",
+    recipes = crate.recipes(
+        srcs = ["@//3rdparty/recipes:etcd-client.json"],
+    ),
+    ...
+)
+```
+
+Bundled recipes are applied to the following crates:
+
+- `libz-sys` and `lzma-sys` are made to build and statically link the libraries vendored in their crates.
+- `openssl-sys` is built against an OpenSSL from your workspace, Eg. a `configure_make` target of
+  [rules_foreign_cc](https://github.com/bazelbuild/rules_foreign_cc). It needs the labels `openssl`, the library to
+  link, and `openssl_dir`, a directory holding its installation (`include` and `lib`) which is passed to the build
+  script as `OPENSSL_DIR`.
+- `rdkafka-sys` links a `librdkafka` library from your workspace given as the label `librdkafka`, instead of running
+  a build script which compiles it in its source tree.
+
+Recipes needing labels are only applied once every label they use is given to [crate.recipes](#craterecipes):
+
+```python
+crates_repository(
+    name = "crate_index",
+    recipes = crate.recipes(
+        labels = {
+            "openssl": "@openssl",
+            "openssl_dir": "@openssl//:gen_dir",
+        },
+    ),
+    ...
+)
+```
+
+`ring` and `prost-build` need no recipe: `ring` builds from the sources and pregenerated assembly in its crate, and
+`prost-build` has no build script of its own. The build scripts of crates using `prost-build` read `PROTOC` from
+their environment, which is set as in the `etcd-client` example above.
+
+Recipes apply beneath any `annotations` of the same crate, so values set by an annotation always win.
+
 ---
 
 ---
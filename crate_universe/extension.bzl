@@ -6,7 +6,7 @@ load("@bazel_tools//tools/build_defs/repo:git.bzl", "new_git_repository")
 load("@bazel_tools//tools/build_defs/repo:http.bzl", "http_archive")
 load("//crate_universe:defs.bzl", _crate_universe_crate = "crate")
 load("//crate_universe/private:crates_vendor.bzl", "CRATES_VENDOR_ATTRS", "generate_config_file", "generate_splicing_manifest")
-load("//crate_universe/private:generate_utils.bzl", "CARGO_BAZEL_GENERATOR_SHA256", "CARGO_BAZEL_GENERATOR_URL", "GENERATOR_ENV_VARS", "render_config")
+load("//crate_universe/private:generate_utils.bzl", "CARGO_BAZEL_GENERATOR_SHA256", "CARGO_BAZEL_GENERATOR_URL", "GENERATOR_ENV_VARS", "render_config", "repin_requested")
 load("//crate_universe/private:urls.bzl", "CARGO_BAZEL_SHA256S", "CARGO_BAZEL_URLS")
 load("//crate_universe/private/module_extensions:cargo_bazel_bootstrap.bzl", "get_cargo_bazel_runner", "get_host_cargo_rustc")
 load("//rust/platform:triple.bzl", "get_host_triple")
@@ -91,6 +91,15 @@ def _generate_hub_and_spokes(module_ctx, cargo_bazel, cfg, annotations, cargo_lo
             annotations = annotations,
             generate_build_scripts = cfg.generate_build_scripts,
             supported_platform_triples = cfg.supported_platform_triples,
+            custom_platforms = cfg.custom_platforms,
+            license_policy = cfg.license_policy,
+            duplicate_versions = cfg.duplicate_versions,
+            recipes = cfg.recipes,
+            crate_url_templates = cfg.crate_url_templates,
+            registry_url_templates = cfg.registry_url_templates,
+            generate_sparse_checkouts = cfg.generate_sparse_checkouts,
+            templates = {template.name: str(module_ctx.path(template)) for template in cfg.templates},
+            target_rules = cfg.target_rules,
             generate_target_compatible_with = True,
             repository_name = cfg.name,
             output_pkg = cfg.name,
@@ -114,29 +123,11 @@ def _generate_hub_and_spokes(module_ctx, cargo_bazel, cfg, annotations, cargo_lo
         ),
     )
 
+    # The rendered context is written separately from any lockfile, since the
+    # `crates` read below to generate spoke repos are absent from resolved lockfiles.
+    context_path = tag_path.get_child("context.json")
     splicing_output_dir = tag_path.get_child("splicing-output")
-    splice_args = [
-        "splice",
-        "--output-dir",
-        splicing_output_dir,
-        "--config",
-        config_file,
-        "--splicing-manifest",
-        splicing_manifest,
-    ]
-    if cargo_lockfile:
-        splice_args.extend([
-            "--cargo-lockfile",
-            cargo_lockfile,
-        ])
-    cargo_bazel(splice_args)
-
-    # Create a lockfile, since we need to parse it to generate spoke
-    # repos.
-    lockfile_path = tag_path.get_child("lockfile.json")
-    module_ctx.file(lockfile_path, "")
-
-    cargo_bazel([
+    generate_args = [
         "generate",
         "--cargo-lockfile",
         cargo_lockfile or splicing_output_dir.get_child("Cargo.lock"),
@@ -146,12 +137,51 @@ def _generate_hub_and_spokes(module_ctx, cargo_bazel, cfg, annotations, cargo_lo
         splicing_manifest,
         "--repository-dir",
         tag_path,
-        "--metadata",
-        splicing_output_dir.get_child("metadata.json"),
-        "--repin",
-        "--lockfile",
-        lockfile_path,
-    ])
+        "--context-output",
+        context_path,
+    ]
+
+    lockfile = module_ctx.path(cfg.lockfile) if cfg.lockfile else None
+    if lockfile:
+        generate_args.extend([
+            "--lockfile",
+            lockfile,
+        ])
+
+    if lockfile and not repin_requested(module_ctx, cfg.name):
+        # Render directly from the lockfile, after ensuring it's up to date.
+        cargo_bazel([
+            "query",
+            "--lockfile",
+            lockfile,
+            "--config",
+            config_file,
+            "--splicing-manifest",
+            splicing_manifest,
+        ])
+        cargo_bazel(generate_args)
+    else:
+        splice_args = [
+            "splice",
+            "--output-dir",
+            splicing_output_dir,
+            "--config",
+            config_file,
+            "--splicing-manifest",
+            splicing_manifest,
+        ]
+        if cargo_lockfile:
+            splice_args.extend([
+                "--cargo-lockfile",
+                cargo_lockfile,
+            ])
+        cargo_bazel(splice_args)
+
+        cargo_bazel(generate_args + [
+            "--metadata",
+            splicing_output_dir.get_child("metadata.json"),
+            "--repin",
+        ])
 
     crates_dir = tag_path.get_child(cfg.name)
     _generate_repo(
@@ -162,7 +192,7 @@ def _generate_hub_and_spokes(module_ctx, cargo_bazel, cfg, annotations, cargo_lo
         },
     )
 
-    contents = json.decode(module_ctx.read(lockfile_path))
+    contents = json.decode(module_ctx.read(context_path))
 
     for crate in contents["crates"].values():
         repo = crate["repository"]
@@ -190,7 +220,7 @@ def _generate_hub_and_spokes(module_ctx, cargo_bazel, cfg, annotations, cargo_lo
                 remote_patch_strip = 1,
                 sha256 = repo.get("sha256", None),
                 type = "tar.gz",
-                urls = [repo["url"]],
+                urls = repo.get("urls", None) or [repo["url"]],
                 strip_prefix = "%s-%s" % (crate["name"], crate["version"]),
                 build_file_content = build_file_content,
             )
@@ -203,9 +233,14 @@ def _generate_hub_and_spokes(module_ctx, cargo_bazel, cfg, annotations, cargo_lo
                     kwargs["commit"] = v
                 else:
                     kwargs[k.lower()] = v
+
+            # `sparse_checkout_patterns` is only known to Bazel 7.1 and later.
+            if repo.get("sparse_checkout_patterns"):
+                kwargs["sparse_checkout_patterns"] = repo["sparse_checkout_patterns"]
             new_git_repository(
                 name = crate_repo_name,
-                init_submodules = True,
+                init_submodules = repo.get("init_submodules", True),
+                recursive_init_submodules = repo.get("recursive_init_submodules", False),
                 patch_args = repo.get("patch_args", None),
                 patch_tool = repo.get("patch_tool", None),
                 patches = repo.get("patches", None),
@@ -285,6 +320,9 @@ def _crate_impl(module_ctx):
             module_ctx.path(cfg.cargo_lockfile)
             for m in cfg.manifests:
                 module_ctx.path(m)
+        for cfg in mod.tags.from_cargo + mod.tags.from_specs:
+            if cfg.lockfile:
+                module_ctx.path(cfg.lockfile)
 
     cargo_bazel_output = _get_generator(module_ctx)
     cargo_bazel = get_cargo_bazel_runner(module_ctx, cargo_bazel_output)
@@ -306,6 +344,10 @@ def _crate_impl(module_ctx):
             if annotation_dict.pop("gen_all_binaries"):
                 annotation_dict["gen_binaries"] = True
             annotation_dict["gen_build_script"] = _OPT_BOOL_VALUES[annotation_dict["gen_build_script"]]
+            annotation_dict["gen_tests"] = _OPT_BOOL_VALUES[annotation_dict["gen_tests"]]
+            annotation_dict["gen_examples"] = _OPT_BOOL_VALUES[annotation_dict["gen_examples"]]
+            annotation_dict["init_submodules"] = _OPT_BOOL_VALUES[annotation_dict["init_submodules"]]
+            annotation_dict["recursive_init_submodules"] = _OPT_BOOL_VALUES[annotation_dict["recursive_init_submodules"]]
 
             # Process the override targets for the annotation.
             # In the non-bzlmod approach, this is given as a dict
@@ -331,6 +373,15 @@ def _crate_impl(module_ctx):
             if replacement:
                 annotation_dict["override_targets"]["bin"] = str(replacement)
 
+            # Likewise, the `cc_library` replacing a build script is split into
+            # several attributes which are collected back into `crate.cc_library`.
+            cc_library = {
+                key: annotation_dict.pop("cc_library_" + key)
+                for key in ["srcs", "hdrs", "exclude", "includes", "defines", "copts", "deps", "rustc_env", "cfgs"]
+            }
+            if cc_library["srcs"]:
+                annotation_dict["cc_library"] = _crate_universe_crate.cc_library(**cc_library)
+
             annotation = _crate_universe_crate.annotation(**{
                 k: v
                 for k, v in annotation_dict.items()
@@ -369,10 +420,11 @@ def _crate_impl(module_ctx):
             _generate_hub_and_spokes(module_ctx, cargo_bazel, cfg, annotations, cargo_lockfile = cargo_lockfile, manifests = manifests)
 
         for cfg in mod.tags.from_specs:
-            # We don't have a Cargo.lock so the resolution can change.
+            # Without a lockfile, we don't have a Cargo.lock so the resolution can change.
             # We could maybe make this reproducible by using `-minimal-version` during resolution.
             # See https://doc.rust-lang.org/nightly/cargo/reference/unstable.html#minimal-versions
-            reproducible = False
+            if not cfg.lockfile:
+                reproducible = False
 
             annotations = _annotations_for_repo(
                 module_annotations,
@@ -400,11 +452,27 @@ _from_cargo = tag_class(
             default = "crates",
         ),
         cargo_lockfile = CRATES_VENDOR_ATTRS["cargo_lockfile"],
+        lockfile = attr.label(
+            doc = (
+                "The path to a cargo-bazel lockfile, in either the rendered or resolved format (see `cargo-bazel migrate`). " +
+                "If set, crates are rendered from the lockfile rather than resolved on each evaluation, and the lockfile " +
+                "is updated when repinning with `CARGO_BAZEL_REPIN`. This file must exist within the workspace (but can be empty)."
+            ),
+        ),
         manifests = CRATES_VENDOR_ATTRS["manifests"],
         cargo_config = CRATES_VENDOR_ATTRS["cargo_config"],
         generate_binaries = CRATES_VENDOR_ATTRS["generate_binaries"],
         generate_build_scripts = CRATES_VENDOR_ATTRS["generate_build_scripts"],
         supported_platform_triples = CRATES_VENDOR_ATTRS["supported_platform_triples"],
+        custom_platforms = CRATES_VENDOR_ATTRS["custom_platforms"],
+        license_policy = CRATES_VENDOR_ATTRS["license_policy"],
+        duplicate_versions = CRATES_VENDOR_ATTRS["duplicate_versions"],
+        recipes = CRATES_VENDOR_ATTRS["recipes"],
+        crate_url_templates = CRATES_VENDOR_ATTRS["crate_url_templates"],
+        registry_url_templates = CRATES_VENDOR_ATTRS["registry_url_templates"],
+        generate_sparse_checkouts = CRATES_VENDOR_ATTRS["generate_sparse_checkouts"],
+        templates = CRATES_VENDOR_ATTRS["templates"],
+        target_rules = CRATES_VENDOR_ATTRS["target_rules"],
     ),
 )
 
@@ -494,6 +562,16 @@ _annotation = tag_class(
             values = _OPT_BOOL_VALUES.keys(),
             default = "auto",
         ),
+        gen_tests = attr.string(
+            doc = "Whether or not to produce `rust_test` targets for the crate's integration tests and library unit tests. Only supported for crates vendored with `vendor_mode = \"local\"`. Cargo only resolves `dev-dependencies` for workspace members, so tests needing them won't compile unless they are added with `deps`. Supported values are 'on', 'off', and 'auto'.",
+            values = _OPT_BOOL_VALUES.keys(),
+            default = "auto",
+        ),
+        gen_examples = attr.string(
+            doc = "Whether or not to produce `rust_binary` targets for the crate's examples and benchmarks whose `required-features` are enabled. Benchmarks using the libtest harness are produced as `rust_test` targets instead. Only supported for crates vendored with `vendor_mode = \"local\"` whose `dev-dependencies` are resolved, which Cargo only does for workspace members. Supported values are 'on', 'off', and 'auto'.",
+            values = _OPT_BOOL_VALUES.keys(),
+            default = "auto",
+        ),
         patch_args = attr.string_list(
             doc = "The `patch_args` attribute of a Bazel repository rule. See [http_archive.patch_args](https://docs.bazel.build/versions/main/repo/http.html#http_archive-patch_args)",
         ),
@@ -518,6 +596,18 @@ _annotation = tag_class(
         shallow_since = attr.string(
             doc = "An optional timestamp used for crates originating from a git repository instead of a crate registry. This flag optimizes fetching the source code.",
         ),
+        init_submodules = optional_bool(
+            doc = "For crates originating from a git repository, whether or not to initialize submodules. Submodules are initialized by default. Supported values are 'on', 'off', and 'auto'.",
+        ),
+        recursive_init_submodules = optional_bool(
+            doc = "For crates originating from a git repository, whether or not to recursively initialize submodules. Supported values are 'on', 'off', and 'auto'.",
+        ),
+        sparse_checkout_patterns = attr.string_list(
+            doc = "For crates originating from a git repository, the patterns of paths to check out. By default the whole repository is checked out, or only the directory of the crate if `generate_sparse_checkouts` is set. Requires Bazel 7.1 or later.",
+        ),
+        feature_variants = attr.string_list_dict(
+            doc = "A mapping of variant names to lists of Cargo features. Each variant is resolved on its own and rendered as additional targets named `{target}-{variant}` in the crates repository. Dependencies of workspace member variants are available through `all_crate_deps(feature_variant = ...)`.",
+        ),
         override_target_lib = attr.label(
             doc = "An optional alternate taget to use when something depends on this crate to allow the parent repo to provide its own version of this dependency.",
         ),
@@ -530,6 +620,33 @@ _annotation = tag_class(
         override_target_bin = attr.label(
             doc = "An optional alternate taget to use when something depends on this crate to allow the parent repo to provide its own version of this dependency.",
         ),
+        cc_library_srcs = attr.string_list(
+            doc = "Glob patterns of C and C++ sources to compile into a `cc_library` which replaces the crate's build script. See `crate.cc_library`.",
+        ),
+        cc_library_hdrs = attr.string_list(
+            doc = "Glob patterns of the headers used by `cc_library_srcs`.",
+        ),
+        cc_library_exclude = attr.string_list(
+            doc = "Glob patterns to exclude from `cc_library_srcs` and `cc_library_hdrs`.",
+        ),
+        cc_library_includes = attr.string_list(
+            doc = "Include directories of the `cc_library` which replaces the crate's build script.",
+        ),
+        cc_library_defines = attr.string_list(
+            doc = "Preprocessor definitions of the `cc_library` which replaces the crate's build script.",
+        ),
+        cc_library_copts = attr.string_list(
+            doc = "Additional compiler flags of the `cc_library` which replaces the crate's build script.",
+        ),
+        cc_library_deps = attr.label_list(
+            doc = "Other `cc_library` targets the `cc_library` which replaces the crate's build script depends on.",
+        ),
+        cc_library_rustc_env = attr.string_dict(
+            doc = "Environment variables the replaced build script would have set with `cargo:rustc-env`.",
+        ),
+        cc_library_cfgs = attr.string_list(
+            doc = "Configuration options the replaced build script would have set with `cargo:rustc-cfg`.",
+        ),
     ),
 )
 
@@ -537,10 +654,26 @@ _from_specs = tag_class(
     doc = "Generates a repo @crates from the defined `spec` tags",
     attrs = dict(
         name = attr.string(doc = "The name of the repo to generate", default = "crates"),
+        lockfile = attr.label(
+            doc = (
+                "The path to a cargo-bazel lockfile, in either the rendered or resolved format (see `cargo-bazel migrate`). " +
+                "If set, crates are rendered from the lockfile rather than resolved on each evaluation, and the lockfile " +
+                "is updated when repinning with `CARGO_BAZEL_REPIN`. This file must exist within the workspace (but can be empty)."
+            ),
+        ),
         cargo_config = CRATES_VENDOR_ATTRS["cargo_config"],
         generate_binaries = CRATES_VENDOR_ATTRS["generate_binaries"],
         generate_build_scripts = CRATES_VENDOR_ATTRS["generate_build_scripts"],
         supported_platform_triples = CRATES_VENDOR_ATTRS["supported_platform_triples"],
+        custom_platforms = CRATES_VENDOR_ATTRS["custom_platforms"],
+        license_policy = CRATES_VENDOR_ATTRS["license_policy"],
+        duplicate_versions = CRATES_VENDOR_ATTRS["duplicate_versions"],
+        recipes = CRATES_VENDOR_ATTRS["recipes"],
+        crate_url_templates = CRATES_VENDOR_ATTRS["crate_url_templates"],
+        registry_url_templates = CRATES_VENDOR_ATTRS["registry_url_templates"],
+        generate_sparse_checkouts = CRATES_VENDOR_ATTRS["generate_sparse_checkouts"],
+        templates = CRATES_VENDOR_ATTRS["templates"],
+        target_rules = CRATES_VENDOR_ATTRS["target_rules"],
     ),
 )
 
@@ -106,6 +106,8 @@ def _annotation(
         gen_binaries = None,
         disable_pipelining = False,
         gen_build_script = None,
+        gen_tests = None,
+        gen_examples = None,
         patch_args = None,
         patch_tool = None,
         patches = None,
@@ -114,7 +116,12 @@ def _annotation(
         rustc_env_files = None,
         rustc_flags = None,
         shallow_since = None,
-        override_targets = None):
+        init_submodules = None,
+        recursive_init_submodules = None,
+        sparse_checkout_patterns = None,
+        override_targets = None,
+        feature_variants = None,
+        cc_library = None):
     """A collection of extra attributes and settings for a particular crate
 
     Args:
@@ -153,6 +160,14 @@ def _annotation(
         disable_pipelining (bool, optional): If True, disables pipelining for library targets for this crate.
         gen_build_script (bool, optional): An authorative flag to determine whether or not to produce
             `cargo_build_script` targets for the current crate.
+        gen_tests (bool, optional): If True, produces `rust_test` targets for the crate's integration tests and
+            library unit tests. Only supported for crates vendored with `vendor_mode = "local"`. Cargo only
+            resolves `dev-dependencies` for workspace members, so tests needing them won't compile unless they
+            are added with `deps`.
+        gen_examples (bool, optional): If True, produces `rust_binary` targets for the crate's examples and
+            benchmarks whose `required-features` are enabled. Benchmarks using the libtest harness are produced
+            as `rust_test` targets instead. Only supported for crates vendored with `vendor_mode = "local"` whose
+            `dev-dependencies` are resolved, which Cargo only does for workspace members.
         patch_args (list, optional): The `patch_args` attribute of a Bazel repository rule. See
             [http_archive.patch_args](https://docs.bazel.build/versions/main/repo/http.html#http_archive-patch_args)
         patch_tool (string, optional): The `patch_tool` attribute of a Bazel repository rule. See
@@ -167,8 +182,20 @@ def _annotation(
         rustc_flags (list, optional): A list of strings to set on a crate's `rust_library::rustc_flags` attribute.
         shallow_since (str, optional): An optional timestamp used for crates originating from a git repository
             instead of a crate registry. This flag optimizes fetching the source code.
+        init_submodules (bool, optional): For crates originating from a git repository, whether or not to initialize
+            submodules. Submodules are initialized by default.
+        recursive_init_submodules (bool, optional): For crates originating from a git repository, whether or not to
+            recursively initialize submodules.
+        sparse_checkout_patterns (list, optional): For crates originating from a git repository, the patterns of
+            paths to check out. By default the whole repository is checked out, or only the directory of the crate if
+            `generate_sparse_checkouts` is set. Requires Bazel 7.1 or later.
         override_targets (dict, optional): A dictionary of alternate tagets to use when something depends on this crate to allow
             the parent repo to provide its own version of this dependency. Keys can be `proc_marco`, `build_script`, `lib`, `bin`.
+        feature_variants (dict, optional): A mapping of variant names to lists of Cargo features. Each variant is
+            resolved on its own and rendered as additional targets named `{target}-{variant}` in the crates repository.
+            Dependencies of workspace member variants are available through `all_crate_deps(feature_variant = ...)`.
+        cc_library (struct, optional): The result of `crate.cc_library`. The crate's build script is replaced by
+            a `cc_library` which its Rust targets link against. Cannot be combined with `gen_build_script = True`.
 
     Returns:
         string: A json encoded string containing the specified version and separately all other inputs.
@@ -204,6 +231,8 @@ def _annotation(
             gen_binaries = gen_binaries,
             disable_pipelining = disable_pipelining,
             gen_build_script = gen_build_script,
+            gen_tests = gen_tests,
+            gen_examples = gen_examples,
             patch_args = patch_args,
             patch_tool = patch_tool,
             patches = _stringify_list(patches),
@@ -212,7 +241,12 @@ def _annotation(
             rustc_env_files = _stringify_list(rustc_env_files),
             rustc_flags = rustc_flags,
             shallow_since = shallow_since,
+            init_submodules = init_submodules,
+            recursive_init_submodules = recursive_init_submodules,
+            sparse_checkout_patterns = sparse_checkout_patterns,
             override_targets = override_targets,
+            feature_variants = feature_variants,
+            cc_library = cc_library,
         ),
     ))
 
@@ -244,9 +278,181 @@ def _select(common, selects):
         selects = selects,
     )
 
+def _cc_library(
+        srcs,
+        hdrs = [],
+        exclude = [],
+        includes = [],
+        defines = [],
+        copts = [],
+        deps = [],
+        rustc_env = {},
+        cfgs = []):
+    """A `cc_library` to build in place of a crate's build script for `crate.annotation()`.
+
+    This suits crates whose build scripts only compile bundled C or C++ sources (Eg. with the
+    `cc` crate). All paths are relative to the root of the crate.
+
+    Args:
+        srcs (list): Glob patterns of the C and C++ sources to compile.
+        hdrs (list, optional): Glob patterns of the headers used by `srcs`.
+        exclude (list, optional): Glob patterns to exclude from `srcs` and `hdrs`.
+        includes (list, optional): Include directories.
+        defines (list, optional): Preprocessor definitions (Eg. `ZSTD_MULTITHREAD=1`).
+        copts (list, optional): Additional flags to pass to the C or C++ compiler.
+        deps (list, optional): Other `cc_library` targets the library depends on.
+        rustc_env (dict, optional): Environment variables the build script would have set with
+            `cargo:rustc-env`.
+        cfgs (list, optional): Configuration options the build script would have set with
+            `cargo:rustc-cfg`.
+
+    Returns:
+        struct: A struct representing the `cc_library`.
+    """
+    return struct(
+        srcs = srcs,
+        hdrs = hdrs,
+        exclude = exclude,
+        includes = includes,
+        defines = defines,
+        copts = copts,
+        deps = _stringify_list(deps),
+        rustc_env = rustc_env,
+        cfgs = cfgs,
+    )
+
+def _platform(
+        triple,
+        target_arch = None,
+        target_os = None,
+        target_env = None,
+        target_features = [],
+        panic = None,
+        cfgs = []):
+    """A platform with explicit `cfg` values for `crates_repository.custom_platforms` or `crates_vendor.custom_platforms`.
+
+    Dependencies conditional on a `cfg` expression are selected on each custom platform
+    which satisfies the expression.
+
+    The `config_setting` a platform is keyed by must specialize the constraints of its
+    `triple` (eg. by adding a constraint to those of `@rules_rust//rust/platform:<triple>`).
+    Otherwise Bazel cannot pick between the two in a `select` when both match.
+
+    Args:
+        triple (str): The platform triple providing any `cfg` values not explicitly set.
+        target_arch (str, optional): The value of `target_arch`.
+        target_os (str, optional): The value of `target_os`.
+        target_env (str, optional): The value of `target_env`.
+        target_features (list, optional): The `target_feature`s enabled on the platform.
+        panic (str, optional): The value of `panic`.
+        cfgs (list, optional): Custom configuration options (as passed to `rustc --cfg`) set
+            on the platform. Eg. `["tokio_unstable", 'foo="bar"']`.
+
+    Returns:
+        string: A json encoded string of all inputs
+    """
+    return json.encode(struct(
+        triple = triple,
+        target_arch = target_arch,
+        target_os = target_os,
+        target_env = target_env,
+        target_features = target_features,
+        panic = panic,
+        cfgs = cfgs,
+    ))
+
+def _license_policy(
+        allow = [],
+        deny = [],
+        exceptions = {}):
+    """A policy for `crates_repository.license_policy` or `crates_vendor.license_policy`.
+
+    The [SPDX](https://spdx.org/licenses/) license expression of every crate which is not a
+    workspace member is evaluated against the policy when dependencies are generated. Generation
+    fails with a report of each violating crate and the chain of dependencies which pulled it in.
+
+    Args:
+        allow (list, optional): SPDX license identifiers crates may use. If empty, any license
+            which isn't denied is allowed.
+        deny (list, optional): SPDX license identifiers crates may not use. This takes precedence
+            over `allow`.
+        exceptions (dict, optional): A mapping of crate names and semver requirements (Eg. `ring *`
+            or `webpki 0.22`) to additional license identifiers allowed for matching crates. An empty
+            list exempts matching crates from the policy entirely.
+
+    Returns:
+        string: A json encoded string of all inputs
+    """
+    return json.encode(struct(
+        allow = allow,
+        deny = deny,
+        exceptions = exceptions,
+    ))
+
+def _duplicate_versions(
+        warn = False,
+        deny = []):
+    """Rules for `crates_repository.duplicate_versions` or `crates_vendor.duplicate_versions`.
+
+    Crates are duplicated when several semver incompatible versions of them (Eg. `rand 0.7.3`
+    and `rand 0.8.5`) are in the dependency graph. Each duplicated crate is reported with the
+    crates which depend on each of its versions.
+
+    Args:
+        warn (bool, optional): Whether or not to warn about every duplicated crate when
+            dependencies are generated.
+        deny (list, optional): The names of crates which must only have a single semver compatible
+            version. Generation fails if any of these are duplicated.
+
+    Returns:
+        string: A json encoded string of all inputs
+    """
+    return json.encode(struct(
+        warn = warn,
+        deny = deny,
+    ))
+
+def _recipes(
+        bundled = True,
+        labels = {},
+        srcs = []):
+    """Default annotations for `crates_repository.recipes` or `crates_vendor.recipes`.
+
+    Recipes are applied to matching crates beneath any `annotations`, so values set by an annotation
+    always win. Unlike annotations, recipes which match no crate in the dependency graph are ignored.
+
+    Args:
+        bundled (bool, optional): Whether or not to apply the recipes bundled with `cargo-bazel` for
+            crates with well known requirements (Eg. `libz-sys`).
+        labels (dict, optional): Labels of targets in your workspace for the bundled recipes which
+            need them, keyed by name (Eg. `openssl`). A bundled recipe needing a label which is not
+            given here is not applied. See [Recipes](#recipes) for the names used.
+        srcs (list, optional): Labels of json files mapping crate names and semver requirements (Eg.
+            `openssl-sys *`) to the attributes of `crate.annotation`. These take priority over bundled
+            recipes. Not supported by `crates_vendor`.
+
+    Returns:
+        string: A json encoded string of all inputs
+    """
+    for label in labels.values():
+        _assert_absolute(label)
+    for src in srcs:
+        _assert_absolute(src)
+
+    return json.encode(struct(
+        bundled = bundled,
+        labels = {name: _stringify_label(label) for name, label in labels.items()},
+        srcs = _stringify_list(srcs),
+    ))
+
 crate = struct(
     spec = _spec,
     annotation = _annotation,
+    cc_library = _cc_library,
     workspace_member = _workspace_member,
     select = _select,
+    platform = _platform,
+    license_policy = _license_policy,
+    duplicate_versions = _duplicate_versions,
+    recipes = _recipes,
 )
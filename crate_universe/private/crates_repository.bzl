@@ -214,6 +214,22 @@ CARGO_BAZEL_REPIN=1 CARGO_BAZEL_REPIN_ONLY=crate_index bazel sync --only=crate_i
             ),
             mandatory = True,
         ),
+        "custom_platforms": attr.string_dict(
+            doc = (
+                "Platforms with explicit `cfg` values to consider when generating dependencies, keyed by the " +
+                "label of a `config_setting` matching the platform. Labels are rendered verbatim into BUILD files so " +
+                "should include a repository name (eg. `@//platforms:x86_64_avx2`) when those files are in an external " +
+                "repository. The `config_setting` must specialize the constraints of the platform's triple (eg. add a " +
+                "constraint to those of `@rules_rust//rust/platform:x86_64-unknown-linux-gnu`), otherwise `select` " +
+                "statements matching both will be ambiguous. See [crate.platform](#crateplatform)."
+            ),
+        ),
+        "duplicate_versions": attr.string(
+            doc = (
+                "Rules for crates with several semver incompatible versions in the dependency graph. " +
+                "See [crate.duplicate_versions](#crateduplicate_versions)."
+            ),
+        ),
         "generate_binaries": attr.bool(
             doc = (
                 "Whether to generate `rust_binary` targets for all the binary crates in every package. " +
@@ -259,6 +275,12 @@ CARGO_BAZEL_REPIN=1 CARGO_BAZEL_REPIN_ONLY=crate_index bazel sync --only=crate_i
             ),
             default = True,
         ),
+        "license_policy": attr.string(
+            doc = (
+                "A policy restricting the licenses of generated dependencies. " +
+                "See [crate.license_policy](#cratelicense_policy)."
+            ),
+        ),
         "lockfile": attr.label(
             doc = (
                 "The path to a file to use for reproducible renderings. " +
@@ -275,6 +297,12 @@ CARGO_BAZEL_REPIN=1 CARGO_BAZEL_REPIN_ONLY=crate_index bazel sync --only=crate_i
             doc = "If stdout and stderr should not be printed to the terminal.",
             default = True,
         ),
+        "recipes": attr.string(
+            doc = (
+                "Default annotations for crates with well known requirements. " +
+                "See [crate.recipes](#craterecipes)."
+            ),
+        ),
         "render_config": attr.string(
             doc = (
                 "The configuration flags to use for rendering. Use `//crate_universe:defs.bzl\\%render_config` to " +
@@ -1,6 +1,6 @@
 """Rules for vendoring Bazel targets into existing workspaces"""
 
-load("//crate_universe/private:generate_utils.bzl", "compile_config", "render_config")
+load("//crate_universe/private:generate_utils.bzl", "compile_config", "parse_target_rules", "render_config")
 load("//crate_universe/private:splicing_utils.bzl", "kebab_case_keys", generate_splicing_config = "splicing_config")
 load("//crate_universe/private:urls.bzl", "CARGO_BAZEL_LABEL")
 load("//rust/platform:triple_mappings.bzl", "SUPPORTED_PLATFORM_TRIPLES")
@@ -153,6 +153,15 @@ def _write_config_file(ctx):
             generate_build_scripts = ctx.attr.generate_build_scripts,
             generate_target_compatible_with = ctx.attr.generate_target_compatible_with,
             supported_platform_triples = ctx.attr.supported_platform_triples,
+            custom_platforms = ctx.attr.custom_platforms,
+            license_policy = ctx.attr.license_policy,
+            duplicate_versions = ctx.attr.duplicate_versions,
+            recipes = ctx.attr.recipes,
+            crate_url_templates = ctx.attr.crate_url_templates,
+            registry_url_templates = ctx.attr.registry_url_templates,
+            generate_sparse_checkouts = ctx.attr.generate_sparse_checkouts,
+            templates = {f.owner.name: f.short_path for f in ctx.files.templates},
+            target_rules = ctx.attr.target_rules,
             repository_name = ctx.attr.repository_name,
             output_pkg = _get_output_package(ctx),
             workspace_name = workspace_name,
@@ -162,7 +171,7 @@ def _write_config_file(ctx):
 
     is_windows = _is_windows(ctx)
     args = ["--config", _runfiles_path(config, is_windows)]
-    runfiles = [config] + ctx.files.manifests
+    runfiles = [config] + ctx.files.manifests + ctx.files.templates
     return args, runfiles
 
 def generate_config_file(
@@ -177,6 +186,15 @@ def generate_config_file(
         output_pkg,
         workspace_name,
         render_config,
+        custom_platforms = {},
+        license_policy = None,
+        duplicate_versions = None,
+        recipes = None,
+        crate_url_templates = [],
+        registry_url_templates = {},
+        generate_sparse_checkouts = False,
+        templates = {},
+        target_rules = {},
         repository_ctx = None):
     """Writes the rendering config to cargo-bazel-config.json.
 
@@ -193,6 +211,21 @@ def generate_config_file(
         output_pkg: The path to the package containing the build files.
         workspace_name (str): The name of the workspace.
         render_config: The render config to use.
+        custom_platforms (dict, optional): A mapping of `config_setting` labels to
+            the json encoded results of `crate.platform`.
+        license_policy (str, optional): The json encoded result of `crate.license_policy`.
+        duplicate_versions (str, optional): The json encoded result of `crate.duplicate_versions`.
+        recipes (str, optional): The json encoded result of `crate.recipes`.
+        crate_url_templates (list, optional): Mirror url templates to download crate sources from.
+            Overrides the value in `render_config` when set.
+        registry_url_templates (dict, optional): Mirror url templates keyed by registry name.
+            Overrides the value in `render_config` when set.
+        generate_sparse_checkouts (bool, optional): Whether to only check out the directory of
+            git sourced crates. Overrides the value in `render_config` when set.
+        templates (dict, optional): The paths of templates which replace the built-in templates,
+            keyed by the name of the template they replace.
+        target_rules (dict, optional): Rules to render in place of the default crate target rules.
+            Overrides the value in `render_config` when set.
         repository_ctx (repository_ctx, optional): A repository context object
             used for enabling certain functionality.
 
@@ -237,6 +270,17 @@ def generate_config_file(
 
     render_config.update(updates)
 
+    if crate_url_templates:
+        render_config["crate_url_templates"] = crate_url_templates
+    if registry_url_templates:
+        render_config["registry_url_templates"] = registry_url_templates
+    if generate_sparse_checkouts:
+        render_config["generate_sparse_checkouts"] = generate_sparse_checkouts
+    if templates:
+        render_config["template_paths"] = templates
+    if target_rules:
+        render_config["target_rules"] = parse_target_rules(target_rules)
+
     # Allow users to override the regen command.
     if "regen_command" not in render_config or not render_config["regen_command"]:
         render_config.update({"regen_command": "bazel run {}".format(ctx.label)})
@@ -249,6 +293,10 @@ def generate_config_file(
         cargo_config = None,
         render_config = render_config,
         supported_platform_triples = supported_platform_triples,
+        custom_platforms = custom_platforms,
+        license_policy = license_policy,
+        duplicate_versions = duplicate_versions,
+        recipes = recipes,
         repository_name = repository_name or ctx.label.name,
         repository_ctx = repository_ctx,
     )
@@ -306,6 +354,15 @@ def _crates_vendor_impl(ctx):
         args.extend(["--buildifier", _runfiles_path(ctx.executable.buildifier, is_windows)])
         cargo_bazel_runfiles.append(ctx.executable.buildifier)
 
+    # Optionally update the `use_repo` calls of a `MODULE.bazel` file
+    if ctx.attr.module_bazel:
+        module_bazel = ctx.file.module_bazel
+        if not module_bazel.is_source or module_bazel.owner.workspace_name:
+            fail("`module_bazel` must be a source file of the main repository: {}".format(module_bazel.owner))
+        if ctx.attr.mode != "remote":
+            fail("`module_bazel` is only supported for the `remote` vendor mode in {}".format(ctx.label))
+        args.extend(["--module-bazel", module_bazel.short_path])
+
     # Optionally include an explicit `bazel` path
     if ctx.attr.bazel:
         args.extend(["--bazel", _runfiles_path(ctx.executable.bazel, is_windows)])
@@ -377,6 +434,29 @@ CRATES_VENDOR_ATTRS = {
         doc = "The path to an existing `Cargo.lock` file",
         allow_single_file = True,
     ),
+    "crate_url_templates": attr.string_list(
+        doc = (
+            "Mirror url templates to download crate sources from, in order, before falling back to each crate's own " +
+            "url. Templates using `{sha256}` are skipped for crates without a known checksum. The available format " +
+            "keys are [`{name}`, `{version}`, `{sha256}`]. Only used by the `remote` vendoring mode."
+        ),
+    ),
+    "custom_platforms": attr.string_dict(
+        doc = (
+            "Platforms with explicit `cfg` values to consider when generating dependencies, keyed by the " +
+            "label of a `config_setting` matching the platform. Labels are rendered verbatim into BUILD files so " +
+            "should include a repository name (eg. `@//platforms:x86_64_avx2`) when those files are in an external " +
+            "repository. The `config_setting` must specialize the constraints of the platform's triple (eg. add a " +
+            "constraint to those of `@rules_rust//rust/platform:x86_64-unknown-linux-gnu`), otherwise `select` " +
+            "statements matching both will be ambiguous. See [crate.platform](#crateplatform)."
+        ),
+    ),
+    "duplicate_versions": attr.string(
+        doc = (
+            "Rules for crates with several semver incompatible versions in the dependency graph. " +
+            "See [crate.duplicate_versions](#crateduplicate_versions)."
+        ),
+    ),
     "generate_binaries": attr.bool(
         doc = (
             "Whether to generate `rust_binary` targets for all the binary crates in every package. " +
@@ -391,10 +471,23 @@ CRATES_VENDOR_ATTRS = {
         ),
         default = True,
     ),
+    "generate_sparse_checkouts": attr.bool(
+        doc = (
+            "Whether to only check out the directory of each crate originating from a git repository, unless the " +
+            "crate has a `sparse_checkout_patterns` annotation. Requires Bazel 7.1 or later."
+        ),
+        default = False,
+    ),
     "generate_target_compatible_with": attr.bool(
         doc = "DEPRECATED: Moved to `render_config`.",
         default = True,
     ),
+    "license_policy": attr.string(
+        doc = (
+            "A policy restricting the licenses of generated dependencies. " +
+            "See [crate.license_policy](#cratelicense_policy)."
+        ),
+    ),
     "manifests": attr.label_list(
         doc = "A list of Cargo manifests (`Cargo.toml` files).",
         allow_files = ["Cargo.toml"],
@@ -411,9 +504,31 @@ CRATES_VENDOR_ATTRS = {
         ],
         default = "remote",
     ),
+    "module_bazel": attr.label(
+        doc = (
+            "A `MODULE.bazel` file of the main repository in which to update the `use_repo` calls for the " +
+            "vendored repositories. The calls are written between the lines `# crates_vendor: begin " +
+            "use_repo(<repository_name>)` and `# crates_vendor: end use_repo(<repository_name>)`, which must " +
+            "already exist. Only supported for the `remote` vendor mode."
+        ),
+        allow_single_file = True,
+    ),
     "packages": attr.string_dict(
         doc = "A set of crates (packages) specifications to depend on. See [crate.spec](#crate.spec).",
     ),
+    "recipes": attr.string(
+        doc = (
+            "Default annotations for crates with well known requirements. " +
+            "See [crate.recipes](#craterecipes)."
+        ),
+    ),
+    "registry_url_templates": attr.string_list_dict(
+        doc = (
+            "A mapping of registry names, as named in the Cargo config (eg. `crates-io`), to mirror url templates " +
+            "used in place of `crate_url_templates` for crates sourced from that registry. The available format " +
+            "keys are [`{name}`, `{version}`, `{sha256}`]."
+        ),
+    ),
     "render_config": attr.string(
         doc = (
             "The configuration flags to use for rendering. Use `//crate_universe:defs.bzl\\%render_config` to " +
@@ -433,6 +548,20 @@ CRATES_VENDOR_ATTRS = {
         doc = "A set of all platform triples to consider when generating dependencies.",
         default = SUPPORTED_PLATFORM_TRIPLES,
     ),
+    "target_rules": attr.string_dict(
+        doc = (
+            "A mapping of the rules rendered for crate targets (`rust_library`, `rust_proc_macro`, `rust_binary` " +
+            "and `cargo_build_script`) to rules to render in their place, in the form '<label to .bzl>:<rule>'. " +
+            "Replacement rules must accept the same attributes as the rule they replace."
+        ),
+    ),
+    "templates": attr.label_list(
+        doc = (
+            "[Tera](https://keats.github.io/tera/) templates which replace the built-in templates named by their " +
+            "path within their package, Eg. `partials/header.j2` or `module_bzl.j2`."
+        ),
+        allow_files = [".j2"],
+    ),
     "vendor_path": attr.string(
         doc = "The path to a directory to write files into. Absolute paths will be treated as relative to the workspace root",
         default = "crates",
@@ -512,6 +641,27 @@ call against the generated workspace. The following table describes how to contr
 | `package_name` | `cargo upgrade --package package_name` |
 | `package_name@1.2.3` | `cargo upgrade --package package_name --precise 1.2.3` |
 
+<a id="#crates_vendor_use_repo"></a>
+
+### Using vendored repositories with bzlmod
+
+In the `remote` vendor mode, the `crate_repositories` macro of the generated `crates.bzl` file returns the
+repositories a module extension should make visible, and the generated `extension.bzl` file defines such an extension,
+also named `crate_repositories`. The repository names are also written to `repositories.json`, and `use_repo` calls
+ready to be pasted into `MODULE.bazel` are written to `use_repo.txt`. These import the repositories from extension
+proxies named `crate_repositories` and, for dev dependencies, `crate_repositories_dev`. For a `crates_vendor` target
+in `//3rdparty` with the default `vendor_path`:
+
+```python
+crate_repositories = use_extension("//3rdparty/crates:extension.bzl", "crate_repositories")
+crate_repositories_dev = use_extension("//3rdparty/crates:extension.bzl", "crate_repositories", dev_dependency = True)
+
+# crates_vendor: begin use_repo(crates_vendor)
+# crates_vendor: end use_repo(crates_vendor)
+```
+
+When the `module_bazel` attribute is set, the lines between the `begin` and `end` comments for the vendored
+repository are replaced with these calls each time the target is run.
 """,
     attrs = CRATES_VENDOR_ATTRS,
     executable = True,
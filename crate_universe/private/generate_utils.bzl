@@ -91,7 +91,12 @@ def render_config(
         platforms_template = "@rules_rust//rust/platform:{triple}",
         regen_command = None,
         vendor_mode = None,
-        generate_rules_license_metadata = False):
+        generate_rules_license_metadata = False,
+        crate_url_templates = [],
+        registry_url_templates = {},
+        templates = [],
+        target_rules = {},
+        generate_sparse_checkouts = False):
     """Various settings used to configure rendered outputs
 
     The template parameters each support a select number of format keys. A description of each key
@@ -105,6 +110,7 @@ def render_config(
     | `version` | The crate version. Eg `1.2.3` |
     | `target` | The library or binary target of the crate |
     | `file` | The basename of a file |
+    | `sha256` | The sha256 checksum of a crate's `.crate` file, when known |
 
     Args:
         build_file_template (str, optional): The base template to use for BUILD file names. The available format keys
@@ -130,6 +136,23 @@ def render_config(
         regen_command (str, optional): An optional command to demonstrate how generated files should be regenerated.
         vendor_mode (str, optional): An optional configuration for rendirng content to be rendered into repositories.
         generate_rules_license_metadata (bool, optional): Whether to generate rules license metedata
+        crate_url_templates (list, optional): Mirror url templates to download crate sources from, in order,
+            before falling back to each crate's own url. Templates using `{sha256}` are skipped for crates
+            without a known checksum. The available format keys are [`{name}`, `{version}`, `{sha256}`].
+        registry_url_templates (dict, optional): A mapping of registry names, as named in the Cargo config
+            (eg. `crates-io`), to mirror url templates used in place of `crate_url_templates` for crates
+            sourced from that registry. The available format keys are [`{name}`, `{version}`, `{sha256}`].
+        templates (list, optional): Labels of [Tera](https://keats.github.io/tera/) templates which replace the
+            built-in templates named by their path within their package, Eg. `partials/header.j2` or
+            `module_bzl.j2`. Each label must name a file rather than a `filegroup`. Not supported by
+            `crates_vendor`, which has a `templates` attribute instead.
+        target_rules (dict, optional): A mapping of the rules rendered for crate targets (`rust_library`,
+            `rust_proc_macro`, `rust_binary` and `cargo_build_script`) to rules to render in their place, in the
+            form '<label to .bzl>:<rule>'. Replacement rules must accept the same attributes as the rule they
+            replace.
+        generate_sparse_checkouts (bool, optional): Whether to only check out the directory of each crate
+            originating from a git repository, unless the crate has a `sparse_checkout_patterns` annotation.
+            Requires Bazel 7.1 or later.
 
     Returns:
         string: A json encoded struct to match the Rust `config::RenderConfig` struct
@@ -146,8 +169,51 @@ def render_config(
         regen_command = regen_command,
         vendor_mode = vendor_mode,
         generate_rules_license_metadata = generate_rules_license_metadata,
+        crate_url_templates = crate_url_templates,
+        registry_url_templates = registry_url_templates,
+        templates = templates,
+        target_rules = parse_target_rules(target_rules),
+        generate_sparse_checkouts = generate_sparse_checkouts,
     ))
 
+_TARGET_RULES = ["rust_library", "rust_proc_macro", "rust_binary", "cargo_build_script"]
+
+def parse_target_rules(target_rules):
+    """Parse the rules to render in place of the default crate target rules.
+
+    Args:
+        target_rules (dict): A mapping of the rules being replaced to strings in the form '<label to .bzl>:<rule>'.
+
+    Returns:
+        dict: A Rust compatible `TargetRules`.
+    """
+    return {
+        kind: _parse_target_rule(kind, value)
+        for kind, value in target_rules.items()
+    }
+
+def _parse_target_rule(kind, value):
+    """Parse a rule to render in place of one of the default crate target rules.
+
+    Args:
+        kind (str): The name of the rule being replaced.
+        value (str): A string in the form '<label to .bzl>:<rule>'.
+
+    Returns:
+        struct: A Rust compatible `CustomRule`.
+    """
+    if kind not in _TARGET_RULES:
+        fail("Unexpected key in `target_rules`: `{}`. Keys must be one of {}.".format(kind, _TARGET_RULES))
+
+    if value.count(":") != 2:
+        fail("Invalid value for `target_rules`.\n{}\nValues must be in the format '<label to .bzl>:<rule>'.".format(value))
+
+    split = value.rsplit(":", 1)
+    return struct(
+        bzl = str(Label(split[0])),
+        rule = split[1],
+    )
+
 def _crate_id(name, version):
     """Creates a `cargo_bazel::config::CrateId`.
 
@@ -232,6 +298,10 @@ def compile_config(
         render_config,
         supported_platform_triples,
         repository_name,
+        custom_platforms = {},
+        license_policy = None,
+        duplicate_versions = None,
+        recipes = None,
         repository_ctx = None):
     """Create a config file for generating crate targets
 
@@ -247,6 +317,11 @@ def compile_config(
         render_config (dict): The deserialized dict of the `render_config` function.
         supported_platform_triples (list): A list of platform triples
         repository_name (str): The name of the repository being generated
+        custom_platforms (dict, optional): A mapping of `config_setting` labels to the json
+            encoded results of `crate.platform`.
+        license_policy (str, optional): The json encoded result of `crate.license_policy`.
+        duplicate_versions (str, optional): The json encoded result of `crate.duplicate_versions`.
+        recipes (str, optional): The json encoded result of `crate.recipes`.
         repository_ctx (repository_ctx, optional): A repository context object used for enabling
             certain functionality.
 
@@ -272,6 +347,24 @@ def compile_config(
     if unexpected:
         fail("The following annotations use `additive_build_file` which is not supported for {}: {}".format(repository_name, unexpected))
 
+    # Locate any recipe files that have been provided.
+    if recipes:
+        recipes = dict(json.decode(recipes))
+        srcs = recipes.pop("srcs", [])
+        if srcs and not repository_ctx:
+            fail("`recipes` with `srcs` are not supported for {}".format(repository_name))
+        recipes.update({"paths": [str(repository_ctx.path(Label(src))) for src in srcs]})
+
+    # Locate any user provided templates. Each is watched for changes by `repository_ctx.path`.
+    templates = render_config.pop("templates", [])
+    if templates:
+        if not repository_ctx:
+            fail("`render_config` with `templates` is not supported for {}".format(repository_name))
+        render_config.setdefault("template_paths", {}).update({
+            Label(template).name: str(repository_ctx.path(Label(template)))
+            for template in templates
+        })
+
     # Deprecated: Apply `generate_target_compatible_with` to `render_config`.
     if not generate_target_compatible_with:
         # buildifier: disable=print
@@ -288,6 +381,13 @@ def compile_config(
             repository_name = repository_name,
         ),
         supported_platform_triples = supported_platform_triples,
+        custom_platforms = {
+            label: json.decode(platform)
+            for label, platform in custom_platforms.items()
+        },
+        license_policy = json.decode(license_policy) if license_policy else None,
+        duplicate_versions = json.decode(duplicate_versions) if duplicate_versions else None,
+        recipes = recipes,
     )
 
     return config
@@ -311,6 +411,10 @@ def generate_config(repository_ctx):
         render_config = _get_render_config(repository_ctx),
         supported_platform_triples = repository_ctx.attr.supported_platform_triples,
         repository_name = repository_ctx.name,
+        custom_platforms = repository_ctx.attr.custom_platforms,
+        license_policy = repository_ctx.attr.license_policy,
+        duplicate_versions = repository_ctx.attr.duplicate_versions,
+        recipes = repository_ctx.attr.recipes,
         repository_ctx = repository_ctx,
     )
 
@@ -336,6 +440,28 @@ def get_lockfiles(repository_ctx):
         bazel = repository_ctx.path(repository_ctx.attr.lockfile) if repository_ctx.attr.lockfile else None,
     )
 
+def repin_requested(repository_ctx, name):
+    """Determine whether a repin was requested through the environment
+
+    Args:
+        repository_ctx (repository_ctx): The rule's or module extension's context object.
+        name (str): The name of the crate universe, which is matched against `CARGO_BAZEL_REPIN_ONLY`.
+
+    Returns:
+        bool: True if dependencies should be re-pinned
+    """
+    for var in REPIN_ENV_VARS:
+        if var in repository_ctx.os.environ and repository_ctx.os.environ[var].lower() not in ["false", "no", "0", "off"]:
+            # If a repin allowlist is present only force repin if name is in list
+            if REPIN_ALLOWLIST_ENV_VAR in repository_ctx.os.environ:
+                indices_to_repin = repository_ctx.os.environ[REPIN_ALLOWLIST_ENV_VAR].split(",")
+                if name in indices_to_repin:
+                    return True
+            else:
+                return True
+
+    return False
+
 def determine_repin(repository_ctx, generator, lockfile_path, config, splicing_manifest, cargo, rustc):
     """Use the `cargo-bazel` binary to determine whether or not dpeendencies need to be re-pinned
 
@@ -353,15 +479,8 @@ def determine_repin(repository_ctx, generator, lockfile_path, config, splicing_m
     """
 
     # If a repin environment variable is set, always repin
-    for var in REPIN_ENV_VARS:
-        if var in repository_ctx.os.environ and repository_ctx.os.environ[var].lower() not in ["false", "no", "0", "off"]:
-            # If a repin allowlist is present only force repin if name is in list
-            if REPIN_ALLOWLIST_ENV_VAR in repository_ctx.os.environ:
-                indices_to_repin = repository_ctx.os.environ[REPIN_ALLOWLIST_ENV_VAR].split(",")
-                if repository_ctx.name in indices_to_repin:
-                    return True
-            else:
-                return True
+    if repin_requested(repository_ctx, repository_ctx.name):
+        return True
 
     # If a deterministic lockfile was not added then always repin
     if not lockfile_path:
@@ -2,21 +2,27 @@
 
 load(":common_utils.bzl", "CARGO_BAZEL_DEBUG", "CARGO_BAZEL_REPIN", "REPIN", "cargo_environ", "execute")
 
-def splicing_config(resolver_version = "2"):
+def splicing_config(resolver_version = "2", local_registry_url_template = None):
     """Various settings used to configure Cargo manifest splicing behavior.
 
     [rv]: https://doc.rust-lang.org/cargo/reference/resolver.html#resolver-versions
+    [lr]: https://doc.rust-lang.org/cargo/reference/source-replacement.html#local-registry-sources
 
     Args:
         resolver_version (str, optional): The [resolver version][rv] to use in generated Cargo
             manifests. This flag is **only** used when splicing a manifest from direct package
             definitions. See `crates_repository::packages`.
+        local_registry_url_template (str, optional): The url of `.crate` files for crates sourced
+            from a [local registry][lr] configured through source replacement in the Cargo config.
+            `{name}`, `{version}`, and `{sha256}` are substituted for each crate. This is required
+            when a local registry is used since its path is specific to the machine repinning.
 
     Returns:
         str: A json encoded string of the parameters provided
     """
     return json.encode(struct(
         resolver_version = resolver_version,
+        local_registry_url_template = local_registry_url_template,
     ))
 
 def kebab_case_keys(data):
@@ -9,14 +9,28 @@ CARGO_BAZEL_SRCS = [
     Label("//crate_universe:src/api.rs"),
     Label("//crate_universe:src/api/lockfile.rs"),
     Label("//crate_universe:src/cli.rs"),
+    Label("//crate_universe:src/cli/audit.rs"),
+    Label("//crate_universe:src/cli/diff.rs"),
     Label("//crate_universe:src/cli/generate.rs"),
+    Label("//crate_universe:src/cli/migrate.rs"),
     Label("//crate_universe:src/cli/query.rs"),
+    Label("//crate_universe:src/cli/sbom.rs"),
     Label("//crate_universe:src/cli/splice.rs"),
     Label("//crate_universe:src/cli/vendor.rs"),
+    Label("//crate_universe:src/cli/why.rs"),
     Label("//crate_universe:src/config.rs"),
+    Label("//crate_universe:src/config/recipes.rs"),
+    Label("//crate_universe:src/config/recipes/libz-sys.json"),
+    Label("//crate_universe:src/config/recipes/lzma-sys.json"),
+    Label("//crate_universe:src/config/recipes/openssl-sys.json"),
+    Label("//crate_universe:src/config/recipes/rdkafka-sys.json"),
     Label("//crate_universe:src/context.rs"),
     Label("//crate_universe:src/context/crate_context.rs"),
+    Label("//crate_universe:src/context/duplicates.rs"),
+    Label("//crate_universe:src/context/licenses.rs"),
+    Label("//crate_universe:src/context/lints.rs"),
     Label("//crate_universe:src/context/platforms.rs"),
+    Label("//crate_universe:src/context/profiles.rs"),
     Label("//crate_universe:src/lib.rs"),
     Label("//crate_universe:src/lockfile.rs"),
     Label("//crate_universe:src/main.rs"),
@@ -29,8 +43,10 @@ CARGO_BAZEL_SRCS = [
     Label("//crate_universe:src/rendering/templates/partials/header.j2"),
     Label("//crate_universe:src/rendering/templates/partials/module/aliases_map.j2"),
     Label("//crate_universe:src/rendering/templates/partials/module/deps_map.j2"),
+    Label("//crate_universe:src/rendering/templates/partials/module/lints_map.j2"),
     Label("//crate_universe:src/rendering/templates/partials/module/repo_git.j2"),
     Label("//crate_universe:src/rendering/templates/partials/module/repo_http.j2"),
+    Label("//crate_universe:src/rendering/templates/vendor_extension.j2"),
     Label("//crate_universe:src/rendering/templates/vendor_module.j2"),
     Label("//crate_universe:src/rendering/verbatim/alias_rules.bzl"),
     Label("//crate_universe:src/select.rs"),
@@ -1,23 +1,43 @@
 //! The lockfile::public module represents a reasonable stable API for inspecting the contents of a lockfile which others can code against.
 
-use std::collections::BTreeSet;
-use std::fs::File;
-use std::io::BufReader;
+use std::collections::{BTreeMap, BTreeSet};
 use std::path::Path;
 
 use anyhow::Result;
 use serde::Deserialize;
 
+use crate::config::Commitish;
 pub use crate::config::CrateId;
-use crate::context::crate_context::{CrateDependency, Rule};
+use crate::context::crate_context::{dependencies, BuildScriptAttributes, CrateDependency, Rule};
 use crate::context::{CommonAttributes, Context};
+use crate::lockfile::{BazelLockfile, DeriveInputs};
+use crate::metadata::SourceAnnotation;
 use crate::select::Select;
 
 /// Parse a lockfile at a path on disk.
+///
+/// Lockfiles which record only the resolved inputs of the dependency graph (as written by
+/// `cargo-bazel migrate`) must be parsed with [parse_resolved] instead.
 pub fn parse(path: &Path) -> Result<impl CargoBazelLockfile> {
-    let reader = BufReader::new(File::open(path)?);
-    let lockfile: CargoBazelLockfileImpl = serde_json::from_reader(reader)?;
-    Ok(lockfile)
+    let context = BazelLockfile::try_from_path(path)?.into_context(None)?;
+    Ok(CargoBazelLockfileImpl(context))
+}
+
+/// Parse a lockfile at a path on disk in any format.
+///
+/// The dependency graph of a lockfile which records only its resolved inputs is derived
+/// by splicing the workspace described by `config` and `splicing_manifest` (the files the
+/// repository rules pass to `cargo-bazel`) and running `cargo metadata` with the given binaries.
+pub fn parse_resolved(
+    path: &Path,
+    config: &Path,
+    splicing_manifest: &Path,
+    cargo: &Path,
+    rustc: &Path,
+) -> Result<impl CargoBazelLockfile> {
+    let inputs = DeriveInputs::try_from_paths(config, splicing_manifest, cargo, rustc)?;
+    let context = BazelLockfile::try_from_path(path)?.into_context(Some(&inputs))?;
+    Ok(CargoBazelLockfileImpl(context))
 }
 
 /// `CargoBazelLockfile` provides a view over `cargo-bazel`'s lockfile format,
@@ -33,6 +53,46 @@ pub trait CargoBazelLockfile {
 
     /// Get information about a specific crate (which may be in the local workspace, or an external dependency).
     fn crate_info(&self, crate_id: &CrateId) -> Option<CrateInfo>;
+
+    /// Get every crate in the lockfile, including the members of the local workspace.
+    ///
+    /// By default, these are the crates reachable from the workspace members.
+    fn crates(&self) -> BTreeSet<CrateId> {
+        let mut crates = BTreeSet::new();
+        let mut pending: Vec<CrateId> = self.workspace_members().into_iter().collect();
+        while let Some(crate_id) = pending.pop() {
+            if let Some(info) = self.crate_info(&crate_id) {
+                pending.extend(
+                    info.dependency_ids()
+                        .into_iter()
+                        .filter(|id| !crates.contains(id)),
+                );
+            }
+            crates.insert(crate_id);
+        }
+        crates
+    }
+
+    /// Get the platforms which satisfy each configuration used in the `select`s of dependencies,
+    /// features and other attributes. Platforms are either target triples (eg. `x86_64-unknown-linux-gnu`)
+    /// or the labels of custom platforms.
+    ///
+    /// By default, no configurations are known.
+    fn conditions(&self) -> BTreeMap<String, BTreeSet<String>> {
+        BTreeMap::new()
+    }
+
+    /// Get the crates which directly depend on a specific crate, with any kind of dependency,
+    /// on any platform.
+    fn reverse_dependencies(&self, crate_id: &CrateId) -> BTreeSet<CrateId> {
+        self.crates()
+            .into_iter()
+            .filter(|id| {
+                self.crate_info(id)
+                    .is_some_and(|info| info.dependency_ids().contains(crate_id))
+            })
+            .collect()
+    }
 }
 
 #[derive(Deserialize)]
@@ -55,8 +115,49 @@ impl CargoBazelLockfile for CargoBazelLockfileImpl {
                 .iter()
                 .any(|t| matches!(t, Rule::ProcMacro(_))),
             common_attributes: crate_context.common_attrs.clone(),
+            build_script_attributes: crate_context.build_script_attrs.clone(),
+            package_url: crate_context.package_url.clone(),
+            source: crate_context.repository.clone(),
+            license: crate_context.license.clone(),
+            license_ids: crate_context.license_ids.clone(),
+            license_file: crate_context.license_file.clone(),
+            extra_aliased_targets: crate_context.extra_aliased_targets.clone(),
         })
     }
+
+    fn crates(&self) -> BTreeSet<CrateId> {
+        self.0.crates.keys().cloned().collect()
+    }
+
+    fn conditions(&self) -> BTreeMap<String, BTreeSet<String>> {
+        let mut conditions: BTreeMap<String, BTreeSet<String>> = self
+            .0
+            .conditions
+            .iter()
+            .map(|(condition, triples)| {
+                (
+                    condition.clone(),
+                    triples.iter().map(|triple| triple.to_bazel()).collect(),
+                )
+            })
+            .collect();
+        for (condition, platforms) in &self.0.custom_conditions {
+            conditions
+                .entry(condition.clone())
+                .or_default()
+                .extend(platforms.iter().cloned());
+        }
+        conditions
+    }
+
+    fn reverse_dependencies(&self, crate_id: &CrateId) -> BTreeSet<CrateId> {
+        self.0
+            .crates
+            .iter()
+            .filter(|(_, krate)| krate.dependency_ids().contains(crate_id))
+            .map(|(id, _)| id.clone())
+            .collect()
+    }
 }
 
 /// Information about a crate (which may be in-workspace or a dependency).
@@ -68,6 +169,14 @@ pub struct CrateInfo {
     is_proc_macro: bool,
 
     common_attributes: CommonAttributes,
+    build_script_attributes: Option<BuildScriptAttributes>,
+
+    package_url: Option<String>,
+    source: Option<SourceAnnotation>,
+    license: Option<String>,
+    license_ids: BTreeSet<String>,
+    license_file: Option<String>,
+    extra_aliased_targets: BTreeMap<String, String>,
 }
 
 impl CrateInfo {
@@ -111,15 +220,208 @@ impl CrateInfo {
     pub fn proc_macro_dev_deps(&self) -> Select<BTreeSet<CrateDependency>> {
         self.common_attributes.proc_macro_deps_dev.clone()
     }
+
+    /// The Cargo features enabled for the crate.
+    pub fn features(&self) -> Select<BTreeSet<String>> {
+        self.common_attributes.crate_features.clone()
+    }
+
+    /// The Rust edition of the crate.
+    pub fn edition(&self) -> &str {
+        &self.common_attributes.edition
+    }
+
+    /// Information about the crate's build script, if it has one which is built.
+    pub fn build_script(&self) -> Option<BuildScriptInfo> {
+        self.build_script_attributes
+            .clone()
+            .map(|attributes| BuildScriptInfo { attributes })
+    }
+
+    /// The url of the crate's homepage or repository, as declared in its manifest.
+    pub fn package_url(&self) -> Option<&str> {
+        self.package_url.as_deref()
+    }
+
+    /// Where the crate's source code is downloaded from. Crates in the local workspace have no source.
+    pub fn source(&self) -> Option<CrateSource> {
+        self.source.as_ref().map(CrateSource::from)
+    }
+
+    /// The SPDX license expression of the crate, as declared in its manifest.
+    pub fn license(&self) -> Option<&str> {
+        self.license.as_deref()
+    }
+
+    /// The SPDX license identifiers in the crate's license expression.
+    pub fn license_ids(&self) -> &BTreeSet<String> {
+        &self.license_ids
+    }
+
+    /// The path to the crate's license file, relative to the root of the crate, if it declares one.
+    pub fn license_file(&self) -> Option<&str> {
+        self.license_file.as_deref()
+    }
+
+    /// The names the crate refers to its dependencies by, where they differ from the dependency's
+    /// crate name. This includes the dependencies of the crate's build script.
+    pub fn aliases(&self) -> BTreeMap<CrateId, String> {
+        dependencies(
+            &self.common_attributes,
+            self.build_script_attributes.as_ref(),
+        )
+        .filter_map(|(_, _, dep)| dep.alias.map(|alias| (dep.id, alias)))
+        .collect()
+    }
+
+    /// Extra targets of the crate which are aliased in the crates repository, keyed by alias.
+    pub fn extra_aliased_targets(&self) -> &BTreeMap<String, String> {
+        &self.extra_aliased_targets
+    }
+
+    /// The crates the crate directly depends on, with any kind of dependency, on any platform.
+    /// This includes the dependencies of the crate's build script.
+    pub fn dependency_ids(&self) -> BTreeSet<CrateId> {
+        dependencies(
+            &self.common_attributes,
+            self.build_script_attributes.as_ref(),
+        )
+        .map(|(_, _, dep)| dep.id)
+        .collect()
+    }
+}
+
+/// Information about the build script of a crate.
+#[derive(PartialEq, Eq, Debug)]
+pub struct BuildScriptInfo {
+    attributes: BuildScriptAttributes,
+}
+
+impl BuildScriptInfo {
+    /// Dependencies required to compile the build script, without procedural macro dependencies.
+    pub fn deps(&self) -> Select<BTreeSet<CrateDependency>> {
+        self.attributes.deps.clone()
+    }
+
+    /// Procedural macro dependencies required to compile the build script.
+    pub fn proc_macro_deps(&self) -> Select<BTreeSet<CrateDependency>> {
+        self.attributes.proc_macro_deps.clone()
+    }
+
+    /// Dependencies which declare a `links` key, whose metadata is made available to the build script.
+    pub fn link_deps(&self) -> Select<BTreeSet<CrateDependency>> {
+        self.attributes.link_deps.clone()
+    }
+
+    /// Labels of the data available to the build script when it runs.
+    pub fn data(&self) -> Select<BTreeSet<String>> {
+        self.attributes.data.clone().map(|label| label.to_string())
+    }
+
+    /// Labels of the tools available to the build script when it runs.
+    pub fn tools(&self) -> Select<BTreeSet<String>> {
+        self.attributes.tools.clone().map(|label| label.to_string())
+    }
+
+    /// Environment variables set when running the build script.
+    pub fn build_script_env(&self) -> Select<BTreeMap<String, String>> {
+        self.attributes.build_script_env.clone()
+    }
+
+    /// Environment variables set when compiling the build script.
+    pub fn rustc_env(&self) -> Select<BTreeMap<String, String>> {
+        self.attributes.rustc_env.clone()
+    }
+
+    /// The name of the native library the crate links to, from the `links` key of its manifest.
+    pub fn links(&self) -> Option<&str> {
+        self.attributes.links.as_deref()
+    }
+}
+
+/// Where the source code of a crate is downloaded from.
+#[derive(PartialEq, Eq, Debug, Clone)]
+#[non_exhaustive]
+pub enum CrateSource {
+    /// An archive downloaded over http, typically from a crate registry.
+    Http {
+        /// The url of the archive.
+        url: String,
+
+        /// The sha256 checksum of the archive, if known.
+        sha256: Option<String>,
+
+        /// The name of the registry the crate is from, as named in the Cargo config.
+        registry: Option<String>,
+    },
+
+    /// A git repository.
+    Git {
+        /// The url of the repository.
+        remote: String,
+
+        /// The commit, tag or branch checked out.
+        reference: GitReference,
+
+        /// The directory of the crate within the repository.
+        strip_prefix: Option<String>,
+    },
+}
+
+/// A reference to a commit of a git repository.
+#[derive(PartialEq, Eq, Debug, Clone)]
+#[non_exhaustive]
+pub enum GitReference {
+    /// A tag.
+    Tag(String),
+
+    /// The HEAD of a branch.
+    Branch(String),
+
+    /// A specific revision.
+    Rev(String),
+}
+
+impl From<&SourceAnnotation> for CrateSource {
+    fn from(source: &SourceAnnotation) -> Self {
+        match source {
+            SourceAnnotation::Http {
+                url,
+                sha256,
+                registry,
+                ..
+            } => CrateSource::Http {
+                url: url.clone(),
+                sha256: sha256.clone(),
+                registry: registry.clone(),
+            },
+            SourceAnnotation::Git {
+                remote,
+                commitish,
+                strip_prefix,
+                ..
+            } => CrateSource::Git {
+                remote: remote.clone(),
+                reference: match commitish {
+                    Commitish::Tag(tag) => GitReference::Tag(tag.clone()),
+                    Commitish::Branch(branch) => GitReference::Branch(branch.clone()),
+                    Commitish::Rev(rev) => GitReference::Rev(rev.clone()),
+                },
+                strip_prefix: strip_prefix.clone(),
+            },
+        }
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use super::{parse, CargoBazelLockfile};
+    use super::{parse, CargoBazelLockfile, CrateSource, GitReference};
     use crate::config::CrateId;
     use crate::context::crate_context::CrateDependency;
+    use crate::lockfile::ResolvedLockfile;
+    use crate::splicing::WorkspaceMetadata;
     use semver::Version;
-    use std::collections::BTreeSet;
+    use std::collections::{BTreeMap, BTreeSet};
 
     #[test]
     fn exercise_public_lockfile_api() {
@@ -217,4 +519,149 @@ mod test {
             .collect::<BTreeSet<_>>(),
         );
     }
+
+    #[test]
+    fn exercise_extended_public_lockfile_api() {
+        let runfiles = runfiles::Runfiles::create().unwrap();
+        let path = runfiles::rlocation!(
+            runfiles, "rules_rust/crate_universe/test_data/cargo_bazel_lockfile/multi_package-cargo-bazel-lock.json");
+
+        let parsed = parse(&path).unwrap();
+        assert!(parsed.crates().len() > parsed.workspace_members().len());
+        assert!(parsed.conditions()["cfg(unix)"].contains("x86_64-unknown-linux-gnu"));
+
+        let async_channel = CrateId::new("async-channel".to_owned(), Version::new(1, 8, 0));
+        let got_async_channel = parsed.crate_info(&async_channel).unwrap();
+        assert_eq!(
+            got_async_channel.source(),
+            Some(CrateSource::Http {
+                url: "https://crates.io/api/v1/crates/async-channel/1.8.0/download".to_owned(),
+                sha256: Some(
+                    "cf46fee83e5ccffc220104713af3292ff9bc7c64c7de289f66dae8e38d826833".to_owned()
+                ),
+                registry: None,
+            })
+        );
+        assert_eq!(got_async_channel.license(), Some("Apache-2.0 OR MIT"));
+        assert_eq!(
+            got_async_channel.license_ids(),
+            &BTreeSet::from(["Apache-2.0".to_owned(), "MIT".to_owned()])
+        );
+        assert_eq!(
+            got_async_channel.package_url(),
+            Some("https://github.com/smol-rs/async-channel")
+        );
+        assert_eq!(
+            parsed
+                .reverse_dependencies(&async_channel)
+                .into_iter()
+                .map(|id| id.name)
+                .collect::<Vec<_>>(),
+            vec![
+                "async-global-executor",
+                "async-std",
+                "blocking",
+                "isahc",
+                "sluice"
+            ]
+        );
+
+        let httpmock = CrateId::new("httpmock".to_owned(), Version::new(0, 6, 7));
+        assert_eq!(
+            parsed.crate_info(&httpmock).unwrap().source(),
+            Some(CrateSource::Git {
+                remote: "https://github.com/alexliesenfeld/httpmock.git".to_owned(),
+                reference: GitReference::Rev("9ecf35255ee154986bc36d06473f1fa088586ad9".to_owned()),
+                strip_prefix: None,
+            })
+        );
+
+        let libc = CrateId::new("libc".to_owned(), Version::new(0, 2, 139));
+        assert_eq!(
+            parsed.crate_info(&libc).unwrap().features().values(),
+            vec!["default".to_owned(), "std".to_owned()]
+        );
+
+        let value_bag = CrateId::new(
+            "value-bag".to_owned(),
+            Version::parse("1.0.0-alpha.9").unwrap(),
+        );
+        let got_value_bag = parsed.crate_info(&value_bag).unwrap();
+        let version_check = CrateId::new("version_check".to_owned(), Version::new(0, 9, 4));
+        assert_eq!(
+            got_value_bag.aliases(),
+            BTreeMap::from([(version_check.clone(), "rustc".to_owned())])
+        );
+        let build_script = got_value_bag.build_script().unwrap();
+        assert_eq!(
+            build_script.deps().values(),
+            vec![CrateDependency {
+                id: version_check,
+                target: String::from("version_check"),
+                alias: Some(String::from("rustc")),
+            }]
+        );
+        assert_eq!(build_script.links(), None);
+
+        let libz_sys = CrateId::new("libz-sys".to_owned(), Version::new(1, 1, 8));
+        assert_eq!(
+            parsed
+                .crate_info(&libz_sys)
+                .unwrap()
+                .build_script()
+                .unwrap()
+                .links(),
+            Some("z")
+        );
+    }
+
+    #[test]
+    fn parse_resolved_lockfile_requires_inputs() {
+        let resolved = ResolvedLockfile::new(
+            crate::test::lockfile::common(),
+            WorkspaceMetadata::default(),
+        );
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.as_ref().join("cargo-bazel-lock.json");
+        std::fs::write(&path, serde_json::to_string_pretty(&resolved).unwrap()).unwrap();
+
+        let error = parse(&path).err().unwrap();
+        assert!(
+            error.to_string().contains("resolved lockfile"),
+            "Unexpected error: {error}"
+        );
+    }
+
+    #[test]
+    fn default_lockfile_methods() {
+        /// A lockfile which only implements the required methods.
+        struct MinimalLockfile<T>(T);
+
+        impl<T: CargoBazelLockfile> CargoBazelLockfile for MinimalLockfile<T> {
+            fn workspace_members(&self) -> BTreeSet<CrateId> {
+                self.0.workspace_members()
+            }
+
+            fn crate_info(&self, crate_id: &CrateId) -> Option<super::CrateInfo> {
+                self.0.crate_info(crate_id)
+            }
+        }
+
+        let runfiles = runfiles::Runfiles::create().unwrap();
+        let path = runfiles::rlocation!(
+            runfiles, "rules_rust/crate_universe/test_data/cargo_bazel_lockfile/multi_package-cargo-bazel-lock.json");
+
+        let parsed = parse(&path).unwrap();
+        let minimal = MinimalLockfile(parse(&path).unwrap());
+
+        assert_eq!(minimal.crates(), parsed.crates());
+        assert!(minimal.conditions().is_empty());
+
+        let async_channel = CrateId::new("async-channel".to_owned(), Version::new(1, 8, 0));
+        assert_eq!(
+            minimal.reverse_dependencies(&async_channel),
+            parsed.reverse_dependencies(&async_channel)
+        );
+    }
 }
@@ -1,11 +1,19 @@
 //! Command line interface entry points and utilities
 
+mod audit;
+mod diff;
 mod generate;
+mod migrate;
 mod query;
+mod sbom;
 mod splice;
 mod vendor;
+mod why;
 
-use clap::Parser;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context as AnyhowContext;
+use clap::{Args, Parser};
 use tracing::{Level, Subscriber};
 use tracing_subscriber::fmt::format::{Format, Full};
 use tracing_subscriber::fmt::time::SystemTime;
@@ -13,16 +21,29 @@ use tracing_subscriber::fmt::{FormatEvent, FormatFields};
 use tracing_subscriber::registry::LookupSpan;
 use tracing_subscriber::FmtSubscriber;
 
+use crate::context::Context;
+use crate::lockfile::{BazelLockfile, DeriveInputs};
+
+pub use self::audit::AuditOptions;
+pub use self::diff::DiffOptions;
 pub use self::generate::GenerateOptions;
+pub use self::migrate::MigrateOptions;
 pub use self::query::QueryOptions;
+pub use self::sbom::{SbomFormat, SbomOptions};
 pub use self::splice::SpliceOptions;
 pub use self::vendor::VendorOptions;
+pub use self::why::WhyOptions;
 
 // Entrypoints
+pub use audit::audit;
+pub use diff::diff;
 pub use generate::generate;
+pub use migrate::migrate;
 pub use query::query;
+pub use sbom::sbom;
 pub use splice::splice;
 pub use vendor::vendor;
+pub use why::why;
 
 #[derive(Parser, Debug)]
 #[clap(
@@ -42,16 +63,80 @@ pub enum Options {
 
     /// Vendor BUILD files to the workspace with either repository definitions or `cargo vendor` generated sources.
     Vendor(VendorOptions),
+
+    /// Explain the differences between two cargo-bazel lockfiles.
+    Diff(DiffOptions),
+
+    /// Convert a cargo-bazel lockfile into one which only records resolved inputs.
+    Migrate(MigrateOptions),
+
+    /// Produce a Software Bill of Materials from a cargo-bazel lockfile.
+    Sbom(SbomOptions),
+
+    /// Report RustSec advisories affecting the crates in a cargo-bazel lockfile.
+    Audit(AuditOptions),
+
+    /// Explain why a crate is in the dependency graph of a cargo-bazel lockfile.
+    Why(WhyOptions),
 }
 
 // Convenience wrappers to avoid dependencies in the binary
 pub type Result<T> = anyhow::Result<T>;
 
+/// Command line options for subcommands which read the dependency graph of a cargo-bazel
+/// lockfile. Resolved lockfiles (see the `migrate` subcommand) don't contain the graph, so
+/// it's derived from the same inputs the repository rule uses, which are only required
+/// when reading one.
+#[derive(Args, Debug)]
+pub struct DeriveOptions {
+    /// The config file the lockfile was resolved with
+    #[clap(long)]
+    pub config: Option<PathBuf>,
+
+    /// The splicing manifest the lockfile was resolved with
+    #[clap(long)]
+    pub splicing_manifest: Option<PathBuf>,
+
+    /// The path to a Cargo binary to use for gathering metadata
+    #[clap(long, env = "CARGO")]
+    pub cargo: Option<PathBuf>,
+
+    /// The path to a rustc binary for use with Cargo
+    #[clap(long, env = "RUSTC")]
+    pub rustc: Option<PathBuf>,
+}
+
+impl DeriveOptions {
+    /// Load the [Context] of a cargo-bazel lockfile in any format.
+    pub(crate) fn load_context(&self, lockfile: &Path) -> Result<Context> {
+        let lockfile_content = BazelLockfile::try_from_path(lockfile)
+            .with_context(|| format!("Failed to load lockfile '{}'", lockfile.display()))?;
+
+        let inputs = match (
+            &self.config,
+            &self.splicing_manifest,
+            &self.cargo,
+            &self.rustc,
+        ) {
+            (Some(config), Some(splicing_manifest), Some(cargo), Some(rustc)) => Some(
+                DeriveInputs::try_from_paths(config, splicing_manifest, cargo, rustc)?,
+            ),
+            _ => None,
+        };
+
+        lockfile_content
+            .into_context(inputs.as_ref())
+            .with_context(|| format!("Failed to read lockfile '{}'", lockfile.display()))
+    }
+}
+
 pub fn parse_args() -> Options {
     Options::parse()
 }
 
-const EXPECTED_LOGGER_NAMES: [&str; 4] = ["Generate", "Splice", "Query", "Vendor"];
+const EXPECTED_LOGGER_NAMES: [&str; 9] = [
+    "Generate", "Splice", "Query", "Vendor", "Diff", "Migrate", "Sbom", "Audit", "Why",
+];
 
 /// A wrapper for the tracing-subscriber default [FormatEvent]
 /// that prepends the name of the active CLI option.
@@ -0,0 +1,480 @@
+//! The cli entrypoint for the `audit` subcommand
+
+use std::collections::{BTreeMap, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context as AnyhowContext, Result};
+use clap::Parser;
+use semver::VersionReq;
+use serde::Deserialize;
+
+use crate::cli::DeriveOptions;
+use crate::config::CrateId;
+use crate::context::Context;
+use crate::metadata::SourceAnnotation;
+
+/// Command line options for the `audit` subcommand
+#[derive(Parser, Debug)]
+#[clap(about = "Command line options for the `audit` subcommand", version)]
+pub struct AuditOptions {
+    /// The path to a cargo-bazel lockfile
+    #[clap(long)]
+    pub lockfile: PathBuf,
+
+    #[clap(flatten)]
+    pub derive: DeriveOptions,
+
+    /// The path to a local checkout of the [RustSec advisory database](https://github.com/rustsec/advisory-db)
+    #[clap(long)]
+    pub advisory_db: PathBuf,
+
+    /// The id of an advisory to ignore. May be passed multiple times.
+    #[clap(long = "ignore")]
+    pub ignore: Vec<String>,
+
+    /// Return an error if any vulnerabilities are found. Informational
+    /// advisories (eg. unmaintained crates) are reported but never fail.
+    #[clap(long)]
+    pub deny: bool,
+}
+
+/// Report any RustSec advisories affecting the crates in a cargo-bazel lockfile
+pub fn audit(opt: AuditOptions) -> Result<()> {
+    let context = opt.derive.load_context(&opt.lockfile)?;
+
+    if !opt.advisory_db.join("crates").is_dir() {
+        bail!(
+            "'{}' does not appear to be a checkout of the RustSec advisory database",
+            opt.advisory_db.display()
+        );
+    }
+
+    let reachable = reachable_crates(&context);
+    let findings = find_advisories(&context, &reachable, &opt.advisory_db, &opt.ignore)?;
+
+    let vulnerabilities = findings
+        .iter()
+        .filter(|finding| finding.advisory.advisory.informational.is_none())
+        .count();
+
+    if findings.is_empty() {
+        println!(
+            "No advisories found for {} crates in '{}'",
+            reachable.len(),
+            opt.lockfile.display()
+        );
+        return Ok(());
+    }
+
+    println!(
+        "Found {} vulnerabilities and {} informational advisories for {} crates in '{}'",
+        vulnerabilities,
+        findings.len() - vulnerabilities,
+        reachable.len(),
+        opt.lockfile.display()
+    );
+    for finding in &findings {
+        println!("\n{}", finding.report(&reachable));
+    }
+
+    if opt.deny && vulnerabilities > 0 {
+        bail!(
+            "{} vulnerable crates were found in '{}'",
+            vulnerabilities,
+            opt.lockfile.display()
+        );
+    }
+
+    Ok(())
+}
+
+/// The contents of a RustSec advisory.
+#[derive(Debug, Deserialize)]
+struct Advisory {
+    advisory: AdvisoryMetadata,
+
+    #[serde(default)]
+    versions: AdvisoryVersions,
+}
+
+#[derive(Debug, Deserialize)]
+struct AdvisoryMetadata {
+    id: String,
+
+    package: String,
+
+    /// The title of the advisory. In markdown advisories this is the first heading
+    /// of the document rather than a field of the front matter.
+    #[serde(default)]
+    title: Option<String>,
+
+    #[serde(default)]
+    url: Option<String>,
+
+    #[serde(default)]
+    aliases: Vec<String>,
+
+    /// The kind of informational advisory (eg. `unmaintained` or `unsound`), if this
+    /// is not a vulnerability.
+    #[serde(default)]
+    informational: Option<String>,
+
+    /// The date the advisory was withdrawn, if it was.
+    #[serde(default)]
+    withdrawn: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct AdvisoryVersions {
+    #[serde(default)]
+    patched: Vec<String>,
+
+    #[serde(default)]
+    unaffected: Vec<String>,
+}
+
+impl Advisory {
+    /// Parse an advisory from either the markdown format (a fenced TOML front matter
+    /// followed by a description) or the legacy TOML format.
+    fn try_from_path(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read advisory '{}'", path.display()))?;
+
+        let is_markdown = path.extension().map(|ext| ext == "md").unwrap_or(false);
+        let (front_matter, body) = match is_markdown {
+            true => {
+                let content = content
+                    .trim_start()
+                    .strip_prefix("```toml")
+                    .ok_or_else(|| anyhow!("Advisory '{}' has no front matter", path.display()))?;
+                content.split_once("```").ok_or_else(|| {
+                    anyhow!(
+                        "Advisory '{}' has unterminated front matter",
+                        path.display()
+                    )
+                })?
+            }
+            false => (content.as_str(), ""),
+        };
+
+        let mut advisory: Advisory = toml::from_str(front_matter)
+            .with_context(|| format!("Failed to parse advisory '{}'", path.display()))?;
+
+        if advisory.advisory.title.is_none() {
+            advisory.advisory.title = body
+                .lines()
+                .find_map(|line| line.strip_prefix("# "))
+                .map(|title| title.trim().to_owned());
+        }
+
+        Ok(advisory)
+    }
+
+    /// Determine whether or not a version of the advisory's crate is affected.
+    fn affects(&self, version: &semver::Version) -> Result<bool> {
+        for req in self
+            .versions
+            .patched
+            .iter()
+            .chain(self.versions.unaffected.iter())
+        {
+            let req = VersionReq::parse(req).with_context(|| {
+                format!(
+                    "Invalid version requirement `{}` in {}",
+                    req, self.advisory.id
+                )
+            })?;
+            if req.matches(version) {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+/// An advisory affecting a crate in the lockfile.
+#[derive(Debug)]
+struct Finding<'a> {
+    id: &'a CrateId,
+    advisory: Advisory,
+}
+
+impl Finding<'_> {
+    fn report(&self, reachable: &BTreeMap<&CrateId, Option<&CrateId>>) -> String {
+        let advisory = &self.advisory.advisory;
+
+        let mut lines = vec![match &advisory.title {
+            Some(title) => format!("{}: {}", advisory.id, title),
+            None => advisory.id.clone(),
+        }];
+        if let Some(informational) = &advisory.informational {
+            lines.push(format!("  Kind:     {informational}"));
+        }
+        lines.push(format!("  Crate:    {}", self.id));
+        if !advisory.aliases.is_empty() {
+            lines.push(format!("  Aliases:  {}", advisory.aliases.join(", ")));
+        }
+        if let Some(url) = &advisory.url {
+            lines.push(format!("  Url:      {url}"));
+        }
+        lines.push(format!(
+            "  Solution: {}",
+            match self.advisory.versions.patched.is_empty() {
+                true => "No fixed upgrade is available".to_owned(),
+                false => format!("Upgrade to {}", self.advisory.versions.patched.join(" OR ")),
+            }
+        ));
+
+        let mut path = vec![self.id];
+        while let Some(Some(parent)) = reachable.get(path[path.len() - 1]) {
+            path.push(parent);
+        }
+        path.reverse();
+        lines.push(format!(
+            "  Path:     {}",
+            path.iter()
+                .map(|id| id.to_string())
+                .collect::<Vec<_>>()
+                .join(" -> ")
+        ));
+
+        lines.join("\n")
+    }
+}
+
+/// Collect all crates reachable from the workspace members on any of the platforms
+/// the lockfile was generated for, each mapped to the crate which depends on it on
+/// the shortest path from a workspace member.
+fn reachable_crates(context: &Context) -> BTreeMap<&CrateId, Option<&CrateId>> {
+    // A dependency is only reachable if its configuration is satisfied by at
+    // least one supported platform triple or custom platform.
+    let is_reachable = |configuration: &Option<String>| match configuration {
+        None => true,
+        Some(configuration) => {
+            context
+                .conditions
+                .get(configuration)
+                .map(|triples| !triples.is_empty())
+                .unwrap_or(false)
+                || context
+                    .custom_conditions
+                    .get(configuration)
+                    .map(|platforms| !platforms.is_empty())
+                    .unwrap_or(false)
+        }
+    };
+
+    let mut parents: BTreeMap<&CrateId, Option<&CrateId>> = BTreeMap::new();
+    let mut queue: VecDeque<&CrateId> = VecDeque::new();
+    for id in context.workspace_members.keys() {
+        if let Some((id, _)) = context.crates.get_key_value(id) {
+            parents.insert(id, None);
+            queue.push_back(id);
+        }
+    }
+
+    while let Some(id) = queue.pop_front() {
+        // Dev dependencies are only considered for workspace members.
+        let include_dev_deps = context.workspace_members.contains_key(id);
+        for dep in context.crates[id]
+            .dependencies()
+            .filter(|(kind, configuration, _)| {
+                (include_dev_deps || !kind.is_dev()) && is_reachable(configuration)
+            })
+            .filter_map(|(_, _, dep)| context.crates.get_key_value(&dep.id).map(|(id, _)| id))
+        {
+            if !parents.contains_key(dep) {
+                parents.insert(dep, Some(id));
+                queue.push_back(dep);
+            }
+        }
+    }
+
+    parents
+}
+
+/// Match each reachable crate against the advisories for its package.
+fn find_advisories<'a>(
+    context: &'a Context,
+    reachable: &BTreeMap<&'a CrateId, Option<&'a CrateId>>,
+    advisory_db: &Path,
+    ignore: &[String],
+) -> Result<Vec<Finding<'a>>> {
+    let mut findings = Vec::new();
+    for id in reachable.keys() {
+        // Advisories only describe crates published to a registry.
+        if matches!(
+            context.crates[*id].repository,
+            Some(SourceAnnotation::Git { .. })
+        ) || context.workspace_members.contains_key(*id)
+        {
+            continue;
+        }
+
+        let dir = advisory_db.join("crates").join(&id.name);
+        if !dir.is_dir() {
+            continue;
+        }
+
+        let mut paths = fs::read_dir(&dir)
+            .with_context(|| format!("Failed to read '{}'", dir.display()))?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<std::io::Result<Vec<_>>>()?;
+        paths.sort();
+
+        for path in paths {
+            if !matches!(
+                path.extension().and_then(|ext| ext.to_str()),
+                Some("md" | "toml")
+            ) {
+                continue;
+            }
+
+            let advisory = Advisory::try_from_path(&path)?;
+            if advisory.advisory.package != id.name
+                || advisory.advisory.withdrawn.is_some()
+                || ignore.contains(&advisory.advisory.id)
+            {
+                continue;
+            }
+
+            if advisory.affects(&id.version)? {
+                findings.push(Finding { id, advisory });
+            }
+        }
+    }
+
+    Ok(findings)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::collections::BTreeSet;
+
+    use semver::Version;
+
+    use crate::test::mock_crate_context;
+    use crate::utils::target_triple::TargetTriple;
+
+    fn mock_context() -> Context {
+        let app = CrateId::new("app".to_owned(), Version::new(0, 1, 0));
+        let time = CrateId::new("time".to_owned(), Version::new(0, 1, 45));
+        let winapi = CrateId::new("winapi".to_owned(), Version::new(0, 3, 9));
+
+        let mut context = Context::default();
+        context.crates.insert(
+            app.clone(),
+            mock_crate_context(&app, &[(&time, None), (&winapi, Some("cfg(windows)"))]),
+        );
+        context
+            .crates
+            .insert(time.clone(), mock_crate_context(&time, &[]));
+        context
+            .crates
+            .insert(winapi.clone(), mock_crate_context(&winapi, &[]));
+        context.workspace_members.insert(app, "app".to_owned());
+        context.conditions.insert(
+            "cfg(windows)".to_owned(),
+            BTreeSet::from([TargetTriple::from_bazel(
+                "x86_64-pc-windows-msvc".to_owned(),
+            )]),
+        );
+        context
+    }
+
+    #[test]
+    fn reachable_on_supported_platforms() {
+        let mut context = mock_context();
+        assert_eq!(
+            reachable_crates(&context)
+                .keys()
+                .map(|id| id.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["app", "time", "winapi"]
+        );
+
+        context
+            .conditions
+            .insert("cfg(windows)".to_owned(), BTreeSet::new());
+        assert_eq!(
+            reachable_crates(&context)
+                .keys()
+                .map(|id| id.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["app", "time"]
+        );
+    }
+
+    #[test]
+    fn find_affected_crates() {
+        let context = mock_context();
+        let advisory_db = tempfile::tempdir().unwrap();
+        let time_dir = advisory_db.path().join("crates/time");
+        fs::create_dir_all(&time_dir).unwrap();
+        fs::write(
+            time_dir.join("RUSTSEC-2020-0071.md"),
+            indoc::indoc! {r#"
+                ```toml
+                [advisory]
+                id = "RUSTSEC-2020-0071"
+                package = "time"
+                date = "2020-11-18"
+                url = "https://github.com/time-rs/time/issues/293"
+                aliases = ["CVE-2020-26235"]
+
+                [versions]
+                patched = [">= 0.2.23"]
+                unaffected = ["= 0.2.0", "= 0.2.1", "= 0.2.2"]
+                ```
+
+                # Potential segfault in the time crate
+            "#},
+        )
+        .unwrap();
+        fs::write(
+            time_dir.join("RUSTSEC-2021-0000.md"),
+            indoc::indoc! {r#"
+                ```toml
+                [advisory]
+                id = "RUSTSEC-2021-0000"
+                package = "time"
+                date = "2021-01-01"
+
+                [versions]
+                patched = [">= 0.1.44"]
+                ```
+
+                # Already patched
+            "#},
+        )
+        .unwrap();
+
+        let reachable = reachable_crates(&context);
+        let findings = find_advisories(&context, &reachable, advisory_db.path(), &[]).unwrap();
+        assert_eq!(findings.len(), 1);
+        assert_eq!(
+            findings[0].report(&reachable),
+            indoc::indoc! {"
+                RUSTSEC-2020-0071: Potential segfault in the time crate
+                  Crate:    time 0.1.45
+                  Aliases:  CVE-2020-26235
+                  Url:      https://github.com/time-rs/time/issues/293
+                  Solution: Upgrade to >= 0.2.23
+                  Path:     app 0.1.0 -> time 0.1.45"
+            }
+        );
+
+        let findings = find_advisories(
+            &context,
+            &reachable,
+            advisory_db.path(),
+            &["RUSTSEC-2020-0071".to_owned()],
+        )
+        .unwrap();
+        assert!(findings.is_empty());
+    }
+}
@@ -0,0 +1,895 @@
+//! The cli entrypoint for the `diff` subcommand
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Display};
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::Parser;
+use serde::Serialize;
+
+use crate::cli::DeriveOptions;
+use crate::config::CrateId;
+use crate::context::{
+    BuildScriptAttributes, CommonAttributes, Context, CrateContext, CrateDependency,
+};
+use crate::select::{Select, Selectable};
+use crate::utils::starlark::Label;
+
+/// Command line options for the `diff` subcommand
+#[derive(Parser, Debug)]
+#[clap(about = "Command line options for the `diff` subcommand", version)]
+pub struct DiffOptions {
+    /// The path to the previous cargo-bazel lockfile
+    #[clap(long)]
+    pub old: PathBuf,
+
+    /// The path to the updated cargo-bazel lockfile
+    #[clap(long)]
+    pub new: PathBuf,
+
+    /// Print the report as JSON instead of human readable text
+    #[clap(long)]
+    pub json: bool,
+
+    #[clap(flatten)]
+    pub derive: DeriveOptions,
+}
+
+/// Print a report of the differences between two cargo-bazel lockfiles
+pub fn diff(opt: DiffOptions) -> Result<()> {
+    let old = opt.derive.load_context(&opt.old)?;
+    let new = opt.derive.load_context(&opt.new)?;
+
+    let report = LockfileDiff::new(&old, &new);
+
+    if opt.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print!("{report}");
+    }
+
+    Ok(())
+}
+
+/// The values added to and removed from a single attribute.
+#[derive(Debug, Default, Serialize, PartialEq, Eq)]
+struct AttributeDiff {
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    added: BTreeSet<String>,
+
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    removed: BTreeSet<String>,
+}
+
+impl AttributeDiff {
+    fn new(old: BTreeSet<String>, new: BTreeSet<String>) -> Self {
+        Self {
+            added: new.difference(&old).cloned().collect(),
+            removed: old.difference(&new).cloned().collect(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// A crate which is present in both lockfiles but at different versions.
+#[derive(Debug, Serialize, PartialEq, Eq)]
+struct VersionChange {
+    name: String,
+    old: semver::Version,
+    new: semver::Version,
+}
+
+/// Attribute changes for a single crate, keyed by attribute name.
+#[derive(Debug, Default, Serialize, PartialEq, Eq)]
+struct CrateDiff {
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    common_attrs: BTreeMap<String, AttributeDiff>,
+
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    build_script_attrs: BTreeMap<String, AttributeDiff>,
+
+    /// Any other fields of the crate which changed but aren't compared value by value.
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    other: BTreeSet<String>,
+}
+
+impl CrateDiff {
+    fn new(old: &CrateContext, new: &CrateContext) -> Self {
+        let mut common_attrs = BTreeMap::new();
+        diff_common_attrs(&mut common_attrs, &old.common_attrs, &new.common_attrs);
+
+        let mut build_script_attrs = BTreeMap::new();
+        match (&old.build_script_attrs, &new.build_script_attrs) {
+            (None, None) => {}
+            (old, new) => diff_build_script_attrs(
+                &mut build_script_attrs,
+                &old.clone().unwrap_or_default(),
+                &new.clone().unwrap_or_default(),
+            ),
+        }
+
+        Self {
+            common_attrs,
+            build_script_attrs,
+            other: diff_other_fields(old, new),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.common_attrs.is_empty() && self.build_script_attrs.is_empty() && self.other.is_empty()
+    }
+}
+
+/// A summary of the differences between two [Context]s.
+#[derive(Debug, Default, Serialize, PartialEq, Eq)]
+struct LockfileDiff {
+    /// Crates only present in the new lockfile.
+    added: BTreeSet<CrateId>,
+
+    /// Crates only present in the old lockfile.
+    removed: BTreeSet<CrateId>,
+
+    /// Crates whose version increased between lockfiles.
+    upgraded: Vec<VersionChange>,
+
+    /// Crates whose version decreased between lockfiles.
+    downgraded: Vec<VersionChange>,
+
+    /// Attribute changes keyed by the crate's id in the new lockfile.
+    changed: BTreeMap<CrateId, CrateDiff>,
+
+    /// Platform triples added to or removed from each `cfg` condition.
+    conditions: BTreeMap<String, AttributeDiff>,
+}
+
+impl LockfileDiff {
+    fn new(old: &Context, new: &Context) -> Self {
+        let mut added: BTreeSet<CrateId> = new
+            .crates
+            .keys()
+            .filter(|id| !old.crates.contains_key(id))
+            .cloned()
+            .collect();
+        let mut removed: BTreeSet<CrateId> = old
+            .crates
+            .keys()
+            .filter(|id| !new.crates.contains_key(id))
+            .cloned()
+            .collect();
+
+        // A crate name that was both removed and added is considered a version change. When
+        // multiple versions of a crate exist, versions are paired up in ascending order.
+        let mut version_changes = Vec::new();
+        let names: BTreeSet<String> = removed.iter().map(|id| id.name.clone()).collect();
+        for name in names {
+            let old_versions: Vec<CrateId> = removed
+                .iter()
+                .filter(|id| id.name == name)
+                .cloned()
+                .collect();
+            let new_versions: Vec<CrateId> =
+                added.iter().filter(|id| id.name == name).cloned().collect();
+            for (old_id, new_id) in old_versions.into_iter().zip(new_versions) {
+                removed.remove(&old_id);
+                added.remove(&new_id);
+                version_changes.push((old_id, new_id));
+            }
+        }
+
+        let changed = new
+            .crates
+            .iter()
+            .filter_map(|(id, new_ctx)| {
+                let old_id = version_changes
+                    .iter()
+                    .find(|(_, new_id)| new_id == id)
+                    .map(|(old_id, _)| old_id)
+                    .unwrap_or(id);
+                let old_ctx = old.crates.get(old_id)?;
+                let crate_diff = CrateDiff::new(old_ctx, new_ctx);
+                (!crate_diff.is_empty()).then(|| (id.clone(), crate_diff))
+            })
+            .collect();
+
+        let condition_names: BTreeSet<&String> = old
+            .conditions
+            .keys()
+            .chain(old.custom_conditions.keys())
+            .chain(new.conditions.keys())
+            .chain(new.custom_conditions.keys())
+            .collect();
+        let conditions = condition_names
+            .into_iter()
+            .filter_map(|cfg| {
+                let platforms = |ctx: &Context| -> BTreeSet<String> {
+                    ctx.conditions
+                        .get(cfg)
+                        .into_iter()
+                        .flat_map(|triples| triples.iter().map(|t| t.to_bazel()))
+                        .chain(
+                            ctx.custom_conditions
+                                .get(cfg)
+                                .into_iter()
+                                .flatten()
+                                .cloned(),
+                        )
+                        .collect()
+                };
+                let attr_diff = AttributeDiff::new(platforms(old), platforms(new));
+                (!attr_diff.is_empty()).then(|| (cfg.clone(), attr_diff))
+            })
+            .collect();
+
+        let (upgraded, downgraded) = version_changes
+            .into_iter()
+            .map(|(old_id, new_id)| VersionChange {
+                name: new_id.name,
+                old: old_id.version,
+                new: new_id.version,
+            })
+            .partition(|change| change.new > change.old);
+
+        Self {
+            added,
+            removed,
+            upgraded,
+            downgraded,
+            changed,
+            conditions,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.upgraded.is_empty()
+            && self.downgraded.is_empty()
+            && self.changed.is_empty()
+            && self.conditions.is_empty()
+    }
+}
+
+impl Display for LockfileDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "No changes");
+        }
+
+        if !self.added.is_empty() {
+            writeln!(f, "Added crates:")?;
+            for id in &self.added {
+                writeln!(f, "  + {id}")?;
+            }
+        }
+
+        if !self.removed.is_empty() {
+            writeln!(f, "Removed crates:")?;
+            for id in &self.removed {
+                writeln!(f, "  - {id}")?;
+            }
+        }
+
+        if !self.upgraded.is_empty() {
+            writeln!(f, "Upgraded crates:")?;
+            for change in &self.upgraded {
+                writeln!(f, "  ~ {} {} -> {}", change.name, change.old, change.new)?;
+            }
+        }
+
+        if !self.downgraded.is_empty() {
+            writeln!(f, "Downgraded crates:")?;
+            for change in &self.downgraded {
+                writeln!(f, "  ~ {} {} -> {}", change.name, change.old, change.new)?;
+            }
+        }
+
+        if !self.changed.is_empty() {
+            writeln!(f, "Changed crates:")?;
+            for (id, crate_diff) in &self.changed {
+                writeln!(f, "  {id}")?;
+                for (attr, attr_diff) in &crate_diff.common_attrs {
+                    write_attribute_diff(f, attr, attr_diff)?;
+                }
+                for (attr, attr_diff) in &crate_diff.build_script_attrs {
+                    write_attribute_diff(f, &format!("build_script.{attr}"), attr_diff)?;
+                }
+                for field in &crate_diff.other {
+                    writeln!(f, "    {field}: changed")?;
+                }
+            }
+        }
+
+        if !self.conditions.is_empty() {
+            writeln!(f, "Changed conditions:")?;
+            for (cfg, attr_diff) in &self.conditions {
+                write_attribute_diff(f, cfg, attr_diff)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn write_attribute_diff(
+    f: &mut fmt::Formatter<'_>,
+    name: &str,
+    diff: &AttributeDiff,
+) -> fmt::Result {
+    writeln!(f, "    {name}:")?;
+    for value in &diff.added {
+        writeln!(f, "      + {value}")?;
+    }
+    for value in &diff.removed {
+        writeln!(f, "      - {value}")?;
+    }
+    Ok(())
+}
+
+/// Flatten a [Select] into a set of strings where configured values are suffixed
+/// with the configuration they apply to.
+fn select_strings<T, F>(select: &Select<T>, to_string: F) -> BTreeSet<String>
+where
+    T: Selectable,
+    F: Fn(&T::ItemType) -> String,
+{
+    select
+        .items()
+        .iter()
+        .map(|(configuration, value)| match configuration {
+            Some(configuration) => format!("{} ({})", to_string(value), configuration),
+            None => to_string(value),
+        })
+        .collect()
+}
+
+fn dep_string(dep: &CrateDependency) -> String {
+    match &dep.alias {
+        Some(alias) => format!("{} (as {})", dep.id, alias),
+        None => dep.id.to_string(),
+    }
+}
+
+fn env_string((key, value): &(String, String)) -> String {
+    format!("{key}={value}")
+}
+
+fn insert_diff(
+    diffs: &mut BTreeMap<String, AttributeDiff>,
+    name: &str,
+    old: BTreeSet<String>,
+    new: BTreeSet<String>,
+) {
+    let attr_diff = AttributeDiff::new(old, new);
+    if !attr_diff.is_empty() {
+        diffs.insert(name.to_owned(), attr_diff);
+    }
+}
+
+fn label_string(label: &Label) -> String {
+    label.to_string()
+}
+
+fn optional_strings(value: &Option<String>) -> BTreeSet<String> {
+    value.iter().cloned().collect()
+}
+
+fn diff_common_attrs(
+    diffs: &mut BTreeMap<String, AttributeDiff>,
+    old: &CommonAttributes,
+    new: &CommonAttributes,
+) {
+    insert_diff(
+        diffs,
+        "compile_data",
+        select_strings(&old.compile_data, label_string),
+        select_strings(&new.compile_data, label_string),
+    );
+    insert_diff(
+        diffs,
+        "compile_data_glob",
+        old.compile_data_glob.clone(),
+        new.compile_data_glob.clone(),
+    );
+    insert_diff(
+        diffs,
+        "crate_features",
+        select_strings(&old.crate_features, String::clone),
+        select_strings(&new.crate_features, String::clone),
+    );
+    insert_diff(
+        diffs,
+        "dependency_features",
+        select_strings(&old.dependency_features, String::clone),
+        select_strings(&new.dependency_features, String::clone),
+    );
+    insert_diff(
+        diffs,
+        "data",
+        select_strings(&old.data, label_string),
+        select_strings(&new.data, label_string),
+    );
+    insert_diff(
+        diffs,
+        "data_glob",
+        old.data_glob.clone(),
+        new.data_glob.clone(),
+    );
+    insert_diff(
+        diffs,
+        "deps",
+        select_strings(&old.deps, dep_string),
+        select_strings(&new.deps, dep_string),
+    );
+    insert_diff(
+        diffs,
+        "extra_deps",
+        select_strings(&old.extra_deps, label_string),
+        select_strings(&new.extra_deps, label_string),
+    );
+    insert_diff(
+        diffs,
+        "deps_dev",
+        select_strings(&old.deps_dev, dep_string),
+        select_strings(&new.deps_dev, dep_string),
+    );
+    insert_diff(
+        diffs,
+        "edition",
+        BTreeSet::from([old.edition.clone()]),
+        BTreeSet::from([new.edition.clone()]),
+    );
+    insert_diff(
+        diffs,
+        "linker_script",
+        optional_strings(&old.linker_script),
+        optional_strings(&new.linker_script),
+    );
+    insert_diff(
+        diffs,
+        "proc_macro_deps",
+        select_strings(&old.proc_macro_deps, dep_string),
+        select_strings(&new.proc_macro_deps, dep_string),
+    );
+    insert_diff(
+        diffs,
+        "extra_proc_macro_deps",
+        select_strings(&old.extra_proc_macro_deps, label_string),
+        select_strings(&new.extra_proc_macro_deps, label_string),
+    );
+    insert_diff(
+        diffs,
+        "proc_macro_deps_dev",
+        select_strings(&old.proc_macro_deps_dev, dep_string),
+        select_strings(&new.proc_macro_deps_dev, dep_string),
+    );
+    insert_diff(
+        diffs,
+        "rustc_env",
+        select_strings(&old.rustc_env, env_string),
+        select_strings(&new.rustc_env, env_string),
+    );
+    insert_diff(
+        diffs,
+        "rustc_env_files",
+        select_strings(&old.rustc_env_files, String::clone),
+        select_strings(&new.rustc_env_files, String::clone),
+    );
+    insert_diff(
+        diffs,
+        "rustc_flags",
+        select_strings(&old.rustc_flags, String::clone),
+        select_strings(&new.rustc_flags, String::clone),
+    );
+    insert_diff(
+        diffs,
+        "lints.rustc_flags",
+        old.lints.rustc_flags.iter().cloned().collect(),
+        new.lints.rustc_flags.iter().cloned().collect(),
+    );
+    insert_diff(
+        diffs,
+        "lints.clippy_flags",
+        old.lints.clippy_flags.iter().cloned().collect(),
+        new.lints.clippy_flags.iter().cloned().collect(),
+    );
+    insert_diff(
+        diffs,
+        "profile_flags.dbg",
+        old.profile_flags.dbg.iter().cloned().collect(),
+        new.profile_flags.dbg.iter().cloned().collect(),
+    );
+    insert_diff(
+        diffs,
+        "profile_flags.fastbuild",
+        old.profile_flags.fastbuild.iter().cloned().collect(),
+        new.profile_flags.fastbuild.iter().cloned().collect(),
+    );
+    insert_diff(
+        diffs,
+        "profile_flags.opt",
+        old.profile_flags.opt.iter().cloned().collect(),
+        new.profile_flags.opt.iter().cloned().collect(),
+    );
+    insert_diff(
+        diffs,
+        "version",
+        BTreeSet::from([old.version.clone()]),
+        BTreeSet::from([new.version.clone()]),
+    );
+    insert_diff(
+        diffs,
+        "tags",
+        old.tags.iter().cloned().collect(),
+        new.tags.iter().cloned().collect(),
+    );
+}
+
+fn diff_build_script_attrs(
+    diffs: &mut BTreeMap<String, AttributeDiff>,
+    old: &BuildScriptAttributes,
+    new: &BuildScriptAttributes,
+) {
+    insert_diff(
+        diffs,
+        "compile_data",
+        select_strings(&old.compile_data, label_string),
+        select_strings(&new.compile_data, label_string),
+    );
+    insert_diff(
+        diffs,
+        "data",
+        select_strings(&old.data, label_string),
+        select_strings(&new.data, label_string),
+    );
+    insert_diff(
+        diffs,
+        "data_glob",
+        old.data_glob.clone(),
+        new.data_glob.clone(),
+    );
+    insert_diff(
+        diffs,
+        "deps",
+        select_strings(&old.deps, dep_string),
+        select_strings(&new.deps, dep_string),
+    );
+    insert_diff(
+        diffs,
+        "extra_deps",
+        select_strings(&old.extra_deps, label_string),
+        select_strings(&new.extra_deps, label_string),
+    );
+    insert_diff(
+        diffs,
+        "link_deps",
+        select_strings(&old.link_deps, dep_string),
+        select_strings(&new.link_deps, dep_string),
+    );
+    insert_diff(
+        diffs,
+        "extra_link_deps",
+        select_strings(&old.extra_link_deps, label_string),
+        select_strings(&new.extra_link_deps, label_string),
+    );
+    insert_diff(
+        diffs,
+        "build_script_env",
+        select_strings(&old.build_script_env, env_string),
+        select_strings(&new.build_script_env, env_string),
+    );
+    insert_diff(
+        diffs,
+        "rundir",
+        select_strings(&old.rundir, String::clone),
+        select_strings(&new.rundir, String::clone),
+    );
+    insert_diff(
+        diffs,
+        "extra_proc_macro_deps",
+        select_strings(&old.extra_proc_macro_deps, label_string),
+        select_strings(&new.extra_proc_macro_deps, label_string),
+    );
+    insert_diff(
+        diffs,
+        "proc_macro_deps",
+        select_strings(&old.proc_macro_deps, dep_string),
+        select_strings(&new.proc_macro_deps, dep_string),
+    );
+    insert_diff(
+        diffs,
+        "rustc_env",
+        select_strings(&old.rustc_env, env_string),
+        select_strings(&new.rustc_env, env_string),
+    );
+    insert_diff(
+        diffs,
+        "rustc_flags",
+        select_strings(&old.rustc_flags, String::clone),
+        select_strings(&new.rustc_flags, String::clone),
+    );
+    insert_diff(
+        diffs,
+        "rustc_env_files",
+        select_strings(&old.rustc_env_files, String::clone),
+        select_strings(&new.rustc_env_files, String::clone),
+    );
+    insert_diff(
+        diffs,
+        "tools",
+        select_strings(&old.tools, label_string),
+        select_strings(&new.tools, label_string),
+    );
+    insert_diff(
+        diffs,
+        "links",
+        optional_strings(&old.links),
+        optional_strings(&new.links),
+    );
+    insert_diff(
+        diffs,
+        "toolchains",
+        old.toolchains.iter().map(label_string).collect(),
+        new.toolchains.iter().map(label_string).collect(),
+    );
+}
+
+/// The names of the remaining fields of a [CrateContext] (e.g. `targets` or `repository`)
+/// which differ between lockfiles. These are reported as changed without listing values
+/// so that no difference in a lockfile goes unreported.
+fn diff_other_fields(old: &CrateContext, new: &CrateContext) -> BTreeSet<String> {
+    const COMPARED_FIELDS: [&str; 4] = ["name", "version", "common_attrs", "build_script_attrs"];
+
+    let to_fields = |ctx: &CrateContext| match serde_json::to_value(ctx) {
+        Ok(serde_json::Value::Object(fields)) => fields,
+        _ => panic!("A CrateContext always serializes to a JSON object"),
+    };
+    let old = to_fields(old);
+    let new = to_fields(new);
+
+    old.keys()
+        .chain(new.keys())
+        .filter(|field| !COMPARED_FIELDS.contains(&field.as_str()))
+        .filter(|field| old.get(*field) != new.get(*field))
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::str::FromStr;
+
+    use semver::Version;
+
+    use crate::test::mock_crate_context;
+    use crate::utils::target_triple::TargetTriple;
+
+    fn mock_crate(name: &str, version: Version) -> CrateContext {
+        mock_crate_context(&CrateId::new(name.to_owned(), version), &[])
+    }
+
+    fn insert_crate(context: &mut Context, crate_context: CrateContext) {
+        context.crates.insert(
+            CrateId::new(crate_context.name.clone(), crate_context.version.clone()),
+            crate_context,
+        );
+    }
+
+    #[test]
+    fn diff_identical_lockfiles() {
+        let mut context = Context::default();
+        insert_crate(&mut context, mock_crate("anyhow", Version::new(1, 0, 0)));
+
+        let report = LockfileDiff::new(&context, &context);
+
+        assert!(report.is_empty());
+        assert_eq!(report.to_string(), "No changes\n");
+    }
+
+    #[test]
+    fn diff_added_removed_and_upgraded() {
+        let mut old = Context::default();
+        insert_crate(&mut old, mock_crate("anyhow", Version::new(1, 0, 0)));
+        insert_crate(&mut old, mock_crate("log", Version::new(0, 4, 0)));
+
+        let mut new = Context::default();
+        insert_crate(&mut new, mock_crate("anyhow", Version::new(1, 0, 1)));
+        insert_crate(&mut new, mock_crate("serde", Version::new(1, 0, 0)));
+
+        let report = LockfileDiff::new(&old, &new);
+
+        assert_eq!(
+            report.added,
+            BTreeSet::from([CrateId::new("serde".to_owned(), Version::new(1, 0, 0))])
+        );
+        assert_eq!(
+            report.removed,
+            BTreeSet::from([CrateId::new("log".to_owned(), Version::new(0, 4, 0))])
+        );
+        assert_eq!(
+            report.upgraded,
+            vec![VersionChange {
+                name: "anyhow".to_owned(),
+                old: Version::new(1, 0, 0),
+                new: Version::new(1, 0, 1),
+            }]
+        );
+        assert!(report.downgraded.is_empty());
+        assert!(report.changed.is_empty());
+    }
+
+    #[test]
+    fn diff_downgraded() {
+        let mut old = Context::default();
+        insert_crate(&mut old, mock_crate("anyhow", Version::new(1, 0, 1)));
+        insert_crate(&mut old, mock_crate("log", Version::new(0, 4, 0)));
+
+        let mut new = Context::default();
+        insert_crate(&mut new, mock_crate("anyhow", Version::new(1, 0, 0)));
+        insert_crate(&mut new, mock_crate("log", Version::new(0, 4, 1)));
+
+        let report = LockfileDiff::new(&old, &new);
+
+        assert_eq!(
+            report.downgraded,
+            vec![VersionChange {
+                name: "anyhow".to_owned(),
+                old: Version::new(1, 0, 1),
+                new: Version::new(1, 0, 0),
+            }]
+        );
+        assert_eq!(
+            report.to_string(),
+            "Upgraded crates:\n  ~ log 0.4.0 -> 0.4.1\nDowngraded crates:\n  ~ anyhow 1.0.1 -> 1.0.0\n"
+        );
+    }
+
+    #[test]
+    fn diff_crate_attributes() {
+        let mut old_crate = mock_crate("mock", Version::new(0, 1, 0));
+        old_crate
+            .common_attrs
+            .crate_features
+            .insert("std".to_owned(), None);
+        old_crate.common_attrs.deps.insert(
+            CrateDependency {
+                id: CrateId::new("libc".to_owned(), Version::new(0, 2, 0)),
+                target: "libc".to_owned(),
+                alias: None,
+            },
+            Some("cfg(unix)".to_owned()),
+        );
+
+        let mut new_crate = mock_crate("mock", Version::new(0, 1, 0));
+        new_crate
+            .common_attrs
+            .crate_features
+            .insert("default".to_owned(), None);
+        new_crate
+            .common_attrs
+            .crate_features
+            .insert("std".to_owned(), None);
+        new_crate.common_attrs.deps.insert(
+            CrateDependency {
+                id: CrateId::new("libc".to_owned(), Version::new(0, 2, 1)),
+                target: "libc".to_owned(),
+                alias: None,
+            },
+            Some("cfg(unix)".to_owned()),
+        );
+        new_crate
+            .common_attrs
+            .data
+            .insert(Label::from_str(":data.txt").unwrap(), None);
+        new_crate.build_script_attrs = Some(BuildScriptAttributes {
+            links: Some("mock".to_owned()),
+            toolchains: BTreeSet::from([Label::from_str("//:toolchain").unwrap()]),
+            ..BuildScriptAttributes::default()
+        });
+        new_crate.build_script_attrs.as_mut().unwrap().tools.insert(
+            Label::from_str("//:tool").unwrap(),
+            Some("cfg(unix)".to_owned()),
+        );
+        new_crate.disable_pipelining = true;
+
+        let mut old = Context::default();
+        insert_crate(&mut old, old_crate);
+        let mut new = Context::default();
+        insert_crate(&mut new, new_crate);
+
+        let report = LockfileDiff::new(&old, &new);
+        let crate_diff = &report.changed[&CrateId::new("mock".to_owned(), Version::new(0, 1, 0))];
+
+        assert_eq!(
+            crate_diff.common_attrs["crate_features"],
+            AttributeDiff {
+                added: BTreeSet::from(["default".to_owned()]),
+                removed: BTreeSet::new(),
+            }
+        );
+        assert_eq!(
+            crate_diff.common_attrs["deps"],
+            AttributeDiff {
+                added: BTreeSet::from(["libc 0.2.1 (cfg(unix))".to_owned()]),
+                removed: BTreeSet::from(["libc 0.2.0 (cfg(unix))".to_owned()]),
+            }
+        );
+        assert_eq!(
+            crate_diff.common_attrs["data"],
+            AttributeDiff {
+                added: BTreeSet::from([":data.txt".to_owned()]),
+                removed: BTreeSet::new(),
+            }
+        );
+        assert_eq!(
+            crate_diff.build_script_attrs["links"],
+            AttributeDiff {
+                added: BTreeSet::from(["mock".to_owned()]),
+                removed: BTreeSet::new(),
+            }
+        );
+        assert_eq!(
+            crate_diff.build_script_attrs["tools"],
+            AttributeDiff {
+                added: BTreeSet::from(["//:tool (cfg(unix))".to_owned()]),
+                removed: BTreeSet::new(),
+            }
+        );
+        assert_eq!(
+            crate_diff.build_script_attrs["toolchains"],
+            AttributeDiff {
+                added: BTreeSet::from(["//:toolchain".to_owned()]),
+                removed: BTreeSet::new(),
+            }
+        );
+        assert_eq!(
+            crate_diff.other,
+            BTreeSet::from(["disable_pipelining".to_owned()])
+        );
+        assert!(report
+            .to_string()
+            .ends_with("    disable_pipelining: changed\n"));
+    }
+
+    #[test]
+    fn diff_conditions() {
+        let mut old = Context::default();
+        old.conditions.insert(
+            "cfg(unix)".to_owned(),
+            BTreeSet::from([TargetTriple::from_bazel(
+                "x86_64-unknown-linux-gnu".to_owned(),
+            )]),
+        );
+
+        let mut new = Context::default();
+        new.conditions.insert(
+            "cfg(unix)".to_owned(),
+            BTreeSet::from([
+                TargetTriple::from_bazel("aarch64-apple-darwin".to_owned()),
+                TargetTriple::from_bazel("x86_64-unknown-linux-gnu".to_owned()),
+            ]),
+        );
+
+        let report = LockfileDiff::new(&old, &new);
+
+        assert_eq!(
+            report.conditions,
+            BTreeMap::from([(
+                "cfg(unix)".to_owned(),
+                AttributeDiff {
+                    added: BTreeSet::from(["aarch64-apple-darwin".to_owned()]),
+                    removed: BTreeSet::new(),
+                }
+            )])
+        );
+        assert_eq!(
+            report.to_string(),
+            "Changed conditions:\n    cfg(unix):\n      + aarch64-apple-darwin\n"
+        );
+    }
+}
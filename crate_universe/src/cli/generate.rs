@@ -9,10 +9,12 @@ use clap::Parser;
 
 use crate::config::Config;
 use crate::context::Context;
-use crate::lockfile::{lock_context, write_lockfile};
+use crate::lockfile::{
+    lock_context, write_lockfile, BazelLockfile, DeriveInputs, ResolvedLockfile,
+};
 use crate::metadata::{load_metadata, Annotations, Cargo};
 use crate::rendering::{write_outputs, Renderer};
-use crate::splicing::SplicingManifest;
+use crate::splicing::{SplicingManifest, WorkspaceMetadata};
 use crate::utils::normalize_cargo_file_paths;
 
 /// Command line options for the `generate` subcommand
@@ -60,6 +62,11 @@ pub struct GenerateOptions {
     #[clap(long)]
     pub metadata: Option<PathBuf>,
 
+    /// The path to write the rendered context to, regardless of the format of the lockfile.
+    /// This allows the rendered crates to be read without deriving them from a resolved lockfile.
+    #[clap(long)]
+    pub context_output: Option<PathBuf>,
+
     /// If true, outputs will be printed instead of written to disk.
     #[clap(long)]
     pub dry_run: bool,
@@ -72,10 +79,29 @@ pub fn generate(opt: GenerateOptions) -> Result<()> {
     // Go straight to rendering if there is no need to repin
     if !opt.repin {
         if let Some(lockfile) = &opt.lockfile {
-            let context = Context::try_from_path(lockfile)?;
+            let context = match BazelLockfile::try_from_path(lockfile)? {
+                BazelLockfile::Context(context) => *context,
+                BazelLockfile::Resolved(resolved) => {
+                    let (cargo, rustc) = match (&opt.cargo, &opt.rustc) {
+                        (Some(cargo), Some(rustc)) => (cargo, rustc),
+                        _ => bail!(
+                            "The `--cargo` and `--rustc` arguments are required when generating from a resolved lockfile"
+                        ),
+                    };
+                    let inputs = DeriveInputs {
+                        config: config.clone(),
+                        splicing_manifest: SplicingManifest::try_from_path(&opt.splicing_manifest)?,
+                        cargo: cargo.clone(),
+                        rustc: rustc.clone(),
+                    };
+                    resolved
+                        .derive_context(&inputs)
+                        .context("Failed to derive context from resolved lockfile")?
+                }
+            };
 
             // Render build files
-            let outputs = Renderer::new(config.rendering, config.supported_platform_triples)
+            let outputs = Renderer::new(config.rendering, config.supported_platform_triples)?
                 .render(&context)?;
 
             // make file paths compatible with bazel labels
@@ -84,6 +110,10 @@ pub fn generate(opt: GenerateOptions) -> Result<()> {
             // Write the outputs to disk
             write_outputs(normalized_outputs, opt.dry_run)?;
 
+            if let Some(path) = &opt.context_output {
+                write_lockfile(context, path, opt.dry_run)?;
+            }
+
             return Ok(());
         }
     }
@@ -107,17 +137,31 @@ pub fn generate(opt: GenerateOptions) -> Result<()> {
     // Load Metadata and Lockfile
     let (cargo_metadata, cargo_lockfile) = load_metadata(metadata_path)?;
 
+    // Existing lockfiles which only record resolved inputs should continue to do so.
+    let resolved = match &opt.lockfile {
+        Some(lockfile) if is_resolved_lockfile(lockfile) => Some(ResolvedLockfile::new(
+            cargo_lockfile.clone(),
+            WorkspaceMetadata::try_from(cargo_metadata.workspace_metadata.clone())?,
+        )),
+        _ => None,
+    };
+
     // Annotate metadata
     let annotations = Annotations::new(cargo_metadata, cargo_lockfile.clone(), config.clone())?;
 
     // Generate renderable contexts for each package
     let context = Context::new(annotations, config.rendering.are_sources_present())?;
 
+    let lock_content = match resolved {
+        Some(resolved) => BazelLockfile::Resolved(Box::new(resolved)),
+        None => BazelLockfile::Context(Box::new(context.clone())),
+    };
+
     // Render build files
     let outputs = Renderer::new(
         config.rendering.clone(),
         config.supported_platform_triples.clone(),
-    )
+    )?
     .render(&context)?;
 
     // make file paths compatible with bazel labels
@@ -130,17 +174,35 @@ pub fn generate(opt: GenerateOptions) -> Result<()> {
     if let Some(lockfile) = opt.lockfile {
         let splicing_manifest = SplicingManifest::try_from_path(&opt.splicing_manifest)?;
 
-        let lock_content =
-            lock_context(context, &config, &splicing_manifest, &cargo_bin, rustc_bin)?;
+        let lock_content = lock_context(
+            lock_content,
+            &config,
+            &splicing_manifest,
+            &cargo_bin,
+            rustc_bin,
+        )?;
 
         write_lockfile(lock_content, &lockfile, opt.dry_run)?;
     }
 
+    if let Some(path) = &opt.context_output {
+        write_lockfile(context, path, opt.dry_run)?;
+    }
+
     update_cargo_lockfile(&opt.cargo_lockfile, cargo_lockfile)?;
 
     Ok(())
 }
 
+/// Determine whether or not an existing lockfile is a [ResolvedLockfile]. Missing, empty,
+/// or otherwise unreadable lockfiles are not.
+fn is_resolved_lockfile(path: &Path) -> bool {
+    matches!(
+        BazelLockfile::try_from_path(path),
+        Ok(BazelLockfile::Resolved(_))
+    )
+}
+
 fn update_cargo_lockfile(path: &Path, cargo_lockfile: Lockfile) -> Result<()> {
     let old_contents = fs::read_to_string(path).ok();
     let new_contents = cargo_lockfile.to_string();
@@ -0,0 +1,131 @@
+//! The cli entrypoint for the `migrate` subcommand
+
+use std::path::PathBuf;
+
+use anyhow::{bail, Context as AnyhowContext, Result};
+use clap::Parser;
+
+use crate::cli::splice::resolve_spliced_workspace;
+use crate::config::Config;
+use crate::context::Context;
+use crate::lockfile::{lock_context, write_lockfile, BazelLockfile, Lockable, ResolvedLockfile};
+use crate::metadata::{Annotations, Cargo, Generator, MetadataGenerator};
+use crate::splicing::{SplicingManifest, WorkspaceMetadata};
+
+/// Command line options for the `migrate` subcommand
+#[derive(Parser, Debug)]
+#[clap(about = "Command line options for the `migrate` subcommand", version)]
+pub struct MigrateOptions {
+    /// The path to an existing cargo-bazel lockfile containing a rendered context
+    #[clap(long)]
+    pub lockfile: PathBuf,
+
+    /// The location to write the migrated lockfile to. If this argument is not
+    /// passed, the lockfile is migrated in place.
+    #[clap(long)]
+    pub output: Option<PathBuf>,
+
+    /// The config file with information about the Bazel and Cargo workspace
+    #[clap(long)]
+    pub config: PathBuf,
+
+    /// A generated manifest of splicing inputs
+    #[clap(long)]
+    pub splicing_manifest: PathBuf,
+
+    /// The path to the [Cargo.lock](https://doc.rust-lang.org/cargo/guide/cargo-toml-vs-cargo-lock.html) file
+    /// the lockfile was generated from.
+    #[clap(long)]
+    pub cargo_lockfile: PathBuf,
+
+    /// The directory in which to build the workspace. If this argument is not
+    /// passed, a temporary directory will be generated.
+    #[clap(long)]
+    pub workspace_dir: Option<PathBuf>,
+
+    /// The path to a Cargo binary to use for gathering metadata
+    #[clap(long, env = "CARGO")]
+    pub cargo: PathBuf,
+
+    /// The path to a rustc binary for use with Cargo
+    #[clap(long, env = "RUSTC")]
+    pub rustc: PathBuf,
+
+    /// If true, outputs will be printed instead of written to disk.
+    #[clap(long)]
+    pub dry_run: bool,
+}
+
+/// Convert a lockfile containing a rendered [Context] into a [ResolvedLockfile].
+pub fn migrate(opt: MigrateOptions) -> Result<()> {
+    let existing = match BazelLockfile::try_from_path(&opt.lockfile)
+        .with_context(|| format!("Failed to load lockfile '{}'", opt.lockfile.display()))?
+    {
+        BazelLockfile::Context(context) => context,
+        BazelLockfile::Resolved(_) => bail!(
+            "The lockfile '{}' has already been migrated",
+            opt.lockfile.display()
+        ),
+    };
+
+    let config = Config::try_from_path(&opt.config).context("Failed to parse config")?;
+    let splicing_manifest = SplicingManifest::try_from_path(&opt.splicing_manifest)
+        .context("Failed to parse splicing manifest")?;
+
+    // Determine the splicing workspace
+    let temp_dir;
+    let splicing_dir = match &opt.workspace_dir {
+        Some(dir) => dir.clone(),
+        None => {
+            temp_dir = tempfile::tempdir().context("Failed to generate temporary directory")?;
+            temp_dir.as_ref().to_path_buf()
+        }
+    };
+
+    // Resolve the workspace against the existing `Cargo.lock` file without updating it.
+    let manifest_path = resolve_spliced_workspace(
+        splicing_manifest.clone(),
+        splicing_dir,
+        &Some(opt.cargo_lockfile.clone()),
+        &None,
+        &config,
+        &opt.cargo,
+        &opt.rustc,
+    )?;
+
+    let cargo = Cargo::new(opt.cargo.clone());
+
+    let (cargo_metadata, cargo_lockfile) = Generator::new()
+        .with_cargo(cargo.clone())
+        .with_rustc(opt.rustc.clone())
+        .generate(manifest_path.as_path_buf())
+        .context("Failed to generate cargo metadata")?;
+
+    let resolved = ResolvedLockfile::new(
+        cargo_lockfile.clone(),
+        WorkspaceMetadata::try_from(cargo_metadata.workspace_metadata.clone())?,
+    );
+
+    // Ensure the migrated lockfile renders the same outputs as the original.
+    let annotations = Annotations::new(cargo_metadata, cargo_lockfile, config.clone())?;
+    let mut derived = Context::new(annotations, config.rendering.are_sources_present())?;
+    let mut existing = *existing;
+    existing.set_checksum(None, None);
+    derived.set_checksum(None, None);
+    if existing != derived {
+        bail!(
+            "The context derived from the migrated lockfile differs from '{}'. The lockfile may be out of date and should be repinned before it's migrated.",
+            opt.lockfile.display()
+        );
+    }
+
+    let lock_content = lock_context(resolved, &config, &splicing_manifest, &cargo, &opt.rustc)?;
+
+    write_lockfile(
+        lock_content,
+        opt.output.as_ref().unwrap_or(&opt.lockfile),
+        opt.dry_run,
+    )?;
+
+    Ok(())
+}
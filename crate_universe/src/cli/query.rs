@@ -2,13 +2,13 @@
 
 use std::fs;
 use std::path::PathBuf;
+use std::str::FromStr;
 
 use anyhow::{bail, Result};
 use clap::Parser;
 
 use crate::config::Config;
-use crate::context::Context;
-use crate::lockfile::Digest;
+use crate::lockfile::{BazelLockfile, Digest, Lockable};
 use crate::metadata::Cargo;
 use crate::splicing::SplicingManifest;
 
@@ -46,13 +46,13 @@ pub fn query(opt: QueryOptions) -> Result<()> {
     };
 
     // Deserialize it so we can easily compare it with
-    let lockfile: Context = match serde_json::from_str(&content) {
-        Ok(ctx) => ctx,
+    let lockfile = match BazelLockfile::from_str(&content) {
+        Ok(lockfile) => lockfile,
         Err(_) => bail!("Could not load lockfile"),
     };
 
     // Check to see if a digest has been set
-    let digest = match &lockfile.checksum {
+    let digest = match lockfile.checksum() {
         Some(d) => d.clone(),
         None => bail!("No digest provided in lockfile"),
     };
@@ -63,16 +63,26 @@ pub fn query(opt: QueryOptions) -> Result<()> {
     let splicing_manifest = SplicingManifest::try_from_path(&opt.splicing_manifest)?;
 
     // Generate a new digest so we can compare it with the one in the lockfile
-    let expected = Digest::new(
-        &lockfile,
+    let expected_components = lockfile.digest_components(
         &config,
         &splicing_manifest,
         &Cargo::new(opt.cargo),
         &opt.rustc,
     )?;
+    let expected = Digest::from(&expected_components);
 
     if digest != expected {
-        bail!("Digests do not match: Current {digest:?} != Expected {expected:?}");
+        // Older lockfiles may not contain the hashes of each input.
+        match lockfile.checksum_components() {
+            Some(components) => {
+                let differences = components.explain_differences(&expected_components);
+                bail!(
+                    "Digests do not match: Current {digest:?} != Expected {expected:?}\nThe following inputs have changed since the lockfile was generated:\n  - {}",
+                    differences.join("\n  - ")
+                );
+            }
+            None => bail!("Digests do not match: Current {digest:?} != Expected {expected:?}"),
+        }
     }
 
     // There is no need to repin
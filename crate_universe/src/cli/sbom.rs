@@ -0,0 +1,761 @@
+//! The cli entrypoint for the `sbom` subcommand
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context as AnyhowContext, Result};
+use clap::{Parser, ValueEnum};
+use hex::ToHex;
+use serde::Serialize;
+use sha2::{Digest as Sha2Digest, Sha256};
+
+use crate::cli::DeriveOptions;
+use crate::config::{Commitish, CrateId};
+use crate::context::{Context, CrateContext};
+use crate::metadata::SourceAnnotation;
+
+/// Command line options for the `sbom` subcommand
+#[derive(Parser, Debug)]
+#[clap(about = "Command line options for the `sbom` subcommand", version)]
+pub struct SbomOptions {
+    /// The path to a cargo-bazel lockfile
+    #[clap(long)]
+    pub lockfile: PathBuf,
+
+    #[clap(flatten)]
+    pub derive: DeriveOptions,
+
+    /// The format of the Software Bill of Materials to produce
+    #[clap(long, value_enum)]
+    pub format: SbomFormat,
+
+    /// Restrict the report to the transitive dependencies of the given workspace
+    /// members. May be passed multiple times.
+    #[clap(long = "member")]
+    pub members: Vec<String>,
+
+    /// Include the dev dependencies of workspace members
+    #[clap(long)]
+    pub include_dev_deps: bool,
+
+    /// The name of the document
+    #[clap(long, default_value = "cargo-bazel")]
+    pub name: String,
+
+    /// The SPDX document namespace. If this argument is not passed, one is
+    /// derived from the name and contents of the document.
+    #[clap(long)]
+    pub namespace: Option<String>,
+
+    /// The creation time of the document in seconds since the Unix epoch. The
+    /// current time is used if this is unset.
+    #[clap(long, env = "SOURCE_DATE_EPOCH")]
+    pub source_date_epoch: Option<u64>,
+
+    /// The location to write the report to. If unset, it's printed to stdout.
+    #[clap(long)]
+    pub output: Option<PathBuf>,
+}
+
+/// The supported Software Bill of Materials formats
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SbomFormat {
+    /// [SPDX 2.3](https://spdx.github.io/spdx-spec/v2.3/) JSON
+    Spdx,
+
+    /// [CycloneDX 1.5](https://cyclonedx.org/docs/1.5/json/) JSON
+    Cyclonedx,
+}
+
+/// Write a Software Bill of Materials for the crates in a cargo-bazel lockfile
+pub fn sbom(opt: SbomOptions) -> Result<()> {
+    let context = opt.derive.load_context(&opt.lockfile)?;
+
+    let graph = DependencyGraph::new(&context, &opt.members, opt.include_dev_deps)?;
+
+    let created = format_timestamp(match opt.source_date_epoch {
+        Some(seconds) => seconds,
+        None => SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .context("The system time is before the Unix epoch")?
+            .as_secs(),
+    });
+
+    let content = match opt.format {
+        SbomFormat::Spdx => serde_json::to_string_pretty(&SpdxDocument::new(
+            &graph,
+            &opt.name,
+            opt.namespace.as_deref(),
+            created,
+        ))?,
+        SbomFormat::Cyclonedx => {
+            serde_json::to_string_pretty(&CycloneDxDocument::new(&graph, created))?
+        }
+    };
+
+    match &opt.output {
+        Some(path) => fs::write(path, content + "\n")
+            .with_context(|| format!("Failed to write file to disk: {}", path.display()))?,
+        None => println!("{content}"),
+    }
+
+    Ok(())
+}
+
+/// The crates to include in a report and the dependencies between them.
+struct DependencyGraph<'a> {
+    /// The workspace members the report describes.
+    roots: BTreeSet<&'a CrateId>,
+
+    /// Each crate in the report mapped to its direct dependencies.
+    crates: BTreeMap<&'a CrateId, (&'a CrateContext, BTreeSet<&'a CrateId>)>,
+}
+
+impl<'a> DependencyGraph<'a> {
+    fn new(context: &'a Context, members: &[String], include_dev_deps: bool) -> Result<Self> {
+        let roots: BTreeSet<&CrateId> = match members.is_empty() {
+            true => context.workspace_members.keys().collect(),
+            false => members
+                .iter()
+                .map(|member| {
+                    context
+                        .workspace_members
+                        .keys()
+                        .find(|id| &id.name == member)
+                        .with_context(|| format!("`{member}` is not a workspace member"))
+                })
+                .collect::<Result<_>>()?,
+        };
+
+        let dependencies = |id: &CrateId, krate: &'a CrateContext| -> BTreeSet<&'a CrateId> {
+            let include_dev_deps = include_dev_deps && context.workspace_members.contains_key(id);
+            krate
+                .dependencies()
+                .filter(|(kind, _, _)| include_dev_deps || !kind.is_dev())
+                .filter_map(|(_, _, dep)| context.crates.get_key_value(&dep.id).map(|(id, _)| id))
+                .collect()
+        };
+
+        // Collect the transitive closure of the roots.
+        let mut crates = BTreeMap::new();
+        let mut queue: VecDeque<&CrateId> = roots.iter().copied().collect();
+        while let Some(id) = queue.pop_front() {
+            if crates.contains_key(id) {
+                continue;
+            }
+            let krate = match context.crates.get(id) {
+                Some(krate) => krate,
+                None => continue,
+            };
+            let deps = dependencies(id, krate);
+            queue.extend(deps.iter().copied());
+            crates.insert(id, (krate, deps));
+        }
+
+        Ok(Self { roots, crates })
+    }
+}
+
+/// Render a [package URL](https://github.com/package-url/purl-spec) for a crate.
+fn package_url(krate: &CrateContext) -> String {
+    let purl = format!("pkg:cargo/{}@{}", krate.name, krate.version);
+    match &krate.repository {
+        Some(SourceAnnotation::Git {
+            remote, commitish, ..
+        }) => format!(
+            "{}?vcs_url={}",
+            purl,
+            percent_encode(&format!("git+{}@{}", remote, commitish_ref(commitish)))
+        ),
+        _ => purl,
+    }
+}
+
+fn commitish_ref(commitish: &Commitish) -> &str {
+    match commitish {
+        Commitish::Tag(tag) => tag,
+        Commitish::Branch(branch) => branch,
+        Commitish::Rev(rev) => rev,
+    }
+}
+
+/// Percent encode any characters which are not unreserved in URLs.
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+/// The location a crate's sources can be downloaded from.
+fn download_location(krate: &CrateContext) -> Option<String> {
+    match &krate.repository {
+        Some(SourceAnnotation::Http { url, .. }) => Some(url.clone()),
+        Some(SourceAnnotation::Git {
+            remote, commitish, ..
+        }) => Some(format!("git+{}@{}", remote, commitish_ref(commitish))),
+        None => None,
+    }
+}
+
+fn sha256(krate: &CrateContext) -> Option<&String> {
+    match &krate.repository {
+        Some(SourceAnnotation::Http { sha256, .. }) => sha256.as_ref(),
+        _ => None,
+    }
+}
+
+/// The crate's license, if it's a valid SPDX license expression.
+fn license_expression(krate: &CrateContext) -> Option<&String> {
+    krate
+        .license
+        .as_ref()
+        .filter(|license| spdx::Expression::parse(license).is_ok())
+}
+
+/// Format a Unix timestamp as an RFC 3339 UTC date time.
+fn format_timestamp(seconds: u64) -> String {
+    let days = (seconds / 86400) as i64;
+    let time = seconds % 86400;
+
+    // Convert days since the epoch into a civil date.
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+/// An [SPDX 2.3](https://spdx.github.io/spdx-spec/v2.3/) document.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SpdxDocument {
+    spdx_version: &'static str,
+    data_license: &'static str,
+    #[serde(rename = "SPDXID")]
+    spdx_id: &'static str,
+    name: String,
+    document_namespace: String,
+    creation_info: SpdxCreationInfo,
+    packages: Vec<SpdxPackage>,
+    relationships: Vec<SpdxRelationship>,
+}
+
+#[derive(Debug, Serialize)]
+struct SpdxCreationInfo {
+    created: String,
+    creators: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SpdxPackage {
+    name: String,
+    #[serde(rename = "SPDXID")]
+    spdx_id: String,
+    version_info: String,
+    download_location: String,
+    files_analyzed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    homepage: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    checksums: Vec<SpdxChecksum>,
+    license_concluded: String,
+    license_declared: String,
+    external_refs: Vec<SpdxExternalRef>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SpdxChecksum {
+    algorithm: &'static str,
+    checksum_value: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SpdxExternalRef {
+    reference_category: &'static str,
+    reference_type: &'static str,
+    reference_locator: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SpdxRelationship {
+    spdx_element_id: String,
+    relationship_type: &'static str,
+    related_spdx_element: String,
+}
+
+const SPDX_NOASSERTION: &str = "NOASSERTION";
+
+impl SpdxDocument {
+    fn new(graph: &DependencyGraph, name: &str, namespace: Option<&str>, created: String) -> Self {
+        let packages: Vec<SpdxPackage> = graph
+            .crates
+            .iter()
+            .map(|(id, (krate, _))| SpdxPackage {
+                name: krate.name.clone(),
+                spdx_id: spdx_id(id),
+                version_info: krate.version.to_string(),
+                download_location: download_location(krate)
+                    .unwrap_or_else(|| SPDX_NOASSERTION.to_owned()),
+                files_analyzed: false,
+                homepage: krate.package_url.clone(),
+                checksums: sha256(krate)
+                    .map(|sha256| SpdxChecksum {
+                        algorithm: "SHA256",
+                        checksum_value: sha256.clone(),
+                    })
+                    .into_iter()
+                    .collect(),
+                license_concluded: SPDX_NOASSERTION.to_owned(),
+                license_declared: license_expression(krate)
+                    .cloned()
+                    .unwrap_or_else(|| SPDX_NOASSERTION.to_owned()),
+                external_refs: vec![SpdxExternalRef {
+                    reference_category: "PACKAGE-MANAGER",
+                    reference_type: "purl",
+                    reference_locator: package_url(krate),
+                }],
+            })
+            .collect();
+
+        let relationships: Vec<SpdxRelationship> = graph
+            .roots
+            .iter()
+            .map(|id| SpdxRelationship {
+                spdx_element_id: "SPDXRef-DOCUMENT".to_owned(),
+                relationship_type: "DESCRIBES",
+                related_spdx_element: spdx_id(id),
+            })
+            .chain(graph.crates.iter().flat_map(|(id, (_, deps))| {
+                deps.iter().map(|dep| SpdxRelationship {
+                    spdx_element_id: spdx_id(id),
+                    relationship_type: "DEPENDS_ON",
+                    related_spdx_element: spdx_id(dep),
+                })
+            }))
+            .collect();
+
+        // The namespace must be unique to the document's contents.
+        let document_namespace = match namespace {
+            Some(namespace) => namespace.to_owned(),
+            None => {
+                let mut hasher = Sha256::new();
+                hasher.update(serde_json::to_string(&packages).unwrap_or_default());
+                hasher.update(serde_json::to_string(&relationships).unwrap_or_default());
+                format!(
+                    "https://spdx.org/spdxdocs/{}-{}",
+                    name,
+                    hasher.finalize().encode_hex::<String>()
+                )
+            }
+        };
+
+        Self {
+            spdx_version: "SPDX-2.3",
+            data_license: "CC0-1.0",
+            spdx_id: "SPDXRef-DOCUMENT",
+            name: name.to_owned(),
+            document_namespace,
+            creation_info: SpdxCreationInfo {
+                created,
+                creators: vec![format!("Tool: cargo-bazel-{}", env!("CARGO_PKG_VERSION"))],
+            },
+            packages,
+            relationships,
+        }
+    }
+}
+
+/// SPDX identifiers may only contain letters, numbers, `.` and `-`. Other characters are
+/// replaced, so a short hash of the crate's id keeps the identifiers unique (Eg. for the
+/// versions `1.0.0+meta` and `1.0.0-meta`).
+fn spdx_id(id: &CrateId) -> String {
+    let sanitized: String = format!("{}-{}", id.name, id.version)
+        .chars()
+        .map(
+            |c| match c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                true => c,
+                false => '-',
+            },
+        )
+        .collect();
+    let hash = Sha256::digest(id.to_string()).encode_hex::<String>();
+    format!("SPDXRef-Package-{sanitized}-{}", &hash[..12])
+}
+
+/// A [CycloneDX 1.5](https://cyclonedx.org/docs/1.5/json/) document.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CycloneDxDocument {
+    bom_format: &'static str,
+    spec_version: &'static str,
+    version: u32,
+    metadata: CycloneDxMetadata,
+    components: Vec<CycloneDxComponent>,
+    dependencies: Vec<CycloneDxDependency>,
+}
+
+#[derive(Debug, Serialize)]
+struct CycloneDxMetadata {
+    timestamp: String,
+    tools: CycloneDxTools,
+}
+
+#[derive(Debug, Serialize)]
+struct CycloneDxTools {
+    components: Vec<CycloneDxTool>,
+}
+
+#[derive(Debug, Serialize)]
+struct CycloneDxTool {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    name: &'static str,
+    version: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CycloneDxComponent {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    #[serde(rename = "bom-ref")]
+    bom_ref: String,
+    name: String,
+    version: String,
+    purl: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    hashes: Vec<CycloneDxHash>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    licenses: Vec<CycloneDxLicense>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    external_references: Vec<CycloneDxExternalReference>,
+}
+
+#[derive(Debug, Serialize)]
+struct CycloneDxHash {
+    alg: &'static str,
+    content: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum CycloneDxLicense {
+    Expression { expression: String },
+    Named { license: CycloneDxNamedLicense },
+}
+
+#[derive(Debug, Serialize)]
+struct CycloneDxNamedLicense {
+    name: String,
+}
+
+#[derive(Debug, Serialize)]
+struct CycloneDxExternalReference {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    url: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CycloneDxDependency {
+    #[serde(rename = "ref")]
+    reference: String,
+    depends_on: Vec<String>,
+}
+
+impl CycloneDxDocument {
+    fn new(graph: &DependencyGraph, timestamp: String) -> Self {
+        let bom_ref = |id: &CrateId| format!("{}@{}", id.name, id.version);
+
+        let components = graph
+            .crates
+            .iter()
+            .map(|(id, (krate, _))| {
+                let licenses = match (license_expression(krate), &krate.license) {
+                    (Some(expression), _) => vec![CycloneDxLicense::Expression {
+                        expression: expression.clone(),
+                    }],
+                    (None, Some(license)) => vec![CycloneDxLicense::Named {
+                        license: CycloneDxNamedLicense {
+                            name: license.clone(),
+                        },
+                    }],
+                    (None, None) => Vec::new(),
+                };
+
+                let external_references = [
+                    match &krate.repository {
+                        Some(SourceAnnotation::Git { .. }) => download_location(krate)
+                            .map(|url| CycloneDxExternalReference { kind: "vcs", url }),
+                        _ => download_location(krate).map(|url| CycloneDxExternalReference {
+                            kind: "distribution",
+                            url,
+                        }),
+                    },
+                    krate
+                        .package_url
+                        .clone()
+                        .map(|url| CycloneDxExternalReference {
+                            kind: "website",
+                            url,
+                        }),
+                ]
+                .into_iter()
+                .flatten()
+                .collect();
+
+                CycloneDxComponent {
+                    kind: match graph.roots.contains(id) {
+                        true => "application",
+                        false => "library",
+                    },
+                    bom_ref: bom_ref(id),
+                    name: krate.name.clone(),
+                    version: krate.version.to_string(),
+                    purl: package_url(krate),
+                    hashes: sha256(krate)
+                        .map(|sha256| CycloneDxHash {
+                            alg: "SHA-256",
+                            content: sha256.clone(),
+                        })
+                        .into_iter()
+                        .collect(),
+                    licenses,
+                    external_references,
+                }
+            })
+            .collect();
+
+        let dependencies = graph
+            .crates
+            .iter()
+            .map(|(id, (_, deps))| CycloneDxDependency {
+                reference: bom_ref(id),
+                depends_on: deps.iter().map(|dep| bom_ref(dep)).collect(),
+            })
+            .collect();
+
+        Self {
+            bom_format: "CycloneDX",
+            spec_version: "1.5",
+            version: 1,
+            metadata: CycloneDxMetadata {
+                timestamp,
+                tools: CycloneDxTools {
+                    components: vec![CycloneDxTool {
+                        kind: "application",
+                        name: "cargo-bazel",
+                        version: env!("CARGO_PKG_VERSION"),
+                    }],
+                },
+            },
+            components,
+            dependencies,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use semver::Version;
+
+    use crate::test::mock_crate_context;
+
+    fn mock_crate(name: &str, version: Version, deps: &[&CrateId]) -> CrateContext {
+        let deps: Vec<_> = deps.iter().map(|dep| (*dep, None)).collect();
+        mock_crate_context(&CrateId::new(name.to_owned(), version), &deps)
+    }
+
+    fn mock_context() -> Context {
+        let version = Version::new(1, 0, 0);
+        let libc = CrateId::new("libc".to_owned(), version.clone());
+        let regex = CrateId::new("regex".to_owned(), version.clone());
+        let app = CrateId::new("app".to_owned(), Version::new(0, 1, 0));
+        let tool = CrateId::new("tool".to_owned(), Version::new(0, 1, 0));
+
+        let mut regex_crate = mock_crate("regex", version.clone(), &[]);
+        regex_crate.license = Some("MIT OR Apache-2.0".to_owned());
+        regex_crate.repository = Some(SourceAnnotation::Http {
+            url: "https://static.crates.io/crates/regex/1.0.0/download".to_owned(),
+            sha256: Some("abcd".to_owned()),
+            registry: Some("crates-io".to_owned()),
+            urls: Vec::new(),
+            patch_args: None,
+            patch_tool: None,
+            patches: None,
+        });
+
+        let mut context = Context::default();
+        context
+            .crates
+            .insert(libc.clone(), mock_crate("libc", version, &[]));
+        context.crates.insert(regex.clone(), regex_crate);
+        context.crates.insert(
+            app.clone(),
+            mock_crate("app", Version::new(0, 1, 0), &[&regex]),
+        );
+        context.crates.insert(
+            tool.clone(),
+            mock_crate("tool", Version::new(0, 1, 0), &[&libc]),
+        );
+        context.workspace_members.insert(app, "app".to_owned());
+        context.workspace_members.insert(tool, "tool".to_owned());
+        context
+    }
+
+    #[test]
+    fn restrict_to_members() {
+        let context = mock_context();
+
+        let graph = DependencyGraph::new(&context, &[], false).unwrap();
+        assert_eq!(graph.crates.len(), 4);
+
+        let graph = DependencyGraph::new(&context, &["app".to_owned()], false).unwrap();
+        assert_eq!(
+            graph
+                .crates
+                .keys()
+                .map(|id| id.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["app", "regex"]
+        );
+
+        assert!(DependencyGraph::new(&context, &["regex".to_owned()], false).is_err());
+    }
+
+    #[test]
+    fn spdx_document() {
+        let context = mock_context();
+        let graph = DependencyGraph::new(&context, &["app".to_owned()], false).unwrap();
+
+        let document = serde_json::to_value(SpdxDocument::new(
+            &graph,
+            "mock",
+            Some("https://example.com/mock"),
+            format_timestamp(0),
+        ))
+        .unwrap();
+
+        assert_eq!(document["spdxVersion"], "SPDX-2.3");
+        assert_eq!(document["creationInfo"]["created"], "1970-01-01T00:00:00Z");
+        assert_eq!(
+            document["packages"][1],
+            serde_json::json!({
+                "name": "regex",
+                "SPDXID": "SPDXRef-Package-regex-1.0.0-45216d2003a1",
+                "versionInfo": "1.0.0",
+                "downloadLocation": "https://static.crates.io/crates/regex/1.0.0/download",
+                "filesAnalyzed": false,
+                "checksums": [{"algorithm": "SHA256", "checksumValue": "abcd"}],
+                "licenseConcluded": "NOASSERTION",
+                "licenseDeclared": "MIT OR Apache-2.0",
+                "externalRefs": [{
+                    "referenceCategory": "PACKAGE-MANAGER",
+                    "referenceType": "purl",
+                    "referenceLocator": "pkg:cargo/regex@1.0.0",
+                }],
+            })
+        );
+        assert_eq!(
+            document["relationships"],
+            serde_json::json!([
+                {
+                    "spdxElementId": "SPDXRef-DOCUMENT",
+                    "relationshipType": "DESCRIBES",
+                    "relatedSpdxElement": "SPDXRef-Package-app-0.1.0-54da2cbb3be8",
+                },
+                {
+                    "spdxElementId": "SPDXRef-Package-app-0.1.0-54da2cbb3be8",
+                    "relationshipType": "DEPENDS_ON",
+                    "relatedSpdxElement": "SPDXRef-Package-regex-1.0.0-45216d2003a1",
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn unique_spdx_ids() {
+        // Each of these pairs sanitizes to the same characters.
+        let ids = [
+            CrateId::new("mock".to_owned(), Version::parse("1.0.0+meta").unwrap()),
+            CrateId::new("mock".to_owned(), Version::parse("1.0.0-meta").unwrap()),
+            CrateId::new("foo_bar".to_owned(), Version::new(0, 1, 0)),
+            CrateId::new("foo-bar".to_owned(), Version::new(0, 1, 0)),
+        ];
+
+        let spdx_ids: BTreeSet<String> = ids.iter().map(spdx_id).collect();
+        assert_eq!(spdx_ids.len(), ids.len());
+    }
+
+    #[test]
+    fn cyclonedx_document() {
+        let context = mock_context();
+        let graph = DependencyGraph::new(&context, &["app".to_owned()], false).unwrap();
+
+        let document =
+            serde_json::to_value(CycloneDxDocument::new(&graph, format_timestamp(0))).unwrap();
+
+        assert_eq!(document["specVersion"], "1.5");
+        assert_eq!(document["components"][0]["type"], "application");
+        assert_eq!(
+            document["components"][1],
+            serde_json::json!({
+                "type": "library",
+                "bom-ref": "regex@1.0.0",
+                "name": "regex",
+                "version": "1.0.0",
+                "purl": "pkg:cargo/regex@1.0.0",
+                "hashes": [{"alg": "SHA-256", "content": "abcd"}],
+                "licenses": [{"expression": "MIT OR Apache-2.0"}],
+                "externalReferences": [{
+                    "type": "distribution",
+                    "url": "https://static.crates.io/crates/regex/1.0.0/download",
+                }],
+            })
+        );
+        assert_eq!(
+            document["dependencies"],
+            serde_json::json!([
+                {"ref": "app@0.1.0", "dependsOn": ["regex@1.0.0"]},
+                {"ref": "regex@1.0.0", "dependsOn": []},
+            ])
+        );
+    }
+
+    #[test]
+    fn timestamps() {
+        assert_eq!(format_timestamp(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_timestamp(951782400), "2000-02-29T00:00:00Z");
+        assert_eq!(format_timestamp(1700000000), "2023-11-14T22:13:20Z");
+    }
+}
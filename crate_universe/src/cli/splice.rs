@@ -1,6 +1,6 @@
 //! The cli entrypoint for the `splice` subcommand
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use anyhow::Context;
 use clap::Parser;
@@ -10,7 +10,9 @@ use crate::config::Config;
 use crate::metadata::{
     write_metadata, Cargo, CargoUpdateRequest, Generator, MetadataGenerator, TreeResolver,
 };
-use crate::splicing::{generate_lockfile, Splicer, SplicingManifest, WorkspaceMetadata};
+use crate::splicing::{
+    generate_lockfile, SplicedManifest, Splicer, SplicingManifest, WorkspaceMetadata,
+};
 
 /// Command line options for the `splice` subcommand
 #[derive(Parser, Debug)]
@@ -74,43 +76,19 @@ pub fn splice(opt: SpliceOptions) -> Result<()> {
         }
     };
 
-    // Generate a splicer for creating a Cargo workspace manifest
-    let splicer = Splicer::new(splicing_dir, splicing_manifest)?;
-
-    // Splice together the manifest
-    let manifest_path = splicer
-        .splice_workspace(&opt.cargo)
-        .context("Failed to splice workspace")?;
-
-    let cargo = Cargo::new(opt.cargo);
+    let config = Config::try_from_path(&opt.config).context("Failed to parse config")?;
 
-    // Generate a lockfile
-    let cargo_lockfile = generate_lockfile(
-        &manifest_path,
+    let manifest_path = resolve_spliced_workspace(
+        splicing_manifest,
+        splicing_dir,
         &opt.cargo_lockfile,
-        cargo.clone(),
-        &opt.rustc,
         &opt.repin,
-    )
-    .context("Failed to generate lockfile")?;
-
-    let config = Config::try_from_path(&opt.config).context("Failed to parse config")?;
+        &config,
+        &opt.cargo,
+        &opt.rustc,
+    )?;
 
-    let resolver_data = TreeResolver::new(cargo.clone(), opt.rustc.clone())
-        .generate(
-            manifest_path.as_path_buf(),
-            &config.supported_platform_triples,
-        )
-        .context("Failed to generate features")?;
-    // Write the registry url info to the manifest now that a lockfile has been generated
-    WorkspaceMetadata::write_registry_urls_and_feature_map(
-        &cargo,
-        &cargo_lockfile,
-        resolver_data,
-        manifest_path.as_path_buf(),
-        manifest_path.as_path_buf(),
-    )
-    .context("Failed to write registry URLs and feature map")?;
+    let cargo = Cargo::new(opt.cargo);
 
     let output_dir = opt.output_dir.clone();
 
@@ -144,3 +122,65 @@ pub fn splice(opt: SpliceOptions) -> Result<()> {
 
     Ok(())
 }
+
+/// Splice together a workspace, generate its lockfile, and record the resolved sources
+/// and per-platform features of each crate in the workspace manifest.
+pub(crate) fn resolve_spliced_workspace(
+    splicing_manifest: SplicingManifest,
+    splicing_dir: PathBuf,
+    cargo_lockfile: &Option<PathBuf>,
+    repin: &Option<CargoUpdateRequest>,
+    config: &Config,
+    cargo_bin: &Path,
+    rustc_bin: &Path,
+) -> Result<SplicedManifest> {
+    let local_registry_url_template = splicing_manifest.local_registry_url_template.clone();
+
+    // Generate a splicer for creating a Cargo workspace manifest
+    let splicer = Splicer::new(splicing_dir, splicing_manifest)?;
+
+    // Splice together the manifest
+    let manifest_path = splicer
+        .splice_workspace(cargo_bin)
+        .context("Failed to splice workspace")?;
+
+    let cargo = Cargo::new(cargo_bin.to_path_buf());
+
+    // Generate a lockfile
+    let cargo_lockfile = generate_lockfile(
+        &manifest_path,
+        cargo_lockfile,
+        cargo.clone(),
+        rustc_bin,
+        repin,
+    )
+    .context("Failed to generate lockfile")?;
+
+    let tree_resolver = TreeResolver::new(cargo.clone(), rustc_bin.to_path_buf());
+    let resolver_data = tree_resolver
+        .generate(
+            manifest_path.as_path_buf(),
+            &config.supported_platform_triples,
+        )
+        .context("Failed to generate features")?;
+    let feature_variants = tree_resolver
+        .generate_feature_variants(
+            manifest_path.as_path_buf(),
+            &config.supported_platform_triples,
+            &config.feature_variants()?,
+        )
+        .context("Failed to resolve feature variants")?;
+    // Write the registry url info to the manifest now that a lockfile has been generated
+    WorkspaceMetadata::write_registry_urls_and_feature_map(
+        &cargo,
+        &cargo_lockfile,
+        resolver_data,
+        feature_variants,
+        manifest_path.as_path_buf(),
+        manifest_path.as_path_buf(),
+        local_registry_url_template.as_deref(),
+    )
+    .context("Failed to write registry URLs and feature map")?;
+
+    Ok(manifest_path)
+}
@@ -1,6 +1,6 @@
 //! The cli entrypoint for the `vendor` subcommand
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::env;
 use std::fs;
 use std::path::{Path, PathBuf};
@@ -8,15 +8,16 @@ use std::process::{self, ExitStatus};
 
 use anyhow::{bail, Context as AnyhowContext, Result};
 use clap::Parser;
+use serde::Deserialize;
 
-use crate::config::{Config, VendorMode};
+use crate::config::{Config, CrateId, VendorMode};
 use crate::context::Context;
 use crate::metadata::CargoUpdateRequest;
 use crate::metadata::TreeResolver;
 use crate::metadata::{Annotations, Cargo, Generator, MetadataGenerator, VendorGenerator};
-use crate::rendering::{render_module_label, write_outputs, Renderer};
+use crate::rendering::{render_module_label, write_outputs, DirectRepositories, Renderer};
 use crate::splicing::{generate_lockfile, Splicer, SplicingManifest, WorkspaceMetadata};
-use crate::utils::normalize_cargo_file_paths;
+use crate::utils::{normalize_cargo_file_paths, sanitize_repository_name};
 
 /// Command line options for the `vendor` subcommand
 #[derive(Parser, Debug)]
@@ -73,6 +74,11 @@ pub struct VendorOptions {
     /// If true, outputs will be printed instead of written to disk.
     #[clap(long)]
     pub dry_run: bool,
+
+    /// The path, relative to `workspace_dir`, of a `MODULE.bazel` file whose marked region
+    /// should be replaced with `use_repo` calls for the vendored repositories.
+    #[clap(long)]
+    pub module_bazel: Option<PathBuf>,
 }
 
 /// Run buildifier on a given file.
@@ -122,6 +128,8 @@ pub fn vendor(opt: VendorOptions) -> Result<()> {
 
     let temp_dir = tempfile::tempdir().context("Failed to create temporary directory")?;
 
+    let local_registry_url_template = splicing_manifest.local_registry_url_template.clone();
+
     // Generate a splicer for creating a Cargo workspace manifest
     let splicer = Splicer::new(PathBuf::from(temp_dir.as_ref()), splicing_manifest)
         .context("Failed to create splicer")?;
@@ -145,9 +153,15 @@ pub fn vendor(opt: VendorOptions) -> Result<()> {
     // Load the config from disk
     let config = Config::try_from_path(&opt.config)?;
 
-    let resolver_data = TreeResolver::new(cargo.clone(), opt.rustc.clone()).generate(
+    let tree_resolver = TreeResolver::new(cargo.clone(), opt.rustc.clone());
+    let resolver_data = tree_resolver.generate(
+        manifest_path.as_path_buf(),
+        &config.supported_platform_triples,
+    )?;
+    let feature_variants = tree_resolver.generate_feature_variants(
         manifest_path.as_path_buf(),
         &config.supported_platform_triples,
+        &config.feature_variants()?,
     )?;
 
     // Write the registry url info to the manifest now that a lockfile has been generated
@@ -155,8 +169,10 @@ pub fn vendor(opt: VendorOptions) -> Result<()> {
         &cargo,
         &cargo_lockfile,
         resolver_data,
+        feature_variants,
         manifest_path.as_path_buf(),
         manifest_path.as_path_buf(),
+        local_registry_url_template.as_deref(),
     )?;
 
     // Write metadata to the workspace for future reuse
@@ -175,16 +191,11 @@ pub fn vendor(opt: VendorOptions) -> Result<()> {
     let outputs = Renderer::new(
         config.rendering.clone(),
         config.supported_platform_triples.clone(),
-    )
+    )?
     .render(&context)?;
 
-    // First ensure vendoring and rendering happen in a clean directory
     let vendor_dir_label = render_module_label(&config.rendering.crates_module_template, "BUILD")?;
     let vendor_dir = opt.workspace_dir.join(vendor_dir_label.package().unwrap());
-    if vendor_dir.exists() {
-        fs::remove_dir_all(&vendor_dir)
-            .with_context(|| format!("Failed to delete {}", vendor_dir.display()))?;
-    }
 
     // Store the updated Cargo.lock
     if let Some(path) = &opt.cargo_lockfile {
@@ -192,20 +203,59 @@ pub fn vendor(opt: VendorOptions) -> Result<()> {
             .context("Failed to write Cargo.lock file back to the workspace.")?;
     }
 
-    if matches!(config.rendering.vendor_mode, Some(VendorMode::Local)) {
+    // Sources are vendored to a staging directory so only the crates which changed
+    // need to be moved into the vendor directory.
+    let mut vendored_crates = BTreeSet::new();
+    if matches!(config.rendering.vendor_mode, Some(VendorMode::Local)) && !opt.dry_run {
+        fs::create_dir_all(&vendor_dir)
+            .with_context(|| format!("Failed to create {}", vendor_dir.display()))?;
+        let staging_dir = tempfile::tempdir_in(&vendor_dir)
+            .context("Failed to create vendor staging directory")?;
+
         VendorGenerator::new(cargo, opt.rustc.clone())
-            .generate(manifest_path.as_path_buf(), &vendor_dir)
+            .generate(manifest_path.as_path_buf(), staging_dir.as_ref())
             .context("Failed to vendor dependencies")?;
+
+        let changes = sync_vendored_crates(staging_dir.as_ref(), &vendor_dir)
+            .context("Failed to update vendored crates")?;
+        changes.log();
+
+        vendored_crates = changes.dirs;
     }
 
     // make cargo versioned crates compatible with bazel labels
     let normalized_outputs = normalize_cargo_file_paths(outputs, &opt.workspace_dir);
 
+    // Remove anything left behind from previous runs which is no longer generated.
+    if vendor_dir.exists() && !opt.dry_run {
+        remove_stale_entries(&vendor_dir, &vendored_crates, &normalized_outputs)?;
+    }
+
+    // Optionally update the `use_repo` calls of a `MODULE.bazel` file.
+    let mut module_outputs = BTreeMap::new();
+    if let Some(module_bazel) = &opt.module_bazel {
+        if !matches!(config.rendering.vendor_mode, Some(VendorMode::Remote)) {
+            bail!("Updating a `MODULE.bazel` file is only supported for the `remote` vendor mode");
+        }
+        let path = opt.workspace_dir.join(module_bazel);
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let use_repo = DirectRepositories::new(&config.rendering, &context).render_use_repo();
+        let updated = update_module_bazel(&content, &config.rendering.repository_name, &use_repo)
+            .with_context(|| format!("Failed to update {}", path.display()))?;
+        module_outputs.insert(path, updated);
+    }
+
     // buildifier files to check
-    let file_names: BTreeSet<PathBuf> = normalized_outputs.keys().cloned().collect();
+    let file_names: BTreeSet<PathBuf> = normalized_outputs
+        .keys()
+        .filter(|path| is_starlark_file(path))
+        .cloned()
+        .collect();
 
     // Write outputs
     write_outputs(normalized_outputs, opt.dry_run).context("Failed writing output files")?;
+    write_outputs(module_outputs, opt.dry_run).context("Failed writing MODULE.bazel file")?;
 
     // Optionally apply buildifier fixes
     if let Some(buildifier_bin) = opt.buildifier {
@@ -218,3 +268,334 @@ pub fn vendor(opt: VendorOptions) -> Result<()> {
 
     Ok(())
 }
+
+/// Whether a generated file contains Starlark (and should be formatted by buildifier).
+fn is_starlark_file(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|ext| ext.to_str()),
+        Some("bazel" | "bzl")
+    ) || path.file_name().is_some_and(|name| name == "BUILD")
+}
+
+/// Replace the lines between the `use_repo` markers for `repository_name` in the content
+/// of a `MODULE.bazel` file. The markers are full line comments, Eg:
+///
+/// ```text
+/// # crates_vendor: begin use_repo(crates)
+/// # crates_vendor: end use_repo(crates)
+/// ```
+fn update_module_bazel(content: &str, repository_name: &str, use_repo: &str) -> Result<String> {
+    let begin = format!("# crates_vendor: begin use_repo({repository_name})");
+    let end = format!("# crates_vendor: end use_repo({repository_name})");
+
+    let lines: Vec<&str> = content.lines().collect();
+    let find = |marker: &str| {
+        let matches: Vec<usize> = lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| line.trim() == marker)
+            .map(|(index, _)| index)
+            .collect();
+        match matches.as_slice() {
+            [index] => Ok(*index),
+            [] => bail!("Missing marker comment `{}`", marker),
+            _ => bail!("Found multiple marker comments `{}`", marker),
+        }
+    };
+    let begin_index = find(&begin)?;
+    let end_index = find(&end)?;
+    if end_index < begin_index {
+        bail!("The marker comment `{}` must come before `{}`", begin, end);
+    }
+
+    let mut updated: Vec<&str> = lines[..=begin_index].to_vec();
+    updated.extend(use_repo.lines());
+    updated.extend(&lines[end_index..]);
+
+    let mut updated = updated.join("\n");
+    if content.ends_with('\n') {
+        updated.push('\n');
+    }
+    Ok(updated)
+}
+
+/// The crates which were updated in a vendor directory by [sync_vendored_crates].
+#[derive(Debug, Default, PartialEq, Eq)]
+struct VendorChanges {
+    /// Crates which were not previously vendored.
+    added: BTreeSet<CrateId>,
+
+    /// Crates which are no longer vendored.
+    removed: BTreeSet<CrateId>,
+
+    /// Crates which were vendored but whose sources have changed.
+    changed: BTreeSet<CrateId>,
+
+    /// The names of all directories of vendored crates.
+    dirs: BTreeSet<String>,
+}
+
+impl VendorChanges {
+    fn log(&self) {
+        for (action, crates) in [
+            ("Added", &self.added),
+            ("Removed", &self.removed),
+            ("Updated", &self.changed),
+        ] {
+            for crate_id in crates {
+                tracing::info!("{} vendored crate {}", action, crate_id);
+            }
+        }
+    }
+}
+
+/// The subset of a vendored crate's `Cargo.toml` needed to identify it.
+#[derive(Debug, Deserialize)]
+struct VendoredManifest {
+    package: VendoredPackage,
+}
+
+#[derive(Debug, Deserialize)]
+struct VendoredPackage {
+    name: String,
+    version: semver::Version,
+}
+
+/// Determine the [CrateId] of a crate vendored by `cargo vendor`.
+fn vendored_crate_id(crate_dir: &Path) -> Result<CrateId> {
+    let manifest_path = crate_dir.join("Cargo.toml");
+    let content = fs::read_to_string(&manifest_path)
+        .with_context(|| format!("Failed to read {}", manifest_path.display()))?;
+    let manifest: VendoredManifest = toml::from_str(&content)
+        .with_context(|| format!("Failed to parse {}", manifest_path.display()))?;
+
+    Ok(CrateId::new(
+        manifest.package.name,
+        manifest.package.version,
+    ))
+}
+
+/// Locate the directories of crates vendored by `cargo vendor` which contain
+/// a `.cargo-checksum.json` file describing their sources.
+fn vendored_crate_dirs(dir: &Path) -> Result<BTreeMap<String, PathBuf>> {
+    let mut crate_dirs = BTreeMap::new();
+    for entry in fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))? {
+        let path = entry?.path();
+        if !path.join(".cargo-checksum.json").exists() {
+            continue;
+        }
+        if let Some(name) = path.file_name().and_then(|name| name.to_str()) {
+            crate_dirs.insert(sanitize_repository_name(name), path);
+        }
+    }
+
+    Ok(crate_dirs)
+}
+
+/// Move crates vendored to `staging_dir` into `vendor_dir`. Crates whose sources are
+/// unchanged (as reported by the `.cargo-checksum.json` file `cargo vendor` writes for
+/// each crate) are left untouched.
+fn sync_vendored_crates(staging_dir: &Path, vendor_dir: &Path) -> Result<VendorChanges> {
+    let staged = vendored_crate_dirs(staging_dir)?;
+    let existing = vendored_crate_dirs(vendor_dir)?;
+
+    let mut changes = VendorChanges::default();
+
+    for (name, path) in existing.iter() {
+        if staged.contains_key(name) {
+            continue;
+        }
+        changes.removed.insert(vendored_crate_id(path)?);
+        fs::remove_dir_all(path).with_context(|| format!("Failed to delete {}", path.display()))?;
+    }
+
+    for (name, staged_path) in staged.into_iter() {
+        let checksum = |dir: &Path| fs::read(dir.join(".cargo-checksum.json")).ok();
+        let crate_id = vendored_crate_id(&staged_path)?;
+        let dest = vendor_dir.join(&name);
+
+        match existing.get(&name) {
+            Some(path) if checksum(path) == checksum(&staged_path) => {}
+            Some(path) => {
+                fs::remove_dir_all(path)
+                    .with_context(|| format!("Failed to delete {}", path.display()))?;
+                fs::rename(&staged_path, &dest)
+                    .with_context(|| format!("Failed to move {}", staged_path.display()))?;
+                changes.changed.insert(crate_id);
+            }
+            None => {
+                fs::rename(&staged_path, &dest)
+                    .with_context(|| format!("Failed to move {}", staged_path.display()))?;
+                changes.added.insert(crate_id);
+            }
+        }
+
+        changes.dirs.insert(name);
+    }
+
+    Ok(changes)
+}
+
+/// Delete any files or directories in `vendor_dir` which are neither vendored crates nor
+/// the parents of generated outputs.
+fn remove_stale_entries(
+    vendor_dir: &Path,
+    vendored_crates: &BTreeSet<String>,
+    outputs: &BTreeMap<PathBuf, String>,
+) -> Result<()> {
+    for entry in fs::read_dir(vendor_dir)
+        .with_context(|| format!("Failed to read {}", vendor_dir.display()))?
+    {
+        let path = entry?.path();
+
+        let is_vendored_crate = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .map(|name| vendored_crates.contains(name))
+            .unwrap_or(false);
+        let is_output = outputs.keys().any(|output| output.starts_with(&path));
+        if is_vendored_crate || is_output {
+            continue;
+        }
+
+        tracing::debug!("Removing stale vendored path {}", path.display());
+        if path.is_dir() {
+            fs::remove_dir_all(&path)
+        } else {
+            fs::remove_file(&path)
+        }
+        .with_context(|| format!("Failed to delete {}", path.display()))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn write_crate(dir: &Path, name: &str, version: &str, checksum: &str) {
+        let crate_dir = dir.join(format!("{name}-{version}"));
+        fs::create_dir_all(&crate_dir).unwrap();
+        fs::write(
+            crate_dir.join("Cargo.toml"),
+            format!("[package]\nname = \"{name}\"\nversion = \"{version}\"\n"),
+        )
+        .unwrap();
+        fs::write(
+            crate_dir.join(".cargo-checksum.json"),
+            format!("{{\"files\":{{}},\"package\":\"{checksum}\"}}"),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn update_module_bazel_region() {
+        let content = indoc::indoc! {r#"
+            module(name = "example")
+
+            # crates_vendor: begin use_repo(crates)
+            use_repo(crate_repositories, "stale")
+            # crates_vendor: end use_repo(crates)
+
+            bazel_dep(name = "rules_rust", version = "0.0.0")
+        "#};
+        let use_repo = "use_repo(\n    crate_repositories,\n    \"crates\",\n)\n";
+
+        assert_eq!(
+            update_module_bazel(content, "crates", use_repo).unwrap(),
+            indoc::indoc! {r#"
+                module(name = "example")
+
+                # crates_vendor: begin use_repo(crates)
+                use_repo(
+                    crate_repositories,
+                    "crates",
+                )
+                # crates_vendor: end use_repo(crates)
+
+                bazel_dep(name = "rules_rust", version = "0.0.0")
+            "#}
+        );
+
+        // The markers must name the repository being vendored.
+        assert!(update_module_bazel(content, "other", use_repo).is_err());
+    }
+
+    #[test]
+    fn sync_only_changed_crates() {
+        let staging_dir = tempfile::tempdir().unwrap();
+        let vendor_dir = tempfile::tempdir().unwrap();
+
+        write_crate(vendor_dir.as_ref(), "removed", "1.0.0", "aaaa");
+        write_crate(vendor_dir.as_ref(), "changed", "1.0.0", "bbbb");
+        write_crate(vendor_dir.as_ref(), "unchanged", "1.0.0", "cccc");
+        // Rendered files within vendored crates should be retained for unchanged crates.
+        let unchanged_build_file = vendor_dir.as_ref().join("unchanged-1.0.0/BUILD.bazel");
+        fs::write(&unchanged_build_file, "# unchanged").unwrap();
+
+        write_crate(staging_dir.as_ref(), "added", "1.0.0+meta", "dddd");
+        write_crate(staging_dir.as_ref(), "changed", "1.0.0", "eeee");
+        write_crate(staging_dir.as_ref(), "unchanged", "1.0.0", "cccc");
+
+        let changes = sync_vendored_crates(staging_dir.as_ref(), vendor_dir.as_ref()).unwrap();
+
+        let crate_id = |name: &str, version: &str| {
+            CrateId::new(name.to_owned(), semver::Version::parse(version).unwrap())
+        };
+        assert_eq!(
+            changes,
+            VendorChanges {
+                added: BTreeSet::from([crate_id("added", "1.0.0+meta")]),
+                removed: BTreeSet::from([crate_id("removed", "1.0.0")]),
+                changed: BTreeSet::from([crate_id("changed", "1.0.0")]),
+                dirs: BTreeSet::from([
+                    "added-1.0.0-meta".to_owned(),
+                    "changed-1.0.0".to_owned(),
+                    "unchanged-1.0.0".to_owned(),
+                ]),
+            }
+        );
+
+        assert!(!vendor_dir.as_ref().join("removed-1.0.0").exists());
+        assert!(vendor_dir.as_ref().join("added-1.0.0-meta").exists());
+        assert_eq!(
+            fs::read_to_string(
+                vendor_dir
+                    .as_ref()
+                    .join("changed-1.0.0/.cargo-checksum.json")
+            )
+            .unwrap(),
+            "{\"files\":{},\"package\":\"eeee\"}"
+        );
+        assert_eq!(
+            fs::read_to_string(unchanged_build_file).unwrap(),
+            "# unchanged"
+        );
+    }
+
+    #[test]
+    fn remove_stale_vendor_entries() {
+        let vendor_dir = tempfile::tempdir().unwrap();
+        let vendor_dir = vendor_dir.as_ref();
+
+        fs::write(vendor_dir.join("BUILD.bazel"), "").unwrap();
+        fs::write(vendor_dir.join("BUILD.stale-1.0.0.bazel"), "").unwrap();
+        fs::create_dir_all(vendor_dir.join("stale-1.0.0")).unwrap();
+        fs::create_dir_all(vendor_dir.join("vendored-1.0.0")).unwrap();
+
+        remove_stale_entries(
+            vendor_dir,
+            &BTreeSet::from(["vendored-1.0.0".to_owned()]),
+            &BTreeMap::from([(vendor_dir.join("BUILD.bazel"), String::new())]),
+        )
+        .unwrap();
+
+        let mut remaining: Vec<String> = fs::read_dir(vendor_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        remaining.sort();
+        assert_eq!(remaining, vec!["BUILD.bazel", "vendored-1.0.0"]);
+    }
+}
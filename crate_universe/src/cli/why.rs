@@ -0,0 +1,381 @@
+//! The cli entrypoint for the `why` subcommand
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+
+use anyhow::{bail, Context as AnyhowContext, Result};
+use clap::Parser;
+use semver::{Version, VersionReq};
+
+use crate::cli::DeriveOptions;
+use crate::config::CrateId;
+use crate::context::{Context, DependencyKind};
+
+/// Command line options for the `why` subcommand
+#[derive(Parser, Debug)]
+#[clap(about = "Command line options for the `why` subcommand", version)]
+pub struct WhyOptions {
+    /// The path to a cargo-bazel lockfile
+    #[clap(long)]
+    pub lockfile: PathBuf,
+
+    #[clap(flatten)]
+    pub derive: DeriveOptions,
+
+    /// List the dependents of a crate every time it appears rather than only the first time,
+    /// similar to `cargo tree --no-dedupe`.
+    #[clap(long)]
+    pub no_dedupe: bool,
+
+    /// The crate to explain, in the form `name` or `name@version`. The version
+    /// may be an exact version or a semver requirement (eg. `syn@1`).
+    pub spec: String,
+}
+
+/// Print the crates which depend on a crate in a cargo-bazel lockfile as an inverted tree
+pub fn why(opt: WhyOptions) -> Result<()> {
+    let context = opt.derive.load_context(&opt.lockfile)?;
+
+    let spec = CrateSpec::parse(&opt.spec)?;
+    let targets: BTreeSet<&CrateId> = context
+        .crates
+        .keys()
+        .filter(|id| spec.matches(id))
+        .collect();
+    if targets.is_empty() {
+        bail!(
+            "No crate matching `{}` was found in '{}'",
+            opt.spec,
+            opt.lockfile.display()
+        );
+    }
+
+    let graph = DependencyGraph::new(&context);
+    for (i, target) in targets.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+
+        match graph.dependents.contains_key(target) {
+            true => println!(
+                "{}",
+                graph.render_inverted_tree(&context, target, !opt.no_dedupe)
+            ),
+            false => println!("{target} is not depended on by any other crate"),
+        }
+    }
+
+    Ok(())
+}
+
+/// A crate name and optional version as passed on the command line.
+#[derive(Debug, PartialEq, Eq)]
+struct CrateSpec {
+    name: String,
+    version: Option<VersionSpec>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum VersionSpec {
+    Exact(Version),
+    Req(VersionReq),
+}
+
+impl CrateSpec {
+    fn parse(spec: &str) -> Result<Self> {
+        let (name, version) = match spec.split_once('@') {
+            Some((name, version)) => {
+                let version = match Version::parse(version) {
+                    Ok(version) => VersionSpec::Exact(version),
+                    Err(_) => VersionSpec::Req(VersionReq::parse(version).with_context(|| {
+                        format!("Invalid version `{version}` in crate spec `{spec}`")
+                    })?),
+                };
+                (name, Some(version))
+            }
+            None => (spec, None),
+        };
+
+        Ok(Self {
+            name: name.to_owned(),
+            version,
+        })
+    }
+
+    fn matches(&self, id: &CrateId) -> bool {
+        id.name == self.name
+            && match &self.version {
+                None => true,
+                Some(VersionSpec::Exact(version)) => &id.version == version,
+                Some(VersionSpec::Req(req)) => req.matches(&id.version),
+            }
+    }
+}
+
+/// A dependency of one crate on another and the configuration it's conditional on, if any.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Edge {
+    kind: DependencyKind,
+    configuration: Option<String>,
+}
+
+/// The dependencies between all crates in a [Context], keyed by the crate depended on.
+struct DependencyGraph<'a> {
+    dependents: BTreeMap<&'a CrateId, BTreeMap<&'a CrateId, BTreeSet<Edge>>>,
+}
+
+impl<'a> DependencyGraph<'a> {
+    fn new(context: &'a Context) -> Self {
+        let mut dependents: BTreeMap<&CrateId, BTreeMap<&CrateId, BTreeSet<Edge>>> =
+            BTreeMap::new();
+        for (id, krate) in &context.crates {
+            for (kind, configuration, dep) in krate.dependencies() {
+                if let Some((dep_id, _)) = context.crates.get_key_value(&dep.id) {
+                    dependents
+                        .entry(dep_id)
+                        .or_default()
+                        .entry(id)
+                        .or_default()
+                        .insert(Edge {
+                            kind,
+                            configuration,
+                        });
+                }
+            }
+        }
+
+        Self { dependents }
+    }
+
+    /// Render the crates depending on the target as an inverted tree, similar to `cargo tree -i`.
+    /// When deduplicating, the dependents of each crate are only listed the first time it appears
+    /// and later occurrences are marked with `(*)`, which keeps the output linear in the size of
+    /// the graph. Otherwise every path is listed and only cycles are marked with `(*)`.
+    fn render_inverted_tree(&self, context: &Context, target: &'a CrateId, dedupe: bool) -> String {
+        let mut lines = vec![target.to_string()];
+        let mut expanded = BTreeSet::from([target]);
+        self.render_dependents(context, target, "", dedupe, &mut expanded, &mut lines);
+        lines.join("\n")
+    }
+
+    /// Render the dependents of a crate. `expanded` holds the crates whose dependents were
+    /// already rendered, or only the crates on the current path when not deduplicating.
+    fn render_dependents(
+        &self,
+        context: &Context,
+        id: &'a CrateId,
+        prefix: &str,
+        dedupe: bool,
+        expanded: &mut BTreeSet<&'a CrateId>,
+        lines: &mut Vec<String>,
+    ) {
+        let dependents = match self.dependents.get(id) {
+            Some(dependents) => dependents,
+            None => return,
+        };
+
+        for (i, (dependent, edges)) in dependents.iter().enumerate() {
+            let (branch, indent) = match i + 1 == dependents.len() {
+                true => ("└── ", "    "),
+                false => ("├── ", "│   "),
+            };
+            let annotations = edges
+                .iter()
+                .map(|edge| match &edge.configuration {
+                    Some(configuration) => format!(
+                        "{}, {}",
+                        edge.kind,
+                        describe_configuration(context, configuration)
+                    ),
+                    None => edge.kind.to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join("; ");
+
+            let is_new = expanded.insert(dependent);
+            let elided = !is_new && self.dependents.contains_key(dependent);
+            lines.push(format!(
+                "{prefix}{branch}{dependent} ({annotations}){}",
+                if elided { " (*)" } else { "" }
+            ));
+            if is_new {
+                self.render_dependents(
+                    context,
+                    dependent,
+                    &format!("{prefix}{indent}"),
+                    dedupe,
+                    expanded,
+                    lines,
+                );
+                if !dedupe {
+                    expanded.remove(dependent);
+                }
+            }
+        }
+    }
+}
+
+/// Describe a configuration along with the platforms which satisfy it.
+fn describe_configuration(context: &Context, configuration: &str) -> String {
+    let platforms: Vec<String> = context
+        .conditions
+        .get(configuration)
+        .into_iter()
+        .flatten()
+        .map(|triple| triple.to_bazel())
+        .chain(
+            context
+                .custom_conditions
+                .get(configuration)
+                .into_iter()
+                .flatten()
+                .cloned(),
+        )
+        .collect();
+
+    match platforms.is_empty() {
+        true => format!("{configuration}: no supported platforms"),
+        false => format!("{}: {}", configuration, platforms.join(", ")),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::test::mock_crate_context;
+    use crate::utils::target_triple::TargetTriple;
+
+    #[test]
+    fn parse_crate_spec() {
+        let spec = CrateSpec::parse("syn").unwrap();
+        assert!(spec.matches(&CrateId::new("syn".to_owned(), Version::new(1, 0, 109))));
+        assert!(spec.matches(&CrateId::new("syn".to_owned(), Version::new(2, 0, 0))));
+
+        let spec = CrateSpec::parse("syn@1").unwrap();
+        assert!(spec.matches(&CrateId::new("syn".to_owned(), Version::new(1, 0, 109))));
+        assert!(!spec.matches(&CrateId::new("syn".to_owned(), Version::new(2, 0, 0))));
+
+        let spec = CrateSpec::parse("syn@1.0.0").unwrap();
+        assert!(spec.matches(&CrateId::new("syn".to_owned(), Version::new(1, 0, 0))));
+        assert!(!spec.matches(&CrateId::new("syn".to_owned(), Version::new(1, 0, 109))));
+
+        assert!(CrateSpec::parse("syn@latest").is_err());
+    }
+
+    #[test]
+    fn explain_dependents() {
+        let app = CrateId::new("app".to_owned(), Version::new(0, 1, 0));
+        let chrono = CrateId::new("chrono".to_owned(), Version::new(0, 4, 19));
+        let winapi = CrateId::new("winapi".to_owned(), Version::new(0, 3, 9));
+
+        let mut context = Context::default();
+        context.crates.insert(
+            app.clone(),
+            mock_crate_context(&app, &[(&chrono, None), (&winapi, Some("cfg(windows)"))]),
+        );
+        context.crates.insert(
+            chrono.clone(),
+            mock_crate_context(&chrono, &[(&winapi, Some("cfg(windows)"))]),
+        );
+        context
+            .crates
+            .insert(winapi.clone(), mock_crate_context(&winapi, &[]));
+        context.workspace_members.insert(app, "app".to_owned());
+        context.conditions.insert(
+            "cfg(windows)".to_owned(),
+            BTreeSet::from([TargetTriple::from_bazel(
+                "x86_64-pc-windows-msvc".to_owned(),
+            )]),
+        );
+
+        let graph = DependencyGraph::new(&context);
+        assert_eq!(
+            graph.render_inverted_tree(&context, &winapi, true),
+            indoc::indoc! {"
+                winapi 0.3.9
+                ├── app 0.1.0 (normal, cfg(windows): x86_64-pc-windows-msvc)
+                └── chrono 0.4.19 (normal, cfg(windows): x86_64-pc-windows-msvc)
+                    └── app 0.1.0 (normal)"
+            }
+        );
+    }
+
+    #[test]
+    fn deduplicate_dependents() {
+        let app = CrateId::new("app".to_owned(), Version::new(0, 1, 0));
+        let chrono = CrateId::new("chrono".to_owned(), Version::new(0, 4, 19));
+        let time = CrateId::new("time".to_owned(), Version::new(0, 1, 45));
+        let winapi = CrateId::new("winapi".to_owned(), Version::new(0, 3, 9));
+
+        let mut context = Context::default();
+        context
+            .crates
+            .insert(app.clone(), mock_crate_context(&app, &[(&chrono, None)]));
+        context.crates.insert(
+            chrono.clone(),
+            mock_crate_context(&chrono, &[(&time, None), (&winapi, None)]),
+        );
+        context
+            .crates
+            .insert(time.clone(), mock_crate_context(&time, &[(&winapi, None)]));
+        context
+            .crates
+            .insert(winapi.clone(), mock_crate_context(&winapi, &[]));
+        context.workspace_members.insert(app, "app".to_owned());
+
+        let graph = DependencyGraph::new(&context);
+        assert_eq!(
+            graph.render_inverted_tree(&context, &winapi, true),
+            indoc::indoc! {"
+                winapi 0.3.9
+                ├── chrono 0.4.19 (normal)
+                │   └── app 0.1.0 (normal)
+                └── time 0.1.45 (normal)
+                    └── chrono 0.4.19 (normal) (*)"
+            }
+        );
+    }
+
+    #[test]
+    fn list_every_path_without_deduplicating() {
+        let app = CrateId::new("app".to_owned(), Version::new(0, 1, 0));
+        let chrono = CrateId::new("chrono".to_owned(), Version::new(0, 4, 19));
+        let time = CrateId::new("time".to_owned(), Version::new(0, 1, 45));
+        let winapi = CrateId::new("winapi".to_owned(), Version::new(0, 3, 9));
+
+        let mut context = Context::default();
+        context
+            .crates
+            .insert(app.clone(), mock_crate_context(&app, &[(&chrono, None)]));
+        context.crates.insert(
+            chrono.clone(),
+            mock_crate_context(&chrono, &[(&time, None), (&winapi, None)]),
+        );
+        // A cycle, such as through a dev dependency, is still only expanded once.
+        context.crates.insert(
+            time.clone(),
+            mock_crate_context(&time, &[(&winapi, None), (&chrono, None)]),
+        );
+        context
+            .crates
+            .insert(winapi.clone(), mock_crate_context(&winapi, &[]));
+        context.workspace_members.insert(app, "app".to_owned());
+
+        let graph = DependencyGraph::new(&context);
+        assert_eq!(
+            graph.render_inverted_tree(&context, &winapi, false),
+            indoc::indoc! {"
+                winapi 0.3.9
+                ├── chrono 0.4.19 (normal)
+                │   ├── app 0.1.0 (normal)
+                │   └── time 0.1.45 (normal)
+                │       └── chrono 0.4.19 (normal) (*)
+                └── time 0.1.45 (normal)
+                    └── chrono 0.4.19 (normal)
+                        ├── app 0.1.0 (normal)
+                        └── time 0.1.45 (normal) (*)"
+            }
+        );
+    }
+}
@@ -1,15 +1,17 @@
 //! A module for configuration information
 
+mod recipes;
+
 use std::cmp::Ordering;
 use std::collections::{BTreeMap, BTreeSet};
 use std::fmt::Formatter;
 use std::iter::Sum;
 use std::ops::Add;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::{fmt, fs};
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use cargo_lock::package::GitReference;
 use cargo_metadata::Package;
 use semver::VersionReq;
@@ -17,6 +19,7 @@ use serde::de::value::SeqAccessDeserializer;
 use serde::de::{Deserializer, SeqAccess, Unexpected, Visitor};
 use serde::{Deserialize, Serialize, Serializer};
 
+pub(crate) use self::recipes::apply_recipe;
 use crate::select::{Select, Selectable};
 use crate::utils::starlark::Label;
 use crate::utils::target_triple::TargetTriple;
@@ -98,6 +101,37 @@ pub(crate) struct RenderConfig {
     /// Whether to generate package metadata
     #[serde(default = "default_generate_rules_license_metadata")]
     pub(crate) generate_rules_license_metadata: bool,
+
+    /// Patterns for mirror urls to download each `http_archive` crate source from, in
+    /// addition to the crate's own url. Eg. `https://mirror.example.com/{name}/{name}-{version}.crate`.
+    /// The available format keys are [`{name}`, `{version}`, `{sha256}`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) crate_url_templates: Vec<String>,
+
+    /// Mirror url patterns used in place of `crate_url_templates` for crates sourced from
+    /// the registry with the given name, as named in the Cargo config. Eg. `crates-io`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) registry_url_templates: BTreeMap<String, Vec<String>>,
+
+    /// Paths to Tera templates which replace the built-in templates, keyed by the name of the
+    /// template they replace (Eg. `partials/header.j2` or `module_bzl.j2`). Their contents are
+    /// loaded into `templates` when the config is read, so the paths are never serialized.
+    #[serde(default, skip_serializing)]
+    pub(crate) template_paths: BTreeMap<String, PathBuf>,
+
+    /// The contents of each template loaded from `template_paths`, keyed by name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) templates: BTreeMap<String, String>,
+
+    /// Rules to render in place of the default rule of each kind of crate target.
+    #[serde(default, skip_serializing_if = "TargetRules::is_empty")]
+    pub(crate) target_rules: TargetRules,
+
+    /// Whether to only check out the directory of each git sourced crate, as found by its
+    /// `strip_prefix`, when the crate has no `sparse_checkout_patterns` annotation. Sparse
+    /// checkouts require Bazel 7.1 or later.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) generate_sparse_checkouts: bool,
 }
 
 // Default is manually implemented so that the default values match the default
@@ -118,6 +152,12 @@ impl Default for RenderConfig {
             regen_command: String::default(),
             vendor_mode: Option::default(),
             generate_rules_license_metadata: default_generate_rules_license_metadata(),
+            crate_url_templates: Vec::default(),
+            registry_url_templates: BTreeMap::default(),
+            template_paths: BTreeMap::default(),
+            templates: BTreeMap::default(),
+            target_rules: TargetRules::default(),
+            generate_sparse_checkouts: bool::default(),
         }
     }
 }
@@ -126,6 +166,58 @@ impl RenderConfig {
     pub(crate) fn are_sources_present(&self) -> bool {
         self.vendor_mode == Some(VendorMode::Local)
     }
+
+    /// Load the templates of `template_paths` into `templates`.
+    ///
+    /// Templates are only parsed once the built-in templates they replace are available
+    /// to the [crate::rendering::Renderer].
+    fn load_templates(&mut self) -> Result<()> {
+        for (name, path) in std::mem::take(&mut self.template_paths) {
+            let content = fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read template {}", path.display()))?;
+            self.templates.insert(name, content);
+        }
+        Ok(())
+    }
+}
+
+/// A rule, loaded from a `.bzl` file, which is rendered in place of a default rule.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct CustomRule {
+    /// The label of the `.bzl` file which defines the rule.
+    pub(crate) bzl: String,
+
+    /// The name of the rule.
+    pub(crate) rule: String,
+}
+
+/// Rules rendered in place of the default rule of each kind of crate target. Each
+/// must accept the same attributes as the rule it replaces.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct TargetRules {
+    /// Replaces `rust_library`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) rust_library: Option<CustomRule>,
+
+    /// Replaces `rust_proc_macro`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) rust_proc_macro: Option<CustomRule>,
+
+    /// Replaces `rust_binary`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) rust_binary: Option<CustomRule>,
+
+    /// Replaces `cargo_build_script`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) cargo_build_script: Option<CustomRule>,
+}
+
+impl TargetRules {
+    fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
 }
 
 fn default_build_file_template() -> String {
@@ -234,6 +326,47 @@ impl AliasRule {
     }
 }
 
+/// A `cc_library` compiled by Bazel in place of a crate's build script.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct CcLibraryAnnotation {
+    /// Globs of the C and C++ sources to compile, relative to the crate's root.
+    #[serde(default)]
+    pub(crate) srcs: BTreeSet<String>,
+
+    /// Globs of the headers used by `srcs`, relative to the crate's root.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub(crate) hdrs: BTreeSet<String>,
+
+    /// Globs to exclude from both `srcs` and `hdrs`.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub(crate) exclude: BTreeSet<String>,
+
+    /// Include directories, relative to the crate's root.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub(crate) includes: BTreeSet<String>,
+
+    /// Preprocessor definitions (Eg. `HAVE_CONFIG_H` or `ZSTD_MULTITHREAD=1`).
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub(crate) defines: BTreeSet<String>,
+
+    /// Additional flags to pass to the C or C++ compiler.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) copts: Vec<String>,
+
+    /// Other `cc_library` targets the library depends on.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub(crate) deps: BTreeSet<Label>,
+
+    /// Environment variables the build script would have set with `cargo:rustc-env`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) rustc_env: BTreeMap<String, String>,
+
+    /// Configuration options the build script would have set with `cargo:rustc-cfg`.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub(crate) cfgs: BTreeSet<String>,
+}
+
 #[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub(crate) struct CrateAnnotations {
     /// Which subset of the crate's bins should get produced as `rust_binary` targets.
@@ -242,6 +375,20 @@ pub(crate) struct CrateAnnotations {
     /// Determins whether or not Cargo build scripts should be generated for the current package
     pub(crate) gen_build_script: Option<bool>,
 
+    /// Whether or not to generate `rust_test` targets for the crate's integration tests and
+    /// library unit tests. Only applies to crates whose sources are vendored locally.
+    ///
+    /// Cargo only resolves the `dev-dependencies` of workspace members, so the tests of other
+    /// crates are only given the crate's normal dependencies.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) gen_tests: Option<bool>,
+
+    /// Whether or not to generate `rust_binary` targets for the crate's examples and benchmarks,
+    /// or `rust_test` targets for benchmarks using the libtest harness. Only applies to crates
+    /// whose sources are vendored locally and whose dev dependencies are resolved.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) gen_examples: Option<bool>,
+
     /// Additional data to pass to
     /// [deps](https://bazelbuild.github.io/rules_rust/defs.html#rust_library-deps) attribute.
     pub(crate) deps: Option<Select<BTreeSet<Label>>>,
@@ -271,6 +418,7 @@ pub(crate) struct CrateAnnotations {
     pub(crate) compile_data_glob: Option<BTreeSet<String>>,
 
     /// If true, disables pipelining for library targets generated for this crate.
+    #[serde(default)]
     pub(crate) disable_pipelining: bool,
 
     /// Additional data to pass to  the target's
@@ -320,6 +468,11 @@ pub(crate) struct CrateAnnotations {
     /// Directory to run the crate's build script in. If not set, will run in the manifest directory, otherwise a directory relative to the exec root.
     pub(crate) build_script_rundir: Option<Select<String>>,
 
+    /// A `cc_library` to build in place of the crate's build script. Setting this
+    /// disables the build script, so it is an error to also set `gen_build_script`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) cc_library: Option<CcLibraryAnnotation>,
+
     /// A scratch pad used to write arbitrary text to target BUILD files.
     pub(crate) additive_build_file_content: Option<String>,
 
@@ -327,6 +480,23 @@ pub(crate) struct CrateAnnotations {
     /// [git_repository::shallow_since](https://docs.bazel.build/versions/main/repo/git.html#new_git_repository-shallow_since) attribute.
     pub(crate) shallow_since: Option<String>,
 
+    /// For git sourced crates, whether or not to initialize submodules. See
+    /// [git_repository::init_submodules](https://docs.bazel.build/versions/main/repo/git.html#new_git_repository-init_submodules).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) init_submodules: Option<bool>,
+
+    /// For git sourced crates, whether or not to recursively initialize submodules. See
+    /// [git_repository::recursive_init_submodules](https://docs.bazel.build/versions/main/repo/git.html#new_git_repository-recursive_init_submodules).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) recursive_init_submodules: Option<bool>,
+
+    /// For git sourced crates, the patterns of paths to check out. If unset or empty, the whole
+    /// repository is checked out, unless [RenderConfig::generate_sparse_checkouts] is set.
+    /// Requires Bazel 7.1 or later. See
+    /// [git_repository::sparse_checkout_patterns](https://docs.bazel.build/versions/main/repo/git.html#new_git_repository-sparse_checkout_patterns).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) sparse_checkout_patterns: Option<BTreeSet<String>>,
+
     /// The `patch_args` attribute of a Bazel repository rule. See
     /// [http_archive.patch_args](https://docs.bazel.build/versions/main/repo/http.html#http_archive-patch_args)
     pub(crate) patch_args: Option<Vec<String>>,
@@ -347,6 +517,12 @@ pub(crate) struct CrateAnnotations {
 
     /// The crates to use instead of the generated one.
     pub(crate) override_targets: Option<BTreeMap<String, Label>>,
+
+    /// A mapping of variant names to sets of Cargo features of the crate. Each variant is
+    /// resolved separately from the rest of the workspace and rendered as additional targets
+    /// named `{target}-{variant}`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) feature_variants: Option<BTreeMap<String, BTreeSet<String>>>,
 }
 
 macro_rules! joined_extra_member {
@@ -392,6 +568,8 @@ impl Add for CrateAnnotations {
         let output = CrateAnnotations {
             gen_binaries: self.gen_binaries.or(rhs.gen_binaries),
             gen_build_script: self.gen_build_script.or(rhs.gen_build_script),
+            gen_tests: self.gen_tests.or(rhs.gen_tests),
+            gen_examples: self.gen_examples.or(rhs.gen_examples),
             deps: select_merge(self.deps, rhs.deps),
             proc_macro_deps: select_merge(self.proc_macro_deps, rhs.proc_macro_deps),
             crate_features: select_merge(self.crate_features, rhs.crate_features),
@@ -412,14 +590,19 @@ impl Add for CrateAnnotations {
             build_script_rustc_env: select_merge(self.build_script_rustc_env, rhs.build_script_rustc_env),
             build_script_toolchains: joined_extra_member!(self.build_script_toolchains, rhs.build_script_toolchains, BTreeSet::new, BTreeSet::extend),
             build_script_rundir: self.build_script_rundir.or(rhs.build_script_rundir),
+            cc_library: self.cc_library.or(rhs.cc_library),
             additive_build_file_content: joined_extra_member!(self.additive_build_file_content, rhs.additive_build_file_content, String::new, concat_string),
             shallow_since: self.shallow_since.or(rhs.shallow_since),
+            init_submodules: self.init_submodules.or(rhs.init_submodules),
+            recursive_init_submodules: self.recursive_init_submodules.or(rhs.recursive_init_submodules),
+            sparse_checkout_patterns: joined_extra_member!(self.sparse_checkout_patterns, rhs.sparse_checkout_patterns, BTreeSet::new, BTreeSet::extend),
             patch_args: joined_extra_member!(self.patch_args, rhs.patch_args, Vec::new, Vec::extend),
             patch_tool: self.patch_tool.or(rhs.patch_tool),
             patches: joined_extra_member!(self.patches, rhs.patches, BTreeSet::new, BTreeSet::extend),
             extra_aliased_targets: joined_extra_member!(self.extra_aliased_targets, rhs.extra_aliased_targets, BTreeMap::new, BTreeMap::extend),
             alias_rule: self.alias_rule.or(rhs.alias_rule),
             override_targets: self.override_targets.or(rhs.override_targets),
+            feature_variants: joined_extra_member!(self.feature_variants, rhs.feature_variants, BTreeMap::new, BTreeMap::extend),
         };
 
         output
@@ -661,6 +844,117 @@ impl<'de> Visitor<'de> for GenBinariesVisitor {
     }
 }
 
+/// A platform which `cfg` expressions are evaluated against in addition to the
+/// `supported_platform_triples`. This allows dependencies to be selected on values
+/// which differ between platforms sharing a triple, such as `target_feature`s.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct CustomPlatform {
+    /// The platform triple providing any `cfg` values not explicitly set below.
+    pub(crate) triple: TargetTriple,
+
+    /// The value of `target_arch`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) target_arch: Option<String>,
+
+    /// The value of `target_os`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) target_os: Option<String>,
+
+    /// The value of `target_env`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) target_env: Option<String>,
+
+    /// The `target_feature`s enabled on the platform.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub(crate) target_features: BTreeSet<String>,
+
+    /// The value of `panic`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) panic: Option<String>,
+
+    /// Custom configuration options (as passed to `rustc --cfg`) set on the platform,
+    /// either as a bare name (`tokio_unstable`) or a key value pair (`foo="bar"`).
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub(crate) cfgs: BTreeSet<String>,
+}
+
+/// A policy restricting which licenses the dependencies of a workspace may use.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct LicensePolicy {
+    /// SPDX license IDs dependencies may use. If empty, any license which is not
+    /// denied is allowed.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub(crate) allow: BTreeSet<String>,
+
+    /// SPDX license IDs dependencies may not use.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub(crate) deny: BTreeSet<String>,
+
+    /// Additional SPDX license IDs specific crates may use. Crates mapped to an
+    /// empty set are exempt from the policy.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) exceptions: BTreeMap<CrateNameAndVersionReq, BTreeSet<String>>,
+}
+
+/// Rules for crates which appear in the dependency graph with several semver incompatible versions.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct DuplicateVersionPolicy {
+    /// Whether or not to warn about every crate with several semver incompatible versions.
+    #[serde(default)]
+    pub(crate) warn: bool,
+
+    /// The names of crates which must only appear with a single semver compatible
+    /// version. Generation fails if any of these are duplicated.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub(crate) deny: BTreeSet<String>,
+}
+
+/// Default annotations for crates with well known requirements. These are applied
+/// beneath any matching `annotations` and do not need to match a package.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct RecipeConfig {
+    /// Whether or not to apply the recipes bundled with cargo-bazel.
+    #[serde(default)]
+    pub(crate) bundled: bool,
+
+    /// Recipe files, or directories of recipe files, to apply. Their contents are
+    /// loaded into `annotations` when the config is read, so the paths themselves
+    /// are never serialized.
+    #[serde(default, skip_serializing)]
+    pub(crate) paths: Vec<PathBuf>,
+
+    /// Labels for the placeholders of bundled recipes which need something from
+    /// the workspace (Eg. `openssl`). Bundled recipes with a placeholder which has
+    /// no label here are not applied.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) labels: BTreeMap<String, Label>,
+
+    /// The default annotations of each crate. Entries here take priority over
+    /// any loaded from `paths`, which take priority over bundled recipes.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) annotations: BTreeMap<CrateNameAndVersionReq, CrateAnnotations>,
+}
+
+impl RecipeConfig {
+    /// Load the recipes of `paths` and, if enabled, the bundled recipes into `annotations`.
+    fn load(&mut self) -> Result<()> {
+        for path in std::mem::take(&mut self.paths) {
+            recipes::merge_recipes(&mut self.annotations, recipes::load_recipes(&path)?);
+        }
+        if self.bundled {
+            recipes::merge_recipes(
+                &mut self.annotations,
+                recipes::bundled_recipes(&self.labels)?,
+            );
+        }
+        Ok(())
+    }
+}
+
 /// Workspace specific settings to control how targets are generated
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
 #[serde(deny_unknown_fields)]
@@ -684,12 +978,74 @@ pub(crate) struct Config {
     /// A set of platform triples to use in generated select statements
     #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
     pub(crate) supported_platform_triples: BTreeSet<TargetTriple>,
+
+    /// Platforms with explicit `cfg` values, keyed by the label of the `config_setting`
+    /// to use for them in generated select statements. Each `config_setting` must specialize
+    /// the constraints of its platform's triple to avoid ambiguous `select` statements.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) custom_platforms: BTreeMap<String, CustomPlatform>,
+
+    /// A policy the licenses of all dependencies must satisfy
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) license_policy: Option<LicensePolicy>,
+
+    /// Rules for crates with several semver incompatible versions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) duplicate_versions: Option<DuplicateVersionPolicy>,
+
+    /// Default annotations for crates with well known requirements
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) recipes: Option<RecipeConfig>,
 }
 
 impl Config {
     pub(crate) fn try_from_path<T: AsRef<Path>>(path: T) -> Result<Self> {
         let data = fs::read_to_string(path)?;
-        Ok(serde_json::from_str(&data)?)
+        let mut config: Self = serde_json::from_str(&data)?;
+        config
+            .rendering
+            .load_templates()
+            .context("Failed to load templates")?;
+        if let Some(recipes) = config.recipes.as_mut() {
+            recipes.load().context("Failed to load recipes")?;
+        }
+        Ok(config)
+    }
+
+    /// The default annotations which apply beneath `annotations`.
+    pub(crate) fn recipes(&self) -> BTreeMap<CrateNameAndVersionReq, CrateAnnotations> {
+        self.recipes
+            .as_ref()
+            .map(|recipes| recipes.annotations.clone())
+            .unwrap_or_default()
+    }
+
+    /// The feature variants of each annotated crate. Variant names are used to name the
+    /// targets rendered for every crate in the dependency tree of the variant so they must
+    /// be unique across all crates.
+    pub(crate) fn feature_variants(
+        &self,
+    ) -> Result<BTreeMap<CrateNameAndVersionReq, BTreeMap<String, BTreeSet<String>>>> {
+        let mut owners: BTreeMap<&String, &CrateNameAndVersionReq> = BTreeMap::new();
+        let mut feature_variants = BTreeMap::new();
+        for (id, annotation) in &self.annotations {
+            let variants = match &annotation.feature_variants {
+                Some(variants) => variants,
+                None => continue,
+            };
+            for variant in variants.keys() {
+                if let Some(owner) = owners.insert(variant, id) {
+                    bail!(
+                        "The feature variant `{}` is declared for both `{}` and `{}`. Feature variant names must be unique.",
+                        variant,
+                        owner,
+                        id
+                    );
+                }
+            }
+            feature_variants.insert(id.clone(), variants.clone());
+        }
+        Ok(feature_variants)
     }
 }
 
@@ -713,6 +1069,15 @@ impl Serialize for CrateNameAndVersionReq {
     }
 }
 
+impl std::fmt::Display for CrateNameAndVersionReq {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(
+            &format!("{} {}", self.name, self.version_req_string.original),
+            f,
+        )
+    }
+}
+
 struct CrateNameAndVersionReqVisitor;
 impl<'de> Visitor<'de> for CrateNameAndVersionReqVisitor {
     type Value = CrateNameAndVersionReq;
@@ -838,14 +1203,23 @@ impl CrateNameAndVersionReq {
 
     /// Compares a [CrateNameAndVersionReq] against a [cargo_metadata::Package].
     pub fn matches(&self, package: &Package) -> bool {
+        self.matches_name_and_version(&package.name, &package.version)
+    }
+
+    /// Compares a [CrateNameAndVersionReq] against a [CrateId].
+    pub(crate) fn matches_crate_id(&self, id: &CrateId) -> bool {
+        self.matches_name_and_version(&id.name, &id.version)
+    }
+
+    fn matches_name_and_version(&self, name: &str, version: &semver::Version) -> bool {
         // If the package name does not match, it's obviously
         // not the right package
-        if self.name != "*" && self.name != package.name {
+        if self.name != "*" && self.name != name {
             return false;
         }
 
         // First see if the package version matches exactly
-        if package.version.to_string() == self.version_req_string.original {
+        if version.to_string() == self.version_req_string.original {
             return true;
         }
 
@@ -861,7 +1235,7 @@ impl CrateNameAndVersionReq {
 
         // Next, check to see if the version provided is a semver req and
         // check if the package matches the condition
-        self.version_req_string.parsed.matches(&package.version)
+        self.version_req_string.parsed.matches(version)
     }
 }
 
@@ -970,4 +1344,79 @@ mod test {
             "//custom/platform:{triple}"
         );
     }
+
+    #[test]
+    fn load_render_config_templates() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let header = temp_dir.path().join("header.j2");
+        fs::write(&header, "# {{regen_command}}").unwrap();
+
+        let mut config = RenderConfig {
+            template_paths: BTreeMap::from([("partials/header.j2".to_owned(), header)]),
+            ..RenderConfig::default()
+        };
+        config.load_templates().unwrap();
+
+        assert!(config.template_paths.is_empty());
+        assert_eq!(
+            config.templates,
+            BTreeMap::from([(
+                "partials/header.j2".to_owned(),
+                "# {{regen_command}}".to_owned()
+            )])
+        );
+
+        // Missing templates are reported when loading.
+        let mut config = RenderConfig {
+            template_paths: BTreeMap::from([(
+                "partials/header.j2".to_owned(),
+                temp_dir.path().join("missing.j2"),
+            )]),
+            ..RenderConfig::default()
+        };
+        assert!(config.load_templates().is_err());
+    }
+
+    #[test]
+    fn feature_variants_are_unique() {
+        let annotation = |variants: &[&str]| CrateAnnotations {
+            feature_variants: Some(
+                variants
+                    .iter()
+                    .map(|variant| (variant.to_string(), BTreeSet::from([variant.to_string()])))
+                    .collect(),
+            ),
+            ..CrateAnnotations::default()
+        };
+        let mut config = Config {
+            annotations: BTreeMap::from([
+                (
+                    CrateNameAndVersionReq::new("a".to_owned(), "1.0.0".parse().unwrap()),
+                    annotation(&["std"]),
+                ),
+                (
+                    CrateNameAndVersionReq::new("a".to_owned(), "2.0.0".parse().unwrap()),
+                    annotation(&["alloc"]),
+                ),
+            ]),
+            ..Config::default()
+        };
+
+        // Variants are keyed by the full annotation so different versions of a crate
+        // may declare different variants.
+        let feature_variants = config.feature_variants().unwrap();
+        assert_eq!(
+            feature_variants
+                .iter()
+                .map(|(id, variants)| (id.version_req_string.original.as_str(), variants.len()))
+                .collect::<Vec<_>>(),
+            vec![("1.0.0", 1), ("2.0.0", 1)]
+        );
+
+        config.annotations.insert(
+            CrateNameAndVersionReq::new("b".to_owned(), "*".parse().unwrap()),
+            annotation(&["std"]),
+        );
+        assert!(config.feature_variants().is_err());
+    }
 }
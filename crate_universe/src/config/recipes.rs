@@ -0,0 +1,369 @@
+//! Default annotations ("recipes") for crates which are known to need them.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use regex::Regex;
+use serde_json::Value;
+
+use crate::config::{CrateAnnotations, CrateNameAndVersionReq};
+use crate::select::Select;
+use crate::utils::starlark::Label;
+
+/// A collection of default annotations keyed by the crates they apply to.
+pub(crate) type Recipes = BTreeMap<CrateNameAndVersionReq, CrateAnnotations>;
+
+/// The recipes shipped with cargo-bazel, keyed by the name of their file.
+///
+/// Recipes for crates which need something from the user's workspace refer to it
+/// with a `{placeholder}` in place of a label (Eg. `{openssl}`).
+const BUNDLED_RECIPES: &[(&str, &str)] = &[
+    (
+        "libz-sys.json",
+        include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/src/config/recipes/libz-sys.json"
+        )),
+    ),
+    (
+        "lzma-sys.json",
+        include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/src/config/recipes/lzma-sys.json"
+        )),
+    ),
+    (
+        "openssl-sys.json",
+        include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/src/config/recipes/openssl-sys.json"
+        )),
+    ),
+    (
+        "rdkafka-sys.json",
+        include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/src/config/recipes/rdkafka-sys.json"
+        )),
+    ),
+];
+
+/// Parse the recipes bundled with cargo-bazel, replacing their placeholders with
+/// `labels`. Recipes using a placeholder which has no label are skipped.
+pub(crate) fn bundled_recipes(labels: &BTreeMap<String, Label>) -> Result<Recipes> {
+    let placeholder = Regex::new(r"\{([a-z_]+)\}")?;
+
+    let mut recipes = Recipes::new();
+    let mut known = BTreeSet::new();
+    for (name, content) in BUNDLED_RECIPES {
+        let mut value: Value = serde_json::from_str(content)
+            .with_context(|| format!("Failed to parse recipes from {name}"))?;
+        let mut missing = BTreeSet::new();
+        replace_placeholders(&mut value, &placeholder, labels, &mut known, &mut missing);
+        if !missing.is_empty() {
+            tracing::debug!(
+                "Skipping the bundled recipe {} which needs labels for {:?}",
+                name,
+                missing
+            );
+            continue;
+        }
+        let parsed = serde_json::from_value(value)
+            .with_context(|| format!("Failed to parse recipes from {name}"))?;
+        merge_recipes(&mut recipes, parsed);
+    }
+
+    let unknown: Vec<&String> = labels.keys().filter(|key| !known.contains(*key)).collect();
+    if !unknown.is_empty() {
+        bail!(
+            "No bundled recipe uses the labels {:?}. The known labels are {:?}",
+            unknown,
+            known
+        );
+    }
+
+    Ok(recipes)
+}
+
+/// Replace every `{placeholder}` in the strings of `value` with its label, recording
+/// the placeholders seen in `known` and those without a label in `missing`.
+fn replace_placeholders(
+    value: &mut Value,
+    placeholder: &Regex,
+    labels: &BTreeMap<String, Label>,
+    known: &mut BTreeSet<String>,
+    missing: &mut BTreeSet<String>,
+) {
+    match value {
+        Value::String(text) => {
+            let replaced = placeholder.replace_all(text, |captures: &regex::Captures| {
+                let name = &captures[1];
+                known.insert(name.to_owned());
+                match labels.get(name) {
+                    Some(label) => label.repr(),
+                    None => {
+                        missing.insert(name.to_owned());
+                        captures[0].to_owned()
+                    }
+                }
+            });
+            *text = replaced.into_owned();
+        }
+        Value::Array(values) => {
+            for value in values {
+                replace_placeholders(value, placeholder, labels, known, missing);
+            }
+        }
+        Value::Object(map) => {
+            for value in map.values_mut() {
+                replace_placeholders(value, placeholder, labels, known, missing);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Load recipes from a json file or from every json file in a directory.
+pub(crate) fn load_recipes(path: &Path) -> Result<Recipes> {
+    let mut recipes = Recipes::new();
+    if path.is_dir() {
+        let mut files = fs::read_dir(path)
+            .with_context(|| format!("Failed to read recipe directory {}", path.display()))?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<std::io::Result<Vec<_>>>()?;
+        files.retain(|file| file.extension().is_some_and(|ext| ext == "json"));
+        files.sort();
+        for file in files {
+            merge_recipes(&mut recipes, load_recipes(&file)?);
+        }
+    } else {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read recipe file {}", path.display()))?;
+        merge_recipes(
+            &mut recipes,
+            parse_recipes(&content, &path.display().to_string())?,
+        );
+    }
+    Ok(recipes)
+}
+
+/// Merge `other` into `recipes`. Annotations already in `recipes` take priority.
+pub(crate) fn merge_recipes(recipes: &mut Recipes, other: Recipes) {
+    for (id, recipe) in other {
+        let merged = match recipes.remove(&id) {
+            Some(existing) => apply_recipe(existing, recipe),
+            None => recipe,
+        };
+        recipes.insert(id, merged);
+    }
+}
+
+/// Combine an annotation with a recipe such that anything set by the annotation wins.
+pub(crate) fn apply_recipe(
+    annotation: CrateAnnotations,
+    mut recipe: CrateAnnotations,
+) -> CrateAnnotations {
+    // `Add` keeps the left hand side of single values but the right hand side
+    // of conflicting map keys, so drop any keys the annotation already sets.
+    shadow_select(&mut recipe.rustc_env, &annotation.rustc_env);
+    shadow_select(&mut recipe.build_script_env, &annotation.build_script_env);
+    shadow_select(
+        &mut recipe.build_script_rustc_env,
+        &annotation.build_script_rustc_env,
+    );
+    shadow_map(
+        &mut recipe.extra_aliased_targets,
+        &annotation.extra_aliased_targets,
+    );
+    shadow_map(&mut recipe.feature_variants, &annotation.feature_variants);
+
+    annotation + recipe
+}
+
+fn shadow_select(
+    recipe: &mut Option<Select<BTreeMap<String, String>>>,
+    annotation: &Option<Select<BTreeMap<String, String>>>,
+) {
+    if let (Some(select), Some(annotation)) = (recipe.as_mut(), annotation) {
+        let overridden: BTreeSet<String> = annotation
+            .values()
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect();
+        let mut shadowed = Select::new();
+        for (configuration, (key, value)) in select.items() {
+            if !overridden.contains(&key) {
+                shadowed.insert((key, value), configuration);
+            }
+        }
+        *select = shadowed;
+    }
+}
+
+fn shadow_map<T>(
+    recipe: &mut Option<BTreeMap<String, T>>,
+    annotation: &Option<BTreeMap<String, T>>,
+) {
+    if let (Some(map), Some(annotation)) = (recipe.as_mut(), annotation) {
+        map.retain(|key, _| !annotation.contains_key(key));
+    }
+}
+
+fn parse_recipes(content: &str, source: &str) -> Result<Recipes> {
+    serde_json::from_str(content).with_context(|| format!("Failed to parse recipes from {source}"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn bundled_recipes_parse() {
+        let recipes = bundled_recipes(&BTreeMap::new()).unwrap();
+
+        assert!(recipes.keys().any(|id| id.name == "libz-sys"));
+        assert!(!recipes.keys().any(|id| id.name == "openssl-sys"));
+        assert!(!recipes.keys().any(|id| id.name == "rdkafka-sys"));
+    }
+
+    #[test]
+    fn bundled_recipes_with_labels() {
+        let labels = BTreeMap::from([
+            ("openssl".to_owned(), "@openssl//:openssl".parse().unwrap()),
+            (
+                "openssl_dir".to_owned(),
+                "@openssl//:gen_dir".parse().unwrap(),
+            ),
+            ("librdkafka".to_owned(), "@librdkafka".parse().unwrap()),
+        ]);
+        let recipes = bundled_recipes(&labels).unwrap();
+
+        assert_eq!(recipes.len(), BUNDLED_RECIPES.len());
+
+        let openssl = recipes
+            .iter()
+            .find(|(id, _)| id.name == "openssl-sys")
+            .map(|(_, recipe)| recipe)
+            .unwrap();
+        assert_eq!(
+            openssl.build_script_data,
+            Some(Select::from_value(BTreeSet::from(["@openssl//:gen_dir"
+                .parse()
+                .unwrap()])))
+        );
+        assert_eq!(
+            openssl.build_script_env,
+            Some(Select::from_value(BTreeMap::from([
+                (
+                    "OPENSSL_DIR".to_owned(),
+                    "$(execpath @openssl//:gen_dir)".to_owned()
+                ),
+                ("OPENSSL_STATIC".to_owned(), "1".to_owned()),
+            ])))
+        );
+        assert_eq!(
+            openssl.deps,
+            Some(Select::from_value(BTreeSet::from(["@openssl//:openssl"
+                .parse()
+                .unwrap()])))
+        );
+
+        let rdkafka = recipes
+            .iter()
+            .find(|(id, _)| id.name == "rdkafka-sys")
+            .map(|(_, recipe)| recipe)
+            .unwrap();
+        assert_eq!(rdkafka.gen_build_script, Some(false));
+        assert_eq!(
+            rdkafka.deps,
+            Some(Select::from_value(BTreeSet::from(["@librdkafka"
+                .parse()
+                .unwrap()])))
+        );
+    }
+
+    #[test]
+    fn bundled_recipes_unknown_label() {
+        let labels = BTreeMap::from([("opensl".to_owned(), "@openssl".parse().unwrap())]);
+
+        let error = bundled_recipes(&labels).unwrap_err();
+
+        assert!(error.to_string().contains("opensl"), "{error}");
+    }
+
+    #[test]
+    fn load_recipe_directory() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::write(
+            temp_dir.path().join("a.json"),
+            r#"{"mock-sys *": {"gen_build_script": false, "data_glob": ["a/**"]}}"#,
+        )
+        .unwrap();
+        fs::write(
+            temp_dir.path().join("b.json"),
+            r#"{"mock-sys *": {"gen_build_script": true, "data_glob": ["b/**"]}}"#,
+        )
+        .unwrap();
+        fs::write(temp_dir.path().join("README.md"), "Not a recipe").unwrap();
+
+        let recipes = load_recipes(temp_dir.path()).unwrap();
+        let id = CrateNameAndVersionReq::new("mock-sys".to_owned(), "*".parse().unwrap());
+
+        assert_eq!(
+            recipes,
+            Recipes::from([(
+                id,
+                CrateAnnotations {
+                    gen_build_script: Some(false),
+                    data_glob: Some(BTreeSet::from(["a/**".to_owned(), "b/**".to_owned()])),
+                    ..CrateAnnotations::default()
+                }
+            )])
+        );
+    }
+
+    #[test]
+    fn merge_recipes_prefers_existing() {
+        let id = CrateNameAndVersionReq::new("mock-sys".to_owned(), "*".parse().unwrap());
+        let mut recipes = Recipes::from([(
+            id.clone(),
+            CrateAnnotations {
+                gen_build_script: Some(false),
+                build_script_env: Some(Select::from_value(BTreeMap::from([(
+                    "STATIC".to_owned(),
+                    "1".to_owned(),
+                )]))),
+                ..CrateAnnotations::default()
+            },
+        )]);
+
+        merge_recipes(
+            &mut recipes,
+            Recipes::from([(
+                id.clone(),
+                CrateAnnotations {
+                    gen_build_script: Some(true),
+                    build_script_env: Some(Select::from_value(BTreeMap::from([
+                        ("STATIC".to_owned(), "0".to_owned()),
+                        ("VENDORED".to_owned(), "1".to_owned()),
+                    ]))),
+                    ..CrateAnnotations::default()
+                },
+            )]),
+        );
+
+        assert_eq!(
+            recipes[&id],
+            CrateAnnotations {
+                gen_build_script: Some(false),
+                build_script_env: Some(Select::from_value(BTreeMap::from([
+                    ("STATIC".to_owned(), "1".to_owned()),
+                    ("VENDORED".to_owned(), "1".to_owned()),
+                ]))),
+                ..CrateAnnotations::default()
+            }
+        );
+    }
+}
@@ -1,19 +1,25 @@
 //! Convert annotated metadata into a renderable context
 
 pub(crate) mod crate_context;
+mod duplicates;
+mod licenses;
+pub(crate) mod lints;
 mod platforms;
+pub(crate) mod profiles;
 
 use std::collections::{BTreeMap, BTreeSet};
-use std::fs;
 use std::path::{Path, PathBuf};
 
 use anyhow::Result;
+use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 
 use crate::config::CrateId;
-use crate::context::platforms::resolve_cfg_platforms;
-use crate::lockfile::Digest;
-use crate::metadata::{Annotations, Dependency};
+use crate::context::duplicates::check_duplicate_versions;
+use crate::context::licenses::check_license_policy;
+use crate::context::platforms::{resolve_cfg_custom_platforms, resolve_cfg_platforms};
+use crate::lockfile::{Digest, DigestComponents};
+use crate::metadata::{Annotations, Dependency, SourceAnnotation};
 use crate::select::Select;
 use crate::utils::target_triple::TargetTriple;
 
@@ -26,6 +32,10 @@ pub(crate) struct Context {
     /// The collective checksum of all inputs to the context
     pub(crate) checksum: Option<Digest>,
 
+    /// The individual hashes of each input which make up the [Context::checksum]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) checksum_components: Option<DigestComponents>,
+
     /// The collection of all crates that make up the dependency graph
     pub(crate) crates: BTreeMap<CrateId, CrateContext>,
 
@@ -39,6 +49,10 @@ pub(crate) struct Context {
     /// A mapping of `cfg` flags to platform triples supporting the configuration
     pub(crate) conditions: BTreeMap<String, BTreeSet<TargetTriple>>,
 
+    /// A mapping of `cfg` flags to the labels of custom platforms supporting the configuration
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) custom_conditions: BTreeMap<String, BTreeSet<String>>,
+
     /// A list of crates visible to any bazel module.
     pub(crate) direct_deps: BTreeSet<CrateId>,
 
@@ -47,30 +61,106 @@ pub(crate) struct Context {
 }
 
 impl Context {
-    pub(crate) fn try_from_path<T: AsRef<Path>>(path: T) -> Result<Self> {
-        let data = fs::read_to_string(path.as_ref())?;
-        Ok(serde_json::from_str(&data)?)
-    }
-
     pub(crate) fn new(annotations: Annotations, sources_are_present: bool) -> Result<Self> {
         // Build a map of crate contexts
-        let crates: BTreeMap<CrateId, CrateContext> = annotations
+        let mut crates: BTreeMap<CrateId, CrateContext> = annotations
             .metadata
             .crates
             .values()
             .map(|annotation| {
-                let context = CrateContext::new(
+                let mut context = CrateContext::new(
                     annotation,
                     &annotations.metadata.packages,
                     &annotations.lockfile.crates,
                     &annotations.pairred_extras,
                     &annotations.metadata.workspace_metadata.tree_metadata,
+                    annotations.metadata.workspace_metadata.profiles.as_ref(),
                     annotations.config.generate_binaries,
                     annotations.config.generate_build_scripts,
                     sources_are_present,
-                );
+                )?;
+
+                // Mirror urls are expanded here rather than when rendering so that every
+                // consumer of the context, such as the `crate` module extension, uses them.
+                if let Some(SourceAnnotation::Http {
+                    url,
+                    sha256,
+                    registry,
+                    urls,
+                    ..
+                }) = &mut context.repository
+                {
+                    let rendering = &annotations.config.rendering;
+                    let templates = registry
+                        .as_ref()
+                        .and_then(|registry| rendering.registry_url_templates.get(registry))
+                        .unwrap_or(&rendering.crate_url_templates);
+                    if !templates.is_empty() {
+                        *urls = render_crate_urls(
+                            templates,
+                            &context.name,
+                            &context.version.to_string(),
+                            sha256.as_deref(),
+                            url,
+                        );
+                    }
+                }
+
+                // Only the files beneath the `strip_prefix` are visible to Bazel so nothing
+                // else needs to be checked out, unless the crate was annotated otherwise.
+                if let Some(SourceAnnotation::Git {
+                    strip_prefix: Some(strip_prefix),
+                    sparse_checkout_patterns: sparse_checkout_patterns @ None,
+                    ..
+                }) = &mut context.repository
+                {
+                    if annotations.config.rendering.generate_sparse_checkouts {
+                        *sparse_checkout_patterns =
+                            Some(BTreeSet::from([format!("{strip_prefix}/**")]));
+                    }
+                }
+
                 let id = CrateId::new(context.name.clone(), context.version.clone());
-                (id, context)
+                Ok((id, context))
+            })
+            .collect::<Result<_>>()?;
+
+        // Feature variants are resolved with their own features so each crate that's built
+        // differently for a variant gets its own set of attributes.
+        for (variant, variant_annotations) in &annotations.metadata.feature_variants {
+            let tree = &annotations.metadata.workspace_metadata.feature_variants[variant];
+            let affected: BTreeSet<CrateId> = variant_annotations
+                .keys()
+                .map(|id| CrateId::from(&annotations.metadata.packages[id]))
+                .collect();
+            for annotation in variant_annotations.values() {
+                let context = CrateContext::new(
+                    annotation,
+                    &annotations.metadata.packages,
+                    &annotations.lockfile.crates,
+                    &annotations.pairred_extras,
+                    &tree.tree_metadata,
+                    annotations.metadata.workspace_metadata.profiles.as_ref(),
+                    annotations.config.generate_binaries,
+                    annotations.config.generate_build_scripts,
+                    sources_are_present,
+                )?;
+                let id = CrateId::new(context.name.clone(), context.version.clone());
+                if let Some(krate) = crates.get_mut(&id) {
+                    krate.feature_variants.insert(
+                        variant.clone(),
+                        FeatureVariant::new(context, variant, &affected),
+                    );
+                }
+            }
+        }
+        let variant_crates: Vec<CrateContext> = crates
+            .values()
+            .flat_map(|krate| {
+                krate
+                    .feature_variants
+                    .keys()
+                    .filter_map(|variant| krate.with_feature_variant(variant))
             })
             .collect();
 
@@ -86,9 +176,13 @@ impl Context {
         // Given a list of all conditional dependencies, build a set of platform
         // triples which satisfy the conditions.
         let conditions = resolve_cfg_platforms(
-            crates.values().collect(),
+            crates.values().chain(&variant_crates).collect(),
             &annotations.config.supported_platform_triples,
         )?;
+        let custom_conditions = resolve_cfg_custom_platforms(
+            crates.values().chain(&variant_crates).collect(),
+            &annotations.config.custom_platforms,
+        )?;
 
         // Generate a list of all workspace members
         let workspace_members = annotations
@@ -117,6 +211,13 @@ impl Context {
             })
             .collect::<Result<BTreeMap<CrateId, String>>>()?;
 
+        if let Some(policy) = &annotations.config.license_policy {
+            check_license_policy(policy, &crates, &workspace_members)?;
+        }
+        if let Some(policy) = &annotations.config.duplicate_versions {
+            check_duplicate_versions(policy, &crates)?;
+        }
+
         let add_crate_ids = |crates: &mut BTreeSet<CrateId>,
                              deps: &Select<BTreeSet<Dependency>>| {
             for dep in deps.values() {
@@ -141,10 +242,12 @@ impl Context {
 
         Ok(Self {
             checksum: None,
+            checksum_components: None,
             crates,
             binary_crates,
             workspace_members,
             conditions,
+            custom_conditions,
             direct_dev_deps: direct_dev_deps.difference(&direct_deps).cloned().collect(),
             direct_deps,
         })
@@ -225,6 +328,35 @@ impl Context {
     }
 }
 
+/// Render the urls an `http_archive` crate source is downloaded from. Each mirror url
+/// template is expanded in order, skipping those which need an unknown `sha256`, and
+/// the crate's own url is kept as the final fallback.
+fn render_crate_urls(
+    templates: &[String],
+    name: &str,
+    version: &str,
+    sha256: Option<&str>,
+    url: &str,
+) -> Vec<String> {
+    let mut urls: Vec<String> = templates
+        .iter()
+        .filter(|template| sha256.is_some() || !template.contains("{sha256}"))
+        .map(|template| {
+            template
+                .replace("{name}", name)
+                .replace("{version}", version)
+                .replace("{sha256}", sha256.unwrap_or_default())
+        })
+        .unique()
+        .collect();
+
+    if !urls.iter().any(|mirror| mirror == url) {
+        urls.push(url.to_owned());
+    }
+
+    urls
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -303,4 +435,69 @@ mod test {
         // The data should be identical
         assert_eq!(context, deserialized_context);
     }
+
+    #[test]
+    fn git_sparse_checkouts() {
+        let sparse_checkout_patterns = |config: Config| {
+            let annotations = Annotations::new(
+                crate::test::metadata::git_repos(),
+                crate::test::lockfile::git_repos(),
+                config,
+            )
+            .unwrap();
+            let context = Context::new(annotations, false).unwrap();
+            match &context.crates[&CrateId::new("tracing-core".to_owned(), Version::new(0, 2, 0))]
+                .repository
+            {
+                Some(SourceAnnotation::Git {
+                    sparse_checkout_patterns,
+                    ..
+                }) => sparse_checkout_patterns.clone(),
+                other => panic!("Wanted SourceAnnotation::Git, got: {:?}", other),
+            }
+        };
+
+        // The whole repository is checked out unless sparse checkouts are enabled.
+        assert_eq!(sparse_checkout_patterns(Config::default()), None);
+
+        let mut config = Config::default();
+        config.rendering.generate_sparse_checkouts = true;
+        assert_eq!(
+            sparse_checkout_patterns(config),
+            Some(BTreeSet::from(["tracing-core/**".to_owned()]))
+        );
+    }
+
+    #[test]
+    fn crate_urls() {
+        let templates = vec![
+            "https://mirror.example.com/{name}/{version}".to_owned(),
+            "https://example.com/{name}/{version}/download".to_owned(),
+            "https://cas.example.com/{sha256}".to_owned(),
+        ];
+
+        assert_eq!(
+            render_crate_urls(
+                &templates,
+                "mock_crate",
+                "0.1.0",
+                None,
+                "https://example.com/mock_crate/0.1.0/download"
+            ),
+            vec![
+                "https://mirror.example.com/mock_crate/0.1.0".to_owned(),
+                "https://example.com/mock_crate/0.1.0/download".to_owned(),
+            ]
+        );
+        assert_eq!(
+            render_crate_urls(
+                &[],
+                "mock_crate",
+                "0.1.0",
+                Some("abcd"),
+                "https://example.com"
+            ),
+            vec!["https://example.com".to_owned()]
+        );
+    }
 }
@@ -1,11 +1,17 @@
 //! Crate specific information embedded into [crate::context::Context] objects.
 
 use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Display};
+use std::fs;
 
+use anyhow::{bail, Context, Result};
 use cargo_metadata::{Node, Package, PackageId};
+use cargo_toml::Profiles;
 use serde::{Deserialize, Serialize};
 
-use crate::config::{AliasRule, CrateId, GenBinaries};
+use crate::config::{AliasRule, CcLibraryAnnotation, CrateId, GenBinaries};
+use crate::context::lints::LintFlags;
+use crate::context::profiles::ProfileFlags;
 use crate::metadata::{
     CrateAnnotation, Dependency, PairedExtras, SourceAnnotation, TreeResolverMetadata,
 };
@@ -13,6 +19,10 @@ use crate::select::Select;
 use crate::utils::sanitize_module_name;
 use crate::utils::starlark::{Glob, Label};
 
+/// The name of the `cc_library` target rendered in place of a build script. This is
+/// kept short to avoid long path name issues on windows.
+pub(crate) const CC_LIBRARY_TARGET_NAME: &str = "_cc";
+
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct CrateDependency {
     /// The [CrateId] of the dependency
@@ -27,6 +37,68 @@ pub struct CrateDependency {
     pub alias: Option<String>,
 }
 
+/// The kinds of dependency edges between crates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum DependencyKind {
+    Normal,
+    Dev,
+    ProcMacro,
+    ProcMacroDev,
+    Build,
+    BuildProcMacro,
+    BuildLink,
+}
+
+impl DependencyKind {
+    /// Whether or not the dependency is only used by tests, examples and benchmarks.
+    pub(crate) fn is_dev(&self) -> bool {
+        matches!(self, DependencyKind::Dev | DependencyKind::ProcMacroDev)
+    }
+}
+
+impl Display for DependencyKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            DependencyKind::Normal => "normal",
+            DependencyKind::Dev => "dev",
+            DependencyKind::ProcMacro => "proc-macro",
+            DependencyKind::ProcMacroDev => "proc-macro dev",
+            DependencyKind::Build => "build",
+            DependencyKind::BuildProcMacro => "build proc-macro",
+            DependencyKind::BuildLink => "build link",
+        })
+    }
+}
+
+/// All direct dependencies declared by the attributes of a crate and its build script, along
+/// with their kind and the configuration they're conditional on, if any.
+pub(crate) fn dependencies<'a>(
+    common_attrs: &'a CommonAttributes,
+    build_script_attrs: Option<&'a BuildScriptAttributes>,
+) -> impl Iterator<Item = (DependencyKind, Option<String>, CrateDependency)> + 'a {
+    let mut selects = vec![
+        (DependencyKind::Normal, &common_attrs.deps),
+        (DependencyKind::Dev, &common_attrs.deps_dev),
+        (DependencyKind::ProcMacro, &common_attrs.proc_macro_deps),
+        (
+            DependencyKind::ProcMacroDev,
+            &common_attrs.proc_macro_deps_dev,
+        ),
+    ];
+    if let Some(attrs) = build_script_attrs {
+        selects.push((DependencyKind::Build, &attrs.deps));
+        selects.push((DependencyKind::BuildProcMacro, &attrs.proc_macro_deps));
+        selects.push((DependencyKind::BuildLink, &attrs.link_deps));
+    }
+
+    selects.into_iter().flat_map(|(kind, select)| {
+        select
+            .items()
+            .into_iter()
+            .map(move |(configuration, dep)| (kind, configuration, dep))
+    })
+}
+
 #[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Clone)]
 #[serde(default)]
 pub(crate) struct TargetAttributes {
@@ -44,6 +116,82 @@ pub(crate) struct TargetAttributes {
 
     /// A glob pattern of all source files required by the target
     pub(crate) srcs: Glob,
+
+    /// The features which must be enabled for the target to be built
+    /// (Cargo's `required-features`).
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    pub(crate) required_features: BTreeSet<String>,
+}
+
+impl TargetAttributes {
+    /// The target's `required-features`. Requirements on the features of optional
+    /// dependencies (`dep?/feature`) are normalized to `dep/feature` since either is only
+    /// satisfied when the dependency is enabled.
+    fn normalized_required_features(&self) -> BTreeSet<String> {
+        self.required_features
+            .iter()
+            .map(|feature| match feature.split_once("?/") {
+                Some((dep, feature)) => format!("{dep}/{feature}"),
+                None => feature.clone(),
+            })
+            .collect()
+    }
+
+    /// Determine whether all of the target's `required-features` are enabled in at least one
+    /// configuration, treating every configurable feature as potentially enabled together.
+    pub(crate) fn required_features_are_satisfiable(
+        &self,
+        features: &Select<BTreeSet<String>>,
+    ) -> bool {
+        let enabled: BTreeSet<String> = features.values().into_iter().collect();
+        self.normalized_required_features().is_subset(&enabled)
+    }
+
+    /// Determine the platforms on which all of the target's `required-features` are enabled.
+    /// Features enabled in different configurations count towards the same platform when
+    /// that platform matches each of them. `None` indicates the target is buildable on every
+    /// platform while an empty set indicates it is buildable on none.
+    pub(crate) fn required_features_platforms(
+        &self,
+        features: &Select<BTreeSet<String>>,
+        platforms: &BTreeMap<String, BTreeSet<String>>,
+    ) -> Option<BTreeSet<String>> {
+        let required = self.normalized_required_features();
+
+        let mut common = BTreeSet::new();
+        let mut features_by_platform: BTreeMap<&String, BTreeSet<String>> = BTreeMap::new();
+        for (configuration, feature) in features.items() {
+            match configuration {
+                Some(configuration) => {
+                    for platform in platforms.get(&configuration).into_iter().flatten() {
+                        features_by_platform
+                            .entry(platform)
+                            .or_default()
+                            .insert(feature.clone());
+                    }
+                }
+                None => {
+                    common.insert(feature);
+                }
+            }
+        }
+
+        if required.is_subset(&common) {
+            return None;
+        }
+
+        Some(
+            features_by_platform
+                .into_iter()
+                .filter(|(_, features)| {
+                    required
+                        .iter()
+                        .all(|feature| common.contains(feature) || features.contains(feature))
+                })
+                .map(|(platform, _)| platform.clone())
+                .collect(),
+        )
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Clone)]
@@ -59,6 +207,21 @@ pub(crate) enum Rule {
 
     /// `cargo_build_script`
     BuildScript(TargetAttributes),
+
+    /// `rust_test` for an integration test (`[[test]]`) target
+    Test(TargetAttributes),
+
+    /// `rust_test` for the unit tests of the crate's library target
+    UnitTest(TargetAttributes),
+
+    /// `rust_binary` for an example (`[[example]]`) target
+    Example(TargetAttributes),
+
+    /// `rust_test` for a benchmark (`[[bench]]`) target using the libtest harness
+    Bench(TargetAttributes),
+
+    /// `rust_binary` for a benchmark (`[[bench]]`) target with `harness = false`
+    BenchBinary(TargetAttributes),
 }
 
 impl Rule {
@@ -70,6 +233,10 @@ impl Rule {
             Self::ProcMacro(..) => "proc-macro",
             Self::Binary(..) => "bin",
             Self::BuildScript(..) => "custom-build",
+            Self::Test(..) => "test",
+            Self::UnitTest(..) => "unit-test",
+            Self::Example(..) => "example",
+            Self::Bench(..) | Self::BenchBinary(..) => "bench",
         }
     }
 
@@ -78,7 +245,12 @@ impl Rule {
             Self::Library(attrs)
             | Self::ProcMacro(attrs)
             | Self::Binary(attrs)
-            | Self::BuildScript(attrs) => &attrs.crate_name,
+            | Self::BuildScript(attrs)
+            | Self::Test(attrs)
+            | Self::UnitTest(attrs)
+            | Self::Example(attrs)
+            | Self::Bench(attrs)
+            | Self::BenchBinary(attrs) => &attrs.crate_name,
         }
     }
 }
@@ -97,6 +269,11 @@ pub(crate) struct CommonAttributes {
     #[serde(skip_serializing_if = "Select::is_empty")]
     pub(crate) crate_features: Select<BTreeSet<String>>,
 
+    /// The enabled features of dependencies referenced by the `required-features` of
+    /// targets, in Cargo's `dep/feature` form.
+    #[serde(skip_serializing_if = "Select::is_empty")]
+    pub(crate) dependency_features: Select<BTreeSet<String>>,
+
     #[serde(skip_serializing_if = "Select::is_empty")]
     pub(crate) data: Select<BTreeSet<Label>>,
 
@@ -135,6 +312,12 @@ pub(crate) struct CommonAttributes {
     #[serde(skip_serializing_if = "Select::is_empty")]
     pub(crate) rustc_flags: Select<Vec<String>>,
 
+    #[serde(skip_serializing_if = "LintFlags::is_empty")]
+    pub(crate) lints: LintFlags,
+
+    #[serde(skip_serializing_if = "ProfileFlags::is_empty")]
+    pub(crate) profile_flags: ProfileFlags,
+
     pub(crate) version: String,
 
     #[serde(skip_serializing_if = "Vec::is_empty")]
@@ -148,6 +331,7 @@ impl Default for CommonAttributes {
             // Generated targets include all files in their package by default
             compile_data_glob: BTreeSet::from(["**".to_owned()]),
             crate_features: Default::default(),
+            dependency_features: Default::default(),
             data: Default::default(),
             data_glob: Default::default(),
             deps: Default::default(),
@@ -161,6 +345,8 @@ impl Default for CommonAttributes {
             rustc_env: Default::default(),
             rustc_env_files: Default::default(),
             rustc_flags: Default::default(),
+            lints: Default::default(),
+            profile_flags: Default::default(),
             version: Default::default(),
             tags: Default::default(),
         }
@@ -266,6 +452,66 @@ impl Default for BuildScriptAttributes {
     }
 }
 
+/// The attributes of a crate which differ when it's built for a feature variant. Dependencies
+/// on crates which are also built differently for the variant refer to the variant's targets,
+/// named `{target}-{variant}`.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct FeatureVariant {
+    #[serde(skip_serializing_if = "Select::is_empty")]
+    pub(crate) crate_features: Select<BTreeSet<String>>,
+
+    #[serde(skip_serializing_if = "Select::is_empty")]
+    pub(crate) deps: Select<BTreeSet<CrateDependency>>,
+
+    #[serde(skip_serializing_if = "Select::is_empty")]
+    pub(crate) deps_dev: Select<BTreeSet<CrateDependency>>,
+
+    #[serde(skip_serializing_if = "Select::is_empty")]
+    pub(crate) proc_macro_deps: Select<BTreeSet<CrateDependency>>,
+
+    #[serde(skip_serializing_if = "Select::is_empty")]
+    pub(crate) proc_macro_deps_dev: Select<BTreeSet<CrateDependency>>,
+
+    #[serde(skip_serializing_if = "Select::is_empty")]
+    pub(crate) build_deps: Select<BTreeSet<CrateDependency>>,
+
+    #[serde(skip_serializing_if = "Select::is_empty")]
+    pub(crate) build_link_deps: Select<BTreeSet<CrateDependency>>,
+
+    #[serde(skip_serializing_if = "Select::is_empty")]
+    pub(crate) build_proc_macro_deps: Select<BTreeSet<CrateDependency>>,
+}
+
+impl FeatureVariant {
+    /// Collect the attributes of a crate built for the given feature variant, where `affected`
+    /// are all crates which are built differently for the variant.
+    pub(crate) fn new(context: CrateContext, variant: &str, affected: &BTreeSet<CrateId>) -> Self {
+        let rename = |select: Select<BTreeSet<CrateDependency>>| {
+            select.map(|dep| match affected.contains(&dep.id) {
+                true => CrateDependency {
+                    target: format!("{}-{}", dep.target, variant),
+                    ..dep
+                },
+                false => dep,
+            })
+        };
+
+        let common_attrs = context.common_attrs;
+        let build_script_attrs = context.build_script_attrs.unwrap_or_default();
+        Self {
+            crate_features: common_attrs.crate_features,
+            deps: rename(common_attrs.deps),
+            deps_dev: rename(common_attrs.deps_dev),
+            proc_macro_deps: rename(common_attrs.proc_macro_deps),
+            proc_macro_deps_dev: rename(common_attrs.proc_macro_deps_dev),
+            build_deps: rename(build_script_attrs.deps),
+            build_link_deps: rename(build_script_attrs.link_deps),
+            build_proc_macro_deps: rename(build_script_attrs.proc_macro_deps),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub(crate) struct CrateContext {
     /// The package name of the current crate
@@ -339,6 +585,17 @@ pub(crate) struct CrateContext {
     #[serde(skip_serializing_if = "BTreeMap::is_empty")]
     #[serde(default)]
     pub(crate) override_targets: BTreeMap<String, Label>,
+
+    /// A `cc_library` built in place of the crate's build script.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub(crate) cc_library: Option<CcLibraryAnnotation>,
+
+    /// The attributes which differ in each feature variant the crate is built for, keyed
+    /// by the name of the variant.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    #[serde(default)]
+    pub(crate) feature_variants: BTreeMap<String, FeatureVariant>,
 }
 
 impl CrateContext {
@@ -349,10 +606,11 @@ impl CrateContext {
         source_annotations: &BTreeMap<PackageId, SourceAnnotation>,
         extras: &BTreeMap<CrateId, PairedExtras>,
         resolver_data: &TreeResolverMetadata,
+        profiles: Option<&Profiles>,
         include_binaries: bool,
         include_build_scripts: bool,
         sources_are_present: bool,
-    ) -> Self {
+    ) -> Result<Self> {
         let package: &Package = &packages[&annotation.node.id];
         let current_crate_id = CrateId::new(package.name.clone(), package.version.clone());
 
@@ -394,14 +652,96 @@ impl CrateContext {
             })
             .unwrap_or_default();
 
+        // `required-features` may also name the features of dependencies (`dep/feature`)
+        // using the name the dependency was given in the manifest.
+        let required_dependencies: BTreeSet<&str> = package
+            .targets
+            .iter()
+            .flat_map(|target| target.required_features.iter())
+            .filter_map(|feature| feature.split_once('/'))
+            .map(|(dep, _)| dep.trim_end_matches('?'))
+            .collect();
+        let mut dependency_features = Select::<BTreeSet<String>>::new();
+        if !required_dependencies.is_empty() {
+            let tree_data = resolver_data
+                .get(&current_crate_id)
+                .map(Select::items)
+                .unwrap_or_default();
+            for (config, data) in tree_data {
+                for dep_id in data.deps {
+                    let dep_features = resolver_data
+                        .get(&dep_id)
+                        .map(Select::items)
+                        .unwrap_or_default();
+                    for dependency in package.dependencies.iter().filter(|dependency| {
+                        dependency.name == dep_id.name && dependency.req.matches(&dep_id.version)
+                    }) {
+                        let name = dependency.rename.as_ref().unwrap_or(&dependency.name);
+                        if !required_dependencies.contains(name.as_str()) {
+                            continue;
+                        }
+                        // The dependency's features apply wherever both it and the
+                        // configuration its features were resolved for apply.
+                        for (dep_config, dep_data) in dep_features.iter() {
+                            let configuration = match (&config, dep_config) {
+                                (None, dep_config) => dep_config.clone(),
+                                (Some(config), None) => Some(config.clone()),
+                                (Some(config), Some(dep_config)) if config == dep_config => {
+                                    Some(config.clone())
+                                }
+                                _ => continue,
+                            };
+                            for feature in dep_data.features.iter() {
+                                dependency_features
+                                    .insert(format!("{name}/{feature}"), configuration.clone());
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        // Lints are only collected for local packages (e.g. workspace members) since
+        // all other crates are built with `--cap-lints=allow`. Their manifests may be
+        // unavailable when the metadata was loaded from a file rather than collected.
+        let lints = if package.source.is_none() {
+            if package.manifest_path.exists() {
+                LintFlags::try_from_manifest(package.manifest_path.as_std_path())
+                    .with_context(|| format!("Failed to load lints for {}", package.name))?
+            } else {
+                tracing::warn!(
+                    "The manifest of {} ({}) is unavailable so its lints will not be applied",
+                    package.name,
+                    package.manifest_path
+                );
+                LintFlags::default()
+            }
+        } else {
+            LintFlags::default()
+        };
+
+        let profile_flags = profiles
+            .map(|profiles| {
+                ProfileFlags::new(
+                    profiles,
+                    &package.name,
+                    &package.version,
+                    package.source.is_none(),
+                )
+            })
+            .unwrap_or_default();
+
         // Gather all "common" attributes
         let mut common_attrs = CommonAttributes {
             crate_features,
+            dependency_features,
             deps,
             deps_dev,
             edition: package.edition.as_str().to_string(),
             proc_macro_deps,
             proc_macro_deps_dev,
+            lints,
+            profile_flags,
             version: package.version.to_string(),
             ..Default::default()
         };
@@ -412,7 +752,7 @@ impl CrateContext {
             .find(|(_, settings)| settings.package_id == package.id);
 
         let include_build_scripts =
-            Self::crate_includes_build_script(package_extra, include_build_scripts);
+            Self::crate_includes_build_script(package_extra, include_build_scripts)?;
 
         let gen_none = GenBinaries::Some(BTreeSet::new());
         let gen_binaries = package_extra
@@ -423,14 +763,47 @@ impl CrateContext {
                 &gen_none
             });
 
+        // Tests can only be built when the crate's sources are available to the build file
+        let include_tests = sources_are_present
+            && package_extra
+                .and_then(|(_, settings)| settings.crate_extra.gen_tests)
+                .unwrap_or(false);
+
+        // Examples and benchmarks are built from the crate's sources against its dev
+        // dependencies, which Cargo only resolves for workspace members.
+        let dev_deps_are_present = package.source.is_none()
+            || !package
+                .dependencies
+                .iter()
+                .any(|dep| dep.kind == cargo_metadata::DependencyKind::Development);
+        let include_examples = sources_are_present
+            && dev_deps_are_present
+            && package_extra
+                .and_then(|(_, settings)| settings.crate_extra.gen_examples)
+                .unwrap_or(false);
+
+        // Features added by annotations also count towards the `required-features` of targets
+        let mut enabled_features = Select::merge(
+            common_attrs.crate_features.clone(),
+            common_attrs.dependency_features.clone(),
+        );
+        if let Some(extra_features) =
+            package_extra.and_then(|(_, settings)| settings.crate_extra.crate_features.clone())
+        {
+            enabled_features = Select::merge(enabled_features, extra_features);
+        }
+
         // Iterate over each target and produce a Bazel target for all supported "kinds"
         let targets = Self::collect_targets(
             &annotation.node,
             packages,
             gen_binaries,
             include_build_scripts,
+            include_tests,
+            include_examples,
+            &enabled_features,
             sources_are_present,
-        );
+        )?;
 
         // Parse the library crate name from the set of included targets
         let library_target_name = {
@@ -511,7 +884,7 @@ impl CrateContext {
         };
 
         // Create the crate's context and apply extra settings
-        CrateContext {
+        Ok(CrateContext {
             name: package.name.clone(),
             version: package.version.clone(),
             license: package.license.clone(),
@@ -528,8 +901,42 @@ impl CrateContext {
             extra_aliased_targets: BTreeMap::new(),
             alias_rule: None,
             override_targets: BTreeMap::new(),
+            cc_library: None,
+            feature_variants: BTreeMap::new(),
         }
-        .with_overrides(extras)
+        .with_overrides(extras))
+    }
+
+    /// The crate as it's built for the given feature variant, without any other variants.
+    pub(crate) fn with_feature_variant(&self, variant: &str) -> Option<Self> {
+        let feature_variant = self.feature_variants.get(variant)?.clone();
+        let mut context = Self {
+            feature_variants: BTreeMap::new(),
+            ..self.clone()
+        };
+        context.common_attrs.crate_features = feature_variant.crate_features;
+        context.common_attrs.deps = feature_variant.deps;
+        context.common_attrs.deps_dev = feature_variant.deps_dev;
+        context.common_attrs.proc_macro_deps = feature_variant.proc_macro_deps;
+        context.common_attrs.proc_macro_deps_dev = feature_variant.proc_macro_deps_dev;
+        if let Some(attrs) = &mut context.build_script_attrs {
+            attrs.deps = feature_variant.build_deps;
+            attrs.link_deps = feature_variant.build_link_deps;
+            attrs.proc_macro_deps = feature_variant.build_proc_macro_deps;
+        }
+        Some(context)
+    }
+
+    /// All direct dependencies of the crate, see [dependencies].
+    pub(crate) fn dependencies(
+        &self,
+    ) -> impl Iterator<Item = (DependencyKind, Option<String>, CrateDependency)> + '_ {
+        dependencies(&self.common_attrs, self.build_script_attrs.as_ref())
+    }
+
+    /// The ids of all direct dependencies of the crate, regardless of their kind or configuration.
+    pub(crate) fn dependency_ids(&self) -> BTreeSet<CrateId> {
+        self.dependencies().map(|(_, _, dep)| dep.id).collect()
     }
 
     fn with_overrides(mut self, extras: &BTreeMap<CrateId, PairedExtras>) -> Self {
@@ -674,6 +1081,25 @@ impl CrateContext {
                 shallow_since.clone_from(&crate_extra.shallow_since);
             }
 
+            // Git checkout attributes
+            if let Some(SourceAnnotation::Git {
+                init_submodules,
+                recursive_init_submodules,
+                sparse_checkout_patterns,
+                ..
+            }) = &mut self.repository
+            {
+                if let Some(extra) = crate_extra.init_submodules {
+                    *init_submodules = Some(extra);
+                }
+                if let Some(extra) = crate_extra.recursive_init_submodules {
+                    *recursive_init_submodules = Some(extra);
+                }
+                if let Some(extra) = &crate_extra.sparse_checkout_patterns {
+                    *sparse_checkout_patterns = Some(extra.clone());
+                }
+            }
+
             // Patch attributes
             if let Some(repository) = &mut self.repository {
                 match repository {
@@ -703,6 +1129,27 @@ impl CrateContext {
             if let Some(override_targets) = &crate_extra.override_targets {
                 self.override_targets.extend(override_targets.clone());
             }
+
+            // Build script replacement
+            if let Some(cc_library) = &crate_extra.cc_library {
+                self.common_attrs.extra_deps.insert(
+                    Label::Relative {
+                        target: CC_LIBRARY_TARGET_NAME.to_owned(),
+                    },
+                    None,
+                );
+                for (key, value) in &cc_library.rustc_env {
+                    self.common_attrs
+                        .rustc_env
+                        .insert((key.clone(), value.clone()), None);
+                }
+                for cfg in &cc_library.cfgs {
+                    self.common_attrs
+                        .rustc_flags
+                        .insert(format!("--cfg={cfg}"), None);
+                }
+                self.cc_library = Some(cc_library.clone());
+            }
         }
 
         self
@@ -740,22 +1187,40 @@ impl CrateContext {
     fn crate_includes_build_script(
         package_extra: Option<(&CrateId, &PairedExtras)>,
         default_generate_build_script: bool,
-    ) -> bool {
+    ) -> Result<bool> {
+        // A `cc_library` replaces the build script entirely.
+        if let Some((id, settings)) = package_extra {
+            if settings.crate_extra.cc_library.is_some() {
+                if settings.crate_extra.gen_build_script == Some(true) {
+                    bail!(
+                        "The annotations of {} set both `cc_library` and `gen_build_script = True`, \
+                        but a `cc_library` replaces the build script",
+                        id
+                    );
+                }
+                return Ok(false);
+            }
+        }
+
         // If the crate has extra settings, which explicitly set `gen_build_script`, always use
         // this value, otherwise, fallback to the provided default.
-        package_extra
+        Ok(package_extra
             .and_then(|(_, settings)| settings.crate_extra.gen_build_script)
-            .unwrap_or(default_generate_build_script)
+            .unwrap_or(default_generate_build_script))
     }
 
     /// Collect all Bazel targets that should be generated for a particular Package
+    #[allow(clippy::too_many_arguments)]
     fn collect_targets(
         node: &Node,
         packages: &BTreeMap<PackageId, Package>,
         gen_binaries: &GenBinaries,
         include_build_scripts: bool,
+        include_tests: bool,
+        include_examples: bool,
+        crate_features: &Select<BTreeSet<String>>,
         sources_are_present: bool,
-    ) -> BTreeSet<Rule> {
+    ) -> Result<BTreeSet<Rule>> {
         let package = &packages[&node.id];
 
         let package_root = package
@@ -764,7 +1229,37 @@ impl CrateContext {
             .parent()
             .expect("Every manifest should have a parent directory");
 
-        package
+        // `cargo metadata` doesn't report whether a benchmark uses the libtest harness, so
+        // the benchmarks which opt out of it are read from the manifest.
+        let has_benches = package
+            .targets
+            .iter()
+            .any(|target| target.kind.iter().any(|kind| kind == "bench"));
+        let harnessless_benches: BTreeSet<String> = if include_examples && has_benches {
+            if package.manifest_path.exists() {
+                let content = fs::read(package.manifest_path.as_std_path())
+                    .with_context(|| format!("Failed to read {}", package.manifest_path))?;
+                cargo_toml::Manifest::from_slice(&content)
+                    .with_context(|| format!("Failed to parse {}", package.manifest_path))?
+                    .bench
+                    .into_iter()
+                    .filter(|bench| !bench.harness)
+                    .filter_map(|bench| bench.name)
+                    .collect()
+            } else {
+                tracing::warn!(
+                    "The manifest of {} ({}) is unavailable so its benchmarks are assumed to use the libtest harness",
+                    package.name,
+                    package.manifest_path
+                );
+                BTreeSet::new()
+            }
+        } else {
+            BTreeSet::new()
+        };
+        let uses_harness = |name: &String| !harnessless_benches.contains(name);
+
+        let mut targets: BTreeSet<Rule> = package
             .targets
             .iter()
             .flat_map(|target| {
@@ -786,6 +1281,7 @@ impl CrateContext {
                             crate_name,
                             crate_root,
                             srcs: Glob::new_rust_srcs(!sources_are_present),
+                            required_features: BTreeSet::new(),
                         }));
                     }
 
@@ -795,6 +1291,7 @@ impl CrateContext {
                             crate_name,
                             crate_root,
                             srcs: Glob::new_rust_srcs(!sources_are_present),
+                            required_features: BTreeSet::new(),
                         }));
                     }
 
@@ -804,9 +1301,24 @@ impl CrateContext {
                             crate_name,
                             crate_root,
                             srcs: Glob::new_rust_srcs(!sources_are_present),
+                            required_features: BTreeSet::new(),
                         }));
                     }
 
+                    // Binary-like targets are skipped by Cargo unless their `required-features`
+                    // are enabled, so only render them if that's the case on some platform.
+                    let binary_attrs = |crate_name: String| -> Option<TargetAttributes> {
+                        let attrs = TargetAttributes {
+                            crate_name,
+                            crate_root: crate_root.clone(),
+                            srcs: Glob::new_rust_srcs(!sources_are_present),
+                            required_features: target.required_features.iter().cloned().collect(),
+                        };
+                        attrs
+                            .required_features_are_satisfiable(crate_features)
+                            .then_some(attrs)
+                    };
+
                     // Check if the target kind is binary and is one of the ones included in gen_binaries
                     if kind == "bin"
                         && match gen_binaries {
@@ -814,17 +1326,57 @@ impl CrateContext {
                             GenBinaries::Some(set) => set.contains(&target.name),
                         }
                     {
-                        return Some(Rule::Binary(TargetAttributes {
-                            crate_name: target.name.clone(),
-                            crate_root,
-                            srcs: Glob::new_rust_srcs(!sources_are_present),
-                        }));
+                        return binary_attrs(target.name.clone()).map(Rule::Binary);
+                    }
+
+                    // Check to see if the target is an integration test and tests were requested
+                    if include_tests && kind == "test" {
+                        return binary_attrs(crate_name).map(Rule::Test);
+                    }
+
+                    // Check to see if the target is an example or benchmark and they were requested
+                    if include_examples && target.crate_types.iter().any(|t| t == "bin") {
+                        if kind == "example" {
+                            return binary_attrs(target.name.clone()).map(Rule::Example);
+                        }
+                        if kind == "bench" {
+                            let rule = if uses_harness(&target.name) {
+                                Rule::Bench
+                            } else {
+                                Rule::BenchBinary
+                            };
+                            return binary_attrs(target.name.clone()).map(rule);
+                        }
                     }
 
                     None
                 })
             })
-            .collect()
+            .collect();
+
+        // Unit tests are built from the library's own sources, so they reuse its attributes
+        // unless the library explicitly opted out of tests (`test = false`).
+        let has_lib_tests = package.targets.iter().any(|target| {
+            target.test
+                && target
+                    .kind
+                    .iter()
+                    .any(|kind| ["lib", "rlib", "proc-macro"].contains(&kind.as_str()))
+        });
+        if include_tests && has_lib_tests {
+            let unit_tests: Vec<Rule> = targets
+                .iter()
+                .filter_map(|rule| match rule {
+                    Rule::Library(attrs) | Rule::ProcMacro(attrs) => {
+                        Some(Rule::UnitTest(attrs.clone()))
+                    }
+                    _ => None,
+                })
+                .collect();
+            targets.extend(unit_tests);
+        }
+
+        Ok(targets)
     }
 }
 
@@ -832,9 +1384,11 @@ impl CrateContext {
 mod test {
     use super::*;
 
+    use std::str::FromStr;
+
     use semver::Version;
 
-    use crate::config::CrateAnnotations;
+    use crate::config::{CrateAnnotations, CrateNameAndVersionReq};
     use crate::metadata::{Annotations, CargoTreeEntry};
 
     fn common_annotations() -> Annotations {
@@ -863,10 +1417,12 @@ mod test {
             &annotations.lockfile.crates,
             &annotations.pairred_extras,
             &annotations.metadata.workspace_metadata.tree_metadata,
+            annotations.metadata.workspace_metadata.profiles.as_ref(),
             include_binaries,
             include_build_scripts,
             are_sources_present,
-        );
+        )
+        .unwrap();
 
         assert_eq!(context.name, "common");
         assert_eq!(
@@ -875,6 +1431,7 @@ mod test {
                 crate_name: "common".to_owned(),
                 crate_root: Some("lib.rs".to_owned()),
                 srcs: Glob::new_rust_srcs(!are_sources_present),
+                required_features: BTreeSet::new(),
             })]),
         );
     }
@@ -911,10 +1468,12 @@ mod test {
             &annotations.lockfile.crates,
             &pairred_extras,
             &annotations.metadata.workspace_metadata.tree_metadata,
+            annotations.metadata.workspace_metadata.profiles.as_ref(),
             include_binaries,
             include_build_scripts,
             are_sources_present,
-        );
+        )
+        .unwrap();
 
         assert_eq!(context.name, "common");
         assert_eq!(
@@ -924,11 +1483,13 @@ mod test {
                     crate_name: "common".to_owned(),
                     crate_root: Some("lib.rs".to_owned()),
                     srcs: Glob::new_rust_srcs(!are_sources_present),
+                    required_features: BTreeSet::new(),
                 }),
                 Rule::Binary(TargetAttributes {
                     crate_name: "common-bin".to_owned(),
                     crate_root: Some("main.rs".to_owned()),
                     srcs: Glob::new_rust_srcs(!are_sources_present),
+                    required_features: BTreeSet::new(),
                 }),
             ]),
         );
@@ -938,6 +1499,330 @@ mod test {
         );
     }
 
+    #[test]
+    fn context_with_tests() {
+        let annotations = common_annotations();
+
+        let package_id = PackageId {
+            repr: "path+file://{TEMP_DIR}/common#0.1.0".to_owned(),
+        };
+
+        let crate_annotation = &annotations.metadata.crates[&package_id];
+
+        let mut pairred_extras = BTreeMap::new();
+        pairred_extras.insert(
+            CrateId::new("common".to_owned(), semver::Version::new(0, 1, 0)),
+            PairedExtras {
+                package_id,
+                crate_extra: CrateAnnotations {
+                    gen_tests: Some(true),
+                    ..CrateAnnotations::default()
+                },
+            },
+        );
+
+        let include_binaries = false;
+        let include_build_scripts = false;
+        let context = CrateContext::new(
+            crate_annotation,
+            &annotations.metadata.packages,
+            &annotations.lockfile.crates,
+            &pairred_extras,
+            &annotations.metadata.workspace_metadata.tree_metadata,
+            annotations.metadata.workspace_metadata.profiles.as_ref(),
+            include_binaries,
+            include_build_scripts,
+            /* sources_are_present = */ true,
+        )
+        .unwrap();
+
+        let lib_attrs = TargetAttributes {
+            crate_name: "common".to_owned(),
+            crate_root: Some("lib.rs".to_owned()),
+            srcs: Glob::new_rust_srcs(false),
+            required_features: BTreeSet::new(),
+        };
+        assert_eq!(
+            context.targets,
+            BTreeSet::from([
+                Rule::Library(lib_attrs.clone()),
+                Rule::UnitTest(lib_attrs),
+                Rule::Test(TargetAttributes {
+                    crate_name: "common_test".to_owned(),
+                    crate_root: Some("test.rs".to_owned()),
+                    srcs: Glob::new_rust_srcs(false),
+                    required_features: BTreeSet::new(),
+                }),
+            ]),
+        );
+
+        // Tests are not generated when sources are not present in the build file's package
+        let context = CrateContext::new(
+            crate_annotation,
+            &annotations.metadata.packages,
+            &annotations.lockfile.crates,
+            &pairred_extras,
+            &annotations.metadata.workspace_metadata.tree_metadata,
+            annotations.metadata.workspace_metadata.profiles.as_ref(),
+            include_binaries,
+            include_build_scripts,
+            /* sources_are_present = */ false,
+        )
+        .unwrap();
+        assert!(!context
+            .targets
+            .iter()
+            .any(|rule| matches!(rule, Rule::Test(..) | Rule::UnitTest(..))));
+    }
+
+    #[test]
+    fn context_with_examples() {
+        let mut annotations = common_annotations();
+
+        let package_id = PackageId {
+            repr: "path+file://{TEMP_DIR}/common#0.1.0".to_owned(),
+        };
+
+        // Add an example, a benchmark and a benchmark which requires a feature that is never enabled
+        let package = annotations.metadata.packages.get_mut(&package_id).unwrap();
+        let bin_target = package
+            .targets
+            .iter()
+            .find(|target| target.kind.contains(&"test".to_owned()))
+            .unwrap()
+            .clone();
+        let package_root = package.manifest_path.parent().unwrap().to_owned();
+
+        let mut example = bin_target.clone();
+        "demo".clone_into(&mut example.name);
+        example.kind = vec!["example".to_owned()];
+        example.src_path = package_root.join("examples/demo.rs");
+        package.targets.push(example);
+
+        let mut bench = bin_target.clone();
+        "throughput".clone_into(&mut bench.name);
+        bench.kind = vec!["bench".to_owned()];
+        bench.src_path = package_root.join("benches/throughput.rs");
+        package.targets.push(bench);
+
+        let mut bench = bin_target;
+        "perf".clone_into(&mut bench.name);
+        bench.kind = vec!["bench".to_owned()];
+        bench.required_features = vec!["unstable".to_owned()];
+        bench.src_path = package_root.join("benches/perf.rs");
+        package.targets.push(bench);
+
+        let crate_annotation = &annotations.metadata.crates[&package_id];
+
+        let mut pairred_extras = BTreeMap::new();
+        pairred_extras.insert(
+            CrateId::new("common".to_owned(), semver::Version::new(0, 1, 0)),
+            PairedExtras {
+                package_id,
+                crate_extra: CrateAnnotations {
+                    gen_examples: Some(true),
+                    ..CrateAnnotations::default()
+                },
+            },
+        );
+
+        let include_binaries = false;
+        let include_build_scripts = false;
+        let are_sources_present = true;
+        let context = CrateContext::new(
+            crate_annotation,
+            &annotations.metadata.packages,
+            &annotations.lockfile.crates,
+            &pairred_extras,
+            &annotations.metadata.workspace_metadata.tree_metadata,
+            annotations.metadata.workspace_metadata.profiles.as_ref(),
+            include_binaries,
+            include_build_scripts,
+            are_sources_present,
+        )
+        .unwrap();
+
+        assert_eq!(
+            context.targets,
+            BTreeSet::from([
+                Rule::Library(TargetAttributes {
+                    crate_name: "common".to_owned(),
+                    crate_root: Some("lib.rs".to_owned()),
+                    srcs: Glob::new_rust_srcs(!are_sources_present),
+                    required_features: BTreeSet::new(),
+                }),
+                Rule::Example(TargetAttributes {
+                    crate_name: "demo".to_owned(),
+                    crate_root: Some("examples/demo.rs".to_owned()),
+                    srcs: Glob::new_rust_srcs(!are_sources_present),
+                    required_features: BTreeSet::new(),
+                }),
+                Rule::Bench(TargetAttributes {
+                    crate_name: "throughput".to_owned(),
+                    crate_root: Some("benches/throughput.rs".to_owned()),
+                    srcs: Glob::new_rust_srcs(!are_sources_present),
+                    required_features: BTreeSet::new(),
+                }),
+            ]),
+        );
+
+        // Examples can't be built without the crate's sources
+        let context = CrateContext::new(
+            crate_annotation,
+            &annotations.metadata.packages,
+            &annotations.lockfile.crates,
+            &pairred_extras,
+            &annotations.metadata.workspace_metadata.tree_metadata,
+            annotations.metadata.workspace_metadata.profiles.as_ref(),
+            include_binaries,
+            include_build_scripts,
+            /* sources_are_present = */ false,
+        )
+        .unwrap();
+        assert!(!context
+            .targets
+            .iter()
+            .any(|rule| matches!(rule, Rule::Example(..) | Rule::Bench(..))));
+    }
+
+    #[test]
+    fn context_with_harnessless_benches() {
+        let mut annotations = common_annotations();
+
+        let package_id = PackageId {
+            repr: "path+file://{TEMP_DIR}/common#0.1.0".to_owned(),
+        };
+
+        // Point the package at a real manifest which opts its benchmark out of the harness
+        let temp_dir = tempfile::tempdir().unwrap();
+        let manifest_path = temp_dir.path().join("Cargo.toml");
+        fs::write(
+            &manifest_path,
+            textwrap::dedent(
+                r#"
+                [package]
+                name = "common"
+                version = "0.1.0"
+
+                [[bench]]
+                name = "throughput"
+                harness = false
+                "#,
+            ),
+        )
+        .unwrap();
+
+        let package = annotations.metadata.packages.get_mut(&package_id).unwrap();
+        let mut bench = package
+            .targets
+            .iter()
+            .find(|target| target.kind.contains(&"test".to_owned()))
+            .unwrap()
+            .clone();
+        "throughput".clone_into(&mut bench.name);
+        bench.kind = vec!["bench".to_owned()];
+        bench.src_path = package
+            .manifest_path
+            .parent()
+            .unwrap()
+            .join("benches/throughput.rs");
+        package.targets.push(bench);
+        package.manifest_path = manifest_path.clone().try_into().unwrap();
+
+        let crate_annotation = &annotations.metadata.crates[&package_id];
+
+        let mut pairred_extras = BTreeMap::new();
+        pairred_extras.insert(
+            CrateId::new("common".to_owned(), semver::Version::new(0, 1, 0)),
+            PairedExtras {
+                package_id,
+                crate_extra: CrateAnnotations {
+                    gen_examples: Some(true),
+                    ..CrateAnnotations::default()
+                },
+            },
+        );
+
+        let new_context = || {
+            CrateContext::new(
+                crate_annotation,
+                &annotations.metadata.packages,
+                &annotations.lockfile.crates,
+                &pairred_extras,
+                &annotations.metadata.workspace_metadata.tree_metadata,
+                annotations.metadata.workspace_metadata.profiles.as_ref(),
+                /* include_binaries = */ false,
+                /* include_build_scripts = */ false,
+                /* sources_are_present = */ true,
+            )
+        };
+
+        let context = new_context().unwrap();
+        assert!(context.targets.iter().any(
+            |rule| matches!(rule, Rule::BenchBinary(attrs) if attrs.crate_name == "throughput")
+        ));
+        assert!(!context
+            .targets
+            .iter()
+            .any(|rule| matches!(rule, Rule::Bench(..))));
+
+        // A manifest which can't be parsed must not silently render the benchmark with a harness
+        fs::write(&manifest_path, "[[bench]\n").unwrap();
+        let error = new_context().unwrap_err();
+        assert!(
+            format!("{error:?}").contains("Failed to parse"),
+            "Unexpected error: {error:?}"
+        );
+    }
+
+    #[test]
+    fn required_features_platforms() {
+        let mut target = TargetAttributes {
+            crate_name: "mock_bin".to_owned(),
+            required_features: BTreeSet::from(["foo".to_owned(), "dep?/bar".to_owned()]),
+            ..TargetAttributes::default()
+        };
+        let platforms = BTreeMap::from([
+            (
+                "cfg(unix)".to_owned(),
+                BTreeSet::from(["linux".to_owned(), "macos".to_owned()]),
+            ),
+            ("linux".to_owned(), BTreeSet::from(["linux".to_owned()])),
+            ("windows".to_owned(), BTreeSet::from(["windows".to_owned()])),
+        ]);
+
+        let mut features = Select::<BTreeSet<String>>::new();
+        features.insert("foo".to_owned(), Some("linux".to_owned()));
+        features.insert("foo".to_owned(), Some("windows".to_owned()));
+        assert!(!target.required_features_are_satisfiable(&features));
+        assert_eq!(
+            target.required_features_platforms(&features, &platforms),
+            Some(BTreeSet::new())
+        );
+
+        // Features enabled by different configurations are combined per platform.
+        features.insert("dep/bar".to_owned(), Some("cfg(unix)".to_owned()));
+        assert!(target.required_features_are_satisfiable(&features));
+        assert_eq!(
+            target.required_features_platforms(&features, &platforms),
+            Some(BTreeSet::from(["linux".to_owned()]))
+        );
+
+        features.insert("dep/bar".to_owned(), None);
+        features.insert("foo".to_owned(), None);
+        assert_eq!(
+            target.required_features_platforms(&features, &platforms),
+            None
+        );
+
+        target.required_features.clear();
+        assert!(target.required_features_are_satisfiable(&Select::new()));
+        assert_eq!(
+            target.required_features_platforms(&Select::new(), &platforms),
+            None
+        );
+    }
+
     fn build_script_annotations() -> Annotations {
         Annotations::new(
             crate::test::metadata::build_scripts(),
@@ -976,10 +1861,12 @@ mod test {
             &annotations.lockfile.crates,
             &annotations.pairred_extras,
             &annotations.metadata.workspace_metadata.tree_metadata,
+            annotations.metadata.workspace_metadata.profiles.as_ref(),
             include_binaries,
             include_build_scripts,
             are_sources_present,
-        );
+        )
+        .unwrap();
 
         assert_eq!(context.name, "openssl-sys");
         assert!(context.build_script_attrs.is_some());
@@ -990,11 +1877,13 @@ mod test {
                     crate_name: "openssl_sys".to_owned(),
                     crate_root: Some("src/lib.rs".to_owned()),
                     srcs: Glob::new_rust_srcs(!are_sources_present),
+                    required_features: BTreeSet::new(),
                 }),
                 Rule::BuildScript(TargetAttributes {
                     crate_name: "build_script_main".to_owned(),
                     crate_root: Some("build/main.rs".to_owned()),
                     srcs: Glob::new_rust_srcs(!are_sources_present),
+                    required_features: BTreeSet::new(),
                 })
             ]),
         );
@@ -1023,10 +1912,12 @@ mod test {
             &annotations.lockfile.crates,
             &annotations.pairred_extras,
             &annotations.metadata.workspace_metadata.tree_metadata,
+            annotations.metadata.workspace_metadata.profiles.as_ref(),
             include_binaries,
             include_build_scripts,
             are_sources_present,
-        );
+        )
+        .unwrap();
 
         assert_eq!(context.name, "openssl-sys");
         assert!(context.build_script_attrs.is_none());
@@ -1036,10 +1927,125 @@ mod test {
                 crate_name: "openssl_sys".to_owned(),
                 crate_root: Some("src/lib.rs".to_owned()),
                 srcs: Glob::new_rust_srcs(!are_sources_present),
+                required_features: BTreeSet::new(),
             })]),
         );
     }
 
+    #[test]
+    fn context_cc_library_replaces_build_script() {
+        let mut config = crate::config::Config::default();
+        config.annotations.insert(
+            CrateNameAndVersionReq::new("openssl-sys".to_owned(), "*".parse().unwrap()),
+            CrateAnnotations {
+                cc_library: Some(CcLibraryAnnotation {
+                    srcs: BTreeSet::from(["openssl/**/*.c".to_owned()]),
+                    rustc_env: BTreeMap::from([("OPENSSL_VERSION".to_owned(), "3".to_owned())]),
+                    cfgs: BTreeSet::from(["ossl300".to_owned()]),
+                    ..CcLibraryAnnotation::default()
+                }),
+                ..CrateAnnotations::default()
+            },
+        );
+        let annotations = Annotations::new(
+            crate::test::metadata::build_scripts(),
+            crate::test::lockfile::build_scripts(),
+            config,
+        )
+        .unwrap();
+
+        let package_id = PackageId {
+            repr: "registry+https://github.com/rust-lang/crates.io-index#openssl-sys@0.9.87"
+                .to_owned(),
+        };
+
+        let crate_annotation = &annotations.metadata.crates[&package_id];
+
+        let include_binaries = false;
+        let include_build_scripts = true;
+        let are_sources_present = false;
+        let context = CrateContext::new(
+            crate_annotation,
+            &annotations.metadata.packages,
+            &annotations.lockfile.crates,
+            &annotations.pairred_extras,
+            &annotations.metadata.workspace_metadata.tree_metadata,
+            annotations.metadata.workspace_metadata.profiles.as_ref(),
+            include_binaries,
+            include_build_scripts,
+            are_sources_present,
+        )
+        .unwrap();
+
+        // The `cc_library` replaces the build script
+        assert!(context.build_script_attrs.is_none());
+        assert!(!context
+            .targets
+            .iter()
+            .any(|rule| matches!(rule, Rule::BuildScript(..))));
+        assert!(context.cc_library.is_some());
+
+        assert_eq!(
+            context.common_attrs.extra_deps.values(),
+            vec![Label::from_str(":_cc").unwrap()]
+        );
+        assert_eq!(
+            context.common_attrs.rustc_env.values(),
+            vec![("OPENSSL_VERSION".to_owned(), "3".to_owned())]
+        );
+        assert_eq!(
+            context.common_attrs.rustc_flags.values(),
+            vec!["--cfg=ossl300".to_owned()]
+        );
+    }
+
+    #[test]
+    fn context_cc_library_conflicts_with_build_script() {
+        let mut config = crate::config::Config::default();
+        config.annotations.insert(
+            CrateNameAndVersionReq::new("openssl-sys".to_owned(), "*".parse().unwrap()),
+            CrateAnnotations {
+                gen_build_script: Some(true),
+                cc_library: Some(CcLibraryAnnotation::default()),
+                ..CrateAnnotations::default()
+            },
+        );
+        let annotations = Annotations::new(
+            crate::test::metadata::build_scripts(),
+            crate::test::lockfile::build_scripts(),
+            config,
+        )
+        .unwrap();
+
+        let package_id = PackageId {
+            repr: "registry+https://github.com/rust-lang/crates.io-index#openssl-sys@0.9.87"
+                .to_owned(),
+        };
+
+        let crate_annotation = &annotations.metadata.crates[&package_id];
+
+        let include_binaries = false;
+        let include_build_scripts = true;
+        let are_sources_present = false;
+        let result = CrateContext::new(
+            crate_annotation,
+            &annotations.metadata.packages,
+            &annotations.lockfile.crates,
+            &annotations.pairred_extras,
+            &annotations.metadata.workspace_metadata.tree_metadata,
+            annotations.metadata.workspace_metadata.profiles.as_ref(),
+            include_binaries,
+            include_build_scripts,
+            are_sources_present,
+        );
+
+        // A contradictory annotation is rejected rather than one setting being ignored
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("set both `cc_library` and `gen_build_script = True`"));
+    }
+
     #[test]
     fn context_rlib_crate_type() {
         let annotations = crate_type_annotations();
@@ -1059,10 +2065,12 @@ mod test {
             &annotations.lockfile.crates,
             &annotations.pairred_extras,
             &annotations.metadata.workspace_metadata.tree_metadata,
+            annotations.metadata.workspace_metadata.profiles.as_ref(),
             include_binaries,
             include_build_scripts,
             are_sources_present,
-        );
+        )
+        .unwrap();
 
         assert_eq!(context.name, "sysinfo");
         assert!(context.build_script_attrs.is_none());
@@ -1072,6 +2080,7 @@ mod test {
                 crate_name: "sysinfo".to_owned(),
                 crate_root: Some("src/lib.rs".to_owned()),
                 srcs: Glob::new_rust_srcs(!are_sources_present),
+                required_features: BTreeSet::new(),
             })]),
         );
     }
@@ -1101,10 +2110,12 @@ mod test {
             &annotations.lockfile.crates,
             &annotations.pairred_extras,
             &annotations.metadata.workspace_metadata.tree_metadata,
+            annotations.metadata.workspace_metadata.profiles.as_ref(),
             include_binaries,
             include_build_scripts,
             are_sources_present,
-        );
+        )
+        .unwrap();
 
         assert_eq!(context.name, "common");
         check_context(context);
@@ -1233,10 +2244,12 @@ mod test {
             &annotations.lockfile.crates,
             &annotations.pairred_extras,
             &annotations.metadata.workspace_metadata.tree_metadata,
+            annotations.metadata.workspace_metadata.profiles.as_ref(),
             include_binaries,
             include_build_scripts,
             are_sources_present,
-        );
+        )
+        .unwrap();
 
         let mut expected = Select::new();
         expected.insert("unique_feature".to_owned(), None);
@@ -0,0 +1,182 @@
+//! Detection of crates with several semver incompatible versions in a dependency graph.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::{bail, Result};
+use semver::Version;
+
+use crate::config::{CrateId, DuplicateVersionPolicy};
+use crate::context::CrateContext;
+
+/// Warn about, or fail on, crates which appear with several semver incompatible versions
+/// according to the policy. Each version is reported with the crates which depend on it.
+pub(crate) fn check_duplicate_versions(
+    policy: &DuplicateVersionPolicy,
+    crates: &BTreeMap<CrateId, CrateContext>,
+) -> Result<()> {
+    let duplicates = find_duplicates(crates);
+
+    let denied: Vec<String> = duplicates
+        .iter()
+        .filter(|(name, _)| policy.deny.contains(**name))
+        .map(|(name, versions)| describe(name, versions))
+        .collect();
+    if !denied.is_empty() {
+        bail!(
+            "The following crates must only have a single semver compatible version:\n{}",
+            denied.join("\n")
+        );
+    }
+
+    if policy.warn {
+        for (name, versions) in &duplicates {
+            tracing::warn!("{}", describe(name, versions));
+        }
+    }
+
+    Ok(())
+}
+
+/// Each crate name with several semver incompatible versions, mapped to each of the
+/// versions and the crates which depend on them.
+fn find_duplicates(
+    crates: &BTreeMap<CrateId, CrateContext>,
+) -> BTreeMap<&str, BTreeMap<&CrateId, BTreeSet<&CrateId>>> {
+    let mut versions: BTreeMap<&str, BTreeMap<&CrateId, BTreeSet<&CrateId>>> = BTreeMap::new();
+    for id in crates.keys() {
+        versions
+            .entry(id.name.as_str())
+            .or_default()
+            .entry(id)
+            .or_default();
+    }
+    for (id, krate) in crates {
+        for dep in krate.dependency_ids() {
+            if let Some(dependents) = versions
+                .get_mut(dep.name.as_str())
+                .and_then(|versions| versions.get_mut(&dep))
+            {
+                dependents.insert(id);
+            }
+        }
+    }
+
+    versions
+        .into_iter()
+        .filter(|(_, versions)| {
+            versions
+                .keys()
+                .map(|id| compatibility(&id.version))
+                .collect::<BTreeSet<_>>()
+                .len()
+                > 1
+        })
+        .collect()
+}
+
+/// The part of a version which must match for two versions to be semver compatible.
+fn compatibility(version: &Version) -> (u64, Option<u64>, Option<u64>) {
+    match (version.major, version.minor) {
+        (0, 0) => (0, Some(0), Some(version.patch)),
+        (0, minor) => (0, Some(minor), None),
+        (major, _) => (major, None, None),
+    }
+}
+
+fn describe(name: &str, versions: &BTreeMap<&CrateId, BTreeSet<&CrateId>>) -> String {
+    let mut lines = vec![format!(
+        "`{name}` has several semver incompatible versions:"
+    )];
+    for (id, dependents) in versions {
+        lines.push(match dependents.is_empty() {
+            true => format!("  - {}", id.version),
+            false => format!(
+                "  - {} required by: {}",
+                id.version,
+                dependents
+                    .iter()
+                    .map(|dependent| dependent.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        });
+    }
+
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::test::mock_crate_context;
+
+    fn mock_crate(id: &CrateId, deps: &[&CrateId]) -> CrateContext {
+        let deps: Vec<_> = deps.iter().map(|dep| (*dep, None)).collect();
+        mock_crate_context(id, &deps)
+    }
+
+    fn mock_crates() -> BTreeMap<CrateId, CrateContext> {
+        let app = CrateId::new("app".to_owned(), Version::new(0, 1, 0));
+        let old_rand = CrateId::new("rand".to_owned(), Version::new(0, 7, 3));
+        let new_rand = CrateId::new("rand".to_owned(), Version::new(0, 8, 5));
+        let legacy = CrateId::new("legacy".to_owned(), Version::new(1, 0, 0));
+        let syn = CrateId::new("syn".to_owned(), Version::new(2, 0, 0));
+        let newer_syn = CrateId::new("syn".to_owned(), Version::new(2, 0, 48));
+
+        BTreeMap::from([
+            (
+                app.clone(),
+                mock_crate(&app, &[&new_rand, &legacy, &syn, &newer_syn]),
+            ),
+            (legacy.clone(), mock_crate(&legacy, &[&old_rand])),
+            (old_rand.clone(), mock_crate(&old_rand, &[])),
+            (new_rand.clone(), mock_crate(&new_rand, &[])),
+            (syn.clone(), mock_crate(&syn, &[])),
+            (newer_syn.clone(), mock_crate(&newer_syn, &[])),
+        ])
+    }
+
+    #[test]
+    fn semver_compatibility() {
+        assert_eq!(
+            compatibility(&Version::new(1, 2, 3)),
+            compatibility(&Version::new(1, 5, 0))
+        );
+        assert_ne!(
+            compatibility(&Version::new(0, 7, 3)),
+            compatibility(&Version::new(0, 8, 5))
+        );
+        assert_ne!(
+            compatibility(&Version::new(0, 0, 1)),
+            compatibility(&Version::new(0, 0, 2))
+        );
+    }
+
+    #[test]
+    fn deny_duplicates() {
+        let crates = mock_crates();
+
+        assert_eq!(
+            find_duplicates(&crates).keys().collect::<Vec<_>>(),
+            vec![&"rand"]
+        );
+
+        let policy = DuplicateVersionPolicy {
+            warn: true,
+            deny: BTreeSet::from(["syn".to_owned()]),
+        };
+        check_duplicate_versions(&policy, &crates).unwrap();
+
+        let policy = DuplicateVersionPolicy {
+            warn: false,
+            deny: BTreeSet::from(["rand".to_owned()]),
+        };
+        assert_eq!(
+            check_duplicate_versions(&policy, &crates)
+                .unwrap_err()
+                .to_string(),
+            "The following crates must only have a single semver compatible version:\n`rand` has several semver incompatible versions:\n  - 0.7.3 required by: legacy 1.0.0\n  - 0.8.5 required by: app 0.1.0"
+        );
+    }
+}
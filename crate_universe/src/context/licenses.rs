@@ -0,0 +1,249 @@
+//! Enforcement of a workspace's [LicensePolicy].
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use anyhow::{bail, Result};
+
+use crate::config::{CrateId, LicensePolicy};
+use crate::context::CrateContext;
+
+/// A crate whose license does not satisfy a [LicensePolicy].
+#[derive(Debug, PartialEq, Eq)]
+struct Violation<'a> {
+    id: &'a CrateId,
+
+    /// The crate's license, if one was declared.
+    license: Option<&'a str>,
+
+    /// The chain of crates from a workspace member to the violating crate.
+    path: Vec<&'a CrateId>,
+}
+
+/// Ensure the licenses of all crates which are not workspace members satisfy the policy,
+/// reporting each violating crate and the dependency path which pulled it in.
+pub(crate) fn check_license_policy(
+    policy: &LicensePolicy,
+    crates: &BTreeMap<CrateId, CrateContext>,
+    workspace_members: &BTreeMap<CrateId, String>,
+) -> Result<()> {
+    let violations = find_violations(policy, crates, workspace_members);
+    if violations.is_empty() {
+        return Ok(());
+    }
+
+    let report: Vec<String> = violations
+        .iter()
+        .map(|violation| {
+            let license = match violation.license {
+                Some(license) => format!("license `{license}`"),
+                None => "no license".to_owned(),
+            };
+            let path = violation
+                .path
+                .iter()
+                .map(|id| id.to_string())
+                .collect::<Vec<_>>()
+                .join(" -> ");
+            format!("{} ({}): {}", violation.id, license, path)
+        })
+        .collect();
+
+    bail!(
+        "The following crates do not satisfy the license policy:\n  - {}",
+        report.join("\n  - ")
+    )
+}
+
+fn find_violations<'a>(
+    policy: &LicensePolicy,
+    crates: &'a BTreeMap<CrateId, CrateContext>,
+    workspace_members: &BTreeMap<CrateId, String>,
+) -> Vec<Violation<'a>> {
+    let violating: BTreeSet<&CrateId> = crates
+        .iter()
+        .filter(|(id, _)| !workspace_members.contains_key(id))
+        .filter(|(id, krate)| !is_allowed(policy, id, krate.license.as_deref()))
+        .map(|(id, _)| id)
+        .collect();
+
+    if violating.is_empty() {
+        return Vec::new();
+    }
+
+    // Walk the dependency graph from each workspace member to find the shortest
+    // path to each crate.
+    let mut parents: BTreeMap<&CrateId, Option<&CrateId>> = BTreeMap::new();
+    let mut queue: VecDeque<&CrateId> = VecDeque::new();
+    for id in crates
+        .keys()
+        .filter(|id| workspace_members.contains_key(id))
+    {
+        parents.insert(id, None);
+        queue.push_back(id);
+    }
+    while let Some(id) = queue.pop_front() {
+        for dep in crates[id].dependency_ids() {
+            if let Some((dep, _)) = crates.get_key_value(&dep) {
+                if !parents.contains_key(dep) {
+                    parents.insert(dep, Some(id));
+                    queue.push_back(dep);
+                }
+            }
+        }
+    }
+
+    violating
+        .into_iter()
+        .map(|id| {
+            let mut path = vec![id];
+            while let Some(Some(parent)) = parents.get(path[path.len() - 1]) {
+                path.push(parent);
+            }
+            path.reverse();
+
+            Violation {
+                id,
+                license: crates[id].license.as_deref(),
+                path,
+            }
+        })
+        .collect()
+}
+
+/// Determine whether or not a crate's license satisfies the policy.
+fn is_allowed(policy: &LicensePolicy, id: &CrateId, license: Option<&str>) -> bool {
+    let exception = policy
+        .exceptions
+        .iter()
+        .find(|(req, _)| req.matches_crate_id(id))
+        .map(|(_, licenses)| licenses);
+
+    if exception.map(BTreeSet::is_empty).unwrap_or(false) {
+        return true;
+    }
+
+    // Crates without a known license can only satisfy policies which don't
+    // restrict the set of allowed licenses.
+    let expression = match license
+        .and_then(|license| spdx::Expression::parse_mode(license, spdx::ParseMode::LAX).ok())
+    {
+        Some(expression) => expression,
+        None => return policy.allow.is_empty(),
+    };
+
+    expression.evaluate(|req| {
+        let license_id = match req.license.id() {
+            Some(license_id) => license_id.name.to_owned(),
+            None => req.license.to_string(),
+        };
+
+        if policy.deny.contains(&license_id) {
+            return false;
+        }
+
+        policy.allow.is_empty()
+            || policy.allow.contains(&license_id)
+            || exception
+                .map(|licenses| licenses.contains(&license_id))
+                .unwrap_or(false)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use semver::Version;
+
+    use crate::config::CrateNameAndVersionReq;
+    use crate::test::mock_crate_context;
+
+    fn mock_crate(id: &CrateId, license: Option<&str>, deps: &[&CrateId]) -> CrateContext {
+        let deps: Vec<_> = deps.iter().map(|dep| (*dep, None)).collect();
+        CrateContext {
+            license: license.map(str::to_owned),
+            ..mock_crate_context(id, &deps)
+        }
+    }
+
+    fn crate_id(name: &str) -> CrateId {
+        CrateId::new(name.to_owned(), Version::new(1, 0, 0))
+    }
+
+    #[test]
+    fn allow_and_deny() {
+        let policy = LicensePolicy {
+            allow: BTreeSet::from(["MIT".to_owned(), "Apache-2.0".to_owned()]),
+            deny: BTreeSet::from(["Apache-2.0".to_owned()]),
+            exceptions: BTreeMap::from([
+                (
+                    CrateNameAndVersionReq::new("ring".to_owned(), "*".parse().unwrap()),
+                    BTreeSet::from(["ISC".to_owned()]),
+                ),
+                (
+                    CrateNameAndVersionReq::new("vendored".to_owned(), "1".parse().unwrap()),
+                    BTreeSet::new(),
+                ),
+            ]),
+        };
+
+        assert!(is_allowed(&policy, &crate_id("a"), Some("MIT")));
+        assert!(is_allowed(
+            &policy,
+            &crate_id("a"),
+            Some("MIT OR Apache-2.0")
+        ));
+        assert!(is_allowed(&policy, &crate_id("a"), Some("MIT/Apache-2.0")));
+        assert!(!is_allowed(&policy, &crate_id("a"), Some("Apache-2.0")));
+        assert!(!is_allowed(&policy, &crate_id("a"), Some("MIT AND ISC")));
+        assert!(!is_allowed(&policy, &crate_id("a"), None));
+        assert!(is_allowed(&policy, &crate_id("ring"), Some("MIT AND ISC")));
+        assert!(is_allowed(&policy, &crate_id("vendored"), None));
+
+        let deny_only = LicensePolicy {
+            deny: BTreeSet::from(["GPL-3.0".to_owned()]),
+            ..LicensePolicy::default()
+        };
+        assert!(is_allowed(&deny_only, &crate_id("a"), None));
+        assert!(!is_allowed(&deny_only, &crate_id("a"), Some("GPL-3.0")));
+    }
+
+    #[test]
+    fn report_dependency_path() {
+        let app = crate_id("app");
+        let middle = crate_id("middle");
+        let copyleft = crate_id("copyleft");
+
+        let crates = BTreeMap::from([
+            (app.clone(), mock_crate(&app, None, &[&middle])),
+            (
+                middle.clone(),
+                mock_crate(&middle, Some("MIT"), &[&copyleft]),
+            ),
+            (
+                copyleft.clone(),
+                mock_crate(&copyleft, Some("GPL-3.0"), &[]),
+            ),
+        ]);
+        let workspace_members = BTreeMap::from([(app.clone(), "app".to_owned())]);
+        let policy = LicensePolicy {
+            allow: BTreeSet::from(["MIT".to_owned()]),
+            ..LicensePolicy::default()
+        };
+
+        assert_eq!(
+            find_violations(&policy, &crates, &workspace_members),
+            vec![Violation {
+                id: &copyleft,
+                license: Some("GPL-3.0"),
+                path: vec![&app, &middle, &copyleft],
+            }]
+        );
+
+        let err = check_license_policy(&policy, &crates, &workspace_members).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "The following crates do not satisfy the license policy:\n  - copyleft 1.0.0 (license `GPL-3.0`): app 1.0.0 -> middle 1.0.0 -> copyleft 1.0.0"
+        );
+    }
+}
@@ -0,0 +1,281 @@
+//! Support for Cargo's `[lints]` manifest tables.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use cargo_toml::{Lint, LintGroups, LintLevel, LintSet, Lints};
+use serde::{Deserialize, Serialize};
+
+/// The lints declared in a crate's `[lints]` table, converted into command line flags.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct LintFlags {
+    /// Flags for lints in the `[lints.rust]` table, to be passed to `rustc`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub(crate) rustc_flags: Vec<String>,
+
+    /// Flags for lints in the `[lints.clippy]` table, to be passed to `clippy`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub(crate) clippy_flags: Vec<String>,
+}
+
+/// The subset of a `Cargo.toml` manifest which describes lints.
+#[derive(Debug, Default, Deserialize)]
+struct LintsManifest {
+    package: Option<PackageWorkspace>,
+    lints: Option<Lints>,
+    workspace: Option<WorkspaceLints>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PackageWorkspace {
+    /// An explicit path to the package's workspace root (`package.workspace`).
+    workspace: Option<PathBuf>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct WorkspaceLints {
+    lints: Option<LintGroups>,
+}
+
+impl LintFlags {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.rustc_flags.is_empty() && self.clippy_flags.is_empty()
+    }
+
+    /// Load the lints of the package defined by the given manifest. Packages which
+    /// inherit their lints (`workspace = true`) use the lints of the workspace named by
+    /// `package.workspace` or otherwise of the nearest parent workspace manifest.
+    pub(crate) fn try_from_manifest(manifest_path: &Path) -> Result<Self> {
+        let manifest = load_manifest(manifest_path)?;
+
+        let lints = match manifest.lints {
+            Some(lints) => lints,
+            None => return Ok(Self::default()),
+        };
+
+        if !lints.workspace {
+            return Ok(Self::from_lint_groups(&lints.groups));
+        }
+
+        let manifest_dir = manifest_path
+            .parent()
+            .expect("Every manifest should have a parent directory");
+
+        // Packages may name their workspace root explicitly.
+        if let Some(workspace_root) = manifest.package.and_then(|package| package.workspace) {
+            let workspace_manifest_path = manifest_dir.join(workspace_root).join("Cargo.toml");
+            return match load_manifest(&workspace_manifest_path)?.workspace {
+                Some(workspace) => Ok(Self::from_lint_groups(&workspace.lints.unwrap_or_default())),
+                None => anyhow::bail!(
+                    "`{}` names `{}` as its workspace root but it does not define a workspace",
+                    manifest_path.display(),
+                    workspace_manifest_path.display()
+                ),
+            };
+        }
+
+        // Search for the workspace root, which may be the package's own manifest.
+        for dir in manifest_dir.ancestors() {
+            let workspace_manifest_path = dir.join("Cargo.toml");
+            if !workspace_manifest_path.exists() {
+                continue;
+            }
+
+            if let Some(workspace) = load_manifest(&workspace_manifest_path)?.workspace {
+                return Ok(Self::from_lint_groups(&workspace.lints.unwrap_or_default()));
+            }
+        }
+
+        anyhow::bail!(
+            "`{}` inherits lints from its workspace but no workspace manifest was found",
+            manifest_path.display()
+        )
+    }
+
+    fn from_lint_groups(groups: &LintGroups) -> Self {
+        Self {
+            rustc_flags: groups
+                .get("rust")
+                .map(|lints| lint_flags(None, lints))
+                .unwrap_or_default(),
+            clippy_flags: groups
+                .get("clippy")
+                .map(|lints| lint_flags(Some("clippy"), lints))
+                .unwrap_or_default(),
+        }
+    }
+}
+
+fn load_manifest(manifest_path: &Path) -> Result<LintsManifest> {
+    let content = fs::read_to_string(manifest_path)
+        .with_context(|| format!("Failed to read {}", manifest_path.display()))?;
+    toml::from_str(&content).with_context(|| format!("Failed to parse {}", manifest_path.display()))
+}
+
+/// Convert a set of lints into flags in the same order Cargo would pass them: sorted
+/// by priority and then by name so that lints override the groups they belong to.
+fn lint_flags(tool: Option<&str>, lints: &LintSet) -> Vec<String> {
+    let mut lints: Vec<(i32, &String, LintLevel)> = lints
+        .iter()
+        .map(|(name, lint)| match lint {
+            Lint::Simple(level) => (0, name, *level),
+            Lint::Detailed { level, priority } => (priority.unwrap_or_default(), name, *level),
+        })
+        .collect();
+    lints.sort_by(|(lhs_priority, lhs_name, _), (rhs_priority, rhs_name, _)| {
+        lhs_priority
+            .cmp(rhs_priority)
+            .then_with(|| lhs_name.cmp(rhs_name))
+    });
+
+    lints
+        .into_iter()
+        .map(|(_, name, level)| {
+            let level = match level {
+                LintLevel::Allow => "allow",
+                LintLevel::Warn => "warn",
+                LintLevel::Deny => "deny",
+                LintLevel::Forbid => "forbid",
+            };
+            match tool {
+                Some(tool) => format!("--{level}={tool}::{name}"),
+                None => format!("--{level}={name}"),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn package_lints() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let manifest_path = temp_dir.as_ref().join("Cargo.toml");
+        fs::write(
+            &manifest_path,
+            indoc::indoc! {r#"
+                [package]
+                name = "mock"
+                version = "0.1.0"
+
+                [lints.rust]
+                unsafe_code = "forbid"
+                unused = { level = "deny", priority = -1 }
+
+                [lints.clippy]
+                pedantic = "warn"
+            "#},
+        )
+        .unwrap();
+
+        assert_eq!(
+            LintFlags::try_from_manifest(&manifest_path).unwrap(),
+            LintFlags {
+                rustc_flags: vec![
+                    "--deny=unused".to_owned(),
+                    "--forbid=unsafe_code".to_owned()
+                ],
+                clippy_flags: vec!["--warn=clippy::pedantic".to_owned()],
+            }
+        );
+    }
+
+    #[test]
+    fn workspace_lints() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::write(
+            temp_dir.as_ref().join("Cargo.toml"),
+            indoc::indoc! {r#"
+                [workspace]
+                members = ["member"]
+
+                [workspace.lints.rust]
+                missing_docs = "warn"
+            "#},
+        )
+        .unwrap();
+
+        let member_dir = temp_dir.as_ref().join("member");
+        fs::create_dir_all(&member_dir).unwrap();
+        let manifest_path = member_dir.join("Cargo.toml");
+        fs::write(
+            &manifest_path,
+            indoc::indoc! {r#"
+                [package]
+                name = "member"
+                version = "0.1.0"
+
+                [lints]
+                workspace = true
+            "#},
+        )
+        .unwrap();
+
+        assert_eq!(
+            LintFlags::try_from_manifest(&manifest_path).unwrap(),
+            LintFlags {
+                rustc_flags: vec!["--warn=missing_docs".to_owned()],
+                clippy_flags: Vec::new(),
+            }
+        );
+    }
+    #[test]
+    fn explicit_workspace_lints() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let workspace_dir = temp_dir.as_ref().join("workspace");
+        fs::create_dir_all(&workspace_dir).unwrap();
+        fs::write(
+            workspace_dir.join("Cargo.toml"),
+            indoc::indoc! {r#"
+                [workspace]
+                members = ["../member"]
+
+                [workspace.lints.rust]
+                missing_docs = "warn"
+            "#},
+        )
+        .unwrap();
+
+        // A nearer workspace which the package doesn't belong to.
+        fs::write(
+            temp_dir.as_ref().join("Cargo.toml"),
+            indoc::indoc! {r#"
+                [workspace]
+                members = []
+
+                [workspace.lints.rust]
+                unsafe_code = "forbid"
+            "#},
+        )
+        .unwrap();
+
+        let member_dir = temp_dir.as_ref().join("member");
+        fs::create_dir_all(&member_dir).unwrap();
+        let manifest_path = member_dir.join("Cargo.toml");
+        fs::write(
+            &manifest_path,
+            indoc::indoc! {r#"
+                [package]
+                name = "member"
+                version = "0.1.0"
+                workspace = "../workspace"
+
+                [lints]
+                workspace = true
+            "#},
+        )
+        .unwrap();
+
+        assert_eq!(
+            LintFlags::try_from_manifest(&manifest_path).unwrap(),
+            LintFlags {
+                rustc_flags: vec!["--warn=missing_docs".to_owned()],
+                clippy_flags: Vec::new(),
+            }
+        );
+    }
+}
@@ -1,9 +1,10 @@
 use std::collections::{BTreeMap, BTreeSet};
 
 use anyhow::{anyhow, Context, Result};
-use cfg_expr::targets::{get_builtin_target_by_triple, TargetInfo};
+use cfg_expr::targets::{get_builtin_target_by_triple, Arch, Env, Os, Panic, TargetInfo};
 use cfg_expr::{Expression, Predicate};
 
+use crate::config::CustomPlatform;
 use crate::context::CrateContext;
 use crate::utils::target_triple::TargetTriple;
 
@@ -14,28 +15,6 @@ pub(crate) fn resolve_cfg_platforms(
     crates: Vec<&CrateContext>,
     supported_platform_triples: &BTreeSet<TargetTriple>,
 ) -> Result<BTreeMap<String, BTreeSet<TargetTriple>>> {
-    // Collect all unique configurations from all dependencies into a single set
-    let configurations: BTreeSet<String> = crates
-        .iter()
-        .flat_map(|ctx| {
-            let attr = &ctx.common_attrs;
-            let mut configurations = BTreeSet::new();
-
-            configurations.extend(attr.deps.configurations());
-            configurations.extend(attr.deps_dev.configurations());
-            configurations.extend(attr.proc_macro_deps.configurations());
-            configurations.extend(attr.proc_macro_deps_dev.configurations());
-
-            // Chain the build dependencies if some are defined
-            if let Some(attr) = &ctx.build_script_attrs {
-                configurations.extend(attr.deps.configurations());
-                configurations.extend(attr.proc_macro_deps.configurations());
-            }
-
-            configurations
-        })
-        .collect();
-
     // Generate target information for each triple string
     let target_infos = supported_platform_triples
         .iter()
@@ -50,29 +29,11 @@ pub(crate) fn resolve_cfg_platforms(
         )
         .collect::<Result<BTreeMap<&TargetTriple, &'static TargetInfo>>>()?;
 
-    // `cfg-expr` does not understand configurations that are simply platform triples
-    // (`x86_64-unknown-linux-gnu` vs `cfg(target = "x86_64-unkonwn-linux-gnu")`). So
-    // in order to parse configurations, the text is renamed for the check but the
-    // original is retained for comaptibility with the manifest.
-    let rename = |cfg: &str| -> String { format!("cfg(target = \"{cfg}\")") };
-    let original_cfgs: BTreeMap<String, String> = configurations
-        .iter()
-        .filter(|cfg| !cfg.starts_with("cfg("))
-        .map(|cfg| (rename(cfg), cfg.clone()))
-        .collect();
-
-    let mut conditions = configurations
+    let mut conditions = collect_configurations(&crates)
         .into_iter()
-        // `cfg-expr` requires that the expressions be actual `cfg` expressions. Any time
-        // there's a target triple (which is a valid constraint), convert it to a cfg expression.
-        .map(|cfg| match cfg.starts_with("cfg(") {
-            true => cfg,
-            false => rename(&cfg),
-        })
         // Check the current configuration with against each supported triple
         .map(|cfg| {
-            let expression =
-                Expression::parse(&cfg).context(format!("Failed to parse expression: '{cfg}'"))?;
+            let expression = parse_configuration(&cfg)?;
 
             let triples = target_infos
                 .iter()
@@ -89,12 +50,6 @@ pub(crate) fn resolve_cfg_platforms(
                 .map(|(triple, _)| (*triple).clone())
                 .collect();
 
-            // Map any renamed configurations back to their original IDs
-            let cfg = match original_cfgs.get(&cfg) {
-                Some(orig) => orig.clone(),
-                None => cfg,
-            };
-
             Ok((cfg, triples))
         })
         .collect::<Result<BTreeMap<String, BTreeSet<TargetTriple>>>>()?;
@@ -108,6 +63,124 @@ pub(crate) fn resolve_cfg_platforms(
     Ok(conditions)
 }
 
+/// Similar to [resolve_cfg_platforms], produce a mapping of configurations/Cargo target_triples
+/// to the labels of compatible [CustomPlatform]s. Custom platforms are also considered compatible
+/// with the configuration of the triple they are based on.
+pub(crate) fn resolve_cfg_custom_platforms(
+    crates: Vec<&CrateContext>,
+    custom_platforms: &BTreeMap<String, CustomPlatform>,
+) -> Result<BTreeMap<String, BTreeSet<String>>> {
+    if custom_platforms.is_empty() {
+        return Ok(BTreeMap::new());
+    }
+
+    let target_infos = custom_platforms
+        .iter()
+        .map(|(label, platform)| {
+            let target_info = custom_target_info(platform)
+                .with_context(|| format!("Invalid custom platform: {label}"))?;
+            Ok((label, (platform, target_info)))
+        })
+        .collect::<Result<BTreeMap<&String, (&CustomPlatform, TargetInfo)>>>()?;
+
+    let mut conditions = collect_configurations(&crates)
+        .into_iter()
+        .map(|cfg| {
+            let expression = parse_configuration(&cfg)?;
+
+            let labels = target_infos
+                .iter()
+                .filter(|(_, (platform, target_info))| {
+                    expression.eval(|p| match p {
+                        Predicate::Target(tp) => tp.matches(target_info),
+                        Predicate::TargetFeature(feature) => {
+                            platform.target_features.contains(*feature)
+                        }
+                        Predicate::Flag(flag) => platform.cfgs.contains(*flag),
+                        Predicate::KeyValue { key, val } => {
+                            (*key == "target" && val == &target_info.triple.as_str())
+                                || platform.cfgs.contains(&format!("{key}=\"{val}\""))
+                        }
+                        _ => false,
+                    })
+                })
+                .map(|(label, _)| (*label).clone())
+                .collect::<BTreeSet<String>>();
+
+            Ok((cfg, labels))
+        })
+        .collect::<Result<BTreeMap<String, BTreeSet<String>>>>()?;
+    // Custom platforms specialize the triple they are based on.
+    for (label, platform) in custom_platforms.iter() {
+        conditions
+            .entry(platform.triple.to_bazel())
+            .or_default()
+            .insert(label.clone());
+    }
+    conditions.retain(|_, labels| !labels.is_empty());
+    Ok(conditions)
+}
+
+/// Collect all unique configurations from all dependencies of the given crates
+fn collect_configurations(crates: &[&CrateContext]) -> BTreeSet<String> {
+    crates
+        .iter()
+        .flat_map(|ctx| {
+            let attr = &ctx.common_attrs;
+            let mut configurations = BTreeSet::new();
+
+            configurations.extend(attr.deps.configurations());
+            configurations.extend(attr.deps_dev.configurations());
+            configurations.extend(attr.proc_macro_deps.configurations());
+            configurations.extend(attr.proc_macro_deps_dev.configurations());
+
+            // Chain the build dependencies if some are defined
+            if let Some(attr) = &ctx.build_script_attrs {
+                configurations.extend(attr.deps.configurations());
+                configurations.extend(attr.proc_macro_deps.configurations());
+            }
+
+            configurations
+        })
+        .collect()
+}
+
+fn parse_configuration(cfg: &str) -> Result<Expression> {
+    // `cfg-expr` does not understand configurations that are simply platform triples
+    // (`x86_64-unknown-linux-gnu` vs `cfg(target = "x86_64-unkonwn-linux-gnu")`). So
+    // in order to parse configurations, the text is renamed for the check but the
+    // original is retained for comaptibility with the manifest.
+    let expression = match cfg.starts_with("cfg(") {
+        true => cfg.to_owned(),
+        false => format!("cfg(target = \"{cfg}\")"),
+    };
+
+    Expression::parse(&expression).context(format!("Failed to parse expression: '{cfg}'"))
+}
+
+/// Produce the target information of a [CustomPlatform] by overriding the values of the
+/// builtin target information for its triple.
+fn custom_target_info(platform: &CustomPlatform) -> Result<TargetInfo> {
+    let mut target_info = get_builtin_target_by_triple(&platform.triple.to_cargo())
+        .ok_or_else(|| anyhow!("Unknown platform triple: {}", platform.triple))?
+        .clone();
+
+    if let Some(arch) = &platform.target_arch {
+        target_info.arch = Arch::new(arch.clone());
+    }
+    if let Some(os) = &platform.target_os {
+        target_info.os = Some(Os::new(os.clone()));
+    }
+    if let Some(env) = &platform.target_env {
+        target_info.env = Some(Env::new(env.clone()));
+    }
+    if let Some(panic) = &platform.panic {
+        target_info.panic = Panic::new(panic.clone());
+    }
+
+    Ok(target_info)
+}
+
 #[cfg(test)]
 mod test {
     use crate::config::CrateId;
@@ -159,6 +232,8 @@ mod test {
             extra_aliased_targets: BTreeMap::default(),
             alias_rule: None,
             override_targets: BTreeMap::default(),
+            cc_library: None,
+            feature_variants: BTreeMap::default(),
         };
 
         let configurations =
@@ -217,6 +292,8 @@ mod test {
             extra_aliased_targets: BTreeMap::default(),
             alias_rule: None,
             override_targets: BTreeMap::default(),
+            cc_library: None,
+            feature_variants: BTreeMap::default(),
         }
     }
 
@@ -303,6 +380,8 @@ mod test {
             extra_aliased_targets: BTreeMap::default(),
             alias_rule: None,
             override_targets: BTreeMap::default(),
+            cc_library: None,
+            feature_variants: BTreeMap::default(),
         };
 
         let configurations =
@@ -369,6 +448,8 @@ mod test {
             extra_aliased_targets: BTreeMap::default(),
             alias_rule: None,
             override_targets: BTreeMap::default(),
+            cc_library: None,
+            feature_variants: BTreeMap::default(),
         };
 
         let configurations =
@@ -396,4 +477,81 @@ mod test {
             ])
         );
     }
+
+    #[test]
+    fn resolve_custom_platforms() {
+        let custom_platforms = BTreeMap::from([
+            (
+                "//platforms:linux_avx2".to_owned(),
+                CustomPlatform {
+                    triple: TargetTriple::from_bazel("x86_64-unknown-linux-gnu".to_owned()),
+                    target_arch: None,
+                    target_os: None,
+                    target_env: None,
+                    target_features: BTreeSet::from(["avx2".to_owned()]),
+                    panic: None,
+                    cfgs: BTreeSet::new(),
+                },
+            ),
+            (
+                "//platforms:linux_abort".to_owned(),
+                CustomPlatform {
+                    triple: TargetTriple::from_bazel("x86_64-unknown-linux-gnu".to_owned()),
+                    target_arch: None,
+                    target_os: None,
+                    target_env: Some("musl".to_owned()),
+                    target_features: BTreeSet::new(),
+                    panic: Some("abort".to_owned()),
+                    cfgs: BTreeSet::from([
+                        "tokio_unstable".to_owned(),
+                        r#"backend="soft""#.to_owned(),
+                    ]),
+                },
+            ),
+        ]);
+
+        let data = BTreeMap::from([
+            (
+                r#"cfg(target_feature = "avx2")"#,
+                BTreeSet::from(["//platforms:linux_avx2"]),
+            ),
+            (
+                r#"cfg(all(panic = "abort", target_env = "musl"))"#,
+                BTreeSet::from(["//platforms:linux_abort"]),
+            ),
+            (
+                r#"cfg(any(tokio_unstable, backend = "soft"))"#,
+                BTreeSet::from(["//platforms:linux_abort"]),
+            ),
+            (
+                r#"cfg(target_os = "linux")"#,
+                BTreeSet::from(["//platforms:linux_abort", "//platforms:linux_avx2"]),
+            ),
+        ]);
+
+        data.into_iter().for_each(|(configuration, expectation)| {
+            let context = mock_resolve_context(configuration.to_owned());
+
+            let configurations =
+                resolve_cfg_custom_platforms(vec![&context], &custom_platforms).unwrap();
+
+            assert_eq!(
+                configurations,
+                BTreeMap::from([
+                    (
+                        configuration.to_owned(),
+                        expectation.into_iter().map(str::to_owned).collect()
+                    ),
+                    // Custom platforms specialize their triple.
+                    (
+                        "x86_64-unknown-linux-gnu".to_owned(),
+                        BTreeSet::from([
+                            "//platforms:linux_abort".to_owned(),
+                            "//platforms:linux_avx2".to_owned(),
+                        ]),
+                    ),
+                ])
+            );
+        })
+    }
 }
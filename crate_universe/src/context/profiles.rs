@@ -0,0 +1,197 @@
+//! Support for Cargo's `[profile.*]` manifest tables.
+
+use cargo_toml::{DebugSetting, Profile, Profiles};
+use serde::{Deserialize, Serialize};
+
+/// Flags derived from the `[profile.*]` tables of the root manifest for a single crate,
+/// keyed by the Bazel [compilation mode](https://bazel.build/docs/user-manual#compilation-mode)
+/// they apply to.
+///
+/// The `dev` profile is used for `dbg` and `fastbuild` builds while the `release` profile
+/// is used for `opt` builds. Only settings which are explicitly defined are translated as
+/// `rules_rust` already picks defaults appropriate to each compilation mode.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct ProfileFlags {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub(crate) dbg: Vec<String>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub(crate) fastbuild: Vec<String>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub(crate) opt: Vec<String>,
+}
+
+impl ProfileFlags {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.dbg.is_empty() && self.fastbuild.is_empty() && self.opt.is_empty()
+    }
+
+    /// Resolve the profile settings which apply to a package, accounting for any
+    /// `[profile.<name>.package.<spec>]` overrides.
+    pub(crate) fn new(
+        profiles: &Profiles,
+        package_name: &str,
+        package_version: &semver::Version,
+        is_workspace_member: bool,
+    ) -> Self {
+        let flags = |profile: &Option<Profile>| -> Vec<String> {
+            profile
+                .as_ref()
+                .map(|profile| {
+                    profile_flags(profile, package_name, package_version, is_workspace_member)
+                })
+                .unwrap_or_default()
+        };
+
+        let dev = flags(&profiles.dev);
+        Self {
+            dbg: dev.clone(),
+            fastbuild: dev,
+            opt: flags(&profiles.release),
+        }
+    }
+}
+
+/// Convert the settings of a profile into `rustc` flags. Overrides for a specific package
+/// take precedence over the `"*"` override (which does not apply to workspace members),
+/// which in turn takes precedence over the profile itself.
+fn profile_flags(
+    profile: &Profile,
+    package_name: &str,
+    package_version: &semver::Version,
+    is_workspace_member: bool,
+) -> Vec<String> {
+    let package_override = |spec: &str| -> Option<Profile> {
+        let value = profile.package.get(spec)?.clone();
+        match value.try_into() {
+            Ok(profile) => Some(profile),
+            Err(e) => {
+                tracing::warn!("Failed to parse profile override for `{}`: {}", spec, e);
+                None
+            }
+        }
+    };
+
+    let overrides: Vec<Profile> = [
+        package_override(&format!("{package_name}@{package_version}")),
+        package_override(package_name),
+        (!is_workspace_member)
+            .then(|| package_override("*"))
+            .flatten(),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    let layers: Vec<&Profile> = overrides.iter().chain(std::iter::once(profile)).collect();
+
+    let mut flags = Vec::new();
+
+    if let Some(opt_level) = layers.iter().find_map(|p| p.opt_level.as_ref()) {
+        // Levels such as `"s"` are strings which should be rendered without quotes
+        let level = match opt_level {
+            toml::Value::String(level) => level.clone(),
+            level => level.to_string(),
+        };
+        flags.push(format!("-Copt-level={level}"));
+    }
+
+    if let Some(debug) = layers.iter().find_map(|p| p.debug.as_ref()) {
+        let level = match debug {
+            DebugSetting::None => 0,
+            DebugSetting::Lines => 1,
+            DebugSetting::Full => 2,
+        };
+        flags.push(format!("-Cdebuginfo={level}"));
+    }
+
+    if let Some(codegen_units) = layers.iter().find_map(|p| p.codegen_units) {
+        flags.push(format!("-Ccodegen-units={codegen_units}"));
+    }
+
+    // `lto` is intentionally not translated. Passing `-Clto` to every crate would not
+    // perform link time optimization of the final artifact and conflicts with crates
+    // which are built as dylibs or proc-macros.
+
+    if let Some(debug_assertions) = layers.iter().find_map(|p| p.debug_assertions) {
+        flags.push(format!("-Cdebug-assertions={}", on_off(debug_assertions)));
+    }
+
+    if let Some(overflow_checks) = layers.iter().find_map(|p| p.overflow_checks) {
+        flags.push(format!("-Coverflow-checks={}", on_off(overflow_checks)));
+    }
+
+    flags
+}
+
+fn on_off(value: bool) -> &'static str {
+    if value {
+        "on"
+    } else {
+        "off"
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn profiles(manifest: &str) -> Profiles {
+        cargo_toml::Manifest::from_str(manifest).unwrap().profile
+    }
+
+    #[test]
+    fn dev_and_release_profiles() {
+        let profiles = profiles(indoc::indoc! {r#"
+            [package]
+            name = "mock"
+            version = "0.1.0"
+
+            [profile.dev]
+            debug = 1
+
+            [profile.release]
+            lto = "thin"
+            codegen-units = 1
+            opt-level = "s"
+        "#});
+
+        assert_eq!(
+            ProfileFlags::new(&profiles, "regex", &semver::Version::new(1, 0, 0), false),
+            ProfileFlags {
+                dbg: vec!["-Cdebuginfo=1".to_owned()],
+                fastbuild: vec!["-Cdebuginfo=1".to_owned()],
+                opt: vec!["-Copt-level=s".to_owned(), "-Ccodegen-units=1".to_owned(),],
+            }
+        );
+    }
+
+    #[test]
+    fn package_overrides() {
+        let profiles = profiles(indoc::indoc! {r#"
+            [package]
+            name = "mock"
+            version = "0.1.0"
+
+            [profile.dev.package."*"]
+            opt-level = 2
+
+            [profile.dev.package.image]
+            opt-level = 3
+            overflow-checks = false
+        "#});
+        let version = semver::Version::new(1, 0, 0);
+
+        assert_eq!(
+            ProfileFlags::new(&profiles, "image", &version, false).dbg,
+            vec!["-Copt-level=3", "-Coverflow-checks=off"]
+        );
+        assert_eq!(
+            ProfileFlags::new(&profiles, "regex", &version, false).dbg,
+            vec!["-Copt-level=2"]
+        );
+        // The `"*"` override does not apply to workspace members
+        assert!(ProfileFlags::new(&profiles, "mock", &version, true).is_empty());
+    }
+}
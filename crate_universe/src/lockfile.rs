@@ -1,42 +1,282 @@
 //! Utility module for interacting with the cargo-bazel lockfile.
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::ffi::OsStr;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::str::FromStr;
 
 use anyhow::{bail, Context as AnyhowContext, Result};
 use hex::ToHex;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest as Sha2Digest, Sha256};
 
-use crate::config::Config;
+use crate::config::{Config, CrateId};
 use crate::context::Context;
-use crate::metadata::Cargo;
-use crate::splicing::{SplicingManifest, SplicingMetadata};
+use crate::metadata::{
+    Annotations, Cargo, FeatureVariantMetadata, Generator, MetadataGenerator, TreeResolverMetadata,
+};
+use crate::splicing::{SourceInfo, Splicer, SplicingManifest, SplicingMetadata, WorkspaceMetadata};
 
-pub(crate) fn lock_context(
-    mut context: Context,
+/// Content which can be written to a Bazel lockfile and identified by a [Digest].
+pub(crate) trait Lockable: Serialize + Clone {
+    fn checksum(&self) -> Option<&Digest>;
+
+    fn checksum_components(&self) -> Option<&DigestComponents>;
+
+    fn set_checksum(&mut self, checksum: Option<Digest>, components: Option<DigestComponents>);
+}
+
+impl Lockable for Context {
+    fn checksum(&self) -> Option<&Digest> {
+        self.checksum.as_ref()
+    }
+
+    fn checksum_components(&self) -> Option<&DigestComponents> {
+        self.checksum_components.as_ref()
+    }
+
+    fn set_checksum(&mut self, checksum: Option<Digest>, components: Option<DigestComponents>) {
+        self.checksum = checksum;
+        self.checksum_components = components;
+    }
+}
+
+/// A lockfile which records only the resolved inputs of a [Context] rather than the
+/// [Context] itself. The [Context] is derived from these inputs when generating
+/// outputs, which keeps the lockfile small and its diffs limited to actual changes
+/// in dependency resolution.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct ResolvedLockfile {
+    /// The collective checksum of all inputs to the lockfile
+    pub(crate) checksum: Option<Digest>,
+
+    /// The individual hashes of each input which make up the [ResolvedLockfile::checksum]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) checksum_components: Option<DigestComponents>,
+
+    /// The `Cargo.lock` file of the spliced workspace.
+    pub(crate) cargo_lockfile: cargo_lock::Lockfile,
+
+    /// A mapping of crates to information about where their source can be downloaded.
+    pub(crate) sources: BTreeMap<CrateId, SourceInfo>,
+
+    /// The per-platform features of each crate, as resolved by `cargo tree`.
+    pub(crate) tree_metadata: TreeResolverMetadata,
+
+    /// The per-platform dependencies of each feature variant of a workspace member.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) feature_variants: FeatureVariantMetadata,
+}
+
+impl Lockable for ResolvedLockfile {
+    fn checksum(&self) -> Option<&Digest> {
+        self.checksum.as_ref()
+    }
+
+    fn checksum_components(&self) -> Option<&DigestComponents> {
+        self.checksum_components.as_ref()
+    }
+
+    fn set_checksum(&mut self, checksum: Option<Digest>, components: Option<DigestComponents>) {
+        self.checksum = checksum;
+        self.checksum_components = components;
+    }
+}
+
+impl ResolvedLockfile {
+    pub(crate) fn new(
+        cargo_lockfile: cargo_lock::Lockfile,
+        workspace_metadata: WorkspaceMetadata,
+    ) -> Self {
+        Self {
+            checksum: None,
+            checksum_components: None,
+            cargo_lockfile,
+            sources: workspace_metadata.sources,
+            tree_metadata: workspace_metadata.tree_metadata,
+            feature_variants: workspace_metadata.feature_variants,
+        }
+    }
+
+    /// Derive a [Context] by splicing a workspace with the recorded resolution. This avoids
+    /// re-running `cargo tree` or querying crate indexes but still requires `cargo metadata`.
+    pub(crate) fn derive_context(&self, inputs: &DeriveInputs) -> Result<Context> {
+        let temp_dir = tempfile::tempdir().context("Failed to generate temporary directory")?;
+
+        let manifest_path = Splicer::new(
+            temp_dir.as_ref().to_path_buf(),
+            inputs.splicing_manifest.clone(),
+        )?
+        .splice_workspace(&inputs.cargo)
+        .context("Failed to splice workspace")?;
+        let manifest_path = manifest_path.as_path_buf();
+
+        let lockfile_path = manifest_path
+            .parent()
+            .expect("Every manifest should be contained in a parent directory")
+            .join("Cargo.lock");
+        fs::write(&lockfile_path, self.cargo_lockfile.to_string())
+            .with_context(|| format!("Failed to write {}", lockfile_path.display()))?;
+
+        WorkspaceMetadata::write_resolved_sources_and_feature_map(
+            self.sources.clone(),
+            self.tree_metadata.clone(),
+            self.feature_variants.clone(),
+            manifest_path,
+        )
+        .context("Failed to write resolved sources and feature map")?;
+
+        let (cargo_metadata, cargo_lockfile) = Generator::new()
+            .with_cargo(Cargo::new(inputs.cargo.clone()))
+            .with_rustc(inputs.rustc.clone())
+            .generate(manifest_path)
+            .context("Failed to generate cargo metadata")?;
+
+        let annotations = Annotations::new(cargo_metadata, cargo_lockfile, inputs.config.clone())?;
+
+        Context::new(annotations, inputs.config.rendering.are_sources_present())
+    }
+}
+
+/// The inputs, other than the lockfile itself, from which the [Context] of a
+/// [ResolvedLockfile] is derived.
+#[derive(Debug, Clone)]
+pub(crate) struct DeriveInputs {
+    /// The config the workspace was resolved with.
+    pub(crate) config: Config,
+
+    /// The manifest of the workspace to splice.
+    pub(crate) splicing_manifest: SplicingManifest,
+
+    /// The Cargo binary used to gather metadata.
+    pub(crate) cargo: PathBuf,
+
+    /// The Rustc binary used by Cargo.
+    pub(crate) rustc: PathBuf,
+}
+
+impl DeriveInputs {
+    /// Load the inputs from the files the repository rules write for `cargo-bazel`.
+    pub(crate) fn try_from_paths(
+        config: &Path,
+        splicing_manifest: &Path,
+        cargo: &Path,
+        rustc: &Path,
+    ) -> Result<Self> {
+        Ok(Self {
+            config: Config::try_from_path(config)?,
+            splicing_manifest: SplicingManifest::try_from_path(splicing_manifest)?,
+            cargo: cargo.to_path_buf(),
+            rustc: rustc.to_path_buf(),
+        })
+    }
+}
+
+/// The contents of a Bazel lockfile in any of the supported formats.
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub(crate) enum BazelLockfile {
+    /// A lockfile containing the fully rendered [Context].
+    Context(Box<Context>),
+
+    /// A lockfile containing only the inputs from which a [Context] is derived.
+    Resolved(Box<ResolvedLockfile>),
+}
+
+impl Lockable for BazelLockfile {
+    fn checksum(&self) -> Option<&Digest> {
+        match self {
+            Self::Context(context) => context.checksum(),
+            Self::Resolved(resolved) => resolved.checksum(),
+        }
+    }
+
+    fn checksum_components(&self) -> Option<&DigestComponents> {
+        match self {
+            Self::Context(context) => context.checksum_components(),
+            Self::Resolved(resolved) => resolved.checksum_components(),
+        }
+    }
+
+    fn set_checksum(&mut self, checksum: Option<Digest>, components: Option<DigestComponents>) {
+        match self {
+            Self::Context(context) => context.set_checksum(checksum, components),
+            Self::Resolved(resolved) => resolved.set_checksum(checksum, components),
+        }
+    }
+}
+
+impl BazelLockfile {
+    /// Load a lockfile, detecting its format from its contents.
+    pub(crate) fn try_from_path<T: AsRef<Path>>(path: T) -> Result<Self> {
+        let data = fs::read_to_string(path.as_ref())?;
+        Self::from_str(&data)
+    }
+
+    /// Get the [Context] of the lockfile. The context of a [ResolvedLockfile] can only be
+    /// derived given the inputs it was resolved from.
+    pub(crate) fn into_context(self, inputs: Option<&DeriveInputs>) -> Result<Context> {
+        match (self, inputs) {
+            (Self::Context(context), _) => Ok(*context),
+            (Self::Resolved(resolved), Some(inputs)) => resolved
+                .derive_context(inputs)
+                .context("Failed to derive context from resolved lockfile"),
+            (Self::Resolved(_), None) => bail!(
+                "The context of a resolved lockfile can only be derived given the config, \
+                splicing manifest, Cargo and Rustc it was resolved with"
+            ),
+        }
+    }
+
+    /// Compute the [DigestComponents] of the lockfile's current inputs.
+    pub(crate) fn digest_components(
+        &self,
+        config: &Config,
+        splicing_manifest: &SplicingManifest,
+        cargo_bin: &Cargo,
+        rustc_bin: &Path,
+    ) -> Result<DigestComponents> {
+        DigestComponents::new(self, config, splicing_manifest, cargo_bin, rustc_bin)
+    }
+}
+
+impl FromStr for BazelLockfile {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let value: serde_json::Value = serde_json::from_str(s)?;
+
+        // Only resolved lockfiles contain the contents of a `Cargo.lock` file.
+        Ok(match value.get("cargo_lockfile") {
+            Some(_) => Self::Resolved(Box::new(serde_json::from_value(value)?)),
+            None => Self::Context(Box::new(serde_json::from_value(value)?)),
+        })
+    }
+}
+
+pub(crate) fn lock_context<T: Lockable>(
+    mut context: T,
     config: &Config,
     splicing_manifest: &SplicingManifest,
     cargo_bin: &Cargo,
     rustc_bin: &Path,
-) -> Result<Context> {
+) -> Result<T> {
     // Ensure there is no existing checksum which could impact the lockfile results
-    context.checksum = None;
+    context.set_checksum(None, None);
 
-    let checksum = Digest::new(&context, config, splicing_manifest, cargo_bin, rustc_bin)
-        .context("Failed to generate context digest")?;
+    let components =
+        DigestComponents::new(&context, config, splicing_manifest, cargo_bin, rustc_bin)
+            .context("Failed to generate context digest")?;
 
-    Ok(Context {
-        checksum: Some(checksum),
-        ..context
-    })
+    context.set_checksum(Some(Digest::from(&components)), Some(components));
+
+    Ok(context)
 }
 
-/// Write a [crate::context::Context] to disk
-pub(crate) fn write_lockfile(lockfile: Context, path: &Path, dry_run: bool) -> Result<()> {
+/// Write a [crate::context::Context] or [ResolvedLockfile] to disk
+pub(crate) fn write_lockfile<T: Serialize>(lockfile: T, path: &Path, dry_run: bool) -> Result<()> {
     let content = serde_json::to_string_pretty(&lockfile)?;
 
     if dry_run {
@@ -57,105 +297,23 @@ pub(crate) fn write_lockfile(lockfile: Context, path: &Path, dry_run: bool) -> R
 pub(crate) struct Digest(String);
 
 impl Digest {
-    pub(crate) fn new(
-        context: &Context,
-        config: &Config,
-        splicing_manifest: &SplicingManifest,
-        cargo_bin: &Cargo,
-        rustc_bin: &Path,
-    ) -> Result<Self> {
-        let splicing_metadata = SplicingMetadata::try_from((*splicing_manifest).clone())?;
-        let cargo_version = cargo_bin.full_version()?;
-        let rustc_version = Self::bin_version(rustc_bin)?;
-        let cargo_bazel_version = env!("CARGO_PKG_VERSION");
-
-        // Ensure the checksum of a digest is not present before computing one
-        Ok(match context.checksum {
-            Some(_) => Self::compute(
-                &Context {
-                    checksum: None,
-                    ..context.clone()
-                },
-                config,
-                &splicing_metadata,
-                cargo_bazel_version,
-                &cargo_version,
-                &rustc_version,
-            ),
-            None => Self::compute(
-                context,
-                config,
-                &splicing_metadata,
-                cargo_bazel_version,
-                &cargo_version,
-                &rustc_version,
-            ),
-        })
-    }
-
-    /// A helper for generating a hash and logging it's contents.
-    fn compute_single_hash(data: &str, id: &str) -> String {
-        let mut hasher = Sha256::new();
-        hasher.update(data.as_bytes());
-        hasher.update(b"\0");
-        let hash = hasher.finalize().encode_hex::<String>();
-        tracing::debug!("{} hash: {}", id, hash);
-        hash
-    }
-
-    fn compute(
-        context: &Context,
+    #[cfg(test)]
+    fn compute<T: Serialize>(
+        context: &T,
         config: &Config,
         splicing_metadata: &SplicingMetadata,
         cargo_bazel_version: &str,
         cargo_version: &str,
         rustc_version: &str,
     ) -> Self {
-        // Since this method is private, it should be expected that context is
-        // always None. This then allows us to have this method not return a
-        // Result.
-        debug_assert!(context.checksum.is_none());
-
-        let mut hasher = Sha256::new();
-
-        hasher.update(Digest::compute_single_hash(
+        Self::from(&DigestComponents::compute(
+            context,
+            config,
+            splicing_metadata,
             cargo_bazel_version,
-            "cargo-bazel version",
-        ));
-        hasher.update(b"\0");
-
-        // The lockfile context (typically `cargo-bazel-lock.json`).
-        hasher.update(Digest::compute_single_hash(
-            &serde_json::to_string(context).unwrap(),
-            "lockfile context",
-        ));
-        hasher.update(b"\0");
-
-        // This content is generated by various attributes in Bazel rules and written to a file behind the scenes.
-        hasher.update(Digest::compute_single_hash(
-            &serde_json::to_string(config).unwrap(),
-            "workspace config",
-        ));
-        hasher.update(b"\0");
-
-        // Data collected about Cargo manifests and configs that feed into dependency generation. This file
-        // is also generated by Bazel behind the scenes based on user inputs.
-        hasher.update(Digest::compute_single_hash(
-            &serde_json::to_string(splicing_metadata).unwrap(),
-            "splicing manifest",
-        ));
-        hasher.update(b"\0");
-
-        hasher.update(Digest::compute_single_hash(cargo_version, "Cargo version"));
-        hasher.update(b"\0");
-
-        hasher.update(Digest::compute_single_hash(rustc_version, "Rustc version"));
-        hasher.update(b"\0");
-
-        let hash = hasher.finalize().encode_hex::<String>();
-        tracing::debug!("Digest hash: {}", hash);
-
-        Self(hash)
+            cargo_version,
+            rustc_version,
+        ))
     }
 
     pub(crate) fn bin_version(binary: &Path) -> Result<String> {
@@ -215,16 +373,204 @@ impl PartialEq<String> for Digest {
     }
 }
 
+impl From<&DigestComponents> for Digest {
+    fn from(components: &DigestComponents) -> Self {
+        let mut hasher = Sha256::new();
+
+        for (_, hash) in components.hashes() {
+            hasher.update(hash);
+            hasher.update(b"\0");
+        }
+
+        let hash = hasher.finalize().encode_hex::<String>();
+        tracing::debug!("Digest hash: {}", hash);
+
+        Self(hash)
+    }
+}
+
+/// The hashes of each individual input to a [Digest]. These are stored in the lockfile
+/// so the reason a [Digest] no longer matches its inputs can be explained.
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub(crate) struct DigestComponents {
+    /// The hash of the cargo-bazel version.
+    pub(crate) cargo_bazel_version: String,
+
+    /// The hash of the lockfile context (typically `cargo-bazel-lock.json`).
+    pub(crate) lockfile_context: String,
+
+    /// The hash of the workspace config.
+    pub(crate) workspace_config: String,
+
+    /// Hashes of each top level key of the workspace config.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) workspace_config_keys: BTreeMap<String, String>,
+
+    /// The hash of the splicing manifest.
+    pub(crate) splicing_manifest: String,
+
+    /// Hashes of each top level key of the splicing manifest.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) splicing_manifest_keys: BTreeMap<String, String>,
+
+    /// The hash of the Cargo version.
+    pub(crate) cargo_version: String,
+
+    /// The hash of the Rustc version.
+    pub(crate) rustc_version: String,
+}
+
+impl DigestComponents {
+    pub(crate) fn new<T: Lockable>(
+        lockfile: &T,
+        config: &Config,
+        splicing_manifest: &SplicingManifest,
+        cargo_bin: &Cargo,
+        rustc_bin: &Path,
+    ) -> Result<Self> {
+        let splicing_metadata = SplicingMetadata::try_from((*splicing_manifest).clone())?;
+        let cargo_version = cargo_bin.full_version()?;
+        let rustc_version = Digest::bin_version(rustc_bin)?;
+        let cargo_bazel_version = env!("CARGO_PKG_VERSION");
+
+        // Ensure the checksum of a digest is not present before computing one
+        let mut lockfile = lockfile.clone();
+        lockfile.set_checksum(None, None);
+
+        Ok(Self::compute(
+            &lockfile,
+            config,
+            &splicing_metadata,
+            cargo_bazel_version,
+            &cargo_version,
+            &rustc_version,
+        ))
+    }
+
+    /// A helper for generating a hash and logging it's contents.
+    fn compute_single_hash(data: &str, id: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(data.as_bytes());
+        hasher.update(b"\0");
+        let hash = hasher.finalize().encode_hex::<String>();
+        tracing::debug!("{} hash: {}", id, hash);
+        hash
+    }
+
+    /// Hash each top level key of a serialized json object.
+    fn compute_key_hashes(value: &serde_json::Value) -> BTreeMap<String, String> {
+        match value.as_object() {
+            Some(object) => object
+                .iter()
+                .map(|(key, value)| {
+                    (
+                        key.clone(),
+                        Self::compute_single_hash(&value.to_string(), key),
+                    )
+                })
+                .collect(),
+            None => BTreeMap::new(),
+        }
+    }
+
+    fn compute<T: Serialize>(
+        context: &T,
+        config: &Config,
+        splicing_metadata: &SplicingMetadata,
+        cargo_bazel_version: &str,
+        cargo_version: &str,
+        rustc_version: &str,
+    ) -> Self {
+        let config_value = serde_json::to_value(config).unwrap();
+        let splicing_value = serde_json::to_value(splicing_metadata).unwrap();
+
+        Self {
+            cargo_bazel_version: Self::compute_single_hash(
+                cargo_bazel_version,
+                "cargo-bazel version",
+            ),
+            // The lockfile context (typically `cargo-bazel-lock.json`).
+            lockfile_context: Self::compute_single_hash(
+                &serde_json::to_string(context).unwrap(),
+                "lockfile context",
+            ),
+            // This content is generated by various attributes in Bazel rules and written to a file behind the scenes.
+            workspace_config: Self::compute_single_hash(
+                &serde_json::to_string(config).unwrap(),
+                "workspace config",
+            ),
+            workspace_config_keys: Self::compute_key_hashes(&config_value),
+            // Data collected about Cargo manifests and configs that feed into dependency generation. This file
+            // is also generated by Bazel behind the scenes based on user inputs.
+            splicing_manifest: Self::compute_single_hash(
+                &serde_json::to_string(splicing_metadata).unwrap(),
+                "splicing manifest",
+            ),
+            splicing_manifest_keys: Self::compute_key_hashes(&splicing_value),
+            cargo_version: Self::compute_single_hash(cargo_version, "Cargo version"),
+            rustc_version: Self::compute_single_hash(rustc_version, "Rustc version"),
+        }
+    }
+
+    /// The hash of each input in the order they contribute to a [Digest].
+    fn hashes(&self) -> [(&'static str, &String); 6] {
+        [
+            ("cargo-bazel version", &self.cargo_bazel_version),
+            ("lockfile context", &self.lockfile_context),
+            ("workspace config", &self.workspace_config),
+            ("splicing manifest", &self.splicing_manifest),
+            ("Cargo version", &self.cargo_version),
+            ("Rustc version", &self.rustc_version),
+        ]
+    }
+
+    /// Describe each input which differs between two sets of components. For the workspace
+    /// config and splicing manifest, the top level keys which differ are also reported.
+    pub(crate) fn explain_differences(&self, other: &Self) -> Vec<String> {
+        let changed_keys = |lhs: &BTreeMap<String, String>, rhs: &BTreeMap<String, String>| {
+            lhs.keys()
+                .chain(rhs.keys())
+                .collect::<BTreeSet<&String>>()
+                .into_iter()
+                .filter(|key| lhs.get(*key) != rhs.get(*key))
+                .cloned()
+                .collect::<Vec<String>>()
+        };
+
+        self.hashes()
+            .into_iter()
+            .zip(other.hashes())
+            .filter(|((_, lhs), (_, rhs))| lhs != rhs)
+            .map(|((id, _), _)| {
+                let keys = match id {
+                    "workspace config" => {
+                        changed_keys(&self.workspace_config_keys, &other.workspace_config_keys)
+                    }
+                    "splicing manifest" => {
+                        changed_keys(&self.splicing_manifest_keys, &other.splicing_manifest_keys)
+                    }
+                    _ => Vec::new(),
+                };
+                match keys.is_empty() {
+                    true => id.to_owned(),
+                    false => format!("{} (keys: {})", id, keys.join(", ")),
+                }
+            })
+            .collect()
+    }
+}
+
 #[cfg(test)]
 mod test {
     use crate::config::{CrateAnnotations, CrateNameAndVersionReq};
+    use crate::metadata::CargoTreeEntry;
+    use crate::select::Select;
     use crate::splicing::cargo_config::{AdditionalRegistry, CargoConfig, Registry};
+    use crate::test;
     use crate::utils::target_triple::TargetTriple;
 
     use super::*;
 
-    use std::collections::BTreeSet;
-
     #[test]
     fn simple_digest() {
         let context = Context::default();
@@ -305,6 +651,8 @@ mod test {
             )]),
             manifests: BTreeMap::new(),
             cargo_config: None,
+            local_registry_url_template: None,
+            profiles: None,
         };
 
         let digest = Digest::compute(
@@ -349,6 +697,7 @@ mod test {
                 token: None,
             },
             source: BTreeMap::new(),
+            root: None,
         };
 
         let splicing_metadata = SplicingMetadata {
@@ -370,4 +719,136 @@ mod test {
             digest,
         );
     }
+
+    #[test]
+    fn explain_digest_component_differences() {
+        let context = Context::default();
+        let splicing_metadata = SplicingMetadata::default();
+
+        let original = DigestComponents::compute(
+            &context,
+            &Config::default(),
+            &splicing_metadata,
+            "0.1.0",
+            "cargo 1.57.0 (b2e52d7ca 2021-10-21)",
+            "rustc 1.57.0 (f1edd0429 2021-11-29)",
+        );
+
+        let updated = DigestComponents::compute(
+            &context,
+            &Config {
+                generate_binaries: true,
+                ..Config::default()
+            },
+            &splicing_metadata,
+            "0.1.0",
+            "cargo 1.57.0 (b2e52d7ca 2021-10-21)",
+            "rustc 1.58.0 (02072b482 2022-01-11)",
+        );
+
+        assert_eq!(
+            original.explain_differences(&original),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            original.explain_differences(&updated),
+            vec![
+                "workspace config (keys: generate_binaries)".to_owned(),
+                "Rustc version".to_owned(),
+            ]
+        );
+        assert_ne!(Digest::from(&original), Digest::from(&updated));
+    }
+
+    #[test]
+    fn detect_lockfile_format() {
+        let context = serde_json::to_string(&Context::default()).unwrap();
+        assert!(matches!(
+            BazelLockfile::from_str(&context).unwrap(),
+            BazelLockfile::Context(_)
+        ));
+
+        let resolved = ResolvedLockfile::new(test::lockfile::common(), common_workspace_metadata());
+        let content = serde_json::to_string_pretty(&resolved).unwrap();
+
+        match BazelLockfile::from_str(&content).unwrap() {
+            BazelLockfile::Resolved(lockfile) => assert_eq!(*lockfile, resolved),
+            lockfile => panic!("Unexpected lockfile format: {lockfile:?}"),
+        }
+    }
+
+    /// The [WorkspaceMetadata] splicing records for the `common` test fixture.
+    fn common_workspace_metadata() -> WorkspaceMetadata {
+        let cargo_metadata = test::metadata::common();
+        let crate_id = |id: &cargo_metadata::PackageId| {
+            CrateId::from(
+                cargo_metadata
+                    .packages
+                    .iter()
+                    .find(|pkg| &pkg.id == id)
+                    .unwrap(),
+            )
+        };
+
+        let sources = test::lockfile::common()
+            .packages
+            .iter()
+            .filter_map(|pkg| {
+                Some((
+                    CrateId::new(pkg.name.as_str().to_owned(), pkg.version.clone()),
+                    SourceInfo {
+                        url: format!(
+                            "https://static.crates.io/crates/{}/{}/download",
+                            pkg.name, pkg.version
+                        ),
+                        sha256: pkg.checksum.as_ref()?.to_string(),
+                        registry: None,
+                    },
+                ))
+            })
+            .collect();
+
+        let tree_metadata = cargo_metadata
+            .resolve
+            .as_ref()
+            .unwrap()
+            .nodes
+            .iter()
+            .map(|node| {
+                let entry = CargoTreeEntry {
+                    features: node.features.iter().cloned().collect(),
+                    deps: node.deps.iter().map(|dep| crate_id(&dep.pkg)).collect(),
+                };
+                (crate_id(&node.id), Select::from_value(Some(entry)))
+            })
+            .collect();
+
+        WorkspaceMetadata {
+            sources,
+            tree_metadata,
+            ..WorkspaceMetadata::default()
+        }
+    }
+
+    #[test]
+    fn resolved_lockfile_is_smaller_than_context() {
+        let workspace_metadata = common_workspace_metadata();
+
+        let mut cargo_metadata = test::metadata::common();
+        cargo_metadata.workspace_metadata = serde_json::json!({
+            "cargo-bazel": workspace_metadata,
+        });
+        let annotations =
+            Annotations::new(cargo_metadata, test::lockfile::common(), Config::default()).unwrap();
+        let context = Context::new(annotations, false).unwrap();
+
+        let resolved = ResolvedLockfile::new(test::lockfile::common(), workspace_metadata);
+
+        let context_len = serde_json::to_string_pretty(&context).unwrap().len();
+        let resolved_len = serde_json::to_string_pretty(&resolved).unwrap().len();
+        assert!(
+            resolved_len < context_len,
+            "The resolved lockfile ({resolved_len} bytes) should be smaller than the context ({context_len} bytes)"
+        );
+    }
 }
@@ -23,5 +23,25 @@ fn main() -> cli::Result<()> {
             cli::init_logging("Vendor");
             cli::vendor(opt)
         }
+        cli::Options::Diff(opt) => {
+            cli::init_logging("Diff");
+            cli::diff(opt)
+        }
+        cli::Options::Migrate(opt) => {
+            cli::init_logging("Migrate");
+            cli::migrate(opt)
+        }
+        cli::Options::Sbom(opt) => {
+            cli::init_logging("Sbom");
+            cli::sbom(opt)
+        }
+        cli::Options::Audit(opt) => {
+            cli::init_logging("Audit");
+            cli::audit(opt)
+        }
+        cli::Options::Why(opt) => {
+            cli::init_logging("Why");
+            cli::why(opt)
+        }
     }
 }
@@ -15,13 +15,15 @@ use std::sync::{Arc, Mutex};
 use anyhow::{anyhow, bail, Context, Result};
 use cargo_lock::Lockfile as CargoLockfile;
 use cargo_metadata::{Metadata as CargoMetadata, MetadataCommand};
+use itertools::Itertools;
 use semver::Version;
 use serde::{Deserialize, Serialize};
 use tracing::debug;
 
-use crate::config::CrateId;
+use crate::config::{CrateId, CrateNameAndVersionReq};
 use crate::lockfile::Digest;
 use crate::select::{Select, SelectableScalar};
+use crate::splicing::WorkspaceMetadata;
 use crate::utils::target_triple::TargetTriple;
 
 pub(crate) use self::dependency::*;
@@ -82,7 +84,7 @@ impl MetadataGenerator for Generator {
             other_options.push("-Zbindeps".to_owned());
         }
 
-        let metadata = self
+        let mut metadata = self
             .cargo_bin
             .metadata_command()?
             .current_dir(manifest_dir)
@@ -90,10 +92,99 @@ impl MetadataGenerator for Generator {
             .other_options(other_options)
             .exec()?;
 
+        // Crates which are only used by feature variants are missing from the metadata of
+        // the workspace so they're collected from the metadata of each variant.
+        let feature_variants = WorkspaceMetadata::try_from(metadata.workspace_metadata.clone())
+            .map(|workspace_metadata| workspace_metadata.feature_variants)
+            .unwrap_or_default();
+        for (variant, tree) in feature_variants {
+            let package = metadata
+                .packages
+                .iter()
+                .find(|package| CrateId::from(*package) == tree.crate_id)
+                .with_context(|| {
+                    format!(
+                        "The crate `{}` of feature variant `{}` is not in the workspace",
+                        tree.crate_id, variant
+                    )
+                })?;
+            let variant_metadata = FeatureVariantWorkspace::new(
+                &self.cargo_bin,
+                manifest_path.as_ref(),
+                &metadata,
+                package,
+                &tree.features,
+            )
+            .and_then(|workspace| workspace.metadata(&self.cargo_bin))
+            .with_context(|| {
+                format!("Failed to generate metadata for feature variant `{variant}`")
+            })?;
+            merge_metadata(&mut metadata, variant_metadata);
+        }
+
         Ok((metadata, lockfile))
     }
 }
 
+/// Adds the packages, dependencies and features in `other` which are missing from `metadata`.
+fn merge_metadata(metadata: &mut CargoMetadata, other: CargoMetadata) {
+    for package in other.packages {
+        if !metadata
+            .packages
+            .iter()
+            .any(|existing| existing.id == package.id)
+        {
+            metadata.packages.push(package);
+        }
+    }
+
+    let (resolve, other_resolve) = match (metadata.resolve.as_mut(), other.resolve) {
+        (Some(resolve), Some(other_resolve)) => (resolve, other_resolve),
+        _ => return,
+    };
+    for node in other_resolve.nodes {
+        let existing = match resolve
+            .nodes
+            .iter_mut()
+            .find(|existing| existing.id == node.id)
+        {
+            Some(existing) => existing,
+            None => {
+                resolve.nodes.push(node);
+                continue;
+            }
+        };
+        for dep in node.deps {
+            match existing
+                .deps
+                .iter_mut()
+                .find(|existing| existing.pkg == dep.pkg)
+            {
+                Some(existing_dep) => {
+                    for kind in dep.dep_kinds {
+                        if !existing_dep.dep_kinds.iter().any(|existing| {
+                            existing.kind == kind.kind && existing.target == kind.target
+                        }) {
+                            existing_dep.dep_kinds.push(kind);
+                        }
+                    }
+                }
+                None => existing.deps.push(dep),
+            }
+        }
+        for dependency in node.dependencies {
+            if !existing.dependencies.contains(&dependency) {
+                existing.dependencies.push(dependency);
+            }
+        }
+        for feature in node.features {
+            if !existing.features.contains(&feature) {
+                existing.features.push(feature);
+            }
+        }
+    }
+}
+
 /// Cargo encapsulates a path to a `cargo` binary.
 /// Any invocations of `cargo` (either as a `std::process::Command` or via `cargo_metadata`) should
 /// go via this wrapper to ensure that any environment variables needed are set appropriately.
@@ -485,6 +576,63 @@ impl SelectableScalar for CargoTreeEntry {}
 /// Feature and dependency metadata generated from [TreeResolver].
 pub(crate) type TreeResolverMetadata = BTreeMap<CrateId, Select<CargoTreeEntry>>;
 
+/// A feature variant of a crate, resolved separately from the rest of the workspace.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct FeatureVariantTree {
+    /// The crate the variant is declared for.
+    pub(crate) crate_id: CrateId,
+
+    /// The features the variant enables on the crate.
+    pub(crate) features: BTreeSet<String>,
+
+    /// The features and dependencies of the crate and everything it depends on when
+    /// only the variant is built.
+    pub(crate) tree_metadata: TreeResolverMetadata,
+
+    /// Lockfile entries of crates which only the variant depends on. These can't be added
+    /// to the lockfile of the workspace as Cargo would remove them again.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) lock_packages: Vec<cargo_lock::Package>,
+}
+
+impl FeatureVariantTree {
+    /// The crates which are built differently in the variant than they are in the rest of
+    /// the workspace. These are the crate the variant is declared for, any crate with
+    /// different features or dependencies and any crate which depends on one of those.
+    pub(crate) fn affected_crates(
+        &self,
+        tree_metadata: &TreeResolverMetadata,
+    ) -> BTreeSet<CrateId> {
+        let mut crates: BTreeSet<CrateId> = self
+            .tree_metadata
+            .iter()
+            .filter(|(id, entry)| **id == self.crate_id || tree_metadata.get(*id) != Some(*entry))
+            .map(|(id, _)| id.clone())
+            .collect();
+        loop {
+            let dependents: Vec<CrateId> = self
+                .tree_metadata
+                .iter()
+                .filter(|(id, _)| !crates.contains(*id))
+                .filter(|(_, entry)| {
+                    entry
+                        .values()
+                        .iter()
+                        .any(|data| data.deps.iter().any(|dep| crates.contains(dep)))
+                })
+                .map(|(id, _)| id.clone())
+                .collect();
+            if dependents.is_empty() {
+                return crates;
+            }
+            crates.extend(dependents);
+        }
+    }
+}
+
+/// Feature variants keyed by their name.
+pub(crate) type FeatureVariantMetadata = BTreeMap<String, FeatureVariantTree>;
+
 /// Generates metadata about a Cargo workspace tree which supplements the inaccuracies in
 /// standard [Cargo metadata](https://doc.rust-lang.org/cargo/commands/cargo-metadata.html)
 /// due lack of [Feature resolver 2](https://doc.rust-lang.org/cargo/reference/resolver.html#feature-resolver-version-2)
@@ -518,6 +666,18 @@ impl TreeResolver {
             manifest_path.display()
         );
 
+        self.resolve(manifest_path, target_triples, &["--workspace".to_owned()])
+    }
+
+    /// Runs `cargo tree` for each target triple with the given package selection arguments
+    /// and combines the results into features and dependencies shared by all triples and
+    /// those specific to some.
+    fn resolve(
+        &self,
+        manifest_path: &Path,
+        target_triples: &BTreeSet<TargetTriple>,
+        package_args: &[String],
+    ) -> Result<TreeResolverMetadata> {
         let manifest_dir = manifest_path.parent().unwrap();
         let mut target_triple_to_child = BTreeMap::new();
         debug!("Spawning processes for {:?}", target_triples);
@@ -541,7 +701,7 @@ impl TreeResolver {
                 // https://doc.rust-lang.org/cargo/commands/cargo-tree.html#tree-formatting-options
                 .arg("--format=|{p}|{f}|")
                 .arg("--color=never")
-                .arg("--workspace")
+                .args(package_args)
                 .arg("--target")
                 .arg(target_triple.to_cargo())
                 .env("RUSTC", &self.rustc_bin)
@@ -638,6 +798,333 @@ impl TreeResolver {
         }
         Ok(result)
     }
+
+    /// Resolves each feature variant in a workspace of its own so the features it enables
+    /// are not unified with those the rest of the workspace enables, and vice versa.
+    #[tracing::instrument(name = "TreeResolver::generate_feature_variants", skip_all)]
+    pub(crate) fn generate_feature_variants(
+        &self,
+        manifest_path: &Path,
+        target_triples: &BTreeSet<TargetTriple>,
+        feature_variants: &BTreeMap<CrateNameAndVersionReq, BTreeMap<String, BTreeSet<String>>>,
+    ) -> Result<FeatureVariantMetadata> {
+        let mut result = FeatureVariantMetadata::new();
+        if feature_variants.is_empty() {
+            return Ok(result);
+        }
+
+        let metadata = self
+            .cargo_bin
+            .metadata_command()?
+            .current_dir(manifest_path.parent().unwrap())
+            .manifest_path(manifest_path)
+            .other_options(vec!["--locked".to_owned()])
+            .exec()?;
+        let lockfile =
+            cargo_lock::Lockfile::load(manifest_path.parent().unwrap().join("Cargo.lock"))?;
+
+        for (id, variants) in feature_variants {
+            let package = match metadata
+                .packages
+                .iter()
+                .filter(|package| id.matches(package))
+                .collect::<Vec<_>>()
+                .as_slice()
+            {
+                [package] => *package,
+                [] => bail!(
+                    "Feature variants were declared for `{}` but no such crate is in the dependency graph",
+                    id
+                ),
+                packages => bail!(
+                    "Feature variants were declared for `{}` which matches several crates: {}",
+                    id,
+                    packages.iter().map(|package| &package.id).join(", ")
+                ),
+            };
+
+            for (variant, features) in variants {
+                debug!(
+                    "Resolving feature variant `{}` of `{}`",
+                    variant, package.id
+                );
+                let workspace = FeatureVariantWorkspace::new(
+                    &self.cargo_bin,
+                    manifest_path,
+                    &metadata,
+                    package,
+                    features,
+                )
+                .with_context(|| {
+                    format!(
+                        "Failed to create a workspace for feature variant `{}` of `{}`",
+                        variant, package.id
+                    )
+                })?;
+                let mut tree_metadata = self
+                    .resolve(
+                        &workspace.manifest_path,
+                        target_triples,
+                        &workspace.tree_args,
+                    )
+                    .with_context(|| {
+                        format!(
+                            "Failed to resolve feature variant `{}` of `{}`",
+                            variant, package.id
+                        )
+                    })?;
+                if let Some(root) = &workspace.synthetic_root {
+                    tree_metadata.remove(root);
+                }
+                let lock_packages = workspace.lock_packages(&lockfile)?;
+
+                result.insert(
+                    variant.clone(),
+                    FeatureVariantTree {
+                        crate_id: CrateId::from(package),
+                        features: features.clone(),
+                        tree_metadata,
+                        lock_packages,
+                    },
+                );
+            }
+        }
+        Ok(result)
+    }
+}
+
+/// A Cargo workspace in which a single feature variant of a crate can be resolved.
+///
+/// Workspace members are resolved in the spliced workspace itself by selecting only the
+/// member. Any other crate is resolved in a temporary workspace containing a single package
+/// which depends on exactly the crate with the features of the variant, reusing the
+/// `Cargo.lock` file and Cargo configuration of the spliced workspace.
+pub(crate) struct FeatureVariantWorkspace {
+    /// The manifest of the workspace to resolve the variant in.
+    manifest_path: PathBuf,
+
+    /// Arguments selecting the variant for `cargo tree`.
+    tree_args: Vec<String>,
+
+    /// Arguments selecting the variant for `cargo metadata`.
+    metadata_args: Vec<String>,
+
+    /// The package created to depend on the crate, if it's not a workspace member.
+    synthetic_root: Option<CrateId>,
+
+    /// The directory of the temporary workspace, if one was created.
+    _temp_dir: Option<tempfile::TempDir>,
+}
+
+/// The name of the package depending on crates outside of the workspace in a [FeatureVariantWorkspace].
+const FEATURE_VARIANT_PACKAGE_NAME: &str = "cargo-bazel-feature-variant";
+
+impl FeatureVariantWorkspace {
+    pub(crate) fn new(
+        cargo: &Cargo,
+        manifest_path: &Path,
+        metadata: &CargoMetadata,
+        package: &cargo_metadata::Package,
+        features: &BTreeSet<String>,
+    ) -> Result<Self> {
+        let spec = format!("{}@{}", package.name, package.version);
+        if metadata.workspace_members.contains(&package.id) {
+            let features = features
+                .iter()
+                .map(|feature| format!("{}/{}", package.name, feature))
+                .join(",");
+            let mut tree_args = vec!["--package".to_owned(), spec];
+            let mut metadata_args = Vec::new();
+            if !features.is_empty() {
+                tree_args.extend(["--features".to_owned(), features.clone()]);
+                metadata_args.extend(["--features".to_owned(), features]);
+            }
+            return Ok(Self {
+                manifest_path: manifest_path.to_path_buf(),
+                tree_args,
+                metadata_args,
+                synthetic_root: None,
+                _temp_dir: None,
+            });
+        }
+
+        let manifest_dir = manifest_path.parent().unwrap();
+        let temp_dir = tempfile::tempdir()?;
+        let root = temp_dir.path();
+
+        let mut manifest: toml::Table = toml::from_str(&format!(
+            "[package]\nname = \"{FEATURE_VARIANT_PACKAGE_NAME}\"\nversion = \"0.0.0\"\nedition = \"2021\"\n\n[lib]\npath = \"lib.rs\"\n\n[workspace]\n"
+        ))?;
+        manifest.insert(
+            "dependencies".to_owned(),
+            toml::Value::Table(toml::Table::from_iter([(
+                package.name.clone(),
+                toml::Value::Table(feature_variant_dependency(package, features)?),
+            )])),
+        );
+        // Patches apply to the crate's dependencies as well, so they're carried over with any
+        // paths made absolute.
+        let spliced_manifest: toml::Table = toml::from_str(&fs::read_to_string(manifest_path)?)?;
+        if let Some(mut patch) = spliced_manifest.get("patch").cloned() {
+            for dependency in patch
+                .as_table_mut()
+                .into_iter()
+                .flat_map(|registries| registries.iter_mut().map(|(_, patches)| patches))
+                .filter_map(toml::Value::as_table_mut)
+                .flat_map(|patches| patches.iter_mut().map(|(_, dependency)| dependency))
+                .filter_map(toml::Value::as_table_mut)
+            {
+                if let Some(path) = dependency.get("path").and_then(toml::Value::as_str) {
+                    let path = manifest_dir.join(path).to_string_lossy().into_owned();
+                    dependency.insert("path".to_owned(), toml::Value::String(path));
+                }
+            }
+            manifest.insert("patch".to_owned(), patch);
+        }
+        fs::write(root.join("Cargo.toml"), toml::to_string(&manifest)?)?;
+        fs::write(root.join("lib.rs"), "")?;
+
+        fs::copy(manifest_dir.join("Cargo.lock"), root.join("Cargo.lock"))?;
+        let cargo_config = manifest_dir.join(".cargo").join("config.toml");
+        if cargo_config.exists() {
+            fs::create_dir_all(root.join(".cargo"))?;
+            fs::copy(cargo_config, root.join(".cargo").join("config.toml"))?;
+        }
+
+        // Add the new package to the lockfile without updating any other entries so the
+        // variant is resolved against the same versions as the rest of the workspace.
+        let output = cargo
+            .command()?
+            .current_dir(root)
+            .arg("update")
+            .arg("--workspace")
+            .arg("--manifest-path")
+            .arg(root.join("Cargo.toml"))
+            .output()?;
+        if !output.status.success() {
+            bail!(
+                "Failed to lock the feature variant workspace: {}",
+                String::from_utf8_lossy(&output.stderr)
+            );
+        }
+
+        Ok(Self {
+            manifest_path: root.join("Cargo.toml"),
+            tree_args: Vec::new(),
+            metadata_args: Vec::new(),
+            synthetic_root: Some(CrateId::new(
+                FEATURE_VARIANT_PACKAGE_NAME.to_owned(),
+                Version::new(0, 0, 0),
+            )),
+            _temp_dir: Some(temp_dir),
+        })
+    }
+
+    /// Runs `cargo metadata` for the variant. The package created to depend on crates outside
+    /// of the workspace is removed from the output.
+    pub(crate) fn metadata(&self, cargo: &Cargo) -> Result<CargoMetadata> {
+        let mut other_options = vec!["--locked".to_owned()];
+        other_options.extend(self.metadata_args.iter().cloned());
+        let mut metadata = cargo
+            .metadata_command()?
+            .current_dir(self.manifest_path.parent().unwrap())
+            .manifest_path(&self.manifest_path)
+            .other_options(other_options)
+            .exec()?;
+
+        if self.synthetic_root.is_some() {
+            let members = std::mem::take(&mut metadata.workspace_members);
+            metadata
+                .packages
+                .retain(|package| !members.contains(&package.id));
+            if let Some(resolve) = metadata.resolve.as_mut() {
+                resolve.nodes.retain(|node| !members.contains(&node.id));
+                resolve.root = None;
+            }
+        }
+
+        Ok(metadata)
+    }
+
+    /// The lockfile entries of the variant which are missing from the given lockfile of the
+    /// spliced workspace.
+    pub(crate) fn lock_packages(
+        &self,
+        lockfile: &CargoLockfile,
+    ) -> Result<Vec<cargo_lock::Package>> {
+        let variant_lockfile =
+            cargo_lock::Lockfile::load(self.manifest_path.parent().unwrap().join("Cargo.lock"))?;
+        Ok(variant_lockfile
+            .packages
+            .into_iter()
+            .filter(|package| {
+                !matches!(&self.synthetic_root, Some(root)
+                    if package.name.as_str() == root.name && package.version == root.version)
+            })
+            .filter(|package| !lockfile.packages.contains(package))
+            .collect())
+    }
+}
+
+/// The manifest entry of a dependency on exactly the given package with the given features.
+fn feature_variant_dependency(
+    package: &cargo_metadata::Package,
+    features: &BTreeSet<String>,
+) -> Result<toml::Table> {
+    let mut dependency = toml::Table::new();
+    dependency.insert(
+        "version".to_owned(),
+        toml::Value::String(format!("={}", package.version)),
+    );
+
+    match package.source.as_ref().map(|source| source.repr.as_str()) {
+        None => {
+            let path = package.manifest_path.parent().unwrap();
+            dependency.insert("path".to_owned(), toml::Value::String(path.to_string()));
+        }
+        Some("registry+https://github.com/rust-lang/crates.io-index")
+        | Some("sparse+https://index.crates.io/") => {}
+        Some(source) if source.starts_with("registry+") || source.starts_with("sparse+") => {
+            let index = source.strip_prefix("registry+").unwrap_or(source);
+            dependency.insert(
+                "registry-index".to_owned(),
+                toml::Value::String(index.to_owned()),
+            );
+        }
+        Some(source) if source.starts_with("git+") => {
+            // e.g. `git+https://github.com/org/repo?branch=main#{commit}`. The commit is
+            // pinned by the lockfile so only the reference needs to match for the package
+            // to be identified as the same one.
+            let url = source["git+".len()..]
+                .split_once('#')
+                .map(|(url, _)| url)
+                .unwrap_or(&source["git+".len()..]);
+            let (url, query) = url.split_once('?').unwrap_or((url, ""));
+            dependency.insert("git".to_owned(), toml::Value::String(url.to_owned()));
+            for (key, value) in query.split('&').filter_map(|param| param.split_once('=')) {
+                if matches!(key, "branch" | "tag" | "rev") {
+                    dependency.insert(key.to_owned(), toml::Value::String(value.to_owned()));
+                }
+            }
+        }
+        Some(source) => bail!(
+            "Feature variants are not supported for crates from {}",
+            source
+        ),
+    }
+
+    if !features.is_empty() {
+        dependency.insert(
+            "features".to_owned(),
+            toml::Value::Array(
+                features
+                    .iter()
+                    .map(|feature| toml::Value::String(feature.clone()))
+                    .collect(),
+            ),
+        );
+    }
+    Ok(dependency)
 }
 
 /// Parses the output of `cargo tree --format=|{p}|{f}|`. Other flags may be
@@ -780,6 +1267,8 @@ pub(crate) fn load_metadata(
 mod test {
     use super::*;
 
+    use indoc::indoc;
+
     #[test]
     fn deserialize_cargo_update_request_for_eager() {
         for value in ["all", "full", "eager"] {
@@ -1137,4 +1626,60 @@ mod test {
             );
         }
     }
+
+    #[test]
+    fn feature_variant_dependency_sources() {
+        let mut package = crate::test::mock_cargo_metadata_package();
+        let features = BTreeSet::from(["std".to_owned()]);
+        let dependency = |package: &cargo_metadata::Package| {
+            feature_variant_dependency(package, &features).unwrap()
+        };
+
+        assert_eq!(
+            dependency(&package),
+            toml::from_str(indoc! {r#"
+                version = "=3.3.3"
+                features = ["std"]
+            "#})
+            .unwrap()
+        );
+
+        package.source = Some(cargo_metadata::Source {
+            repr: "sparse+https://example.com/index/".to_owned(),
+        });
+        assert_eq!(
+            dependency(&package),
+            toml::from_str(indoc! {r#"
+                version = "=3.3.3"
+                registry-index = "sparse+https://example.com/index/"
+                features = ["std"]
+            "#})
+            .unwrap()
+        );
+
+        package.source = Some(cargo_metadata::Source {
+            repr: "git+https://github.com/org/repo?branch=main#a0b1c2d3".to_owned(),
+        });
+        assert_eq!(
+            dependency(&package),
+            toml::from_str(indoc! {r#"
+                version = "=3.3.3"
+                git = "https://github.com/org/repo"
+                branch = "main"
+                features = ["std"]
+            "#})
+            .unwrap()
+        );
+
+        package.source = None;
+        assert_eq!(
+            dependency(&package),
+            toml::from_str(indoc! {r#"
+                version = "=3.3.3"
+                path = "/tmp/mock-pkg-3.3.3"
+                features = ["std"]
+            "#})
+            .unwrap()
+        );
+    }
 }
@@ -8,7 +8,7 @@ use cargo_metadata::{Node, Package, PackageId};
 use hex::ToHex;
 use serde::{Deserialize, Serialize};
 
-use crate::config::{Commitish, Config, CrateAnnotations, CrateId};
+use crate::config::{apply_recipe, Commitish, Config, CrateAnnotations, CrateId};
 use crate::metadata::dependency::DependencySet;
 use crate::metadata::TreeResolverMetadata;
 use crate::splicing::{SourceInfo, WorkspaceMetadata};
@@ -43,6 +43,11 @@ pub(crate) struct MetadataAnnotation {
 
     /// Information on the Cargo workspace.
     pub(crate) workspace_metadata: WorkspaceMetadata,
+
+    /// The [CrateAnnotation]s of the crates which are built differently in each feature
+    /// variant, keyed by the name of the variant.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) feature_variants: BTreeMap<String, BTreeMap<PackageId, CrateAnnotation>>,
 }
 
 impl MetadataAnnotation {
@@ -82,6 +87,32 @@ impl MetadataAnnotation {
             })
             .collect();
 
+        let feature_variants = workspace_metadata
+            .feature_variants
+            .iter()
+            .map(|(variant, tree)| {
+                let affected = tree.affected_crates(&workspace_metadata.tree_metadata);
+                let crates = resolve
+                    .nodes
+                    .iter()
+                    .filter(|node| {
+                        metadata
+                            .packages
+                            .iter()
+                            .find(|pkg| pkg.id == node.id)
+                            .is_some_and(|pkg| affected.contains(&CrateId::from(pkg)))
+                    })
+                    .map(|node| {
+                        (
+                            node.id.clone(),
+                            Self::annotate_crate(node.clone(), &metadata, &tree.tree_metadata),
+                        )
+                    })
+                    .collect();
+                (variant.clone(), crates)
+            })
+            .collect();
+
         let packages = metadata
             .packages
             .into_iter()
@@ -94,6 +125,7 @@ impl MetadataAnnotation {
             workspace_members,
             workspace_root: PathBuf::from(metadata.workspace_root.as_std_path()),
             workspace_metadata,
+            feature_variants,
         }
     }
 
@@ -130,6 +162,19 @@ pub(crate) enum SourceAnnotation {
         #[serde(default, skip_serializing_if = "Option::is_none")]
         strip_prefix: Option<String>,
 
+        /// See [git_repository::init_submodules](https://docs.bazel.build/versions/main/repo/git.html#git_repository-init_submodules).
+        /// Submodules are initialized if this is unset.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        init_submodules: Option<bool>,
+
+        /// See [git_repository::recursive_init_submodules](https://docs.bazel.build/versions/main/repo/git.html#git_repository-recursive_init_submodules)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        recursive_init_submodules: Option<bool>,
+
+        /// See [git_repository::sparse_checkout_patterns](https://docs.bazel.build/versions/main/repo/git.html#git_repository-sparse_checkout_patterns)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        sparse_checkout_patterns: Option<BTreeSet<String>>,
+
         /// See [git_repository::patch_args](https://docs.bazel.build/versions/main/repo/git.html#git_repository-patch_args)
         #[serde(default, skip_serializing_if = "Option::is_none")]
         patch_args: Option<Vec<String>>,
@@ -150,6 +195,17 @@ pub(crate) enum SourceAnnotation {
         #[serde(default, skip_serializing_if = "Option::is_none")]
         sha256: Option<String>,
 
+        /// The name of the registry the crate is sourced from, as named in the Cargo config.
+        /// Used to select mirror url templates.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        registry: Option<String>,
+
+        /// The urls to download the crate from, in order, when mirror url templates are
+        /// configured. Otherwise only `url` is used.
+        /// See [http_archive::urls](https://docs.bazel.build/versions/main/repo/http.html#http_archive-urls)
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        urls: Vec<String>,
+
         /// See [http_archive::patch_args](https://docs.bazel.build/versions/main/repo/http.html#http_archive-patch_args)
         #[serde(default, skip_serializing_if = "Option::is_none")]
         patch_args: Option<Vec<String>>,
@@ -173,9 +229,17 @@ pub(crate) struct LockfileAnnotation {
 }
 
 impl LockfileAnnotation {
-    pub(crate) fn new(lockfile: CargoLockfile, metadata: &CargoMetadata) -> Result<Self> {
+    pub(crate) fn new(mut lockfile: CargoLockfile, metadata: &CargoMetadata) -> Result<Self> {
         let workspace_metadata = find_workspace_metadata(metadata).unwrap_or_default();
 
+        // Crates which are only used by feature variants are locked alongside the variant.
+        lockfile.packages.extend(
+            workspace_metadata
+                .feature_variants
+                .values()
+                .flat_map(|tree| tree.lock_packages.iter().cloned()),
+        );
+
         let nodes: Vec<&Node> = metadata
             .resolve
             .as_ref()
@@ -233,6 +297,8 @@ impl LockfileAnnotation {
                     return Ok(SourceAnnotation::Http {
                         url: info.url,
                         sha256: Some(info.sha256),
+                        registry: info.registry,
+                        urls: Vec::new(),
                         patch_args: None,
                         patch_tool: None,
                         patches: None,
@@ -258,6 +324,9 @@ impl LockfileAnnotation {
                     .unwrap_or(Commitish::from(git_ref.clone())),
                 shallow_since: None,
                 strip_prefix,
+                init_submodules: None,
+                recursive_init_submodules: None,
+                sparse_checkout_patterns: None,
                 patch_args: None,
                 patch_tool: None,
                 patches: None,
@@ -270,6 +339,8 @@ impl LockfileAnnotation {
             return Ok(SourceAnnotation::Http {
                 url: info.url,
                 sha256: Some(info.sha256),
+                registry: info.registry,
+                urls: Vec::new(),
                 patch_args: None,
                 patch_tool: None,
                 patches: None,
@@ -297,6 +368,8 @@ impl LockfileAnnotation {
                         }
                     })
                     .map(|sum| sum.encode_hex::<String>()),
+                registry: Some("crates-io".to_owned()),
+                urls: Vec::new(),
                 patch_args: None,
                 patch_tool: None,
                 patches: None,
@@ -386,6 +459,9 @@ impl Annotations {
 
         let mut unused_extra_annotations = config.annotations.clone();
 
+        // Unlike annotations, recipes are not required to match any package
+        let recipes = config.recipes();
+
         // Ensure each override matches a particular package
         let pairred_extras = metadata_annotation
             .packages
@@ -405,6 +481,16 @@ impl Annotations {
                     .cloned()
                     .sum();
 
+                crate_extra = apply_recipe(
+                    crate_extra,
+                    recipes
+                        .iter()
+                        .filter(|(id, _)| id.matches(pkg))
+                        .map(|(_, recipe)| recipe)
+                        .cloned()
+                        .sum(),
+                );
+
                 crate_extra.apply_defaults_from_package_metadata(&pkg.metadata);
 
                 if crate_extra == CrateAnnotations::default() {
@@ -478,7 +564,7 @@ mod test {
     use semver::Version;
     use serde_json::json;
 
-    use crate::config::CrateNameAndVersionReq;
+    use crate::config::{CrateNameAndVersionReq, RecipeConfig};
     use crate::metadata::CargoTreeEntry;
     use crate::select::Select;
     use crate::test::*;
@@ -594,6 +680,67 @@ mod test {
         assert!(result_str.contains("mock-crate"));
     }
 
+    #[test]
+    fn recipes_apply_beneath_annotations() {
+        let crate_id = CrateId::new(
+            "has_package_metadata".to_owned(),
+            semver::Version::new(0, 0, 0),
+        );
+        let crate_name_and_version_req =
+            CrateNameAndVersionReq::new("has_package_metadata".to_owned(), "*".parse().unwrap());
+
+        let mut config = Config::default();
+        config.annotations.insert(
+            crate_name_and_version_req.clone(),
+            CrateAnnotations {
+                gen_build_script: Some(true),
+                build_script_env: Some(Select::from_value(BTreeMap::from([(
+                    "STATIC".to_owned(),
+                    "0".to_owned(),
+                )]))),
+                ..CrateAnnotations::default()
+            },
+        );
+        config.recipes = Some(RecipeConfig {
+            annotations: BTreeMap::from([
+                (
+                    crate_name_and_version_req,
+                    CrateAnnotations {
+                        gen_build_script: Some(false),
+                        build_script_env: Some(Select::from_value(BTreeMap::from([
+                            ("STATIC".to_owned(), "1".to_owned()),
+                            ("VENDORED".to_owned(), "1".to_owned()),
+                        ]))),
+                        ..CrateAnnotations::default()
+                    },
+                ),
+                // Recipes for crates outside of the dependency graph are ignored
+                (
+                    CrateNameAndVersionReq::new("mock-crate".to_owned(), "*".parse().unwrap()),
+                    CrateAnnotations::default(),
+                ),
+            ]),
+            ..RecipeConfig::default()
+        });
+
+        let annotations = Annotations::new(
+            test::metadata::has_package_metadata(),
+            test::lockfile::has_package_metadata(),
+            config,
+        )
+        .unwrap();
+
+        let extras = &annotations.pairred_extras[&crate_id].crate_extra;
+        assert_eq!(extras.gen_build_script, Some(true));
+        assert_eq!(
+            extras.build_script_env,
+            Some(Select::from_value(BTreeMap::from([
+                ("STATIC".to_owned(), "0".to_owned()),
+                ("VENDORED".to_owned(), "1".to_owned()),
+            ])))
+        );
+    }
+
     #[test]
     fn defaults_from_package_metadata() {
         let crate_id = CrateId::new(
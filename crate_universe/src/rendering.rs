@@ -9,17 +9,21 @@ use std::str::FromStr;
 
 use anyhow::{bail, Context as AnyhowContext, Result};
 use itertools::Itertools;
+use serde::Serialize;
 
-use crate::config::{AliasRule, RenderConfig, VendorMode};
-use crate::context::crate_context::{CrateContext, CrateDependency, Rule};
+use crate::config::{
+    AliasRule, CcLibraryAnnotation, CrateId, CustomRule, RenderConfig, VendorMode,
+};
+use crate::context::crate_context::{CrateContext, CrateDependency, Rule, CC_LIBRARY_TARGET_NAME};
+use crate::context::profiles::ProfileFlags;
 use crate::context::{Context, TargetAttributes};
 use crate::rendering::template_engine::TemplateEngine;
 use crate::select::Select;
 use crate::splicing::default_splicing_package_crate_id;
 use crate::utils::starlark::{
-    self, Alias, CargoBuildScript, CommonAttrs, Data, ExportsFiles, Filegroup, Glob, Label, Load,
-    Package, RustBinary, RustLibrary, RustProcMacro, SelectDict, SelectList, SelectScalar,
-    SelectSet, Starlark, TargetCompatibleWith,
+    self, Alias, CargoBuildScript, CcLibrary, CommonAttrs, Data, ExportsFiles, Filegroup, Glob,
+    Label, Load, Package, RustBinary, RustLibrary, RustProcMacro, RustTest, RustcFlags, SelectDict,
+    SelectList, SelectScalar, SelectSet, Starlark, TargetCompatibleWith,
 };
 use crate::utils::target_triple::TargetTriple;
 use crate::utils::{self, sanitize_repository_name};
@@ -38,13 +42,13 @@ impl Renderer {
     pub(crate) fn new(
         config: RenderConfig,
         supported_platform_triples: BTreeSet<TargetTriple>,
-    ) -> Self {
-        let engine = TemplateEngine::new(&config);
-        Self {
+    ) -> Result<Self> {
+        let engine = TemplateEngine::new(&config)?;
+        Ok(Self {
             config,
             supported_platform_triples,
             engine,
-        }
+        })
     }
 
     pub(crate) fn render(&self, context: &Context) -> Result<BTreeMap<PathBuf, String>> {
@@ -69,7 +73,7 @@ impl Renderer {
     }
 
     fn render_platform_labels(&self, context: &Context) -> BTreeMap<String, BTreeSet<String>> {
-        context
+        let mut platforms: Platforms = context
             .conditions
             .iter()
             .map(|(cfg, target_triples)| {
@@ -86,7 +90,31 @@ impl Renderer {
                         .collect(),
                 )
             })
-            .collect()
+            .collect();
+
+        // Custom platforms are already represented by the label of a `config_setting`
+        for (cfg, labels) in context.custom_conditions.iter() {
+            platforms
+                .entry(cfg.clone())
+                .or_default()
+                .extend(labels.iter().cloned());
+        }
+
+        // Custom platforms specialize the platform of their triple, so selects need a branch
+        // for them wherever there is one for their triple's platform.
+        for triple in self.supported_platform_triples.iter() {
+            let specializations = match context.custom_conditions.get(&triple.to_bazel()) {
+                Some(labels) => labels,
+                None => continue,
+            };
+            let label = render_platform_constraint_label(&self.config.platforms_template, triple);
+            platforms
+                .entry(label.clone())
+                .or_insert_with(|| BTreeSet::from([label]))
+                .extend(specializations.iter().cloned());
+        }
+
+        platforms
     }
 
     fn render_crates_module(
@@ -103,10 +131,13 @@ impl Renderer {
             render_module_label(&self.config.crates_module_template, "alias_rules.bzl")
                 .context("Failed to resolve string to module file label")?;
 
+        let repositories = DirectRepositories::new(&self.config, context);
+
         let mut map = BTreeMap::new();
         map.insert(
             Renderer::label_to_path(&module_label),
-            self.engine.render_module_bzl(context, platforms)?,
+            self.engine
+                .render_module_bzl(context, platforms, &repositories)?,
         );
         map.insert(
             Renderer::label_to_path(&module_build_label),
@@ -186,14 +217,30 @@ impl Renderer {
 
             if let Some(library_target_name) = &krate.library_target_name {
                 let rename = dep.alias.as_ref().unwrap_or(&krate.name);
+                // If duplicates exist, include version to disambiguate them.
+                let name = if context.has_duplicate_workspace_member_dep(&dep) {
+                    format!("{}-{}", rename, krate.version)
+                } else {
+                    rename.clone()
+                };
+
+                // The library each feature variant of the crate is built as.
+                for variant in krate.feature_variants.keys() {
+                    dependencies.push(Alias {
+                        rule: alias_rule.rule(),
+                        name: format!("{name}-{variant}"),
+                        actual: self.crate_label(
+                            &krate.name,
+                            &krate.version.to_string(),
+                            &format!("{library_target_name}-{variant}"),
+                        ),
+                        tags: BTreeSet::from(["manual".to_owned()]),
+                    });
+                }
+
                 dependencies.push(Alias {
                     rule: alias_rule.rule(),
-                    // If duplicates exist, include version to disambiguate them.
-                    name: if context.has_duplicate_workspace_member_dep(&dep) {
-                        format!("{}-{}", rename, krate.version)
-                    } else {
-                        rename.clone()
-                    },
+                    name,
                     actual: self.crate_label(
                         &krate.name,
                         &krate.version.to_string(),
@@ -380,38 +427,84 @@ impl Renderer {
                 }));
             } else {
                 match rule {
-                    Rule::BuildScript(target) => {
-                        load("@rules_rust//cargo:defs.bzl", "cargo_build_script");
-                        let cargo_build_script =
-                            self.make_cargo_build_script(platforms, krate, target)?;
-                        starlark.push(Starlark::CargoBuildScript(cargo_build_script));
-                        starlark.push(Starlark::Alias(Alias {
-                            rule: AliasRule::default().rule(),
-                            name: target.crate_name.clone(),
-                            actual: Label::from_str("_bs").unwrap(),
-                            tags: BTreeSet::from(["manual".to_owned()]),
-                        }));
-                    }
-                    Rule::ProcMacro(target) => {
-                        load("@rules_rust//rust:defs.bzl", "rust_proc_macro");
-                        let rust_proc_macro =
-                            self.make_rust_proc_macro(platforms, krate, target)?;
-                        starlark.push(Starlark::RustProcMacro(rust_proc_macro));
-                    }
-                    Rule::Library(target) => {
-                        load("@rules_rust//rust:defs.bzl", "rust_library");
-                        let rust_library = self.make_rust_library(platforms, krate, target)?;
-                        starlark.push(Starlark::RustLibrary(rust_library));
+                    Rule::BuildScript(..) | Rule::ProcMacro(..) | Rule::Library(..) => {
+                        self.render_library_target(
+                            platforms,
+                            krate,
+                            rule,
+                            None,
+                            &mut load,
+                            &mut starlark,
+                        )?;
                     }
                     Rule::Binary(target) => {
-                        load("@rules_rust//rust:defs.bzl", "rust_binary");
+                        let rule = self.rust_binary_rule();
+                        load(&rule.bzl, &rule.rule);
                         let rust_binary = self.make_rust_binary(platforms, krate, target)?;
                         starlark.push(Starlark::RustBinary(rust_binary));
                     }
+                    Rule::Test(target) => {
+                        load("@rules_rust//rust:defs.bzl", "rust_test");
+                        let rust_test = self.make_rust_test(platforms, krate, target, "test")?;
+                        starlark.push(Starlark::RustTest(rust_test));
+                    }
+                    Rule::UnitTest(target) => {
+                        load("@rules_rust//rust:defs.bzl", "rust_test");
+                        let rust_test = self.make_rust_unit_test(platforms, krate, target)?;
+                        starlark.push(Starlark::RustTest(rust_test));
+                    }
+                    Rule::Example(target) => {
+                        let rule = self.rust_binary_rule();
+                        load(&rule.bzl, &rule.rule);
+                        let rust_binary =
+                            self.make_rust_example(platforms, krate, target, "example")?;
+                        starlark.push(Starlark::RustBinary(rust_binary));
+                    }
+                    Rule::Bench(target) => {
+                        load("@rules_rust//rust:defs.bzl", "rust_test");
+                        let rust_test = self.make_rust_test(platforms, krate, target, "bench")?;
+                        starlark.push(Starlark::RustTest(rust_test));
+                    }
+                    Rule::BenchBinary(target) => {
+                        let rule = self.rust_binary_rule();
+                        load(&rule.bzl, &rule.rule);
+                        let rust_binary =
+                            self.make_rust_example(platforms, krate, target, "bench")?;
+                        starlark.push(Starlark::RustBinary(rust_binary));
+                    }
+                }
+            }
+        }
+
+        // The library of the crate, and the build script it depends on, as they're built
+        // for each feature variant.
+        for variant in krate.feature_variants.keys() {
+            let variant_krate = krate
+                .with_feature_variant(variant)
+                .expect("The variant should exist");
+            for rule in &krate.targets {
+                if krate
+                    .override_targets
+                    .contains_key(rule.override_target_key())
+                {
+                    continue;
                 }
+                self.render_library_target(
+                    platforms,
+                    &variant_krate,
+                    rule,
+                    Some(variant),
+                    &mut load,
+                    &mut starlark,
+                )?;
             }
         }
 
+        if let Some(cc_library) = &krate.cc_library {
+            load("@rules_cc//cc:defs.bzl", "cc_library");
+            starlark.push(Starlark::CcLibrary(make_cc_library(cc_library)));
+        }
+
         if let Some(additive_build_file_content) = &krate.additive_build_file_content {
             let comment = "# Additive BUILD file content".to_owned();
             starlark.push(Starlark::Verbatim(comment));
@@ -428,15 +521,62 @@ impl Renderer {
         Ok(starlark)
     }
 
+    /// Render the targets of a build script, proc-macro or library, optionally as they're
+    /// built for a feature variant. Any other kind of target is ignored.
+    fn render_library_target(
+        &self,
+        platforms: &Platforms,
+        krate: &CrateContext,
+        rule: &Rule,
+        variant: Option<&str>,
+        load: &mut dyn FnMut(&str, &str) -> bool,
+        starlark: &mut Vec<Starlark>,
+    ) -> Result<()> {
+        match rule {
+            Rule::BuildScript(target) => {
+                let rule = self.cargo_build_script_rule();
+                load(&rule.bzl, &rule.rule);
+                let cargo_build_script =
+                    self.make_cargo_build_script(platforms, krate, target, variant)?;
+                starlark.push(Starlark::CargoBuildScript(cargo_build_script));
+                starlark.push(Starlark::Alias(Alias {
+                    rule: AliasRule::default().rule(),
+                    name: variant_target_name(&target.crate_name, variant),
+                    actual: Label::Relative {
+                        target: variant_target_name("_bs", variant),
+                    },
+                    tags: BTreeSet::from(["manual".to_owned()]),
+                }));
+            }
+            Rule::ProcMacro(target) => {
+                let rule = self.rust_proc_macro_rule();
+                load(&rule.bzl, &rule.rule);
+                let rust_proc_macro =
+                    self.make_rust_proc_macro(platforms, krate, target, variant)?;
+                starlark.push(Starlark::RustProcMacro(rust_proc_macro));
+            }
+            Rule::Library(target) => {
+                let rule = self.rust_library_rule();
+                load(&rule.bzl, &rule.rule);
+                let rust_library = self.make_rust_library(platforms, krate, target, variant)?;
+                starlark.push(Starlark::RustLibrary(rust_library));
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
     fn make_cargo_build_script(
         &self,
         platforms: &Platforms,
         krate: &CrateContext,
         target: &TargetAttributes,
+        variant: Option<&str>,
     ) -> Result<CargoBuildScript> {
         let attrs = krate.build_script_attrs.as_ref();
 
         Ok(CargoBuildScript {
+            rule: self.cargo_build_script_rule().rule,
             // Because `cargo_build_script` does some invisible target name
             // mutating to determine the package and crate name for a build
             // script, the Bazel target name of any build script cannot be the
@@ -446,7 +586,7 @@ impl Renderer {
             // Do not change this name to "cargo_build_script".
             //
             // This is set to a short name to avoid long path name issues on windows.
-            name: "_bs".to_string(),
+            name: variant_target_name("_bs", variant),
             aliases: SelectDict::new(self.make_aliases(krate, true, false), platforms),
             build_script_env: SelectDict::new(
                 attrs
@@ -560,9 +700,11 @@ impl Renderer {
         platforms: &Platforms,
         krate: &CrateContext,
         target: &TargetAttributes,
+        variant: Option<&str>,
     ) -> Result<RustProcMacro> {
         Ok(RustProcMacro {
-            name: target.crate_name.clone(),
+            rule: self.rust_proc_macro_rule().rule,
+            name: variant_target_name(&target.crate_name, variant),
             deps: SelectSet::new(
                 self.make_deps(
                     krate.common_attrs.deps.clone(),
@@ -578,7 +720,11 @@ impl Renderer {
                 platforms,
             ),
             aliases: SelectDict::new(self.make_aliases(krate, false, false), platforms),
-            common: self.make_common_attrs(platforms, krate, target)?,
+            common: CommonAttrs {
+                // Variants keep the name of the crate they're built from.
+                crate_name: variant.map(|_| utils::sanitize_module_name(&target.crate_name)),
+                ..self.make_common_attrs(platforms, krate, target)?
+            },
         })
     }
 
@@ -587,9 +733,11 @@ impl Renderer {
         platforms: &Platforms,
         krate: &CrateContext,
         target: &TargetAttributes,
+        variant: Option<&str>,
     ) -> Result<RustLibrary> {
         Ok(RustLibrary {
-            name: target.crate_name.clone(),
+            rule: self.rust_library_rule().rule,
+            name: variant_target_name(&target.crate_name, variant),
             deps: SelectSet::new(
                 self.make_deps(
                     krate.common_attrs.deps.clone(),
@@ -605,11 +753,47 @@ impl Renderer {
                 platforms,
             ),
             aliases: SelectDict::new(self.make_aliases(krate, false, false), platforms),
-            common: self.make_common_attrs(platforms, krate, target)?,
+            common: CommonAttrs {
+                // Variants keep the name of the crate they're built from.
+                crate_name: variant.map(|_| utils::sanitize_module_name(&target.crate_name)),
+                ..self.make_common_attrs(platforms, krate, target)?
+            },
             disable_pipelining: krate.disable_pipelining,
         })
     }
 
+    fn cargo_build_script_rule(&self) -> CustomRule {
+        target_rule(
+            &self.config.target_rules.cargo_build_script,
+            "@rules_rust//cargo:defs.bzl",
+            "cargo_build_script",
+        )
+    }
+
+    fn rust_proc_macro_rule(&self) -> CustomRule {
+        target_rule(
+            &self.config.target_rules.rust_proc_macro,
+            "@rules_rust//rust:defs.bzl",
+            "rust_proc_macro",
+        )
+    }
+
+    fn rust_library_rule(&self) -> CustomRule {
+        target_rule(
+            &self.config.target_rules.rust_library,
+            "@rules_rust//rust:defs.bzl",
+            "rust_library",
+        )
+    }
+
+    fn rust_binary_rule(&self) -> CustomRule {
+        target_rule(
+            &self.config.target_rules.rust_binary,
+            "@rules_rust//rust:defs.bzl",
+            "rust_binary",
+        )
+    }
+
     fn make_rust_binary(
         &self,
         platforms: &Platforms,
@@ -617,6 +801,7 @@ impl Renderer {
         target: &TargetAttributes,
     ) -> Result<RustBinary> {
         Ok(RustBinary {
+            rule: self.rust_binary_rule().rule,
             name: format!("{}__bin", target.crate_name),
             deps: {
                 let mut deps = self.make_deps(
@@ -643,6 +828,72 @@ impl Renderer {
         })
     }
 
+    fn make_rust_test(
+        &self,
+        platforms: &Platforms,
+        krate: &CrateContext,
+        target: &TargetAttributes,
+        suffix: &str,
+    ) -> Result<RustTest> {
+        Ok(RustTest {
+            name: format!("{}__{}", target.crate_name, suffix),
+            crate_: None,
+            deps: SelectSet::new(self.make_dev_deps(krate), platforms),
+            proc_macro_deps: SelectSet::new(self.make_dev_proc_macro_deps(krate), platforms),
+            aliases: SelectDict::new(self.make_aliases(krate, false, true), platforms),
+            common: self.make_common_attrs(platforms, krate, target)?,
+        })
+    }
+
+    /// Examples and benchmarks without the libtest harness are built like
+    /// integration tests, against the crate's library and its dev dependencies.
+    fn make_rust_example(
+        &self,
+        platforms: &Platforms,
+        krate: &CrateContext,
+        target: &TargetAttributes,
+        suffix: &str,
+    ) -> Result<RustBinary> {
+        Ok(RustBinary {
+            rule: self.rust_binary_rule().rule,
+            name: format!("{}__{}", target.crate_name, suffix),
+            deps: SelectSet::new(self.make_dev_deps(krate), platforms),
+            proc_macro_deps: SelectSet::new(self.make_dev_proc_macro_deps(krate), platforms),
+            aliases: SelectDict::new(self.make_aliases(krate, false, true), platforms),
+            common: self.make_common_attrs(platforms, krate, target)?,
+        })
+    }
+
+    fn make_rust_unit_test(
+        &self,
+        platforms: &Platforms,
+        krate: &CrateContext,
+        target: &TargetAttributes,
+    ) -> Result<RustTest> {
+        // The library's dependencies are inherited through `crate`, so only the
+        // dev dependencies need to be listed here.
+        Ok(RustTest {
+            name: format!("{}__unit_test", target.crate_name),
+            crate_: Some(Label::from_str(&format!(":{}", target.crate_name)).unwrap()),
+            deps: SelectSet::new(
+                self.make_deps(krate.common_attrs.deps_dev.clone(), Default::default()),
+                platforms,
+            ),
+            proc_macro_deps: SelectSet::new(
+                self.make_deps(
+                    krate.common_attrs.proc_macro_deps_dev.clone(),
+                    Default::default(),
+                ),
+                platforms,
+            ),
+            aliases: SelectDict::new(self.make_aliases(krate, false, true), platforms),
+            common: CommonAttrs {
+                crate_root: None,
+                ..self.make_common_attrs(platforms, krate, target)?
+            },
+        })
+    }
+
     fn make_common_attrs(
         &self,
         platforms: &Platforms,
@@ -650,6 +901,7 @@ impl Renderer {
         target: &TargetAttributes,
     ) -> Result<CommonAttrs> {
         Ok(CommonAttrs {
+            crate_name: None,
             compile_data: make_data(
                 platforms,
                 krate.common_attrs.compile_data_glob.clone(),
@@ -666,17 +918,26 @@ impl Renderer {
             linker_script: krate.common_attrs.linker_script.clone(),
             rustc_env: SelectDict::new(krate.common_attrs.rustc_env.clone(), platforms),
             rustc_env_files: SelectSet::new(krate.common_attrs.rustc_env_files.clone(), platforms),
-            rustc_flags: SelectList::new(
-                // In most cases, warnings in 3rd party crates are not
-                // interesting as they're out of the control of consumers. The
-                // flag here silences warnings. For more details see:
-                // https://doc.rust-lang.org/rustc/lints/levels.html
-                Select::merge(
-                    Select::from_value(Vec::from(["--cap-lints=allow".to_owned()])),
-                    krate.common_attrs.rustc_flags.clone(),
+            rustc_flags: RustcFlags {
+                select: SelectList::new(
+                    // In most cases, warnings in 3rd party crates are not
+                    // interesting as they're out of the control of consumers. The
+                    // flag here silences warnings. For more details see:
+                    // https://doc.rust-lang.org/rustc/lints/levels.html
+                    // Local crates declaring `[lints]` are linted as they would be by
+                    // Cargo instead, as capping lints would disable them all.
+                    Select::merge(
+                        Select::from_value(if krate.common_attrs.lints.rustc_flags.is_empty() {
+                            Vec::from(["--cap-lints=allow".to_owned()])
+                        } else {
+                            krate.common_attrs.lints.rustc_flags.clone()
+                        }),
+                        krate.common_attrs.rustc_flags.clone(),
+                    ),
+                    platforms,
                 ),
-                platforms,
-            ),
+                compilation_mode: make_compilation_mode_flags(&krate.common_attrs.profile_flags),
+            },
             srcs: target.srcs.clone(),
             tags: {
                 let mut tags = BTreeSet::from_iter(krate.common_attrs.tags.iter().cloned());
@@ -687,19 +948,30 @@ impl Renderer {
                 tags.insert(format!("crate-name={}", krate.name));
                 tags
             },
-            target_compatible_with: self.config.generate_target_compatible_with.then(|| {
-                TargetCompatibleWith::new(
-                    self.supported_platform_triples
-                        .iter()
-                        .map(|target_triple| {
-                            render_platform_constraint_label(
-                                &self.config.platforms_template,
-                                target_triple,
-                            )
-                        })
-                        .collect(),
-                )
-            }),
+            target_compatible_with: match target.required_features_platforms(
+                &Select::merge(
+                    krate.common_attrs.crate_features.clone(),
+                    krate.common_attrs.dependency_features.clone(),
+                ),
+                platforms,
+            ) {
+                // Targets with `required-features` are only compatible with the platforms
+                // on which those features are enabled.
+                Some(platforms) => Some(TargetCompatibleWith::new(platforms)),
+                None => self.config.generate_target_compatible_with.then(|| {
+                    TargetCompatibleWith::new(
+                        self.supported_platform_triples
+                            .iter()
+                            .map(|target_triple| {
+                                render_platform_constraint_label(
+                                    &self.config.platforms_template,
+                                    target_triple,
+                                )
+                            })
+                            .collect(),
+                    )
+                }),
+            },
             version: krate.common_attrs.version.clone(),
         })
     }
@@ -742,6 +1014,38 @@ impl Renderer {
         aliases
     }
 
+    /// The dependencies of targets which are built against the crate's library
+    /// and its dev dependencies (e.g. tests and examples).
+    fn make_dev_deps(&self, krate: &CrateContext) -> Select<BTreeSet<Label>> {
+        let mut deps = self.make_deps(
+            Select::merge(
+                krate.common_attrs.deps.clone(),
+                krate.common_attrs.deps_dev.clone(),
+            ),
+            krate.common_attrs.extra_deps.clone(),
+        );
+        if let Some(library) = make_library_label(krate, false) {
+            deps.insert(library, None);
+        }
+        deps
+    }
+
+    /// The proc-macro counterpart of [Renderer::make_dev_deps], which holds the
+    /// crate's library when it is a proc-macro.
+    fn make_dev_proc_macro_deps(&self, krate: &CrateContext) -> Select<BTreeSet<Label>> {
+        let mut deps = self.make_deps(
+            Select::merge(
+                krate.common_attrs.proc_macro_deps.clone(),
+                krate.common_attrs.proc_macro_deps_dev.clone(),
+            ),
+            krate.common_attrs.extra_proc_macro_deps.clone(),
+        );
+        if let Some(library) = make_library_label(krate, true) {
+            deps.insert(library, None);
+        }
+        deps
+    }
+
     fn make_deps(
         &self,
         deps: Select<BTreeSet<CrateDependency>>,
@@ -758,12 +1062,35 @@ impl Renderer {
     fn render_vendor_support_files(&self, context: &Context) -> Result<BTreeMap<PathBuf, String>> {
         let module_label = render_module_label(&self.config.crates_module_template, "crates.bzl")
             .context("Failed to resolve string to module file label")?;
+        let repositories_label =
+            render_module_label(&self.config.crates_module_template, "repositories.json")
+                .context("Failed to resolve string to module file label")?;
+        let extension_label =
+            render_module_label(&self.config.crates_module_template, "extension.bzl")
+                .context("Failed to resolve string to module file label")?;
+        let use_repo_label =
+            render_module_label(&self.config.crates_module_template, "use_repo.txt")
+                .context("Failed to resolve string to module file label")?;
 
         let mut map = BTreeMap::new();
         map.insert(
             Renderer::label_to_path(&module_label),
             self.engine.render_vendor_module_file(context)?,
         );
+        map.insert(
+            Renderer::label_to_path(&extension_label),
+            self.engine.render_vendor_extension_file()?,
+        );
+
+        let repositories = DirectRepositories::new(&self.config, context);
+        let mut content = serde_json::to_string_pretty(&repositories)
+            .context("Failed to serialize direct repositories")?;
+        content.push('\n');
+        map.insert(Renderer::label_to_path(&repositories_label), content);
+        map.insert(
+            Renderer::label_to_path(&use_repo_label),
+            repositories.render_use_repo(),
+        );
 
         Ok(map)
     }
@@ -804,6 +1131,11 @@ pub(crate) fn write_outputs(outputs: BTreeMap<PathBuf, String>, dry_run: bool) -
         }
     } else {
         for (path, content) in outputs {
+            // Leave identical files untouched to avoid needlessly updating their timestamps.
+            if fs::read(&path).ok().as_deref() == Some(content.as_bytes()) {
+                continue;
+            }
+
             // Ensure the output directory exists
             fs::create_dir_all(
                 path.parent()
@@ -817,6 +1149,70 @@ pub(crate) fn write_outputs(outputs: BTreeMap<PathBuf, String>, dry_run: bool) -
     Ok(())
 }
 
+/// The name of the extension proxy which `use_repo` snippets import repositories from. This
+/// matches the `crate_repositories` extension of the `extension.bzl` file of remote vendored crates.
+pub(crate) const USE_REPO_EXTENSION_PROXY: &str = "crate_repositories";
+
+/// The repositories which the `crate_repositories` macro of remote vendored crates makes
+/// visible to a bazel module. These are rendered into both the macro and `use_repo` snippets.
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub(crate) struct DirectRepositories {
+    /// The repository containing aliases to all direct dependencies.
+    pub(crate) repository: String,
+
+    /// The repositories of crates visible to any bazel module.
+    pub(crate) direct_deps: BTreeSet<String>,
+
+    /// The repositories of crates visible to only this bazel module.
+    pub(crate) direct_dev_deps: BTreeSet<String>,
+}
+
+impl DirectRepositories {
+    pub(crate) fn new(config: &RenderConfig, context: &Context) -> Self {
+        let repositories = |ids: &BTreeSet<CrateId>| -> BTreeSet<String> {
+            ids.iter()
+                .filter(|id| context.crates[id].repository.is_some())
+                .map(|id| {
+                    sanitize_repository_name(&render_crate_bazel_repository(
+                        &config.crate_repository_template,
+                        &config.repository_name,
+                        &id.name,
+                        &id.version.to_string(),
+                    ))
+                })
+                .collect()
+        };
+
+        Self {
+            repository: config.repository_name.clone(),
+            direct_deps: repositories(&context.direct_deps),
+            direct_dev_deps: repositories(&context.direct_dev_deps),
+        }
+    }
+
+    /// Render `use_repo` calls, ready to be pasted into a `MODULE.bazel` file, for all
+    /// repositories. Dev dependencies are imported from a `dev_dependency = True` proxy.
+    pub(crate) fn render_use_repo(&self) -> String {
+        let mut content = format!("use_repo(\n    {USE_REPO_EXTENSION_PROXY},\n");
+        for repository in std::iter::once(&self.repository).chain(&self.direct_deps) {
+            content.push_str(&format!("    \"{repository}\",\n"));
+        }
+        content.push_str(")\n");
+
+        if !self.direct_dev_deps.is_empty() {
+            content.push_str(&format!(
+                "\nuse_repo(\n    {USE_REPO_EXTENSION_PROXY}_dev,\n"
+            ));
+            for repository in &self.direct_dev_deps {
+                content.push_str(&format!("    \"{repository}\",\n"));
+            }
+            content.push_str(")\n");
+        }
+
+        content
+    }
+}
+
 /// Render the Bazel label of a crate
 pub(crate) fn render_crate_bazel_label(
     template: &str,
@@ -862,6 +1258,22 @@ fn render_platform_constraint_label(template: &str, target_triple: &TargetTriple
     template.replace("{triple}", &target_triple.to_bazel())
 }
 
+/// The rule rendered for a kind of crate target, unless the user has replaced it.
+fn target_rule(custom: &Option<CustomRule>, bzl: &str, rule: &str) -> CustomRule {
+    custom.clone().unwrap_or_else(|| CustomRule {
+        bzl: bzl.to_owned(),
+        rule: rule.to_owned(),
+    })
+}
+
+/// The name of a target of a crate as it's built for the given feature variant, if any.
+fn variant_target_name(name: &str, variant: Option<&str>) -> String {
+    match variant {
+        Some(variant) => format!("{name}-{variant}"),
+        None => name.to_owned(),
+    }
+}
+
 fn render_build_file_template(template: &str, name: &str, version: &str) -> Result<Label> {
     Label::from_str(
         &template
@@ -870,6 +1282,36 @@ fn render_build_file_template(template: &str, name: &str, version: &str) -> Resu
     )
 }
 
+fn make_cc_library(cc_library: &CcLibraryAnnotation) -> CcLibrary {
+    let glob = |include: &BTreeSet<String>| Glob {
+        allow_empty: false,
+        include: include.clone(),
+        exclude: cc_library.exclude.clone(),
+    };
+
+    CcLibrary {
+        name: CC_LIBRARY_TARGET_NAME.to_owned(),
+        copts: cc_library.copts.clone(),
+        defines: cc_library.defines.clone(),
+        deps: cc_library.deps.clone(),
+        hdrs: glob(&cc_library.hdrs),
+        includes: cc_library.includes.clone(),
+        srcs: glob(&cc_library.srcs),
+    }
+}
+
+/// The label of the crate's library, if it has one and it is a proc-macro or
+/// not, as requested, so it can be put in the matching dependency attribute.
+fn make_library_label(krate: &CrateContext, proc_macro: bool) -> Option<Label> {
+    let library_target_name = krate.library_target_name.as_ref()?;
+    let is_proc_macro = krate
+        .targets
+        .iter()
+        .any(|rule| matches!(rule, Rule::ProcMacro(..)));
+    (is_proc_macro == proc_macro)
+        .then(|| Label::from_str(&format!(":{library_target_name}")).unwrap())
+}
+
 fn make_data(
     platforms: &Platforms,
     glob: BTreeSet<String>,
@@ -897,15 +1339,35 @@ fn make_data(
     }
 }
 
+/// Key the flags derived from Cargo profiles by the `config_setting` matching each
+/// Bazel `compilation_mode`.
+fn make_compilation_mode_flags(profile_flags: &ProfileFlags) -> BTreeMap<String, Vec<String>> {
+    [
+        ("dbg", &profile_flags.dbg),
+        ("fastbuild", &profile_flags.fastbuild),
+        ("opt", &profile_flags.opt),
+    ]
+    .into_iter()
+    .filter(|(_, flags)| !flags.is_empty())
+    .map(|(mode, flags)| {
+        (
+            format!("@rules_rust//rust/settings:compilation_mode_{mode}"),
+            flags.clone(),
+        )
+    })
+    .collect()
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
 
     use indoc::indoc;
 
-    use crate::config::{Config, CrateId};
-    use crate::context::{BuildScriptAttributes, CommonAttributes};
-    use crate::metadata::Annotations;
+    use crate::config::{Commitish, Config, CrateId};
+    use crate::context::lints::LintFlags;
+    use crate::context::{BuildScriptAttributes, CommonAttributes, FeatureVariant};
+    use crate::metadata::{Annotations, SourceAnnotation};
     use crate::test;
     use crate::utils::normalize_cargo_file_paths;
 
@@ -978,10 +1440,13 @@ mod test {
                 extra_aliased_targets: BTreeMap::default(),
                 alias_rule: None,
                 override_targets: BTreeMap::default(),
+                cc_library: None,
+                feature_variants: BTreeMap::default(),
             },
         );
 
-        let renderer = Renderer::new(mock_render_config(None), mock_supported_platform_triples());
+        let renderer =
+            Renderer::new(mock_render_config(None), mock_supported_platform_triples()).unwrap();
         let output = renderer.render(&context).unwrap();
 
         let build_file_content = output
@@ -1016,10 +1481,13 @@ mod test {
                 extra_aliased_targets: BTreeMap::default(),
                 alias_rule: None,
                 override_targets: BTreeMap::default(),
+                cc_library: None,
+                feature_variants: BTreeMap::default(),
             },
         );
 
-        let renderer = Renderer::new(mock_render_config(None), mock_supported_platform_triples());
+        let renderer =
+            Renderer::new(mock_render_config(None), mock_supported_platform_triples()).unwrap();
         let output = renderer.render(&context).unwrap();
 
         let build_file_content = output
@@ -1057,10 +1525,13 @@ mod test {
                 extra_aliased_targets: BTreeMap::default(),
                 alias_rule: None,
                 override_targets: BTreeMap::default(),
+                cc_library: None,
+                feature_variants: BTreeMap::default(),
             },
         );
 
-        let renderer = Renderer::new(mock_render_config(None), mock_supported_platform_triples());
+        let renderer =
+            Renderer::new(mock_render_config(None), mock_supported_platform_triples()).unwrap();
         let output = renderer.render(&context).unwrap();
 
         let build_file_content = output
@@ -1076,9 +1547,85 @@ mod test {
     }
 
     #[test]
-    fn render_proc_macro() {
+    fn render_custom_target_rules() {
+        let mut context = Context::default();
+        let crate_id = CrateId::new("mock_crate".to_owned(), VERSION_ZERO_ONE_ZERO);
+        context.crates.insert(
+            crate_id.clone(),
+            CrateContext {
+                targets: BTreeSet::from([
+                    Rule::Library(mock_target_attributes()),
+                    Rule::BuildScript(TargetAttributes {
+                        crate_name: "build_script_build".to_owned(),
+                        crate_root: Some("build.rs".to_owned()),
+                        ..TargetAttributes::default()
+                    }),
+                ]),
+                library_target_name: Some("mock_crate".to_owned()),
+                build_script_attrs: Some(BuildScriptAttributes::default()),
+                ..test::mock_crate_context(&crate_id, &[])
+            },
+        );
+
+        let mut config = mock_render_config(None);
+        config.target_rules.rust_library = Some(CustomRule {
+            bzl: "@//tools:rust.bzl".to_owned(),
+            rule: "company_rust_library".to_owned(),
+        });
+        config.templates.insert(
+            "partials/header.j2".to_owned(),
+            "# Generated by {{regen_command}}".to_owned(),
+        );
+        let renderer = Renderer::new(config, mock_supported_platform_triples()).unwrap();
+        let output = renderer.render(&context).unwrap();
+
+        let build_file_content = output
+            .get(&PathBuf::from("BUILD.mock_crate-0.1.0.bazel"))
+            .unwrap();
+
+        assert!(build_file_content.starts_with("# Generated by cargo_bazel_regen_command\n"));
+        assert!(build_file_content.contains(r#"load("@//tools:rust.bzl", "company_rust_library")"#));
+        assert!(build_file_content.contains("company_rust_library(\n    name = \"mock_crate\","));
+        assert!(!build_file_content.contains("\nrust_library("));
+
+        // Rules which were not replaced are unaffected.
+        assert!(build_file_content
+            .contains(r#"load("@rules_rust//cargo:defs.bzl", "cargo_build_script")"#));
+        assert!(build_file_content.contains("cargo_build_script(\n    name = \"_bs\","));
+    }
+
+    #[test]
+    fn render_invalid_templates() {
+        // Templates may include the built-in templates they don't replace.
+        let mut config = mock_render_config(None);
+        config.templates.insert(
+            "partials/header.j2".to_owned(),
+            "{% include \"partials/module/lints_map.j2\" %}".to_owned(),
+        );
+        assert!(Renderer::new(config, mock_supported_platform_triples()).is_ok());
+
+        let mut config = mock_render_config(None);
+        config
+            .templates
+            .insert("partials/header.j2".to_owned(), "{% if %}".to_owned());
+        assert!(Renderer::new(config, mock_supported_platform_triples()).is_err());
+
+        let mut config = mock_render_config(None);
+        config.templates.insert(
+            "partials/header.j2".to_owned(),
+            "{% extends \"missing.j2\" %}".to_owned(),
+        );
+        assert!(Renderer::new(config, mock_supported_platform_triples()).is_err());
+    }
+
+    #[test]
+    fn render_cc_library() {
         let mut context = Context::default();
         let crate_id = CrateId::new("mock_crate".to_owned(), VERSION_ZERO_ONE_ZERO);
+        let mut common_attrs = CommonAttributes::default();
+        common_attrs
+            .extra_deps
+            .insert(Label::from_str(":_cc").unwrap(), None);
         context.crates.insert(
             crate_id.clone(),
             CrateContext {
@@ -1086,9 +1633,9 @@ mod test {
                 version: crate_id.version,
                 package_url: None,
                 repository: None,
-                targets: BTreeSet::from([Rule::ProcMacro(mock_target_attributes())]),
+                targets: BTreeSet::from([Rule::Library(mock_target_attributes())]),
                 library_target_name: None,
-                common_attrs: CommonAttributes::default(),
+                common_attrs,
                 build_script_attrs: None,
                 license: None,
                 license_ids: BTreeSet::default(),
@@ -1098,23 +1645,57 @@ mod test {
                 extra_aliased_targets: BTreeMap::default(),
                 alias_rule: None,
                 override_targets: BTreeMap::default(),
+                cc_library: Some(CcLibraryAnnotation {
+                    srcs: BTreeSet::from(["zstd/lib/**/*.c".to_owned()]),
+                    hdrs: BTreeSet::from(["zstd/lib/**/*.h".to_owned()]),
+                    exclude: BTreeSet::from(["zstd/lib/legacy/**".to_owned()]),
+                    includes: BTreeSet::from(["zstd/lib".to_owned()]),
+                    defines: BTreeSet::from(["ZSTD_MULTITHREAD".to_owned()]),
+                    copts: vec!["-O3".to_owned()],
+                    deps: BTreeSet::new(),
+                    rustc_env: BTreeMap::new(),
+                    cfgs: BTreeSet::new(),
+                }),
+                feature_variants: BTreeMap::default(),
             },
         );
 
-        let renderer = Renderer::new(mock_render_config(None), mock_supported_platform_triples());
+        let renderer =
+            Renderer::new(mock_render_config(None), mock_supported_platform_triples()).unwrap();
         let output = renderer.render(&context).unwrap();
 
         let build_file_content = output
             .get(&PathBuf::from("BUILD.mock_crate-0.1.0.bazel"))
             .unwrap();
 
-        assert!(build_file_content.contains("rust_proc_macro("));
-        assert!(build_file_content.contains("name = \"mock_crate\""));
-        assert!(build_file_content.contains("\"crate-name=mock_crate\""));
+        assert!(build_file_content.contains(r#"load("@rules_cc//cc:defs.bzl", "cc_library")"#));
+        let expected = indoc! {r#"
+            cc_library(
+                name = "_cc",
+                copts = ["-O3"],
+                defines = ["ZSTD_MULTITHREAD"],
+                hdrs = glob(
+                    allow_empty = False,
+                    include = ["zstd/lib/**/*.h"],
+                    exclude = ["zstd/lib/legacy/**"],
+                ),
+                includes = ["zstd/lib"],
+                srcs = glob(
+                    allow_empty = False,
+                    include = ["zstd/lib/**/*.c"],
+                    exclude = ["zstd/lib/legacy/**"],
+                ),
+            )
+        "#};
+        assert!(
+            build_file_content.contains(expected),
+            "{build_file_content}"
+        );
+        assert!(build_file_content.contains(r#"":_cc","#));
     }
 
     #[test]
-    fn render_binary() {
+    fn render_proc_macro() {
         let mut context = Context::default();
         let crate_id = CrateId::new("mock_crate".to_owned(), VERSION_ZERO_ONE_ZERO);
         context.crates.insert(
@@ -1124,7 +1705,7 @@ mod test {
                 version: crate_id.version,
                 package_url: None,
                 repository: None,
-                targets: BTreeSet::from([Rule::Binary(mock_target_attributes())]),
+                targets: BTreeSet::from([Rule::ProcMacro(mock_target_attributes())]),
                 library_target_name: None,
                 common_attrs: CommonAttributes::default(),
                 build_script_attrs: None,
@@ -1136,23 +1717,26 @@ mod test {
                 extra_aliased_targets: BTreeMap::default(),
                 alias_rule: None,
                 override_targets: BTreeMap::default(),
+                cc_library: None,
+                feature_variants: BTreeMap::default(),
             },
         );
 
-        let renderer = Renderer::new(mock_render_config(None), mock_supported_platform_triples());
+        let renderer =
+            Renderer::new(mock_render_config(None), mock_supported_platform_triples()).unwrap();
         let output = renderer.render(&context).unwrap();
 
         let build_file_content = output
             .get(&PathBuf::from("BUILD.mock_crate-0.1.0.bazel"))
             .unwrap();
 
-        assert!(build_file_content.contains("rust_binary("));
-        assert!(build_file_content.contains("name = \"mock_crate__bin\""));
+        assert!(build_file_content.contains("rust_proc_macro("));
+        assert!(build_file_content.contains("name = \"mock_crate\""));
         assert!(build_file_content.contains("\"crate-name=mock_crate\""));
     }
 
     #[test]
-    fn render_additive_build_contents() {
+    fn render_binary() {
         let mut context = Context::default();
         let crate_id = CrateId::new("mock_crate".to_owned(), VERSION_ZERO_ONE_ZERO);
         context.crates.insert(
@@ -1169,41 +1753,220 @@ mod test {
                 license: None,
                 license_ids: BTreeSet::default(),
                 license_file: None,
-                additive_build_file_content: Some(
-                    "# Hello World from additive section!".to_owned(),
-                ),
+                additive_build_file_content: None,
                 disable_pipelining: false,
                 extra_aliased_targets: BTreeMap::default(),
                 alias_rule: None,
                 override_targets: BTreeMap::default(),
+                cc_library: None,
+                feature_variants: BTreeMap::default(),
             },
         );
 
-        let renderer = Renderer::new(mock_render_config(None), mock_supported_platform_triples());
+        let renderer =
+            Renderer::new(mock_render_config(None), mock_supported_platform_triples()).unwrap();
         let output = renderer.render(&context).unwrap();
 
         let build_file_content = output
             .get(&PathBuf::from("BUILD.mock_crate-0.1.0.bazel"))
             .unwrap();
 
-        assert!(build_file_content.contains("# Hello World from additive section!"));
+        assert!(build_file_content.contains("rust_binary("));
+        assert!(build_file_content.contains("name = \"mock_crate__bin\""));
+        assert!(build_file_content.contains("\"crate-name=mock_crate\""));
     }
 
     #[test]
-    fn render_aliases() {
-        let config = Config {
-            generate_binaries: true,
-            ..Config::default()
-        };
-        let annotations =
-            Annotations::new(test::metadata::alias(), test::lockfile::alias(), config).unwrap();
-        let context = Context::new(annotations, false).unwrap();
+    fn render_tests() {
+        let mut context = Context::default();
+        let crate_id = CrateId::new("mock_crate".to_owned(), VERSION_ZERO_ONE_ZERO);
+        context.crates.insert(
+            crate_id.clone(),
+            CrateContext {
+                targets: BTreeSet::from([
+                    Rule::Library(mock_target_attributes()),
+                    Rule::UnitTest(mock_target_attributes()),
+                    Rule::Test(TargetAttributes {
+                        crate_name: "integration".to_owned(),
+                        crate_root: Some("tests/integration.rs".to_owned()),
+                        srcs: Glob::new_rust_srcs(false),
+                        required_features: BTreeSet::new(),
+                    }),
+                ]),
+                library_target_name: Some("mock_crate".to_owned()),
+                ..test::mock_crate_context(&crate_id, &[])
+            },
+        );
 
-        let renderer = Renderer::new(mock_render_config(None), mock_supported_platform_triples());
+        let renderer =
+            Renderer::new(mock_render_config(None), mock_supported_platform_triples()).unwrap();
         let output = renderer.render(&context).unwrap();
 
-        let build_file_content = output.get(&PathBuf::from("BUILD.bazel")).unwrap();
-
+        let build_file_content = output
+            .get(&PathBuf::from("BUILD.mock_crate-0.1.0.bazel"))
+            .unwrap();
+
+        assert!(build_file_content.contains("\"rust_test\""));
+        assert!(build_file_content.contains("name = \"mock_crate__unit_test\""));
+        assert!(build_file_content.contains("crate = \":mock_crate\""));
+        assert!(build_file_content.contains("name = \"integration__test\""));
+        assert!(build_file_content.contains("crate_root = \"tests/integration.rs\""));
+        assert!(build_file_content.contains("\":mock_crate\""));
+    }
+
+    #[test]
+    fn render_examples() {
+        let mut context = Context::default();
+        let crate_id = CrateId::new("mock_crate".to_owned(), VERSION_ZERO_ONE_ZERO);
+        context.crates.insert(
+            crate_id.clone(),
+            CrateContext {
+                targets: BTreeSet::from([
+                    Rule::Library(mock_target_attributes()),
+                    Rule::Example(TargetAttributes {
+                        crate_name: "demo".to_owned(),
+                        crate_root: Some("examples/demo.rs".to_owned()),
+                        srcs: Glob::new_rust_srcs(false),
+                        required_features: BTreeSet::new(),
+                    }),
+                    Rule::Bench(TargetAttributes {
+                        crate_name: "perf".to_owned(),
+                        crate_root: Some("benches/perf.rs".to_owned()),
+                        srcs: Glob::new_rust_srcs(false),
+                        required_features: BTreeSet::new(),
+                    }),
+                    Rule::BenchBinary(TargetAttributes {
+                        crate_name: "custom".to_owned(),
+                        crate_root: Some("benches/custom.rs".to_owned()),
+                        srcs: Glob::new_rust_srcs(false),
+                        required_features: BTreeSet::new(),
+                    }),
+                ]),
+                library_target_name: Some("mock_crate".to_owned()),
+                ..test::mock_crate_context(&crate_id, &[])
+            },
+        );
+
+        let renderer =
+            Renderer::new(mock_render_config(None), mock_supported_platform_triples()).unwrap();
+        let output = renderer.render(&context).unwrap();
+
+        let build_file_content = output
+            .get(&PathBuf::from("BUILD.mock_crate-0.1.0.bazel"))
+            .unwrap();
+
+        assert!(build_file_content.contains("rust_binary(\n    name = \"demo__example\""));
+        assert!(build_file_content.contains("crate_root = \"examples/demo.rs\""));
+
+        // Benchmarks using the libtest harness have no `main` and are built as tests.
+        assert!(build_file_content.contains("rust_test(\n    name = \"perf__bench\""));
+        assert!(build_file_content.contains("crate_root = \"benches/perf.rs\""));
+        assert!(build_file_content.contains("rust_binary(\n    name = \"custom__bench\""));
+        assert!(build_file_content.contains("crate_root = \"benches/custom.rs\""));
+    }
+
+    #[test]
+    fn render_proc_macro_tests_and_examples() {
+        let mut context = Context::default();
+        let crate_id = CrateId::new("mock_crate".to_owned(), VERSION_ZERO_ONE_ZERO);
+        context.crates.insert(
+            crate_id.clone(),
+            CrateContext {
+                targets: BTreeSet::from([
+                    Rule::ProcMacro(mock_target_attributes()),
+                    Rule::Test(TargetAttributes {
+                        crate_name: "integration".to_owned(),
+                        crate_root: Some("tests/integration.rs".to_owned()),
+                        srcs: Glob::new_rust_srcs(false),
+                        required_features: BTreeSet::new(),
+                    }),
+                    Rule::Example(TargetAttributes {
+                        crate_name: "demo".to_owned(),
+                        crate_root: Some("examples/demo.rs".to_owned()),
+                        srcs: Glob::new_rust_srcs(false),
+                        required_features: BTreeSet::new(),
+                    }),
+                ]),
+                library_target_name: Some("mock_crate".to_owned()),
+                ..test::mock_crate_context(&crate_id, &[])
+            },
+        );
+
+        let renderer =
+            Renderer::new(mock_render_config(None), mock_supported_platform_triples()).unwrap();
+        let output = renderer.render(&context).unwrap();
+
+        let build_file_content = output
+            .get(&PathBuf::from("BUILD.mock_crate-0.1.0.bazel"))
+            .unwrap();
+
+        // A proc-macro library can only be used through `proc_macro_deps`.
+        let proc_macro_deps = "    proc_macro_deps = [\n        \":mock_crate\",\n    ],\n";
+        assert_eq!(
+            build_file_content.matches(proc_macro_deps).count(),
+            2,
+            "{build_file_content}"
+        );
+        assert!(!build_file_content.contains("    deps = [\n        \":mock_crate\""));
+    }
+
+    #[test]
+    fn render_additive_build_contents() {
+        let mut context = Context::default();
+        let crate_id = CrateId::new("mock_crate".to_owned(), VERSION_ZERO_ONE_ZERO);
+        context.crates.insert(
+            crate_id.clone(),
+            CrateContext {
+                name: crate_id.name,
+                version: crate_id.version,
+                package_url: None,
+                repository: None,
+                targets: BTreeSet::from([Rule::Binary(mock_target_attributes())]),
+                library_target_name: None,
+                common_attrs: CommonAttributes::default(),
+                build_script_attrs: None,
+                license: None,
+                license_ids: BTreeSet::default(),
+                license_file: None,
+                additive_build_file_content: Some(
+                    "# Hello World from additive section!".to_owned(),
+                ),
+                disable_pipelining: false,
+                extra_aliased_targets: BTreeMap::default(),
+                alias_rule: None,
+                override_targets: BTreeMap::default(),
+                cc_library: None,
+                feature_variants: BTreeMap::default(),
+            },
+        );
+
+        let renderer =
+            Renderer::new(mock_render_config(None), mock_supported_platform_triples()).unwrap();
+        let output = renderer.render(&context).unwrap();
+
+        let build_file_content = output
+            .get(&PathBuf::from("BUILD.mock_crate-0.1.0.bazel"))
+            .unwrap();
+
+        assert!(build_file_content.contains("# Hello World from additive section!"));
+    }
+
+    #[test]
+    fn render_aliases() {
+        let config = Config {
+            generate_binaries: true,
+            ..Config::default()
+        };
+        let annotations =
+            Annotations::new(test::metadata::alias(), test::lockfile::alias(), config).unwrap();
+        let context = Context::new(annotations, false).unwrap();
+
+        let renderer =
+            Renderer::new(mock_render_config(None), mock_supported_platform_triples()).unwrap();
+        let output = renderer.render(&context).unwrap();
+
+        let build_file_content = output.get(&PathBuf::from("BUILD.bazel")).unwrap();
+
         assert!(build_file_content.contains(r#"name = "names-0.12.1-dev__names","#));
         assert!(build_file_content.contains(r#"name = "names-0.13.0__names","#));
     }
@@ -1231,10 +1994,13 @@ mod test {
                 extra_aliased_targets: BTreeMap::default(),
                 alias_rule: None,
                 override_targets: BTreeMap::default(),
+                cc_library: None,
+                feature_variants: BTreeMap::default(),
             },
         );
 
-        let renderer = Renderer::new(mock_render_config(None), mock_supported_platform_triples());
+        let renderer =
+            Renderer::new(mock_render_config(None), mock_supported_platform_triples()).unwrap();
         let output = renderer.render(&context).unwrap();
 
         let defs_module = output.get(&PathBuf::from("defs.bzl")).unwrap();
@@ -1242,6 +2008,341 @@ mod test {
         assert!(defs_module.contains("def crate_repositories():"));
     }
 
+    #[test]
+    fn render_git_repositories() {
+        let mut context = Context::default();
+        let crate_id = CrateId::new("mock_crate".to_owned(), VERSION_ZERO_ONE_ZERO);
+        context.crates.insert(
+            crate_id.clone(),
+            CrateContext {
+                repository: Some(SourceAnnotation::Git {
+                    remote: "https://github.com/mock/monorepo.git".to_owned(),
+                    commitish: Commitish::Rev("abcdef".to_owned()),
+                    shallow_since: None,
+                    strip_prefix: Some("crates/mock_crate".to_owned()),
+                    init_submodules: Some(false),
+                    recursive_init_submodules: None,
+                    sparse_checkout_patterns: Some(BTreeSet::from([
+                        "/crates/mock_crate/".to_owned()
+                    ])),
+                    patch_args: None,
+                    patch_tool: None,
+                    patches: None,
+                }),
+                targets: BTreeSet::from([Rule::Library(mock_target_attributes())]),
+                ..test::mock_crate_context(&crate_id, &[])
+            },
+        );
+
+        let renderer =
+            Renderer::new(mock_render_config(None), mock_supported_platform_triples()).unwrap();
+        let output = renderer.render(&context).unwrap();
+
+        let defs_module = output.get(&PathBuf::from("defs.bzl")).unwrap();
+
+        assert!(defs_module.contains("new_git_repository,"));
+        assert!(!defs_module.contains("init_submodules = True"));
+        assert!(defs_module.contains(concat!(
+            "        sparse_checkout_patterns = [\n",
+            "            \"/crates/mock_crate/\",\n",
+            "        ],\n",
+        )));
+    }
+
+    #[test]
+    fn render_http_mirror_urls() {
+        let mut context = Context::default();
+        for (name, urls) in [
+            (
+                "mock_crate",
+                vec![
+                    "https://mirror.example.com/mock_crate/0.1.0".to_owned(),
+                    "https://example.com/mock_crate/0.1.0/download".to_owned(),
+                ],
+            ),
+            ("other_crate", Vec::new()),
+        ] {
+            let crate_id = CrateId::new(name.to_owned(), VERSION_ZERO_ONE_ZERO);
+            context.crates.insert(
+                crate_id.clone(),
+                CrateContext {
+                    repository: Some(SourceAnnotation::Http {
+                        url: format!("https://example.com/{name}/0.1.0/download"),
+                        sha256: Some("abcd".to_owned()),
+                        registry: None,
+                        urls,
+                        patch_args: None,
+                        patch_tool: None,
+                        patches: None,
+                    }),
+                    targets: BTreeSet::from([Rule::Library(mock_target_attributes())]),
+                    ..test::mock_crate_context(&crate_id, &[])
+                },
+            );
+        }
+
+        let renderer =
+            Renderer::new(mock_render_config(None), mock_supported_platform_triples()).unwrap();
+        let output = renderer.render(&context).unwrap();
+
+        let defs_module = output.get(&PathBuf::from("defs.bzl")).unwrap();
+
+        assert!(defs_module.contains(
+            "urls = [\"https://mirror.example.com/mock_crate/0.1.0\", \"https://example.com/mock_crate/0.1.0/download\"],"
+        ));
+        assert!(
+            defs_module.contains("urls = [\"https://example.com/other_crate/0.1.0/download\"],")
+        );
+    }
+
+    #[test]
+    fn render_feature_variants() {
+        let mut context = Context::default();
+        let crate_id = CrateId::new("mock_crate".to_owned(), VERSION_ZERO_ONE_ZERO);
+        let dep_id = CrateId::new("dep".to_owned(), VERSION_ZERO_ONE_ZERO);
+        let mut variant = FeatureVariant::default();
+        variant.crate_features.insert("extra".to_owned(), None);
+        variant.deps.insert(
+            CrateDependency {
+                id: dep_id,
+                target: "dep-extra".to_owned(),
+                alias: None,
+            },
+            None,
+        );
+        context.crates.insert(
+            crate_id.clone(),
+            CrateContext {
+                targets: BTreeSet::from([Rule::Library(mock_target_attributes())]),
+                library_target_name: Some("mock_crate".to_owned()),
+                feature_variants: BTreeMap::from([("extra".to_owned(), variant)]),
+                ..test::mock_crate_context(&crate_id, &[])
+            },
+        );
+
+        let renderer =
+            Renderer::new(mock_render_config(None), mock_supported_platform_triples()).unwrap();
+        let output = renderer.render(&context).unwrap();
+
+        let build_file = output
+            .get(&PathBuf::from("BUILD.mock_crate-0.1.0.bazel"))
+            .unwrap();
+
+        // The variant is built as a library of its own which keeps the name of the crate.
+        assert!(build_file.contains(indoc! {r#"
+            rust_library(
+                name = "mock_crate-extra",
+                deps = [
+                    "@test_rendering__dep-0.1.0//:dep-extra",
+                ],
+        "#}));
+        assert!(build_file.contains(
+            "    crate_features = [\n        \"extra\",\n    ],\n    crate_name = \"mock_crate\",\n"
+        ));
+        assert_eq!(build_file.matches("crate_name = ").count(), 1);
+    }
+
+    #[test]
+    fn render_workspace_member_feature_variants() {
+        let mut context = Context::default();
+        let crate_id = CrateId::new("mock_crate".to_owned(), VERSION_ZERO_ONE_ZERO);
+        let dep_id = CrateId::new("dep".to_owned(), VERSION_ZERO_ONE_ZERO);
+        let mut variant = FeatureVariant::default();
+        variant.deps.insert(
+            CrateDependency {
+                id: dep_id.clone(),
+                target: "dep-extra".to_owned(),
+                alias: None,
+            },
+            None,
+        );
+        context.crates.insert(
+            crate_id.clone(),
+            CrateContext {
+                targets: BTreeSet::from([Rule::Library(mock_target_attributes())]),
+                feature_variants: BTreeMap::from([("extra".to_owned(), variant)]),
+                ..test::mock_crate_context(&crate_id, &[])
+            },
+        );
+        context
+            .crates
+            .insert(dep_id.clone(), test::mock_crate_context(&dep_id, &[]));
+        context
+            .workspace_members
+            .insert(crate_id, "mock/crate".to_owned());
+
+        let renderer =
+            Renderer::new(mock_render_config(None), mock_supported_platform_triples()).unwrap();
+        let output = renderer.render(&context).unwrap();
+
+        let defs_module = output.get(&PathBuf::from("defs.bzl")).unwrap();
+
+        // The dependencies of the member built for the variant are available by its name.
+        let expected = indoc! {r#"
+            _FEATURE_VARIANT_DEPENDENCY_MAPS = {
+                "extra": {
+                    "normal": {
+                "mock/crate": {
+                    _COMMON_CONDITION: {
+                        "dep": Label("@test_rendering__dep-0.1.0//:dep-extra"),
+                    },
+                },
+            }
+        "#};
+        assert!(defs_module.contains(expected), "{}", defs_module);
+    }
+
+    #[test]
+    fn render_workspace_member_feature_variant_aliases() {
+        let mut context = Context::default();
+        let crate_id = CrateId::new("mock_crate".to_owned(), VERSION_ZERO_ONE_ZERO);
+        let macro_id = CrateId::new("macro".to_owned(), VERSION_ZERO_ONE_ZERO);
+        let dev_macro_id = CrateId::new("dev_macro".to_owned(), VERSION_ZERO_ONE_ZERO);
+        let mut variant = FeatureVariant::default();
+        variant.proc_macro_deps.insert(
+            CrateDependency {
+                id: macro_id.clone(),
+                target: "macro".to_owned(),
+                alias: Some("renamed_macro".to_owned()),
+            },
+            None,
+        );
+        variant.proc_macro_deps_dev.insert(
+            CrateDependency {
+                id: dev_macro_id.clone(),
+                target: "dev_macro".to_owned(),
+                alias: Some("renamed_dev_macro".to_owned()),
+            },
+            None,
+        );
+        context.crates.insert(
+            crate_id.clone(),
+            CrateContext {
+                targets: BTreeSet::from([Rule::Library(mock_target_attributes())]),
+                feature_variants: BTreeMap::from([("extra".to_owned(), variant)]),
+                ..test::mock_crate_context(&crate_id, &[])
+            },
+        );
+        context
+            .crates
+            .insert(macro_id.clone(), test::mock_crate_context(&macro_id, &[]));
+        context.crates.insert(
+            dev_macro_id.clone(),
+            test::mock_crate_context(&dev_macro_id, &[]),
+        );
+        context
+            .workspace_members
+            .insert(crate_id, "mock/crate".to_owned());
+
+        let renderer =
+            Renderer::new(mock_render_config(None), mock_supported_platform_triples()).unwrap();
+        let output = renderer.render(&context).unwrap();
+
+        let defs_module = output.get(&PathBuf::from("defs.bzl")).unwrap();
+
+        // Each kind of dependency keeps its own aliases in the variant.
+        let expected = indoc! {r#"
+                    "proc_macro": {
+                "mock/crate": {
+                    _COMMON_CONDITION: {
+                        "@test_rendering__macro-0.1.0//:macro": "renamed_macro",
+                    },
+                },
+            }
+            ,
+                    "proc_macro_dev": {
+                "mock/crate": {
+                    _COMMON_CONDITION: {
+                        "@test_rendering__dev_macro-0.1.0//:dev_macro": "renamed_dev_macro",
+                    },
+                },
+            }
+            ,
+        "#};
+        let alias_maps = defs_module
+            .split("_FEATURE_VARIANT_ALIAS_MAPS = {")
+            .nth(1)
+            .unwrap();
+        assert!(alias_maps.contains(expected), "{}", alias_maps);
+    }
+
+    #[test]
+    fn render_workspace_member_lints() {
+        let mut context = Context::default();
+        let crate_id = CrateId::new("mock_crate".to_owned(), VERSION_ZERO_ONE_ZERO);
+        context.crates.insert(
+            crate_id.clone(),
+            CrateContext {
+                targets: BTreeSet::from([Rule::Library(mock_target_attributes())]),
+                common_attrs: CommonAttributes {
+                    lints: LintFlags {
+                        rustc_flags: vec!["--forbid=unsafe_code".to_owned()],
+                        clippy_flags: vec!["--warn=clippy::pedantic".to_owned()],
+                    },
+                    ..CommonAttributes::default()
+                },
+                ..test::mock_crate_context(&crate_id, &[])
+            },
+        );
+        context
+            .workspace_members
+            .insert(crate_id, "mock/crate".to_owned());
+
+        let renderer =
+            Renderer::new(mock_render_config(None), mock_supported_platform_triples()).unwrap();
+        let output = renderer.render(&context).unwrap();
+
+        let defs_module = output.get(&PathBuf::from("defs.bzl")).unwrap();
+
+        let expected = indoc! {r#"
+            _RUSTC_LINT_FLAGS = {
+                "mock/crate": [
+                    "--forbid=unsafe_code",
+                ],
+            }
+
+            _CLIPPY_LINT_FLAGS = {
+                "mock/crate": [
+                    "--warn=clippy::pedantic",
+                ],
+            }
+        "#};
+        assert!(defs_module.contains(expected), "{}", defs_module);
+    }
+
+    #[test]
+    fn render_crate_lints() {
+        let mut context = Context::default();
+        let crate_id = CrateId::new("mock_crate".to_owned(), VERSION_ZERO_ONE_ZERO);
+        context.crates.insert(
+            crate_id.clone(),
+            CrateContext {
+                targets: BTreeSet::from([Rule::Library(mock_target_attributes())]),
+                common_attrs: CommonAttributes {
+                    lints: LintFlags {
+                        rustc_flags: vec!["--forbid=unsafe_code".to_owned()],
+                        clippy_flags: Vec::new(),
+                    },
+                    ..CommonAttributes::default()
+                },
+                ..test::mock_crate_context(&crate_id, &[])
+            },
+        );
+
+        let renderer =
+            Renderer::new(mock_render_config(None), mock_supported_platform_triples()).unwrap();
+        let output = renderer.render(&context).unwrap();
+
+        let build_file_content = output
+            .get(&PathBuf::from("BUILD.mock_crate-0.1.0.bazel"))
+            .unwrap();
+
+        // Capping lints would override the crate's own lints.
+        assert!(build_file_content
+            .contains("    rustc_flags = [\n        \"--forbid=unsafe_code\",\n    ],\n"));
+        assert!(!build_file_content.contains("--cap-lints=allow"));
+    }
+
     #[test]
     fn remote_remote_vendor_mode() {
         let mut context = Context::default();
@@ -1265,6 +2366,8 @@ mod test {
                 extra_aliased_targets: BTreeMap::default(),
                 alias_rule: None,
                 override_targets: BTreeMap::default(),
+                cc_library: None,
+                feature_variants: BTreeMap::default(),
             },
         );
 
@@ -1272,7 +2375,8 @@ mod test {
         let renderer = Renderer::new(
             mock_render_config(Some(VendorMode::Remote)),
             mock_supported_platform_triples(),
-        );
+        )
+        .unwrap();
         let output = renderer.render(&context).unwrap();
 
         let defs_module = output.get(&PathBuf::from("defs.bzl")).unwrap();
@@ -1282,6 +2386,83 @@ mod test {
         assert!(crates_module.contains("def crate_repositories():"));
     }
 
+    #[test]
+    fn remote_vendor_mode_direct_repositories() {
+        let mut context = Context::default();
+        for (name, is_dev) in [("mock_crate", false), ("mock_dev_crate", true)] {
+            let crate_id = CrateId::new(name.to_owned(), VERSION_ZERO_ONE_ZERO);
+            context.crates.insert(
+                crate_id.clone(),
+                CrateContext {
+                    repository: Some(SourceAnnotation::Http {
+                        url: format!("https://example.com/{name}/0.1.0/download"),
+                        sha256: Some("abcd".to_owned()),
+                        registry: None,
+                        urls: Vec::new(),
+                        patch_args: None,
+                        patch_tool: None,
+                        patches: None,
+                    }),
+                    targets: BTreeSet::from([Rule::Library(mock_target_attributes())]),
+                    ..test::mock_crate_context(&crate_id, &[])
+                },
+            );
+            if is_dev {
+                context.direct_dev_deps.insert(crate_id);
+            } else {
+                context.direct_deps.insert(crate_id);
+            }
+        }
+
+        let renderer = Renderer::new(
+            mock_render_config(Some(VendorMode::Remote)),
+            mock_supported_platform_triples(),
+        )
+        .unwrap();
+        let output = renderer.render(&context).unwrap();
+
+        let repositories = output.get(&PathBuf::from("repositories.json")).unwrap();
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(repositories).unwrap(),
+            serde_json::json!({
+                "repository": "test_rendering",
+                "direct_deps": ["test_rendering__mock_crate-0.1.0"],
+                "direct_dev_deps": ["test_rendering__mock_dev_crate-0.1.0"],
+            })
+        );
+
+        let use_repo = output.get(&PathBuf::from("use_repo.txt")).unwrap();
+        assert_eq!(
+            use_repo,
+            indoc! {r#"
+                use_repo(
+                    crate_repositories,
+                    "test_rendering",
+                    "test_rendering__mock_crate-0.1.0",
+                )
+
+                use_repo(
+                    crate_repositories_dev,
+                    "test_rendering__mock_dev_crate-0.1.0",
+                )
+            "#}
+        );
+
+        // The `crate_repositories` macro returns the same repositories.
+        let defs_module = output.get(&PathBuf::from("defs.bzl")).unwrap();
+        assert!(defs_module
+            .contains(r#"struct(repo="test_rendering__mock_crate-0.1.0", is_dev_dep = False),"#));
+        assert!(defs_module.contains(
+            r#"struct(repo = "test_rendering__mock_dev_crate-0.1.0", is_dev_dep = True),"#
+        ));
+
+        // The extension the `use_repo` snippets import from is generated alongside them.
+        let extension = output.get(&PathBuf::from("extension.bzl")).unwrap();
+        assert!(extension
+            .contains(r#"load("//:crates.bzl", _crate_repositories = "crate_repositories")"#));
+        assert!(extension.contains("\ncrate_repositories = module_extension("));
+    }
+
     #[test]
     fn remote_local_vendor_mode() {
         let mut context = Context::default();
@@ -1305,6 +2486,8 @@ mod test {
                 extra_aliased_targets: BTreeMap::default(),
                 alias_rule: None,
                 override_targets: BTreeMap::default(),
+                cc_library: None,
+                feature_variants: BTreeMap::default(),
             },
         );
 
@@ -1312,7 +2495,8 @@ mod test {
         let renderer = Renderer::new(
             mock_render_config(Some(VendorMode::Local)),
             mock_supported_platform_triples(),
-        );
+        )
+        .unwrap();
         let output = renderer.render(&context).unwrap();
 
         // Local vendoring does not produce a `crate_repositories` macro
@@ -1357,6 +2541,8 @@ mod test {
                 extra_aliased_targets: BTreeMap::default(),
                 alias_rule: None,
                 override_targets: BTreeMap::default(),
+                cc_library: None,
+                feature_variants: BTreeMap::default(),
             },
         );
 
@@ -1364,7 +2550,8 @@ mod test {
         let renderer = Renderer::new(
             mock_render_config(Some(VendorMode::Local)),
             mock_supported_platform_triples(),
-        );
+        )
+        .unwrap();
         let output = renderer.render(&context).unwrap();
 
         let build_file_content = output
@@ -1382,6 +2569,62 @@ mod test {
         ));
     }
 
+    #[test]
+    fn render_profile_flags() {
+        let mut context = Context::default();
+        let crate_id = CrateId::new("mock_crate".to_owned(), VERSION_ZERO_ONE_ZERO);
+
+        context.crates.insert(
+            crate_id.clone(),
+            CrateContext {
+                targets: BTreeSet::from([Rule::Library(mock_target_attributes())]),
+                common_attrs: CommonAttributes {
+                    profile_flags: ProfileFlags {
+                        dbg: vec!["-Copt-level=3".to_owned()],
+                        fastbuild: vec!["-Copt-level=3".to_owned()],
+                        opt: vec!["-Ccodegen-units=1".to_owned()],
+                    },
+                    ..CommonAttributes::default()
+                },
+                ..test::mock_crate_context(&crate_id, &[])
+            },
+        );
+
+        let renderer =
+            Renderer::new(mock_render_config(None), mock_supported_platform_triples()).unwrap();
+        let output = renderer.render(&context).unwrap();
+
+        let build_file_content = output
+            .get(&PathBuf::from("BUILD.mock_crate-0.1.0.bazel"))
+            .unwrap();
+
+        let expected = indoc! {r#"
+            rustc_flags = [
+                "--cap-lints=allow",
+            ] + select({
+                "@rules_rust//rust/settings:compilation_mode_dbg": [
+                    "-Copt-level=3",
+                ],
+                "@rules_rust//rust/settings:compilation_mode_fastbuild": [
+                    "-Copt-level=3",
+                ],
+                "@rules_rust//rust/settings:compilation_mode_opt": [
+                    "-Ccodegen-units=1",
+                ],
+                "//conditions:default": [],
+            }),
+        "#};
+
+        // Strip all spaces as the flags are nested in the `rust_library` target.
+        assert!(
+            build_file_content
+                .replace(' ', "")
+                .contains(&expected.replace(' ', "")),
+            "{}",
+            build_file_content
+        );
+    }
+
     #[test]
     fn test_render_build_file_deps() {
         let config: Config = serde_json::from_value(serde_json::json!({
@@ -1405,7 +2648,7 @@ mod test {
         let annotations = Annotations::new(metadata, lockfile, config.clone()).unwrap();
         let context = Context::new(annotations, false).unwrap();
 
-        let renderer = Renderer::new(config.rendering, config.supported_platform_triples);
+        let renderer = Renderer::new(config.rendering, config.supported_platform_triples).unwrap();
         let output = renderer.render(&context).unwrap();
 
         let build_file_content = output
@@ -1468,10 +2711,13 @@ mod test {
                 extra_aliased_targets: BTreeMap::default(),
                 alias_rule: None,
                 override_targets: BTreeMap::default(),
+                cc_library: None,
+                feature_variants: BTreeMap::default(),
             },
         );
 
-        let renderer = Renderer::new(mock_render_config(None), mock_supported_platform_triples());
+        let renderer =
+            Renderer::new(mock_render_config(None), mock_supported_platform_triples()).unwrap();
         let output = renderer.render(&context).unwrap();
 
         let build_file_content = output
@@ -1492,6 +2738,122 @@ mod test {
             .contains(&expected.replace(' ', "")));
     }
 
+    #[test]
+    fn binary_required_features_by_target() {
+        let mut context = Context {
+            conditions: mock_supported_platform_triples()
+                .iter()
+                .map(|platform| (platform.to_bazel(), BTreeSet::from([platform.clone()])))
+                .collect(),
+            ..Context::default()
+        };
+        let crate_id = CrateId::new("mock_crate".to_owned(), VERSION_ZERO_ONE_ZERO);
+        let mut crate_features: Select<BTreeSet<String>> = Select::default();
+        crate_features.insert("foo".to_owned(), Some("aarch64-apple-darwin".to_owned()));
+        crate_features.insert("bar".to_owned(), None);
+        context.crates.insert(
+            crate_id.clone(),
+            CrateContext {
+                targets: BTreeSet::from([
+                    Rule::Binary(TargetAttributes {
+                        crate_name: "bin_foo".to_owned(),
+                        required_features: BTreeSet::from(["foo".to_owned()]),
+                        ..mock_target_attributes()
+                    }),
+                    Rule::Binary(TargetAttributes {
+                        crate_name: "bin_bar".to_owned(),
+                        required_features: BTreeSet::from(["bar".to_owned()]),
+                        ..mock_target_attributes()
+                    }),
+                    Rule::Binary(TargetAttributes {
+                        crate_name: "bin_dep".to_owned(),
+                        required_features: BTreeSet::from([
+                            "foo".to_owned(),
+                            "dep?/baz".to_owned(),
+                        ]),
+                        ..mock_target_attributes()
+                    }),
+                ]),
+                common_attrs: CommonAttributes {
+                    crate_features,
+                    dependency_features: Select::from_value(BTreeSet::from(["dep/baz".to_owned()])),
+                    ..CommonAttributes::default()
+                },
+                ..test::mock_crate_context(&crate_id, &[])
+            },
+        );
+
+        let mut config = mock_render_config(None);
+        config.generate_target_compatible_with = false;
+        let renderer = Renderer::new(config, mock_supported_platform_triples()).unwrap();
+        let output = renderer.render(&context).unwrap();
+
+        let build_file_content = output
+            .get(&PathBuf::from("BUILD.mock_crate-0.1.0.bazel"))
+            .unwrap();
+
+        // Only the binaries whose required features are conditionally enabled are restricted.
+        let expected = indoc! {r#"
+            target_compatible_with = select({
+                "@rules_rust//rust/platform:aarch64-apple-darwin": [],
+                "//conditions:default": ["@platforms//:incompatible"],
+            }),
+        "#};
+        assert!(build_file_content
+            .replace(' ', "")
+            .contains(&expected.replace(' ', "")));
+        assert_eq!(
+            build_file_content.matches("target_compatible_with").count(),
+            2
+        );
+    }
+
+    #[test]
+    fn custom_platforms_specialize_triple_selects() {
+        let negated_cfg = r#"cfg(not(target_feature = "avx2"))"#;
+        let triple = TargetTriple::from_bazel("x86_64-unknown-linux-gnu".to_owned());
+        let mut context = Context {
+            conditions: BTreeMap::from([
+                (negated_cfg.to_owned(), BTreeSet::from([triple.clone()])),
+                (triple.to_bazel(), BTreeSet::from([triple.clone()])),
+            ]),
+            custom_conditions: BTreeMap::from([(
+                triple.to_bazel(),
+                BTreeSet::from(["@//platforms:avx2".to_owned()]),
+            )]),
+            ..Context::default()
+        };
+        let crate_id = CrateId::new("mock_crate".to_owned(), VERSION_ZERO_ONE_ZERO);
+        let dep_id = CrateId::new("mock_dep".to_owned(), VERSION_ZERO_ONE_ZERO);
+        context.crates.insert(
+            crate_id.clone(),
+            CrateContext {
+                targets: BTreeSet::from([Rule::Library(mock_target_attributes())]),
+                ..test::mock_crate_context(&crate_id, &[(&dep_id, Some(negated_cfg))])
+            },
+        );
+
+        let renderer =
+            Renderer::new(mock_render_config(None), mock_supported_platform_triples()).unwrap();
+        let output = renderer.render(&context).unwrap();
+
+        let build_file_content = output
+            .get(&PathBuf::from("BUILD.mock_crate-0.1.0.bazel"))
+            .unwrap();
+
+        // The custom platform doesn't satisfy the configuration so must not fall back to the
+        // branch of its triple.
+        let expected = indoc! {r#"
+            "@//platforms:avx2": [],
+            "@rules_rust//rust/platform:x86_64-unknown-linux-gnu": [
+                "@test_rendering__mock_dep-0.1.0//:mock_dep",  # cfg(not(target_feature = "avx2"))
+            ],
+        "#};
+        assert!(build_file_content
+            .replace(' ', "")
+            .contains(&expected.replace(' ', "")));
+    }
+
     #[test]
     fn crate_package_metadata_without_license_ids() {
         let mut context = Context::default();
@@ -1515,12 +2877,14 @@ mod test {
                 extra_aliased_targets: BTreeMap::default(),
                 alias_rule: None,
                 override_targets: BTreeMap::default(),
+                cc_library: None,
+                feature_variants: BTreeMap::default(),
             },
         );
 
         let mut render_config = mock_render_config(None);
         render_config.generate_rules_license_metadata = true;
-        let renderer = Renderer::new(render_config, mock_supported_platform_triples());
+        let renderer = Renderer::new(render_config, mock_supported_platform_triples()).unwrap();
         let output = renderer.render(&context).unwrap();
 
         let build_file_content = output
@@ -1568,12 +2932,14 @@ mod test {
                 license: None,
                 alias_rule: None,
                 override_targets: BTreeMap::default(),
+                cc_library: None,
+                feature_variants: BTreeMap::default(),
             },
         );
 
         let mut render_config = mock_render_config(None);
         render_config.generate_rules_license_metadata = true;
-        let renderer = Renderer::new(render_config, mock_supported_platform_triples());
+        let renderer = Renderer::new(render_config, mock_supported_platform_triples()).unwrap();
         let output = renderer.render(&context).unwrap();
 
         let build_file_content = output
@@ -1632,12 +2998,14 @@ mod test {
                 license: None,
                 alias_rule: None,
                 override_targets: BTreeMap::default(),
+                cc_library: None,
+                feature_variants: BTreeMap::default(),
             },
         );
 
         let mut render_config = mock_render_config(None);
         render_config.generate_rules_license_metadata = true;
-        let renderer = Renderer::new(render_config, mock_supported_platform_triples());
+        let renderer = Renderer::new(render_config, mock_supported_platform_triples()).unwrap();
         let output = renderer.render(&context).unwrap();
 
         let build_file_content = output
@@ -1703,6 +3071,8 @@ mod test {
                 extra_aliased_targets: BTreeMap::default(),
                 alias_rule: None,
                 override_targets: BTreeMap::default(),
+                cc_library: None,
+                feature_variants: BTreeMap::default(),
             },
         );
 
@@ -1711,7 +3081,7 @@ mod test {
         config.build_file_template = "//{name}-{version}:BUILD.bazel".into();
 
         // Enable local vendor mode
-        let renderer = Renderer::new(config, mock_supported_platform_triples());
+        let renderer = Renderer::new(config, mock_supported_platform_triples()).unwrap();
         let output = renderer.render(&context).unwrap();
         eprintln!("output before {:?}", output.keys());
         // Local vendoring does not produce a `crate_repositories` macro
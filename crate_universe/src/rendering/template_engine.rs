@@ -1,15 +1,15 @@
 //! A template engine backed by [tera::Tera] for rendering Files.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 use anyhow::{Context as AnyhowContext, Result};
 use serde_json::{from_value, to_value, Value};
 
 use crate::config::RenderConfig;
-use crate::context::Context;
+use crate::context::{Context, CrateContext};
 use crate::rendering::{
     render_crate_bazel_label, render_crate_bazel_repository, render_crate_build_file,
-    render_module_label, Platforms,
+    render_module_label, DirectRepositories, Platforms,
 };
 use crate::select::Select;
 use crate::utils::sanitize_repository_name;
@@ -20,7 +20,7 @@ pub(crate) struct TemplateEngine {
 }
 
 impl TemplateEngine {
-    pub(crate) fn new(render_config: &RenderConfig) -> Self {
+    pub(crate) fn new(render_config: &RenderConfig) -> Result<Self> {
         let mut tera = tera::Tera::default();
         tera.add_raw_templates(vec![
             (
@@ -37,6 +37,13 @@ impl TemplateEngine {
                     "/src/rendering/templates/partials/module/deps_map.j2"
                 )),
             ),
+            (
+                "partials/module/lints_map.j2",
+                include_str!(concat!(
+                    env!("CARGO_MANIFEST_DIR"),
+                    "/src/rendering/templates/partials/module/lints_map.j2"
+                )),
+            ),
             (
                 "partials/module/repo_git.j2",
                 include_str!(concat!(
@@ -65,6 +72,13 @@ impl TemplateEngine {
                     "/src/rendering/templates/module_bzl.j2"
                 )),
             ),
+            (
+                "vendor_extension.j2",
+                include_str!(concat!(
+                    env!("CARGO_MANIFEST_DIR"),
+                    "/src/rendering/templates/vendor_extension.j2"
+                )),
+            ),
             (
                 "vendor_module.j2",
                 include_str!(concat!(
@@ -75,6 +89,16 @@ impl TemplateEngine {
         ])
         .unwrap();
 
+        // User provided templates replace built-in templates of the same name. These are
+        // parsed alongside the built-in templates so any they include or extend are resolved.
+        tera.add_raw_templates(
+            render_config
+                .templates
+                .iter()
+                .map(|(name, content)| (name.as_str(), content.as_str())),
+        )
+        .context("Failed to parse user provided templates")?;
+
         tera.register_function(
             "crate_build_file",
             crate_build_file_fn_generator(render_config.build_file_template.clone()),
@@ -112,10 +136,10 @@ impl TemplateEngine {
             },
         );
 
-        Self {
+        Ok(Self {
             engine: tera,
             context,
-        }
+        })
     }
 
     fn new_tera_ctx(&self) -> tera::Context {
@@ -136,10 +160,32 @@ impl TemplateEngine {
         &self,
         data: &Context,
         platforms: &Platforms,
+        repositories: &DirectRepositories,
     ) -> Result<String> {
+        // Workspace members by their path, along with the members of each feature variant
+        // built for the variant.
+        let mut workspace_members = BTreeMap::new();
+        let mut feature_variant_members: BTreeMap<&String, BTreeMap<&String, CrateContext>> =
+            BTreeMap::new();
+        for (id, path) in &data.workspace_members {
+            let krate = &data.crates[id];
+            workspace_members.insert(path, krate);
+            for variant in krate.feature_variants.keys() {
+                if let Some(variant_krate) = krate.with_feature_variant(variant) {
+                    feature_variant_members
+                        .entry(variant)
+                        .or_default()
+                        .insert(path, variant_krate);
+                }
+            }
+        }
+
         let mut context = self.new_tera_ctx();
         context.insert("context", data);
         context.insert("platforms", platforms);
+        context.insert("workspace_members", &workspace_members);
+        context.insert("feature_variant_members", &feature_variant_members);
+        context.insert("direct_repositories", repositories);
 
         self.engine
             .render("module_bzl.j2", &context)
@@ -154,6 +200,12 @@ impl TemplateEngine {
             .render("vendor_module.j2", &context)
             .context("Failed to render vendor module")
     }
+
+    pub(crate) fn render_vendor_extension_file(&self) -> Result<String> {
+        self.engine
+            .render("vendor_extension.j2", &self.new_tera_ctx())
+            .context("Failed to render vendor extension")
+    }
 }
 
 /// A convienience wrapper for parsing parameters to tera functions
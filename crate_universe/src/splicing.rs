@@ -11,16 +11,18 @@ use std::str::FromStr;
 
 use anyhow::{anyhow, bail, Context, Result};
 use cargo_lock::package::SourceKind;
-use cargo_toml::Manifest;
+use cargo_toml::{Manifest, Profiles};
 use serde::{Deserialize, Serialize};
 
 use crate::config::CrateId;
-use crate::metadata::{Cargo, CargoUpdateRequest, LockGenerator, TreeResolverMetadata};
+use crate::metadata::{
+    Cargo, CargoUpdateRequest, FeatureVariantMetadata, LockGenerator, TreeResolverMetadata,
+};
 use crate::utils;
 use crate::utils::starlark::Label;
 
 use self::cargo_config::CargoConfig;
-use self::crate_index_lookup::CrateIndexLookup;
+use self::crate_index_lookup::{CrateIndexLookup, LocalRegistry};
 pub(crate) use self::splicer::*;
 
 type DirectPackageManifest = BTreeMap<String, cargo_toml::DependencyDetail>;
@@ -40,6 +42,10 @@ pub(crate) struct SplicingManifest {
 
     /// The Cargo resolver version to use for splicing
     pub(crate) resolver_version: cargo_toml::Resolver,
+
+    /// A url template for crates sourced from a local registry. See [SplicingMetadata].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) local_registry_url_template: Option<String>,
 }
 
 impl FromStr for SplicingManifest {
@@ -106,6 +112,15 @@ pub(crate) struct SplicingMetadata {
 
     /// The path of a Cargo config file
     pub(crate) cargo_config: Option<CargoConfig>,
+
+    /// A url template for crates sourced from a [local registry](https://doc.rust-lang.org/cargo/reference/source-replacement.html#local-registry-sources).
+    /// `{name}`, `{version}`, and `{sha256}` are substituted for each crate.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) local_registry_url_template: Option<String>,
+
+    /// The `[profile.*]` tables of the root manifest. See [root_manifest_profiles].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) profiles: Option<Profiles>,
 }
 
 impl TryFrom<SplicingManifest> for SplicingMetadata {
@@ -113,6 +128,7 @@ impl TryFrom<SplicingManifest> for SplicingMetadata {
 
     fn try_from(value: SplicingManifest) -> Result<Self, Self::Error> {
         let direct_packages = value.direct_packages;
+        let local_registry_url_template = value.local_registry_url_template;
 
         let manifests = value
             .manifests
@@ -132,6 +148,8 @@ impl TryFrom<SplicingManifest> for SplicingMetadata {
             })
             .collect::<Result<BTreeMap<Label, Manifest>>>()?;
 
+        let profiles = root_manifest_profiles(&manifests);
+
         let cargo_config = match value.cargo_config {
             Some(path) => Some(
                 CargoConfig::try_from_path(&path)
@@ -144,21 +162,48 @@ impl TryFrom<SplicingManifest> for SplicingMetadata {
             direct_packages,
             manifests,
             cargo_config,
+            local_registry_url_template,
+            profiles,
         })
     }
 }
 
-#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+/// Locate the `[profile.*]` tables which apply to a set of manifests. Cargo only
+/// honors profiles defined in the root manifest, which is either the workspace root
+/// or the only manifest provided.
+fn root_manifest_profiles(manifests: &BTreeMap<Label, Manifest>) -> Option<Profiles> {
+    let root = match manifests
+        .values()
+        .find(|manifest| manifest.workspace.is_some())
+    {
+        Some(manifest) => manifest,
+        None if manifests.len() == 1 => manifests.values().next()?,
+        None => return None,
+    };
+
+    manifest_profiles(root)
+}
+
+/// The `[profile.*]` tables of a manifest, if any are defined.
+pub(crate) fn manifest_profiles(manifest: &Manifest) -> Option<Profiles> {
+    (manifest.profile != Profiles::default()).then(|| manifest.profile.clone())
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub(crate) struct SourceInfo {
     /// A url where to a `.crate` file.
     pub(crate) url: String,
 
     /// The `.crate` file's sha256 checksum.
     pub(crate) sha256: String,
+
+    /// The name of the registry the crate is sourced from, as named in the Cargo config.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) registry: Option<String>,
 }
 
 /// Information about the Cargo workspace relative to the Bazel workspace
-#[derive(Debug, Default, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub(crate) struct WorkspaceMetadata {
     /// A mapping of crates to information about where their source can be downloaded
     pub(crate) sources: BTreeMap<CrateId, SourceInfo>,
@@ -174,6 +219,16 @@ pub(crate) struct WorkspaceMetadata {
     /// We store this here because it's computed during the splicing phase via
     /// calls to "cargo tree" which need the full spliced workspace.
     pub(crate) tree_metadata: TreeResolverMetadata,
+
+    /// The feature and dependency tree of each feature variant, which are also
+    /// resolved with "cargo tree" during the splicing phase.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) feature_variants: FeatureVariantMetadata,
+
+    /// The `[profile.*]` tables of the root manifest, from which per-crate
+    /// `rustc_flags` are derived.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) profiles: Option<Profiles>,
 }
 
 impl TryFrom<toml::Value> for WorkspaceMetadata {
@@ -207,6 +262,7 @@ impl WorkspaceMetadata {
     fn new(
         splicing_manifest: &SplicingManifest,
         member_manifests: BTreeMap<&PathBuf, String>,
+        profiles: Option<Profiles>,
     ) -> Result<Self> {
         let mut package_prefixes: BTreeMap<String, String> = member_manifests
             .iter()
@@ -247,6 +303,8 @@ impl WorkspaceMetadata {
             workspace_prefix,
             package_prefixes,
             tree_metadata: TreeResolverMetadata::new(),
+            feature_variants: FeatureVariantMetadata::new(),
+            profiles,
         })
     }
 
@@ -257,8 +315,10 @@ impl WorkspaceMetadata {
         cargo: &Cargo,
         lockfile: &cargo_lock::Lockfile,
         resolver_data: TreeResolverMetadata,
+        feature_variants: FeatureVariantMetadata,
         input_manifest_path: &Path,
         output_manifest_path: &Path,
+        local_registry_url_template: Option<&str>,
     ) -> Result<()> {
         let mut manifest = read_manifest(input_manifest_path)?;
 
@@ -277,6 +337,11 @@ impl WorkspaceMetadata {
         let pkg_sources: Vec<&cargo_lock::Package> = lockfile
             .packages
             .iter()
+            .chain(
+                feature_variants
+                    .values()
+                    .flat_map(|tree| tree.lock_packages.iter()),
+            )
             .filter(|pkg| pkg.source.is_some())
             .filter(|pkg| pkg.source.as_ref().unwrap().is_registry())
             .collect();
@@ -310,6 +375,23 @@ impl WorkspaceMetadata {
         let crate_indexes = index_urls
             .into_iter()
             .map(|(source_kind, url)| {
+                // Registries replaced by a local registry can be resolved without network access.
+                if let Some(path) = cargo_config
+                    .as_ref()
+                    .and_then(|config| config.resolve_local_registry(&url))
+                {
+                    let url_template = match local_registry_url_template {
+                        Some(template) => template.to_owned(),
+                        None => bail!(
+                            "`{}` is replaced by the local registry at `{}` but no `local_registry_url_template` was set in the splicing config. A url template is required to render hermetic urls for its crates.",
+                            url,
+                            path.display()
+                        ),
+                    };
+                    let registry = LocalRegistry::new(&path, url_template);
+                    return Ok((url, CrateIndexLookup::Local(registry)));
+                }
+
                 // Ensure the correct registry is mapped based on the give Cargo config.
                 let index_url = if let Some(config) = &cargo_config {
                     config.resolve_replacement_url(&url)?
@@ -363,6 +445,7 @@ impl WorkspaceMetadata {
             .context("Failed to locate crate indexes")?;
 
         // Get the download URL of each package based on it's registry url.
+        let default_cargo_config = CargoConfig::default();
         let additional_sources = pkg_sources
             .iter()
             .map(|pkg| {
@@ -374,10 +457,18 @@ impl WorkspaceMetadata {
                         source_id
                     )
                 })?;
+                let registry = cargo_config
+                    .as_ref()
+                    .unwrap_or(&default_cargo_config)
+                    .get_registry_name_from_url(&source_url)
+                    .map(str::to_owned);
                 lookup.get_source_info(pkg).map(|source_info| {
                     (
                         CrateId::new(pkg.name.as_str().to_owned(), pkg.version.clone()),
-                        source_info,
+                        source_info.map(|source_info| SourceInfo {
+                            registry,
+                            ..source_info
+                        }),
                     )
                 })
             })
@@ -393,6 +484,7 @@ impl WorkspaceMetadata {
                     }),
             );
         workspace_metaata.tree_metadata = resolver_data;
+        workspace_metaata.feature_variants = feature_variants;
         workspace_metaata.inject_into(&mut manifest)?;
 
         write_root_manifest(output_manifest_path, manifest)?;
@@ -400,6 +492,34 @@ impl WorkspaceMetadata {
         Ok(())
     }
 
+    /// Update an existing Cargo manifest with previously resolved registry urls and target
+    /// features, such as those recorded in a [crate::lockfile::ResolvedLockfile].
+    pub(crate) fn write_resolved_sources_and_feature_map(
+        sources: BTreeMap<CrateId, SourceInfo>,
+        tree_metadata: TreeResolverMetadata,
+        feature_variants: FeatureVariantMetadata,
+        manifest_path: &Path,
+    ) -> Result<()> {
+        let mut manifest = read_manifest(manifest_path)?;
+
+        let mut workspace_metadata = WorkspaceMetadata::try_from(
+            manifest
+                .workspace
+                .as_ref()
+                .and_then(|workspace| workspace.metadata.clone())
+                .context("The spliced manifest is expected to contain workspace metadata")?,
+        )?;
+
+        workspace_metadata.sources = sources;
+        workspace_metadata.tree_metadata = tree_metadata;
+        workspace_metadata.feature_variants = feature_variants;
+        workspace_metadata.inject_into(&mut manifest)?;
+
+        write_root_manifest(manifest_path, manifest)?;
+
+        Ok(())
+    }
+
     fn inject_into(&self, manifest: &mut Manifest) -> Result<()> {
         let metadata_value = toml::Value::try_from(self)?;
         let workspace = manifest.workspace.as_mut().unwrap();
@@ -645,6 +765,7 @@ mod test {
             ]),
             cargo_config: None,
             resolver_version: cargo_toml::Resolver::V2,
+            local_registry_url_template: None,
         };
         let metadata = SplicingMetadata::try_from(manifest).unwrap();
         let metadata = serde_json::to_string(&metadata).unwrap();
@@ -653,4 +774,63 @@ mod test {
             "serialized metadata should not contain absolute path"
         );
     }
+
+    #[test]
+    fn splicing_metadata_root_profiles() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let workspace_manifest_path = temp_dir.as_ref().join("Cargo.toml");
+        fs::write(
+            &workspace_manifest_path,
+            indoc::indoc! {r#"
+                [workspace]
+                members = ["member"]
+
+                [profile.dev.package.image]
+                opt-level = 3
+            "#},
+        )
+        .unwrap();
+
+        let member_dir = temp_dir.as_ref().join("member");
+        fs::create_dir_all(&member_dir).unwrap();
+        let member_manifest_path = member_dir.join("Cargo.toml");
+        fs::write(
+            &member_manifest_path,
+            indoc::indoc! {r#"
+                [package]
+                name = "member"
+                version = "0.1.0"
+
+                [profile.release]
+                lto = true
+            "#},
+        )
+        .unwrap();
+
+        let manifest = SplicingManifest {
+            direct_packages: BTreeMap::new(),
+            manifests: BTreeMap::from([
+                (
+                    workspace_manifest_path,
+                    Label::from_str("//:Cargo.toml").unwrap(),
+                ),
+                (
+                    member_manifest_path,
+                    Label::from_str("//member:Cargo.toml").unwrap(),
+                ),
+            ]),
+            cargo_config: None,
+            resolver_version: cargo_toml::Resolver::V2,
+            local_registry_url_template: None,
+        };
+        let metadata = SplicingMetadata::try_from(manifest).unwrap();
+
+        // Only the profiles of the workspace root are honored.
+        let profiles = metadata.profiles.unwrap();
+        assert!(profiles.release.is_none());
+        assert_eq!(
+            profiles.dev.unwrap().package["image"],
+            toml::Value::Table(toml::toml! { opt-level = 3 })
+        );
+    }
 }
@@ -2,7 +2,7 @@
 
 use std::collections::BTreeMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
 use crate::utils;
@@ -31,8 +31,16 @@ pub(crate) struct Source {
     /// URL to a registry source
     #[serde(default = "default_registry_url")]
     pub(crate) registry: String,
+
+    /// Path to a directory containing `.crate` files and an index of a
+    /// [local registry](https://doc.rust-lang.org/cargo/reference/source-replacement.html#local-registry-sources)
+    #[serde(rename = "local-registry", skip_serializing_if = "Option::is_none")]
+    pub(crate) local_registry: Option<PathBuf>,
 }
 
+/// The url of the sparse `crates.io` index, without the `sparse+` prefix.
+const CRATES_IO_SPARSE_INDEX_URL: &str = "https://index.crates.io/";
+
 /// This is the default registry url per what's defined by Cargo.
 fn default_registry_url() -> String {
     utils::CRATES_IO_INDEX_URL.to_owned()
@@ -64,6 +72,11 @@ pub(crate) struct CargoConfig {
     /// source definition and replacement
     #[serde(default = "BTreeMap::new")]
     pub(crate) source: BTreeMap<String, Source>,
+
+    /// The directory relative paths in the config are resolved against. See [config_root].
+    /// This is never serialized so that the config is the same on every machine.
+    #[serde(skip)]
+    pub(crate) root: Option<PathBuf>,
 }
 
 /// Each Cargo config is expected to have a default `crates-io` registry.
@@ -97,6 +110,7 @@ impl Default for CargoConfig {
             registries,
             registry,
             source,
+            root: None,
         }
     }
 }
@@ -114,16 +128,80 @@ impl FromStr for CargoConfig {
     }
 }
 
+/// The directory relative paths in a Cargo config are resolved against. Like Cargo, this is
+/// the parent of the directory containing the config file, whatever that directory is named.
+/// Eg. the directory containing `.cargo/config.toml`.
+fn config_root(path: &Path) -> Option<PathBuf> {
+    let path = match path.is_relative() {
+        true => std::env::current_dir().ok()?.join(path),
+        false => path.to_path_buf(),
+    };
+    path.parent().and_then(Path::parent).map(Path::to_path_buf)
+}
+
+/// The `local-registry` values of a Cargo config along with their location in its content.
+#[derive(Debug, Deserialize)]
+struct LocalRegistrySpans {
+    #[serde(default)]
+    source: BTreeMap<String, LocalRegistrySpan>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LocalRegistrySpan {
+    #[serde(rename = "local-registry")]
+    local_registry: Option<toml::Spanned<String>>,
+}
+
+/// Rewrite the relative `local-registry` paths of a Cargo config loaded from `path` into
+/// absolute paths, allowing the content to be installed in another directory. Only those
+/// values are replaced so the rest of the content, including comments, is kept as is.
+pub(crate) fn absolutize_local_registries(content: &str, path: &Path) -> Result<String> {
+    let root = match config_root(path) {
+        Some(root) => root,
+        None => return Ok(content.to_owned()),
+    };
+
+    let spans: LocalRegistrySpans = toml::from_str(content)?;
+    let mut local_registries: Vec<toml::Spanned<String>> = spans
+        .source
+        .into_values()
+        .filter_map(|source| source.local_registry)
+        .filter(|local_registry| Path::new(local_registry.get_ref()).is_relative())
+        .collect();
+
+    // Replace values from the end of the content so earlier spans remain valid.
+    local_registries.sort_by_key(|local_registry| std::cmp::Reverse(local_registry.span().start));
+    let mut content = content.to_owned();
+    for local_registry in local_registries {
+        let absolute = root.join(local_registry.get_ref());
+        content.replace_range(
+            local_registry.span(),
+            &toml::Value::String(absolute.to_string_lossy().to_string()).to_string(),
+        );
+    }
+
+    Ok(content)
+}
+
 impl CargoConfig {
     /// Load a Cargo config from a path to a file on disk.
     pub(crate) fn try_from_path(path: &Path) -> Result<Self> {
         let content = fs::read_to_string(path)?;
-        Self::from_str(&content)
+        let config = Self::from_str(&content)?;
+
+        Ok(Self {
+            root: config_root(path),
+            ..config
+        })
     }
 
     /// Look up a registry [Source] by its url.
     pub(crate) fn get_source_from_url(&self, url: &str) -> Option<&Source> {
-        if let Some(found) = self.source.values().find(|v| v.registry == url) {
+        if let Some(found) = self
+            .source
+            .values()
+            .find(|v| v.local_registry.is_none() && v.registry == url)
+        {
             Some(found)
         } else if url == utils::CRATES_IO_INDEX_URL {
             self.source.get("crates-io")
@@ -142,6 +220,42 @@ impl CargoConfig {
         }
     }
 
+    /// Look up the name of the registry or source with the given index url. `crates.io`
+    /// is always known as `crates-io`, regardless of whether it's accessed via git or sparse.
+    pub(crate) fn get_registry_name_from_url(&self, url: &str) -> Option<&str> {
+        let url = url.strip_prefix("sparse+").unwrap_or(url);
+        let matches = |index: &str| index.strip_prefix("sparse+").unwrap_or(index) == url;
+
+        if let Some((name, _)) = self
+            .registries
+            .iter()
+            .find(|(_, registry)| matches(&registry.index))
+        {
+            Some(name)
+        } else if let Some((name, _)) = self
+            .source
+            .iter()
+            .find(|(_, source)| source.local_registry.is_none() && matches(&source.registry))
+        {
+            Some(name)
+        } else if url == utils::CRATES_IO_INDEX_URL || url == CRATES_IO_SPARSE_INDEX_URL {
+            Some("crates-io")
+        } else {
+            None
+        }
+    }
+
+    /// Look up the path of a local registry which replaces the registry at the given url.
+    /// Like Cargo, relative paths are resolved against the config's root.
+    pub(crate) fn resolve_local_registry(&self, url: &str) -> Option<PathBuf> {
+        let replace_with = self.get_source_from_url(url)?.replace_with.as_ref()?;
+        let local_registry = self.source.get(replace_with)?.local_registry.as_ref()?;
+        match &self.root {
+            Some(root) => Some(root.join(local_registry)),
+            None => Some(local_registry.clone()),
+        }
+    }
+
     pub(crate) fn resolve_replacement_url<'a>(&'a self, url: &'a str) -> Result<&'a str> {
         if let Some(source) = self.get_source_from_url(url) {
             if let Some(replace_with) = &source.replace_with {
@@ -212,6 +326,7 @@ mod test {
                     token: None,
                 },
                 source: BTreeMap::new(),
+                root: Some(temp_dir.as_ref().parent().unwrap().to_path_buf()),
             },
         )
     }
@@ -241,6 +356,51 @@ mod test {
         );
     }
 
+    #[test]
+    fn registry_settings_get_registry_name_from_url() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let config = temp_dir.as_ref().join("config.toml");
+
+        fs::write(&config, textwrap::dedent(
+            r#"
+                [registries]
+                art-crates-remote = { index = "sparse+https://artprod.mycompany/artifactory/api/cargo/cargo-remote/index/" }
+
+                [source.crates-io]
+                replace-with = "some-mirror"
+
+                [source.some-mirror]
+                registry = "https://artmirror.mycompany/artifactory/cargo-mirror.git"
+            "#,
+        )).unwrap();
+
+        let config = CargoConfig::try_from_path(&config).unwrap();
+        assert_eq!(
+            config.get_registry_name_from_url(
+                "https://artprod.mycompany/artifactory/api/cargo/cargo-remote/index/"
+            ),
+            Some("art-crates-remote"),
+        );
+        assert_eq!(
+            config.get_registry_name_from_url(
+                "https://artmirror.mycompany/artifactory/cargo-mirror.git"
+            ),
+            Some("some-mirror"),
+        );
+        assert_eq!(
+            config.get_registry_name_from_url(utils::CRATES_IO_INDEX_URL),
+            Some("crates-io"),
+        );
+        assert_eq!(
+            config.get_registry_name_from_url("sparse+https://index.crates.io/"),
+            Some("crates-io"),
+        );
+        assert_eq!(
+            config.get_registry_name_from_url("https://example.com/index"),
+            None,
+        );
+    }
+
     #[test]
     fn registry_settings_get_index_url_by_name_from_registry() {
         let temp_dir = tempfile::tempdir().unwrap();
@@ -392,4 +552,119 @@ mod test {
             "https://artprod.mycompany/artifactory/git/cargo-remote.git"
         );
     }
+
+    #[test]
+    fn resolve_relative_local_registry() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dot_cargo = temp_dir.as_ref().join(".cargo");
+        fs::create_dir_all(&dot_cargo).unwrap();
+        let config = dot_cargo.join("config.toml");
+
+        let content = textwrap::dedent(
+            r#"
+                [source.crates-io]
+                replace-with = "local-mirror"
+
+                # A mirror checked into the repository.
+                [source.local-mirror]
+                local-registry = "third_party/local-registry"
+            "#,
+        );
+        fs::write(&config, &content).unwrap();
+
+        let expected = temp_dir.as_ref().join("third_party/local-registry");
+        let loaded = CargoConfig::try_from_path(&config).unwrap();
+        assert_eq!(
+            loaded.resolve_local_registry(utils::CRATES_IO_INDEX_URL),
+            Some(expected.clone()),
+        );
+
+        // The path is only resolved when looked up so that the serialized config, which
+        // is part of the lockfile digest, doesn't depend on where it was loaded from.
+        assert_eq!(
+            loaded.source["local-mirror"].local_registry,
+            Some(PathBuf::from("third_party/local-registry")),
+        );
+        assert!(!serde_json::to_string(&loaded)
+            .unwrap()
+            .contains(&*temp_dir.as_ref().to_string_lossy()));
+
+        // Copies of the config installed elsewhere keep pointing at the same registry.
+        let absolutized = absolutize_local_registries(&content, &config).unwrap();
+        let copy = CargoConfig::from_str(&absolutized).unwrap();
+        assert_eq!(
+            copy.resolve_local_registry(utils::CRATES_IO_INDEX_URL),
+            Some(expected.clone()),
+        );
+
+        // Only the path is rewritten, the rest of the config is left untouched.
+        assert_eq!(
+            absolutized,
+            content.replace(
+                "\"third_party/local-registry\"",
+                &toml::Value::String(expected.to_string_lossy().to_string()).to_string(),
+            ),
+        );
+    }
+
+    #[test]
+    fn resolve_relative_local_registry_outside_dot_cargo() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let cargo_dir = temp_dir.as_ref().join("cargo");
+        fs::create_dir_all(&cargo_dir).unwrap();
+        let config = cargo_dir.join("config.toml");
+
+        fs::write(
+            &config,
+            textwrap::dedent(
+                r#"
+                [source.crates-io]
+                replace-with = "local-mirror"
+
+                [source.local-mirror]
+                local-registry = "third_party/local-registry"
+            "#,
+            ),
+        )
+        .unwrap();
+
+        // Like Cargo, paths are relative to the parent of the config's directory,
+        // whatever that directory is named.
+        let config = CargoConfig::try_from_path(&config).unwrap();
+        assert_eq!(
+            config.resolve_local_registry(utils::CRATES_IO_INDEX_URL),
+            Some(temp_dir.as_ref().join("third_party/local-registry")),
+        );
+    }
+
+    #[test]
+    fn resolve_local_registry() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let config = temp_dir.as_ref().join("config.toml");
+
+        fs::write(
+            &config,
+            textwrap::dedent(
+                r#"
+                [source.crates-io]
+                replace-with = "local-mirror"
+
+                [source.local-mirror]
+                local-registry = "/opt/cargo/local-registry"
+            "#,
+            ),
+        )
+        .unwrap();
+
+        let config = CargoConfig::try_from_path(&config).unwrap();
+        assert_eq!(
+            config.resolve_local_registry(utils::CRATES_IO_INDEX_URL),
+            Some(PathBuf::from("/opt/cargo/local-registry")),
+        );
+        assert_eq!(
+            config
+                .resolve_local_registry("https://artmirror.mycompany/artifactory/cargo-mirror.git"),
+            None,
+        );
+    }
 }
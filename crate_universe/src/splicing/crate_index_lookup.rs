@@ -1,15 +1,24 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
 use crate::splicing::SourceInfo;
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use crates_index::IndexConfig;
 use hex::ToHex;
+use sha2::{Digest, Sha256};
 
 pub(crate) enum CrateIndexLookup {
     Git(crates_index::GitIndex),
     Http(crates_index::SparseIndex),
+    Local(LocalRegistry),
 }
 
 impl CrateIndexLookup {
     pub(crate) fn get_source_info(&self, pkg: &cargo_lock::Package) -> Result<Option<SourceInfo>> {
+        if let Self::Local(registry) = self {
+            return registry.get_source_info(pkg).map(Some);
+        }
+
         let index_config = self
             .index_config()
             .context("Failed to get crate index config")?;
@@ -22,6 +31,7 @@ impl CrateIndexLookup {
                     .with_context(|| format!("Failed to get crate from cache for {pkg:?}"))?,
             ),
             Self::Git(index) => index.crate_(pkg.name.as_str()),
+            Self::Local(_) => unreachable!("Local registries do not use an index config"),
         };
         let source_info = crate_.and_then(|crate_idx| {
             crate_idx
@@ -35,7 +45,11 @@ impl CrateIndexLookup {
                             .as_ref()
                             .and_then(|sum| sum.as_sha256().map(|sum| sum.encode_hex::<String>()))
                             .unwrap_or_else(|| v.checksum().encode_hex::<String>());
-                        SourceInfo { url, sha256 }
+                        SourceInfo {
+                            url,
+                            sha256,
+                            registry: None,
+                        }
                     })
                 })
         });
@@ -47,13 +61,73 @@ impl CrateIndexLookup {
         match self {
             Self::Git(index) => index.index_config(),
             Self::Http(index) => index.index_config(),
+            Self::Local(_) => unreachable!("Local registries do not use an index config"),
+        }
+    }
+}
+
+/// A [local registry](https://doc.rust-lang.org/cargo/reference/source-replacement.html#local-registry-sources)
+/// on disk which contains `.crate` files alongside an index. Crates sourced from a local registry
+/// can be resolved without any network access.
+pub(crate) struct LocalRegistry {
+    /// The root directory of the registry.
+    path: PathBuf,
+
+    /// A template for the url of each `.crate` file where `{name}`, `{version}`, and `{sha256}`
+    /// are substituted. The registry itself is never referenced since its path is specific to
+    /// the machine the lockfile was generated on.
+    url_template: String,
+}
+
+impl LocalRegistry {
+    pub(crate) fn new(path: &Path, url_template: String) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            url_template,
+        }
+    }
+
+    fn get_source_info(&self, pkg: &cargo_lock::Package) -> Result<SourceInfo> {
+        let crate_file = self
+            .path
+            .join(format!("{}-{}.crate", pkg.name.as_str(), pkg.version));
+
+        if !crate_file.exists() {
+            bail!(
+                "Failed to find {} {} in local registry: {}",
+                pkg.name.as_str(),
+                pkg.version,
+                crate_file.display()
+            );
         }
+
+        let sha256 = match pkg.checksum.as_ref().and_then(|sum| sum.as_sha256()) {
+            Some(sum) => sum.encode_hex::<String>(),
+            None => {
+                let content = fs::read(&crate_file).with_context(|| {
+                    format!("Failed to read crate file: {}", crate_file.display())
+                })?;
+                Sha256::digest(content).encode_hex::<String>()
+            }
+        };
+
+        let url = self
+            .url_template
+            .replace("{name}", pkg.name.as_str())
+            .replace("{version}", &pkg.version.to_string())
+            .replace("{sha256}", &sha256);
+
+        Ok(SourceInfo {
+            url,
+            sha256,
+            registry: None,
+        })
     }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::splicing::crate_index_lookup::CrateIndexLookup;
+    use crate::splicing::crate_index_lookup::{CrateIndexLookup, LocalRegistry};
     use semver::Version;
     use std::ffi::OsString;
 
@@ -124,6 +198,43 @@ mod test {
         }
     }
 
+    #[test]
+    fn local_registry() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        std::fs::write(temp_dir.as_ref().join("lazy_static-1.4.0.crate"), "mock").unwrap();
+
+        let package = cargo_lock::Package {
+            name: "lazy_static".parse().unwrap(),
+            version: Version::parse("1.4.0").unwrap(),
+            source: None,
+            checksum: None,
+            dependencies: Vec::new(),
+            replace: None,
+        };
+
+        // Without a checksum in the lockfile, the `.crate` file is hashed.
+        let index = CrateIndexLookup::Local(LocalRegistry::new(
+            temp_dir.as_ref(),
+            "https://mirror.mycompany/crates/{name}/{name}-{version}.crate".to_owned(),
+        ));
+        let source_info = index.get_source_info(&package).unwrap().unwrap();
+        assert_eq!(
+            source_info.url,
+            "https://mirror.mycompany/crates/lazy_static/lazy_static-1.4.0.crate"
+        );
+        assert_eq!(
+            source_info.sha256,
+            "ec864fe99b539704b8872ac591067ef22d836a8d942087f2dba274b301ebe6e5"
+        );
+
+        // Crates missing from the registry are an error.
+        let missing = cargo_lock::Package {
+            version: Version::parse("1.5.0").unwrap(),
+            ..package
+        };
+        assert!(index.get_source_info(&missing).is_err());
+    }
+
     struct EnvVarResetter {
         key: OsString,
         value: Option<OsString>,
@@ -10,10 +10,11 @@ use cargo_toml::{Dependency, Manifest};
 use normpath::PathExt;
 
 use crate::config::CrateId;
+use crate::splicing::cargo_config::absolutize_local_registries;
 use crate::splicing::{SplicedManifest, SplicingManifest};
 use crate::utils::starlark::Label;
 
-use super::{read_manifest, DirectPackageManifest, WorkspaceMetadata};
+use super::{manifest_profiles, read_manifest, DirectPackageManifest, WorkspaceMetadata};
 
 /// The core splicer implementation. Each style of Bazel workspace should be represented
 /// here and a splicing implementation defined.
@@ -233,7 +234,11 @@ impl<'a> SplicerKind<'a> {
         let member_manifests = BTreeMap::from([(*path, String::new())]);
 
         // Write the generated metadata to the manifest
-        let workspace_metadata = WorkspaceMetadata::new(splicing_manifest, member_manifests)?;
+        let workspace_metadata = WorkspaceMetadata::new(
+            splicing_manifest,
+            member_manifests,
+            manifest_profiles(&manifest),
+        )?;
         workspace_metadata.inject_into(&mut manifest)?;
 
         // Write the root manifest
@@ -276,7 +281,11 @@ impl<'a> SplicerKind<'a> {
         let member_manifests = BTreeMap::from([(*path, String::new())]);
 
         // Write the generated metadata to the manifest
-        let workspace_metadata = WorkspaceMetadata::new(splicing_manifest, member_manifests)?;
+        let workspace_metadata = WorkspaceMetadata::new(
+            splicing_manifest,
+            member_manifests,
+            manifest_profiles(&manifest),
+        )?;
         workspace_metadata.inject_into(&mut manifest)?;
 
         // Write the root manifest
@@ -313,8 +322,9 @@ impl<'a> SplicerKind<'a> {
             })?;
         }
 
-        // Write the generated metadata to the manifest
-        let workspace_metadata = WorkspaceMetadata::new(splicing_manifest, installations)?;
+        // Write the generated metadata to the manifest. Profiles are only honored in the
+        // root manifest of a workspace so none are collected from the individual packages.
+        let workspace_metadata = WorkspaceMetadata::new(splicing_manifest, installations, None)?;
         workspace_metadata.inject_into(&mut manifest)?;
 
         // Add any additional depeendencies to the root package
@@ -409,7 +419,12 @@ impl<'a> SplicerKind<'a> {
                 fs::create_dir_all(&dot_cargo_dir)?;
             }
 
-            fs::copy(cargo_config_path, dot_cargo_dir.join("config.toml"))?;
+            // Relative paths are resolved against the location of the original config.
+            let content = fs::read_to_string(cargo_config_path)?;
+            fs::write(
+                dot_cargo_dir.join("config.toml"),
+                absolutize_local_registries(&content, cargo_config_path)?,
+            )?;
         }
 
         Ok(())
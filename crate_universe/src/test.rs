@@ -42,6 +42,49 @@ pub(crate) fn mock_cargo_lock_package() -> cargo_lock::Package {
     .unwrap()
 }
 
+/// A [crate::context::CrateContext] without any targets whose only attributes are its normal
+/// dependencies, each optionally conditional on a configuration.
+pub(crate) fn mock_crate_context(
+    id: &crate::config::CrateId,
+    deps: &[(&crate::config::CrateId, Option<&str>)],
+) -> crate::context::CrateContext {
+    let mut dep_select = crate::select::Select::new();
+    for (dep, configuration) in deps {
+        dep_select.insert(
+            crate::context::CrateDependency {
+                id: (*dep).clone(),
+                target: dep.name.clone(),
+                alias: None,
+            },
+            configuration.map(str::to_owned),
+        );
+    }
+
+    crate::context::CrateContext {
+        name: id.name.clone(),
+        version: id.version.clone(),
+        package_url: None,
+        repository: None,
+        targets: Default::default(),
+        library_target_name: None,
+        common_attrs: crate::context::CommonAttributes {
+            deps: dep_select,
+            ..Default::default()
+        },
+        build_script_attrs: None,
+        license: None,
+        license_ids: Default::default(),
+        license_file: None,
+        additive_build_file_content: None,
+        disable_pipelining: false,
+        extra_aliased_targets: Default::default(),
+        alias_rule: None,
+        override_targets: Default::default(),
+        cc_library: None,
+        feature_variants: Default::default(),
+    }
+}
+
 pub(crate) mod metadata {
     pub(crate) fn alias() -> cargo_metadata::Metadata {
         serde_json::from_str(include_str!(concat!(
@@ -44,8 +44,11 @@ pub(crate) fn normalize_cargo_file_paths(
 
             let new_path = if original_parent_path_str.contains('+') {
                 let new_parent_file_path = sanitize_repository_name(original_parent_path_str);
-                std::fs::rename(original_parent_path_str, new_parent_file_path)
-                    .expect("Could not rename paths");
+                // The directory may have already been renamed when vendoring incrementally.
+                if Path::new(original_parent_path_str).exists() {
+                    std::fs::rename(original_parent_path_str, new_parent_file_path)
+                        .expect("Could not rename paths");
+                }
                 PathBuf::from(&original_path_str.replace('+', "-"))
             } else {
                 path
@@ -10,7 +10,7 @@ mod select_set;
 mod serialize;
 mod target_compatible_with;
 
-use std::collections::BTreeSet as Set;
+use std::collections::{BTreeMap, BTreeSet as Set};
 
 use serde::{Serialize, Serializer};
 use serde_starlark::{Error as StarlarkError, FunctionCall};
@@ -34,13 +34,17 @@ pub(crate) enum Starlark {
     ExportsFiles(ExportsFiles),
     Filegroup(Filegroup),
     Alias(Alias),
+    #[serde(serialize_with = "serialize::cargo_build_script")]
     CargoBuildScript(CargoBuildScript),
+    CcLibrary(CcLibrary),
     #[serde(serialize_with = "serialize::rust_proc_macro")]
     RustProcMacro(RustProcMacro),
     #[serde(serialize_with = "serialize::rust_library")]
     RustLibrary(RustLibrary),
     #[serde(serialize_with = "serialize::rust_binary")]
     RustBinary(RustBinary),
+    #[serde(serialize_with = "serialize::rust_test")]
+    RustTest(RustTest),
 
     #[serde(skip_serializing)]
     Verbatim(String),
@@ -81,6 +85,23 @@ pub(crate) struct Filegroup {
     pub(crate) srcs: Glob,
 }
 
+#[derive(Serialize)]
+#[serde(rename = "cc_library")]
+pub(crate) struct CcLibrary {
+    pub(crate) name: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub(crate) copts: Vec<String>,
+    #[serde(skip_serializing_if = "Set::is_empty")]
+    pub(crate) defines: Set<String>,
+    #[serde(skip_serializing_if = "Set::is_empty")]
+    pub(crate) deps: Set<Label>,
+    #[serde(skip_serializing_if = "Glob::has_any_include")]
+    pub(crate) hdrs: Glob,
+    #[serde(skip_serializing_if = "Set::is_empty")]
+    pub(crate) includes: Set<String>,
+    pub(crate) srcs: Glob,
+}
+
 pub(crate) struct Alias {
     pub(crate) rule: String,
     pub(crate) name: String,
@@ -89,8 +110,9 @@ pub(crate) struct Alias {
 }
 
 #[derive(Serialize)]
-#[serde(rename = "cargo_build_script")]
 pub(crate) struct CargoBuildScript {
+    #[serde(skip)]
+    pub(crate) rule: String,
     pub(crate) name: String,
     #[serde(skip_serializing_if = "SelectDict::is_empty")]
     pub(crate) aliases: SelectDict<Label, String>,
@@ -139,6 +161,8 @@ pub(crate) struct CargoBuildScript {
 
 #[derive(Serialize)]
 pub(crate) struct RustProcMacro {
+    #[serde(skip)]
+    pub(crate) rule: String,
     pub(crate) name: String,
     #[serde(skip_serializing_if = "SelectSet::is_empty")]
     pub(crate) deps: SelectSet<Label>,
@@ -152,6 +176,8 @@ pub(crate) struct RustProcMacro {
 
 #[derive(Serialize)]
 pub(crate) struct RustLibrary {
+    #[serde(skip)]
+    pub(crate) rule: String,
     pub(crate) name: String,
     #[serde(skip_serializing_if = "SelectSet::is_empty")]
     pub(crate) deps: SelectSet<Label>,
@@ -167,7 +193,24 @@ pub(crate) struct RustLibrary {
 
 #[derive(Serialize)]
 pub(crate) struct RustBinary {
+    #[serde(skip)]
+    pub(crate) rule: String,
+    pub(crate) name: String,
+    #[serde(skip_serializing_if = "SelectSet::is_empty")]
+    pub(crate) deps: SelectSet<Label>,
+    #[serde(skip_serializing_if = "SelectSet::is_empty")]
+    pub(crate) proc_macro_deps: SelectSet<Label>,
+    #[serde(skip_serializing_if = "SelectDict::is_empty")]
+    pub(crate) aliases: SelectDict<Label, String>,
+    #[serde(flatten)]
+    pub(crate) common: CommonAttrs,
+}
+
+#[derive(Serialize)]
+pub(crate) struct RustTest {
     pub(crate) name: String,
+    #[serde(rename = "crate", skip_serializing_if = "Option::is_none")]
+    pub(crate) crate_: Option<Label>,
     #[serde(skip_serializing_if = "SelectSet::is_empty")]
     pub(crate) deps: SelectSet<Label>,
     #[serde(skip_serializing_if = "SelectSet::is_empty")]
@@ -185,6 +228,8 @@ pub(crate) struct CommonAttrs {
     #[serde(skip_serializing_if = "SelectSet::is_empty")]
     pub(crate) crate_features: SelectSet<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) crate_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub(crate) crate_root: Option<String>,
     #[serde(skip_serializing_if = "Data::is_empty")]
     pub(crate) data: Data,
@@ -195,8 +240,8 @@ pub(crate) struct CommonAttrs {
     pub(crate) rustc_env: SelectDict<String, String>,
     #[serde(skip_serializing_if = "SelectSet::is_empty")]
     pub(crate) rustc_env_files: SelectSet<String>,
-    #[serde(skip_serializing_if = "SelectList::is_empty")]
-    pub(crate) rustc_flags: SelectList<String>,
+    #[serde(skip_serializing_if = "RustcFlags::is_empty")]
+    pub(crate) rustc_flags: RustcFlags,
     pub(crate) srcs: Glob,
     #[serde(skip_serializing_if = "Set::is_empty")]
     pub(crate) tags: Set<String>,
@@ -210,6 +255,19 @@ pub(crate) struct Data {
     pub(crate) select: SelectSet<Label>,
 }
 
+pub(crate) struct RustcFlags {
+    pub(crate) select: SelectList<String>,
+    /// Flags which only apply to a given `compilation_mode`, keyed by the label
+    /// of the `config_setting` matching it.
+    pub(crate) compilation_mode: BTreeMap<String, Vec<String>>,
+}
+
+impl RustcFlags {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.select.is_empty() && self.compilation_mode.is_empty()
+    }
+}
+
 impl Package {
     pub(crate) fn default_visibility_public(default_package_metadata: Set<Label>) -> Self {
         let mut default_visibility = Set::new();
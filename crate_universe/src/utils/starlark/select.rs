@@ -1,4 +1,4 @@
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 
 use serde::ser::Serializer;
 use serde::Serialize;
@@ -38,3 +38,23 @@ where
 pub(crate) fn looks_like_bazel_configuration_label(configuration: &str) -> bool {
     configuration.contains("//")
 }
+
+/// Custom platforms specialize the `config_setting` of the triple they are based on, so Bazel
+/// only falls back to the triple's branch of a `select` when the custom platform has no branch
+/// of its own. The `platforms` mapping maps the label of such a triple to its specializations,
+/// for which an empty branch is added to prevent them inheriting values of the triple that
+/// they don't satisfy (eg. from `cfg(not(target_feature = "avx2"))`).
+pub(crate) fn add_specialized_configurations<T: Default>(
+    selects: &mut BTreeMap<String, T>,
+    platforms: &BTreeMap<String, BTreeSet<String>>,
+) {
+    let specializations: Vec<String> = selects
+        .keys()
+        .filter_map(|configuration| platforms.get(configuration))
+        .flatten()
+        .cloned()
+        .collect();
+    for configuration in specializations {
+        selects.entry(configuration).or_default();
+    }
+}
@@ -8,7 +8,8 @@ use serde_starlark::{FunctionCall, MULTILINE};
 use crate::select::{Select, SelectableOrderedValue};
 use crate::utils::starlark::serialize::MultilineArray;
 use crate::utils::starlark::{
-    looks_like_bazel_configuration_label, NoMatchingPlatformTriples, WithOriginalConfigurations,
+    add_specialized_configurations, looks_like_bazel_configuration_label,
+    NoMatchingPlatformTriples, WithOriginalConfigurations,
 };
 
 #[derive(Debug, PartialEq, Eq)]
@@ -78,6 +79,8 @@ where
             }
         }
 
+        add_specialized_configurations(&mut remapped, platforms);
+
         Self {
             common,
             selects: remapped
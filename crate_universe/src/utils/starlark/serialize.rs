@@ -1,9 +1,12 @@
-use serde::ser::{SerializeSeq, SerializeStruct, SerializeTupleStruct, Serializer};
+use std::collections::BTreeMap;
+
+use serde::ser::{SerializeMap, SerializeSeq, SerializeStruct, SerializeTupleStruct, Serializer};
 use serde::Serialize;
 use serde_starlark::{FunctionCall, MULTILINE, ONELINE};
 
 use super::{
-    Data, ExportsFiles, License, Load, Package, PackageInfo, RustBinary, RustLibrary, RustProcMacro,
+    CargoBuildScript, Data, ExportsFiles, License, Load, Package, PackageInfo, RustBinary,
+    RustLibrary, RustProcMacro, RustTest, RustcFlags,
 };
 
 // For structs that contain #[serde(flatten)], a quirk of how Serde processes
@@ -14,21 +17,40 @@ pub(crate) fn rust_proc_macro<S>(rule: &RustProcMacro, serializer: S) -> Result<
 where
     S: Serializer,
 {
-    FunctionCall::new("rust_proc_macro", rule).serialize(serializer)
+    FunctionCall::new(&rule.rule, rule).serialize(serializer)
 }
 
 pub(crate) fn rust_library<S>(rule: &RustLibrary, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
 {
-    FunctionCall::new("rust_library", rule).serialize(serializer)
+    FunctionCall::new(&rule.rule, rule).serialize(serializer)
 }
 
 pub(crate) fn rust_binary<S>(rule: &RustBinary, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
 {
-    FunctionCall::new("rust_binary", rule).serialize(serializer)
+    FunctionCall::new(&rule.rule, rule).serialize(serializer)
+}
+
+// The rule names of the above, and of `cargo_build_script`, can be replaced by
+// the user so they are not known statically.
+pub(crate) fn cargo_build_script<S>(
+    rule: &CargoBuildScript,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    FunctionCall::new(&rule.rule, rule).serialize(serializer)
+}
+
+pub(crate) fn rust_test<S>(rule: &RustTest, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    FunctionCall::new("rust_test", rule).serialize(serializer)
 }
 
 // Serialize an array with each element on its own line, even if there is just a
@@ -149,3 +171,54 @@ impl Serialize for Data {
         plus.end()
     }
 }
+
+impl Serialize for RustcFlags {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        // Output looks like:
+        //
+        //     [
+        //         "common...",
+        //     ] + select({
+        //         "configuration": [
+        //             "value...",  # cfg(whatever)
+        //         ],
+        //         "//conditions:default": [],
+        //     }) + select({
+        //         "@rules_rust//rust/settings:compilation_mode_opt": [
+        //             "value...",
+        //         ],
+        //         "//conditions:default": [],
+        //     })
+
+        struct CompilationModeSelect<'a>(&'a BTreeMap<String, Vec<String>>);
+
+        impl Serialize for CompilationModeSelect<'_> {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: Serializer,
+            {
+                let mut map = serializer.serialize_map(Some(MULTILINE))?;
+                for (setting, flags) in self.0 {
+                    map.serialize_entry(setting, &MultilineArray(flags))?;
+                }
+                map.serialize_entry("//conditions:default", &[] as &[String])?;
+                map.end()
+            }
+        }
+
+        let mut plus = serializer.serialize_tuple_struct("+", MULTILINE)?;
+        if !self.select.is_empty() || self.compilation_mode.is_empty() {
+            plus.serialize_field(&self.select)?;
+        }
+        if !self.compilation_mode.is_empty() {
+            plus.serialize_field(&FunctionCall::new(
+                "select",
+                [CompilationModeSelect(&self.compilation_mode)],
+            ))?;
+        }
+        plus.end()
+    }
+}